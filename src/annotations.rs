@@ -0,0 +1,103 @@
+//! Curation notes about the items of a bank, such as tags, categories and the
+//! demo tracks a preset is used in, kept in a JSON file inside the bank or
+//! next to it. Kilohearts products ignore the file.
+//!
+//! The file maps the path of each item in the bank to anything:
+//!
+//! ```json
+//! {
+//!   "phaseplant/Big Bass.phaseplant": {
+//!     "tags": ["bass", "dark"],
+//!     "category": "Bass",
+//!     "demos": ["Track 3"]
+//!   }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io;
+use std::io::{BufRead, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::read::BankReader;
+
+/// Name of the file inside the bank, and when creating a bank, among the
+/// files it is created from.
+pub const FILE_NAME: &str = "annotations.json";
+
+/// Added to the file name of a bank to find annotations kept next to it.
+pub const SIDECAR_SUFFIX: &str = ".annotations.json";
+
+/// The annotations of the items of a bank, by their path in the bank.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Annotations {
+    pub items: BTreeMap<String, Value>,
+}
+
+impl Annotations {
+    /// # Errors
+    ///
+    /// Will return `Err` if the bytes cannot be parsed as a JSON object.
+    pub fn parse(json: &[u8]) -> io::Result<Annotations> {
+        serde_json::from_slice(json).map_err(Into::into)
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the annotations cannot be serialized.
+    pub fn to_json(&self) -> io::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self).map_err(Into::into)
+    }
+
+    /// The annotations in the bank, if it has any.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the annotations cannot be read or parsed.
+    pub fn read<ReaderType: Read + Seek + BufRead>(
+        reader: &mut BankReader<ReaderType>,
+    ) -> io::Result<Option<Annotations>> {
+        match reader.get(FILE_NAME).cloned() {
+            Some(item) => Annotations::parse(&reader.read_contents(&item)?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Path of the annotations kept next to a bank, such as
+    /// `MyBank.bank.annotations.json` for `MyBank.bank`.
+    #[must_use]
+    pub fn sidecar_path<P: AsRef<Path>>(bank_path: P) -> PathBuf {
+        let mut path = OsString::from(bank_path.as_ref());
+        path.push(SIDECAR_SUFFIX);
+        PathBuf::from(path)
+    }
+
+    /// The annotation of the item with the path.
+    #[must_use]
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        self.items.get(path)
+    }
+
+    /// The strings in the `tags` array of the annotation of the item.
+    #[must_use]
+    pub fn tags(&self, path: &str) -> Vec<&str> {
+        self.get(path)
+            .and_then(|annotation| annotation.get("tags"))
+            .and_then(Value::as_array)
+            .map(|tags| tags.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// The `category` string of the annotation of the item.
+    #[must_use]
+    pub fn category(&self, path: &str) -> Option<&str> {
+        self.get(path)
+            .and_then(|annotation| annotation.get("category"))
+            .and_then(Value::as_str)
+    }
+}
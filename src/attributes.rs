@@ -0,0 +1,106 @@
+//! Timestamps and permissions of the files a bank was created from. The bank
+//! format has no place for them so they are kept in an extra JSON file inside
+//! the bank.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Name of the file inside the bank that contains the attributes.
+pub const FILE_NAME: &str = ".kibank-attrs.json";
+
+/// The attributes of one file.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FileAttributes {
+    /// Time the file was last modified, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+
+    /// Unix permission bits, only recorded and restored on Unix.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+
+    #[serde(default)]
+    pub readonly: bool,
+}
+
+impl FileAttributes {
+    /// The attributes of the file at the path.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the metadata of the file cannot be read.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<FileAttributes> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs());
+
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(metadata.permissions().mode())
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        Ok(FileAttributes {
+            modified,
+            mode,
+            readonly: metadata.permissions().readonly(),
+        })
+    }
+
+    /// Change the file at the path to have these attributes. The time is
+    /// changed before the permissions in case the file becomes read only.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be changed.
+    pub fn apply<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(modified) = self.modified {
+            let file = File::options().write(true).open(path)?;
+            file.set_modified(UNIX_EPOCH + Duration::from_secs(modified))?;
+        }
+
+        let mut permissions = fs::metadata(path)?.permissions();
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            use std::os::unix::fs::PermissionsExt;
+            permissions.set_mode(mode);
+        }
+        #[cfg(not(unix))]
+        permissions.set_readonly(self.readonly);
+        fs::set_permissions(path, permissions)
+    }
+}
+
+/// The attributes of every file in a bank, by their path in the bank.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Attributes {
+    pub files: BTreeMap<String, FileAttributes>,
+}
+
+impl Attributes {
+    /// # Errors
+    ///
+    /// Will return `Err` if the bytes cannot be parsed as JSON.
+    pub fn parse(json: &[u8]) -> io::Result<Attributes> {
+        serde_json::from_slice(json).map_err(Into::into)
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the attributes cannot be serialized.
+    pub fn to_json(&self) -> io::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self).map_err(Into::into)
+    }
+}
@@ -0,0 +1,138 @@
+//! Details of the background image of a bank, found without decoding the image.
+
+use std::fmt;
+use std::io;
+use std::io::{BufRead, Read, Seek};
+
+use crate::read::BankReader;
+
+#[cfg(feature = "image")]
+pub mod placeholder;
+#[cfg(feature = "image")]
+mod png;
+#[cfg(feature = "image")]
+pub mod thumbnail;
+
+/// Formats of images supported as backgrounds.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+}
+
+impl ImageFormat {
+    /// Find the format from the first bytes of the image, regardless of the
+    /// file name extension.
+    #[must_use]
+    pub fn detect(bytes: &[u8]) -> Option<ImageFormat> {
+        if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(ImageFormat::Jpeg)
+        } else if bytes.starts_with(&[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]) {
+            Some(ImageFormat::Png)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageFormat::Jpeg => write!(f, "JPEG"),
+            ImageFormat::Png => write!(f, "PNG"),
+        }
+    }
+}
+
+/// The background of a bank as far as it can be known without decoding it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BackgroundDetails {
+    /// Path of the background in the bank.
+    pub path: String,
+
+    /// Size of the image file in bytes.
+    pub size: u64,
+
+    /// Format found from the first bytes of the image, or `None` if it is
+    /// neither JPEG nor PNG whatever its extension.
+    pub format: Option<ImageFormat>,
+
+    /// Width and height in pixels, if the headers can be read.
+    pub dimensions: Option<(u32, u32)>,
+}
+
+impl BackgroundDetails {
+    /// The details of the first background in the bank, or `None` if the
+    /// bank has no background.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the background cannot be read.
+    pub fn of_bank<ReaderType: Read + Seek + BufRead>(
+        reader: &mut BankReader<ReaderType>,
+    ) -> io::Result<Option<BackgroundDetails>> {
+        let Some(item) = reader
+            .items()
+            .into_iter()
+            .find(|item| item.is_background_file())
+        else {
+            return Ok(None);
+        };
+        let image = reader.read_contents(&item)?;
+        Ok(Some(BackgroundDetails {
+            path: item.file_name_lossy(),
+            size: item.size(),
+            format: ImageFormat::detect(&image),
+            dimensions: dimensions(&image),
+        }))
+    }
+}
+
+/// Width and height of a JPEG or PNG image in pixels, read from the headers.
+#[must_use]
+pub fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    match ImageFormat::detect(bytes)? {
+        // The IHDR chunk is always first.
+        ImageFormat::Png => Some((read_u32(bytes, 16)?, read_u32(bytes, 20)?)),
+        ImageFormat::Jpeg => jpeg_dimensions(bytes),
+    }
+}
+
+/// Walk the JPEG segments until a start of frame segment is found.
+fn jpeg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut pos = 2;
+    loop {
+        if *bytes.get(pos)? != 0xff {
+            return None;
+        }
+        let marker = *bytes.get(pos + 1)?;
+        match marker {
+            // Padding before a marker.
+            0xff => pos += 1,
+
+            // Markers without a length.
+            0x01 | 0xd0..=0xd7 => pos += 2,
+
+            // Start of frame, excluding DHT, JPG and DAC which share the range.
+            0xc0..=0xcf if marker != 0xc4 && marker != 0xc8 && marker != 0xcc => {
+                let height = read_u16(bytes, pos + 5)?;
+                let width = read_u16(bytes, pos + 7)?;
+                return Some((u32::from(width), u32::from(height)));
+            }
+
+            _ => pos += 2 + usize::from(read_u16(bytes, pos + 2)?),
+        }
+    }
+}
+
+fn read_u16(bytes: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        bytes.get(pos..pos + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        bytes.get(pos..pos + 4)?.try_into().ok()?,
+    ))
+}
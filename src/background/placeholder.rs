@@ -0,0 +1,168 @@
+//! A simple background for banks that don't have one: the name of the bank
+//! in white on a solid color, so a bank is never published with the default
+//! gray tile by accident. The color is picked from the name so banks in a
+//! series can be told apart.
+
+use std::io;
+use std::io::{Error, ErrorKind};
+
+use super::png;
+
+/// Width of a placeholder in pixels, twice the smallest accepted by linting.
+pub const WIDTH: u32 = 800;
+
+/// Height of a placeholder in pixels.
+pub const HEIGHT: u32 = 400;
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+/// Space between characters, in unscaled pixels.
+const GLYPH_SPACING: u32 = 1;
+
+const TEXT_COLOR: [u8; 3] = [0xff, 0xff, 0xff];
+
+/// Draw a PNG image of the text centered on a solid color, scaled to fill as
+/// much of the width as fits. Letters are drawn in upper case, characters
+/// without a glyph as `?`, and characters that don't fit are left out.
+///
+/// # Errors
+///
+/// Will return `Err` if the width or height is zero, the image is too large
+/// or it cannot be compressed.
+pub fn render(text: &str, width: u32, height: u32) -> io::Result<Vec<u8>> {
+    let Some(size) = width
+        .checked_mul(height)
+        .and_then(|count| count.checked_mul(3))
+        .filter(|size| *size > 0)
+    else {
+        let msg = format!("Cannot draw a placeholder of {width}x{height} pixels");
+        return Err(Error::new(ErrorKind::InvalidInput, msg));
+    };
+    let background = color(text);
+    let mut pixels = Vec::with_capacity(size as usize);
+    for _ in 0..width * height {
+        pixels.extend_from_slice(&background);
+    }
+
+    let glyphs = text
+        .trim()
+        .chars()
+        .map(|c| {
+            glyph(c.to_ascii_uppercase())
+                .or_else(|| glyph('?'))
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    let advance = GLYPH_WIDTH + GLYPH_SPACING;
+    // Nine tenths of the width always fits.
+    let text_space = u32::try_from(u64::from(width) * 9 / 10).unwrap_or(width);
+    let max_glyphs = ((text_space + GLYPH_SPACING) / advance) as usize;
+    let glyphs = &glyphs[..glyphs.len().min(max_glyphs)];
+    if let Ok(count) = u32::try_from(glyphs.len()) {
+        if count > 0 {
+            let scale = (text_space / (count * advance - GLYPH_SPACING))
+                .min(height / 4 / GLYPH_HEIGHT)
+                .max(1);
+            let text_width = (count * advance - GLYPH_SPACING) * scale;
+            let left = width.saturating_sub(text_width) / 2;
+            let top = height.saturating_sub(GLYPH_HEIGHT * scale) / 2;
+            for (index, rows) in (0..).zip(glyphs) {
+                let glyph_left = left + index * advance * scale;
+                for (row, bits) in (0..).zip(rows) {
+                    for column in 0..GLYPH_WIDTH {
+                        if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                            fill(
+                                &mut pixels,
+                                width,
+                                height,
+                                glyph_left + column * scale,
+                                top + row * scale,
+                                scale,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    png::encode(&pixels, width, height, 3)
+}
+
+/// Darkened color from the hash of the text so white text is readable.
+fn color(text: &str) -> [u8; 3] {
+    let digest = sha1_smol::Sha1::from(text).digest().bytes();
+    [
+        0x30 + digest[0] % 0x60,
+        0x30 + digest[1] % 0x60,
+        0x30 + digest[2] % 0x60,
+    ]
+}
+
+/// Paint a square of the text color, clipped to the image.
+fn fill(pixels: &mut [u8], width: u32, height: u32, x: u32, y: u32, size: u32) {
+    for y in y..(y + size).min(height) {
+        for x in x..(x + size).min(width) {
+            let offset = ((y * width + x) * 3) as usize;
+            pixels[offset..offset + 3].copy_from_slice(&TEXT_COLOR);
+        }
+    }
+}
+
+/// Rows of a 5x7 pixel character from the top, with the leftmost pixel in
+/// the highest of the five bits.
+fn glyph(c: char) -> Option<[u8; 7]> {
+    Some(match c {
+        'A' => [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'B' => [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1e],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'F' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+        'G' => [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+        'H' => [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'I' => [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+        'M' => [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'P' => [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+        'Q' => [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+        'R' => [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+        'S' => [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+        'T' => [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x0a, 0x04, 0x04, 0x04, 0x04],
+        'Z' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        ' ' => [0x00; 7],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        '&' => [0x0c, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0d],
+        '\'' => [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '+' => [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        ':' => [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00],
+        '?' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f],
+        _ => return None,
+    })
+}
@@ -0,0 +1,215 @@
+//! Just enough of PNG to draw and shrink backgrounds: encoding 8-bit RGB or
+//! RGBA pixels without filtering, and decoding the non-interlaced images
+//! written by image editors to RGBA.
+
+use std::io;
+use std::io::{Error, ErrorKind, Read, Write};
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+/// Largest number of pixels decoded, far more than any background needs, so
+/// a malicious image cannot use up the memory.
+const MAX_PIXELS: u64 = 64 * 1024 * 1024;
+
+/// Pixels of a decoded image, four bytes of red, green, blue and alpha for
+/// each pixel, row by row from the top.
+pub struct Rgba {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Encode 8-bit pixels with three channels for RGB or four for RGBA.
+pub fn encode(pixels: &[u8], width: u32, height: u32, channels: u32) -> io::Result<Vec<u8>> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Bit depth, RGB or RGBA color, then the only compression and filter
+    // methods and no interlacing.
+    let color_type = if channels == 4 { 6 } else { 2 };
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in pixels.chunks((width * channels) as usize) {
+        // Each row starts with its filter type, which is none.
+        encoder.write_all(&[0])?;
+        encoder.write_all(row)?;
+    }
+    let data = encoder.finish()?;
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &data);
+    write_chunk(&mut png, b"IEND", &[]);
+    Ok(png)
+}
+
+/// Decode an image with a bit depth of 8 or 16 in any color type, or a bit
+/// depth of 8 for palettes. Transparency chunks are ignored.
+pub fn decode(png: &[u8]) -> io::Result<Rgba> {
+    let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+    let mut rest = png
+        .strip_prefix(&SIGNATURE)
+        .ok_or_else(|| invalid("Not a PNG image"))?;
+
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut data = Vec::new();
+    while rest.len() >= 12 {
+        let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let kind = &rest[4..8];
+        let chunk = rest
+            .get(8..8 + len)
+            .ok_or_else(|| invalid("PNG chunk is longer than the image"))?;
+        match kind {
+            b"IHDR" if len == 13 => header = Some(chunk.to_vec()),
+            b"PLTE" => palette = chunk.to_vec(),
+            b"IDAT" => data.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+        rest = rest.get(8 + len + 4..).unwrap_or_default();
+    }
+    let header = header.ok_or_else(|| invalid("PNG image has no header"))?;
+
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+    let (bit_depth, color_type, interlace) = (header[8], header[9], header[12]);
+    if width == 0 || height == 0 || u64::from(width) * u64::from(height) > MAX_PIXELS {
+        return Err(invalid("PNG image is empty or too large"));
+    }
+    if interlace != 0 {
+        return Err(invalid("Interlaced PNG images are not supported"));
+    }
+    let channels = match (color_type, bit_depth) {
+        (3, 8) => 1,
+        (0, 8 | 16) => 1,
+        (4, 8 | 16) => 2,
+        (2, 8 | 16) => 3,
+        (6, 8 | 16) => 4,
+        _ => return Err(invalid("PNG bit depth or color type is not supported")),
+    };
+    let bytes_per_pixel = channels * usize::from(bit_depth / 8);
+    let stride = width as usize * bytes_per_pixel;
+
+    // One filter type byte starts each row.
+    let mut filtered = Vec::with_capacity((stride + 1) * height as usize);
+    ZlibDecoder::new(data.as_slice())
+        .take(((stride + 1) * height as usize) as u64)
+        .read_to_end(&mut filtered)?;
+    if filtered.len() != (stride + 1) * height as usize {
+        return Err(invalid("PNG image data is truncated"));
+    }
+
+    let mut previous = vec![0_u8; stride];
+    let mut row = vec![0_u8; stride];
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    for line in filtered.chunks(stride + 1) {
+        row.copy_from_slice(&line[1..]);
+        unfilter(line[0], &mut row, &previous, bytes_per_pixel)?;
+        for sample in row.chunks(bytes_per_pixel) {
+            // The most significant byte of 16-bit samples is enough.
+            let value = |channel: usize| sample[channel * usize::from(bit_depth / 8)];
+            let rgba = match color_type {
+                3 => {
+                    let index = usize::from(sample[0]) * 3;
+                    let color = palette
+                        .get(index..index + 3)
+                        .ok_or_else(|| invalid("PNG palette index is out of range"))?;
+                    [color[0], color[1], color[2], 0xff]
+                }
+                0 => [value(0), value(0), value(0), 0xff],
+                4 => [value(0), value(0), value(0), value(1)],
+                2 => [value(0), value(1), value(2), 0xff],
+                _ => [value(0), value(1), value(2), value(3)],
+            };
+            pixels.extend_from_slice(&rgba);
+        }
+        std::mem::swap(&mut previous, &mut row);
+    }
+    Ok(Rgba {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Reverse the filter of a row given the row above it.
+fn unfilter(filter: u8, row: &mut [u8], previous: &[u8], bytes_per_pixel: usize) -> io::Result<()> {
+    for i in 0..row.len() {
+        let left = if i >= bytes_per_pixel {
+            row[i - bytes_per_pixel]
+        } else {
+            0
+        };
+        let up = previous[i];
+        let up_left = if i >= bytes_per_pixel {
+            previous[i - bytes_per_pixel]
+        } else {
+            0
+        };
+        let prediction = match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            // The average is at most 255 so the truncation is safe.
+            #[allow(clippy::cast_possible_truncation)]
+            3 => ((u16::from(left) + u16::from(up)) / 2) as u8,
+            4 => paeth(left, up, up_left),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Unknown PNG filter type {filter}"),
+                ))
+            }
+        };
+        row[i] = row[i].wrapping_add(prediction);
+    }
+    Ok(())
+}
+
+/// Whichever of the neighbours is closest to their linear prediction.
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+    let distance_left = (estimate - i16::from(left)).abs();
+    let distance_up = (estimate - i16::from(up)).abs();
+    let distance_up_left = (estimate - i16::from(up_left)).abs();
+    if distance_left <= distance_up && distance_left <= distance_up_left {
+        left
+    } else if distance_up <= distance_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    #[allow(clippy::cast_possible_truncation)]
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// The CRC-32 used by PNG chunks, computed a bit at a time since only a few
+/// chunks are written.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xedb8_8320
+            };
+        }
+    }
+    !crc
+}
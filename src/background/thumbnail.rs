@@ -0,0 +1,93 @@
+//! Small copies of backgrounds for showing many banks at once, such as in the
+//! library browser of another application, without opening every bank.
+
+use std::io;
+use std::io::{Error, ErrorKind};
+
+use super::png::{self, Rgba};
+use super::ImageFormat;
+
+/// Longest side of a thumbnail in pixels.
+pub const SIZE: u32 = 256;
+
+/// Shrink a background so its longest side is at most `size` pixels, keeping
+/// the proportions, and encode it as a PNG image. Smaller backgrounds keep
+/// their size. Only PNG backgrounds can be read.
+///
+/// # Errors
+///
+/// Will return `Err` with [`ErrorKind::Unsupported`] for JPEG images, or if
+/// the image cannot be decoded.
+pub fn thumbnail(image: &[u8], size: u32) -> io::Result<Vec<u8>> {
+    match ImageFormat::detect(image) {
+        Some(ImageFormat::Png) => {}
+        Some(ImageFormat::Jpeg) => {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "Thumbnails of JPEG backgrounds are not supported",
+            ))
+        }
+        None => {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Background is not a JPEG or PNG image",
+            ))
+        }
+    }
+
+    let image = png::decode(image)?;
+    let longest = image.width.max(image.height);
+    let (width, height) = if longest <= size {
+        (image.width, image.height)
+    } else {
+        (
+            scale(image.width, size, longest),
+            scale(image.height, size, longest),
+        )
+    };
+    png::encode(&shrink(&image, width, height), width, height, 4)
+}
+
+/// The length scaled by `size / longest`, at least one pixel.
+fn scale(length: u32, size: u32, longest: u32) -> u32 {
+    // The result is at most `size`.
+    #[allow(clippy::cast_possible_truncation)]
+    let scaled = (u64::from(length) * u64::from(size) / u64::from(longest)) as u32;
+    scaled.max(1)
+}
+
+/// Each pixel of the smaller image is the average of the pixels it covers.
+fn shrink(image: &Rgba, width: u32, height: u32) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        let top = span_start(y, image.height, height);
+        let bottom = span_start(y + 1, image.height, height).max(top + 1);
+        for x in 0..width {
+            let left = span_start(x, image.width, width);
+            let right = span_start(x + 1, image.width, width).max(left + 1);
+            let mut sums = [0_u64; 4];
+            for source_y in top..bottom {
+                let row = source_y as usize * image.width as usize * 4;
+                for source_x in left..right {
+                    let offset = row + source_x as usize * 4;
+                    for (sum, value) in sums.iter_mut().zip(&image.pixels[offset..offset + 4]) {
+                        *sum += u64::from(*value);
+                    }
+                }
+            }
+            let count = u64::from(bottom - top) * u64::from(right - left);
+            // The average of bytes fits in a byte.
+            #[allow(clippy::cast_possible_truncation)]
+            pixels.extend(sums.iter().map(|sum| (sum / count) as u8));
+        }
+    }
+    pixels
+}
+
+/// First source pixel covered by a pixel of the smaller image.
+fn span_start(position: u32, source_len: u32, len: u32) -> u32 {
+    // The result is at most `source_len`.
+    #[allow(clippy::cast_possible_truncation)]
+    let start = (u64::from(position) * u64::from(source_len) / u64::from(len)) as u32;
+    start
+}
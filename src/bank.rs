@@ -0,0 +1,558 @@
+//! A whole bank in memory that can be changed and written again, for editing
+//! an existing bank without moving every item from a reader to a writer by
+//! hand. Paths are compared ignoring ASCII case, like Kilohearts products do.
+//!
+//! Master banks in an archive can be protected from being replaced by
+//! putting an empty marker file next to them, such as `MyBank.bank.read-only`,
+//! or by only opening them as a [`ReadOnlyBank`].
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::io::{BufRead, BufWriter, Error, ErrorKind, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use log::{debug, warn};
+
+use crate::read::BankReader;
+use crate::write::{normalize_path, AtomicFile, BankWriter, BankWriterOptions, ContentSource};
+use crate::{ItemKind, Metadata, BACKGROUND_FILE_STEM, PATH_SEPARATOR};
+
+/// Added to the file name of a bank for the marker file that protects it from
+/// being replaced.
+pub const READ_ONLY_SUFFIX: &str = ".read-only";
+
+/// Where the marker that protects the bank from being replaced is.
+#[must_use]
+pub fn read_only_marker<P: AsRef<Path>>(bank_path: P) -> PathBuf {
+    let mut path = bank_path.as_ref().as_os_str().to_owned();
+    path.push(READ_ONLY_SUFFIX);
+    PathBuf::from(path)
+}
+
+/// A bank may be written to the path because there is no read-only marker
+/// next to it.
+///
+/// # Errors
+///
+/// Will return `Err` with [`ErrorKind::PermissionDenied`] if the path is
+/// marked read-only.
+pub fn check_writable<P: AsRef<Path>>(bank_path: P) -> io::Result<()> {
+    let marker = read_only_marker(&bank_path);
+    if marker.exists() {
+        let msg = format!(
+            "{} is read-only because {} exists",
+            bank_path.as_ref().display(),
+            marker.display()
+        );
+        return Err(Error::new(ErrorKind::PermissionDenied, msg));
+    }
+    Ok(())
+}
+
+/// A file of a bank with its contents. The contents of files read with
+/// [`Bank::paths_from_reader`] are empty until they are copied from the bank
+/// with [`Bank::copy_to`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BankFile {
+    /// Path of the file within the bank, including any leading directory.
+    pub path: String,
+
+    pub kind: ItemKind,
+    pub contents: Vec<u8>,
+}
+
+/// The metadata, files and directories of a bank. The metadata file is not one
+/// of the files, and the directories of the kinds of files are written
+/// automatically so only other directories are kept.
+#[derive(Clone, Debug, Default)]
+pub struct Bank {
+    pub metadata: Metadata,
+    files: Vec<BankFile>,
+    directories: Vec<String>,
+}
+
+impl Bank {
+    /// An empty bank with the metadata.
+    #[must_use]
+    pub fn new(metadata: Metadata) -> Bank {
+        Bank {
+            metadata,
+            ..Bank::default()
+        }
+    }
+
+    /// Read the whole bank into memory. Files of unknown kinds cannot be
+    /// written and are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` on read failure or if the metadata cannot be read.
+    pub fn from_reader<ReaderType: Read + Seek + BufRead>(
+        reader: &mut BankReader<ReaderType>,
+    ) -> io::Result<Bank> {
+        Bank::read(reader, true)
+    }
+
+    /// Read the metadata and the paths of the files and directories of the
+    /// bank, but not the contents of the files, for adding and removing files
+    /// without holding the whole bank in memory. The bank is written again
+    /// with [`Bank::copy_to`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the metadata cannot be read.
+    pub fn paths_from_reader<ReaderType: Read + Seek + BufRead>(
+        reader: &mut BankReader<ReaderType>,
+    ) -> io::Result<Bank> {
+        Bank::read(reader, false)
+    }
+
+    fn read<ReaderType: Read + Seek + BufRead>(
+        reader: &mut BankReader<ReaderType>,
+        with_contents: bool,
+    ) -> io::Result<Bank> {
+        let mut bank = Bank::default();
+        for item in reader.items() {
+            let path = item.file_name_lossy();
+            if item.is_directory() {
+                if item.directory_kind().is_none() {
+                    bank.directories.push(path);
+                }
+            } else if item.is_metadata_file() {
+                bank.metadata = reader.read_metadata(&item)?;
+            } else if let Some(kind) = ItemKind::from(Path::new(&path)) {
+                let contents = if with_contents {
+                    reader.read_contents(&item)?
+                } else {
+                    Vec::new()
+                };
+                bank.files.push(BankFile {
+                    path,
+                    kind,
+                    contents,
+                });
+            } else {
+                warn!("Skipping {path} because it is an unknown type of file");
+            }
+        }
+        debug!(
+            "Read {} files and {} directories",
+            bank.files.len(),
+            bank.directories.len()
+        );
+        Ok(bank)
+    }
+
+    /// Read the whole bank at the path into memory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file is not a readable bank.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Bank> {
+        Bank::from_reader(&mut BankReader::open(path)?)
+    }
+
+    /// Every file in the order it was read or added.
+    #[must_use]
+    pub fn files(&self) -> &[BankFile] {
+        &self.files
+    }
+
+    /// Directories that don't belong to any kind of file.
+    #[must_use]
+    pub fn directories(&self) -> &[String] {
+        &self.directories
+    }
+
+    /// The file at the path.
+    #[must_use]
+    pub fn get(&self, path: &str) -> Option<&BankFile> {
+        self.position(path).map(|position| &self.files[position])
+    }
+
+    /// The file at the path, to change its contents.
+    pub fn get_mut(&mut self, path: &str) -> Option<&mut BankFile> {
+        self.position(path)
+            .map(|position| &mut self.files[position])
+    }
+
+    /// Add a file in the directory of its kind, like [`BankWriter::add`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` with [`ErrorKind::AlreadyExists`] if the bank
+    /// already has a file at the path, or if the file name is not valid in a
+    /// bank. See [`normalize_path`].
+    pub fn add(&mut self, kind: ItemKind, file_name: &str, contents: Vec<u8>) -> io::Result<()> {
+        let path = match kind.directory() {
+            Some(directory) => format!("{directory}{PATH_SEPARATOR}{file_name}"),
+            None => file_name.to_string(),
+        };
+        self.add_with_path(kind, &path, contents)
+    }
+
+    /// Like `add()` except the path already includes any leading directory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` with [`ErrorKind::AlreadyExists`] if the bank
+    /// already has a file at the path, or if the path is not valid in a bank.
+    pub fn add_with_path(
+        &mut self,
+        kind: ItemKind,
+        path: &str,
+        contents: Vec<u8>,
+    ) -> io::Result<()> {
+        let path = self.vacant_path(path)?;
+        self.files.push(BankFile {
+            path,
+            kind,
+            contents,
+        });
+        Ok(())
+    }
+
+    /// Add a file from outside the bank, with its kind found from its path
+    /// like when creating a bank. A background is named `background` with
+    /// its extension. Returns the path of the file in the bank.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` with [`ErrorKind::InvalidInput`] if the file is not
+    /// a kind that can be added or is empty, with [`ErrorKind::AlreadyExists`]
+    /// if the bank already has a file at the path or already has a
+    /// background, or if the file cannot be read.
+    pub fn add_file<P: AsRef<Path>>(&mut self, data_path: P) -> io::Result<String> {
+        let data_path = data_path.as_ref();
+        let file_name = data_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let kind = match ItemKind::from(data_path) {
+            Some(ItemKind::Metadata) | None => {
+                let msg = format!(
+                    "{} is not a kind of file that can be added",
+                    data_path.display()
+                );
+                return Err(Error::new(ErrorKind::InvalidInput, msg));
+            }
+            Some(kind) => kind,
+        };
+        let file_name = if kind == ItemKind::Background {
+            if let Some(background) = self.files.iter().find(|file| file.kind == kind) {
+                let msg = format!("The bank already has the background {}", background.path);
+                return Err(Error::new(ErrorKind::AlreadyExists, msg));
+            }
+            let extension = data_path.extension().unwrap_or_default().to_string_lossy();
+            format!("{BACKGROUND_FILE_STEM}.{extension}")
+        } else {
+            file_name
+        };
+
+        // Empty contents would be written as a directory.
+        let contents = fs::read(data_path)?;
+        if contents.is_empty() {
+            let msg = format!("{} is empty and cannot be added", data_path.display());
+            return Err(Error::new(ErrorKind::InvalidInput, msg));
+        }
+        self.add(kind, &file_name, contents)?;
+        Ok(self.files[self.files.len() - 1].path.clone())
+    }
+
+    /// Add a directory that doesn't belong to any kind of file. Adding a
+    /// directory that is already in the bank does nothing.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the path is not valid in a bank.
+    pub fn add_directory(&mut self, path: &str) -> io::Result<()> {
+        let path = normalize(path)?;
+        if !self
+            .directories
+            .iter()
+            .any(|directory| directory.eq_ignore_ascii_case(&path))
+        {
+            self.directories.push(path);
+        }
+        Ok(())
+    }
+
+    /// Take the file at the path out of the bank.
+    pub fn remove(&mut self, path: &str) -> Option<BankFile> {
+        self.position(path)
+            .map(|position| self.files.remove(position))
+    }
+
+    /// Take every file and directory matching the pattern out of the bank,
+    /// with the files inside the directories, and give their paths in the
+    /// order they were in. See [`path_matches`] for the patterns.
+    pub fn remove_matching(&mut self, pattern: &str) -> Vec<String> {
+        let matching: Vec<String> = self
+            .directories
+            .iter()
+            .filter(|directory| path_matches(pattern, directory))
+            .cloned()
+            .collect();
+        let matches = |path: &str| {
+            path_matches(pattern, path)
+                || matching.iter().any(|directory| is_inside(path, directory))
+        };
+
+        let mut removed = Vec::new();
+        self.directories.retain(|directory| {
+            let remove = matches(directory);
+            if remove {
+                removed.push(directory.clone());
+            }
+            !remove
+        });
+        self.files.retain(|file| {
+            let remove = matches(&file.path);
+            if remove {
+                removed.push(file.path.clone());
+            }
+            !remove
+        });
+        removed
+    }
+
+    /// Move the file to another path, keeping its kind and contents.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` with [`ErrorKind::NotFound`] if there is no file at
+    /// `from`, with [`ErrorKind::AlreadyExists`] if there is already a file
+    /// at `to`, or if `to` is not valid in a bank.
+    pub fn rename(&mut self, from: &str, to: &str) -> io::Result<()> {
+        let position = self.position(from).ok_or_else(|| {
+            Error::new(ErrorKind::NotFound, format!("No file {from} in the bank"))
+        })?;
+        let to = if self.files[position].path.eq_ignore_ascii_case(to) {
+            normalize(to)?
+        } else {
+            self.vacant_path(to)?
+        };
+        self.files[position].path = to;
+        Ok(())
+    }
+
+    /// Add the metadata, directories and files to the writer in their
+    /// order. The ID of the metadata is made from its author and name if it
+    /// has none, like [`BankWriter::add_metadata`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the bank has already been written.
+    pub fn add_to<WriterType: Write>(self, writer: &mut BankWriter<WriterType>) -> io::Result<()> {
+        self.add_metadata_and_directories_to(writer)?;
+        for file in self.files {
+            writer.add_with_path(file.kind, OsStr::new(&file.path), file.contents)?;
+        }
+        Ok(())
+    }
+
+    /// Like `add_to()` except files without contents, such as those read
+    /// with [`Bank::paths_from_reader`], are copied from the bank the reader
+    /// reads. Their contents are read as the writer writes the bank when the
+    /// reader was opened from a file. See [`BankReader::content_source`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` with [`ErrorKind::NotFound`] if a file without
+    /// contents is not in the bank the reader reads, if its contents cannot
+    /// be read or if the bank has already been written.
+    pub fn copy_to<ReaderType, WriterType>(
+        self,
+        reader: &mut BankReader<ReaderType>,
+        writer: &mut BankWriter<WriterType>,
+    ) -> io::Result<()>
+    where
+        ReaderType: Read + Seek + BufRead,
+        WriterType: Write,
+    {
+        self.add_metadata_and_directories_to(writer)?;
+        for file in self.files {
+            let source = if file.contents.is_empty() {
+                let item = reader.get(&file.path).cloned().ok_or_else(|| {
+                    let msg = format!("No file {} in the bank to copy", file.path);
+                    Error::new(ErrorKind::NotFound, msg)
+                })?;
+                reader.content_source(&item)?
+            } else {
+                ContentSource::Bytes(file.contents)
+            };
+            writer.add_source_with_path(file.kind, OsStr::new(&file.path), source)?;
+        }
+        Ok(())
+    }
+
+    fn add_metadata_and_directories_to<WriterType: Write>(
+        &self,
+        writer: &mut BankWriter<WriterType>,
+    ) -> io::Result<()> {
+        writer.add_metadata(&self.metadata)?;
+        for directory in &self.directories {
+            writer.add_directory(OsStr::new(directory))?;
+        }
+        Ok(())
+    }
+
+    /// Write the bank to the stream.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` on write failure.
+    pub fn write<WriterType: Write>(
+        self,
+        inner: WriterType,
+        options: BankWriterOptions,
+    ) -> io::Result<()> {
+        let mut writer = BankWriter::with_options(inner, options);
+        self.add_to(&mut writer)?;
+        writer.write()
+    }
+
+    /// Write the bank to the path, replacing any file there only once the
+    /// bank has been completely written. The bank may have been read from
+    /// the same path.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be written, or with
+    /// [`ErrorKind::PermissionDenied`] if the path is marked read-only.
+    pub fn save<P: AsRef<Path>>(self, path: P, options: BankWriterOptions) -> io::Result<()> {
+        check_writable(&path)?;
+        let mut file = AtomicFile::create(path)?;
+        self.write(BufWriter::new(&mut file), options)?;
+        file.commit()
+    }
+
+    fn position(&self, path: &str) -> Option<usize> {
+        self.files
+            .iter()
+            .position(|file| file.path.eq_ignore_ascii_case(path))
+    }
+
+    /// The path normalized for a bank, if no other file has it.
+    fn vacant_path(&self, path: &str) -> io::Result<String> {
+        let path = normalize(path)?;
+        if self.position(&path).is_some() || path.eq_ignore_ascii_case(Metadata::FILE_NAME) {
+            let msg = format!("The bank already has a file {path}");
+            return Err(Error::new(ErrorKind::AlreadyExists, msg));
+        }
+        Ok(path)
+    }
+}
+
+/// The path matches the pattern, ignoring ASCII case. In the pattern `*`
+/// stands for any number of characters and `?` for exactly one, neither
+/// matching the path separator, so `samples/*.wav` doesn't match
+/// `samples/drums/kick.wav`.
+#[must_use]
+pub fn path_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+
+    // Where to resume after the last star if the rest doesn't match.
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+    while t < path.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len()
+            && (pattern[p].eq_ignore_ascii_case(&path[t])
+                || (pattern[p] == '?' && path[t] != PATH_SEPARATOR))
+        {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            if path[star_t] == PATH_SEPARATOR {
+                return false;
+            }
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, t));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn is_inside(path: &str, directory: &str) -> bool {
+    path.get(..directory.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(directory))
+        && path[directory.len()..].starts_with(PATH_SEPARATOR)
+}
+
+fn normalize(path: &str) -> io::Result<String> {
+    Ok(normalize_path(OsStr::new(path))?
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// A bank that can be looked at but not changed or saved, for master banks
+/// that must never be modified by accident. An editable copy can be made
+/// with [`ReadOnlyBank::to_bank`] and saved somewhere else.
+#[derive(Clone, Debug, Default)]
+pub struct ReadOnlyBank(Bank);
+
+impl ReadOnlyBank {
+    /// Read the whole bank into memory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` on read failure or if the metadata cannot be read.
+    pub fn from_reader<ReaderType: Read + Seek + BufRead>(
+        reader: &mut BankReader<ReaderType>,
+    ) -> io::Result<ReadOnlyBank> {
+        Bank::from_reader(reader).map(ReadOnlyBank)
+    }
+
+    /// Read the whole bank at the path into memory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file is not a readable bank.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<ReadOnlyBank> {
+        Bank::open(path).map(ReadOnlyBank)
+    }
+
+    #[must_use]
+    pub fn metadata(&self) -> &Metadata {
+        &self.0.metadata
+    }
+
+    /// Every file in the order it was read.
+    #[must_use]
+    pub fn files(&self) -> &[BankFile] {
+        self.0.files()
+    }
+
+    /// Directories that don't belong to any kind of file.
+    #[must_use]
+    pub fn directories(&self) -> &[String] {
+        self.0.directories()
+    }
+
+    /// The file at the path.
+    #[must_use]
+    pub fn get(&self, path: &str) -> Option<&BankFile> {
+        self.0.get(path)
+    }
+
+    /// A copy of the bank that can be changed.
+    #[must_use]
+    pub fn to_bank(&self) -> Bank {
+        self.0.clone()
+    }
+}
+
+impl From<Bank> for ReadOnlyBank {
+    fn from(bank: Bank) -> Self {
+        ReadOnlyBank(bank)
+    }
+}
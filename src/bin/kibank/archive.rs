@@ -0,0 +1,247 @@
+//! Commands that package banks for distribution, by compressing,
+//! encrypting or splitting them.
+
+use std::ffi::OsString;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::builder::OsStringValueParser;
+use clap::{Arg, ArgMatches, Command, ValueHint};
+use log::info;
+
+use crate::password;
+use kibank::catalog::BANK_EXTENSION;
+use kibank::compress::Compression;
+use kibank::read::BankReader;
+use kibank::{compress, concat, encrypt};
+
+/// Options and arguments of `compress`.
+pub fn compress_command() -> Command {
+    Command::new("compress")
+        .about("Compress a bank for distribution")
+        .arg(
+            Arg::new("format")
+                .help("Compression format")
+                .long("format")
+                .short('f')
+                .value_parser(["gzip", "zstd"])
+                .default_value("zstd"),
+        )
+        .arg(
+            Arg::new("output")
+                .help("File name of the compressed bank, instead of adding .zst or .gz")
+                .long("output")
+                .short('o')
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new()),
+        )
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `decompress`.
+pub fn decompress_command() -> Command {
+    Command::new("decompress")
+        .about("Decompress a compressed bank")
+        .arg(
+            Arg::new("output")
+                .help("File name of the bank, instead of removing .zst or .gz")
+                .long("output")
+                .short('o')
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new()),
+        )
+        .arg(
+            Arg::new("COMPRESSED_FILE")
+                .help("File name of the compressed bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `encrypt`.
+pub fn encrypt_command() -> Command {
+    Command::new("encrypt")
+        .about("Encrypt a bank with a password given with --password or --keyfile")
+        .arg(
+            Arg::new("output")
+                .help("File name of the encrypted bank, instead of adding .enc")
+                .long("output")
+                .short('o')
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new()),
+        )
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `split`.
+pub fn split_command() -> Command {
+    Command::new("split")
+        .about("Recover every bank from a file of banks written one after another")
+        .arg(
+            Arg::new("dest")
+                .long("dest")
+                .short('d')
+                .value_hint(ValueHint::DirPath)
+                .value_parser(OsStringValueParser::new())
+                .help("Destination directory"),
+        )
+        .arg(
+            Arg::new("BANKS_FILE")
+                .help("File containing the banks")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Compress a bank for distribution.
+pub fn compress(args: &ArgMatches) -> Result<()> {
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+    let compression = match args.get_one::<String>("format").map(String::as_str) {
+        Some("gzip") => Compression::Gzip,
+        _ => Compression::Zstd,
+    };
+
+    // Only banks are compressed to avoid compressing the wrong file.
+    BankReader::open(bank_path)
+        .with_context(|| format!("Cannot open bank {}", bank_path.display()))?;
+
+    let out_path = match args.get_one::<OsString>("output") {
+        Some(output) => PathBuf::from(output),
+        None => {
+            let mut out_path = bank_file_name.clone();
+            out_path.push(".");
+            out_path.push(compression.extension());
+            PathBuf::from(out_path)
+        }
+    };
+    let source = File::open(bank_path)
+        .with_context(|| format!("Cannot open bank {}", bank_path.display()))?;
+    let dest =
+        File::create(&out_path).with_context(|| format!("Cannot create {}", out_path.display()))?;
+    let len = compress::compress(BufReader::new(source), BufWriter::new(dest), compression)
+        .with_context(|| format!("Cannot compress bank {}", bank_path.display()))?;
+    info!(
+        "Compressed {len} bytes with {compression} to {}",
+        out_path.display()
+    );
+    Ok(())
+}
+
+/// Decompress a bank compressed with the compress command.
+pub fn decompress(args: &ArgMatches) -> Result<()> {
+    let compressed_file_name = args
+        .get_one::<OsString>("COMPRESSED_FILE")
+        .with_context(|| "Expected a compressed bank file name")?;
+    let compressed_path = Path::new(compressed_file_name);
+    let mut source = BufReader::new(
+        File::open(compressed_path)
+            .with_context(|| format!("Cannot open {}", compressed_path.display()))?,
+    );
+    let compression = Compression::detect(source.fill_buf()?)
+        .with_context(|| format!("{} is not compressed", compressed_path.display()))?;
+
+    let out_path = match args.get_one::<OsString>("output") {
+        Some(output) => PathBuf::from(output),
+        None if Compression::from_path(compressed_path) == Some(compression) => {
+            compressed_path.with_extension("")
+        }
+        None => {
+            return Err(anyhow!(
+                "Cannot choose a file name for the bank, use --output"
+            ))
+        }
+    };
+    let dest =
+        File::create(&out_path).with_context(|| format!("Cannot create {}", out_path.display()))?;
+    let len = compress::decompress(source, BufWriter::new(dest), compression)
+        .with_context(|| format!("Cannot decompress {}", compressed_path.display()))?;
+    info!("Decompressed {len} bytes to {}", out_path.display());
+    Ok(())
+}
+
+/// Encrypt a bank so it can only be read with the password.
+pub fn encrypt(args: &ArgMatches) -> Result<()> {
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+    let password =
+        password(args)?.with_context(|| "A password is required, use --password or --keyfile")?;
+
+    let bank =
+        fs::read(bank_path).with_context(|| format!("Cannot read bank {}", bank_path.display()))?;
+    if encrypt::is_encrypted(&bank) {
+        return Err(anyhow!("Bank {} is already encrypted", bank_path.display()));
+    }
+    BankReader::new(Cursor::new(&bank))
+        .with_context(|| format!("Cannot open bank {}", bank_path.display()))?;
+
+    let out_path = match args.get_one::<OsString>("output") {
+        Some(output) => PathBuf::from(output),
+        None => {
+            let mut out_path = bank_file_name.clone();
+            out_path.push(".");
+            out_path.push(encrypt::EXTENSION);
+            PathBuf::from(out_path)
+        }
+    };
+    let encrypted = encrypt::encrypt(&bank, &password)?;
+    fs::write(&out_path, encrypted)
+        .with_context(|| format!("Cannot write {}", out_path.display()))?;
+    info!("Encrypted bank written to {}", out_path.display());
+    Ok(())
+}
+
+/// Write each bank in a file of banks written one after another to its own
+/// file, named after the file with the number of the bank.
+pub fn split(args: &ArgMatches) -> Result<()> {
+    let banks_file_name = args
+        .get_one::<OsString>("BANKS_FILE")
+        .with_context(|| "Expected a file name")?;
+    let banks_path = Path::new(banks_file_name);
+    let mut file =
+        File::open(banks_path).with_context(|| format!("Cannot open {}", banks_path.display()))?;
+    let ranges = concat::bank_ranges(&mut file)
+        .with_context(|| format!("Cannot find the banks in {}", banks_path.display()))?;
+
+    let dest_dir = match args.get_one::<OsString>("dest") {
+        Some(dest) => PathBuf::from(dest),
+        None => banks_path.parent().unwrap_or(Path::new("")).to_owned(),
+    };
+    let stem = banks_path.file_stem().unwrap_or_default().to_string_lossy();
+    for (number, range) in ranges.into_iter().enumerate() {
+        let out_path = dest_dir.join(format!("{stem}-{}.{BANK_EXTENSION}", number + 1));
+        let mut dest = BufWriter::new(
+            File::create(&out_path)
+                .with_context(|| format!("Cannot create {}", out_path.display()))?,
+        );
+        let len = range.end - range.start;
+        file.seek(SeekFrom::Start(range.start))?;
+        io::copy(&mut (&file).take(len), &mut dest)
+            .with_context(|| format!("Cannot write {}", out_path.display()))?;
+        dest.flush()?;
+        println!("{}", out_path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,975 @@
+//! Commands that make new banks, from files, projects or an existing bank.
+
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::builder::{ArgAction, OsStringValueParser};
+use clap::{value_parser, Arg, ArgMatches, Command, ValueHint};
+use log::{debug, info, warn};
+use serde::Serialize;
+
+use crate::messages::Message;
+use crate::{
+    cancel_on_ctrl_c, copy_items, open_bank, parse_size, print_bank_diff, read_metadata, save_bank,
+    x_compress_arg,
+};
+use kibank::attributes::{Attributes, FileAttributes};
+use kibank::bank;
+use kibank::catalog::Catalog;
+use kibank::collect::{SkipReason, Skipped, SourceFile};
+use kibank::create::{Classified, Collected, Merged};
+use kibank::diff::BankDiff;
+use kibank::project::{Project, PROJECT_FILE_NAME};
+use kibank::read::{BankReader, BankReaderOptions};
+use kibank::volume::{VolumeIndex, VolumeWriter};
+use kibank::write::{AtomicFile, BankWriter, BankWriterOptions, IdOnEmpty};
+use kibank::{attributes, hash, template, volume, ItemKind, Metadata, MetadataChanges};
+
+/// Options and arguments of `create`.
+pub fn create_command() -> Command {
+    Command::new("create")
+        .about("Create a new bank")
+        .visible_alias("c")
+        .args(metadata_args())
+        .arg(
+            Arg::new("check-id")
+                .help("Fail if a bank in the directory or catalog file has the same ID")
+                .long("check-id")
+                .value_hint(ValueHint::AnyPath)
+                .value_parser(OsStringValueParser::new())
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("stamp")
+                .help("Record the tool, time and source files in the metadata")
+                .long("stamp")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("preserve-input-order")
+                .help("Write the items in the order they were given instead of grouped by kind")
+                .long("preserve-input-order")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("natural-order")
+                .help(
+                    "Write the items of each kind in natural order, with Preset 2 before Preset 10",
+                )
+                .long("natural-order")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("preserve-input-order"),
+        )
+        .arg(x_compress_arg())
+        .arg(
+            Arg::new("changelog")
+                .help("Include the file as the changelog of the bank")
+                .long("changelog")
+                .value_name("FILE")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new()),
+        )
+        .arg(
+            Arg::new("previews")
+                .help("Include the MP3 and FLAC files in the directory as previews of the presets")
+                .long("previews")
+                .value_name("DIR")
+                .value_hint(ValueHint::DirPath)
+                .value_parser(OsStringValueParser::new()),
+        )
+        .arg(
+            Arg::new("require-background")
+                .help("Fail if there is no background image")
+                .long("require-background")
+                .action(ArgAction::SetTrue),
+        )
+        .args(create_image_args())
+        .arg(
+            Arg::new("threads")
+                .help("Number of files to hash at once for --stamp, 0 for one per CPU")
+                .long("threads")
+                .value_parser(value_parser!(usize))
+                .default_value("0")
+                .requires("stamp"),
+        )
+        .arg(
+            Arg::new("preserve-times")
+                .help("Record the modification times and permissions of the files")
+                .long("preserve-times")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("strict-metadata")
+                .help("Fail if the metadata breaks the rules followed by Kilohearts")
+                .long("strict-metadata")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("summary")
+                .help("How to show what was added to and skipped from the bank")
+                .long("summary")
+                .value_parser(["text", "json", "none"])
+                .default_value("text"),
+        )
+        .arg(
+            Arg::new("volume-size")
+                .help("Split the bank into volumes of at most this size, such as 2G")
+                .long("volume-size")
+                .value_parser(parse_size),
+        )
+        .arg(
+            Arg::new("show-diff")
+                .help("Show what would change in an existing bank without writing it")
+                .long("show-diff")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["volume-size", "summary"]),
+        )
+        // These hash and version fields occur in the metadata in the
+        // Kilohearts factory content banks but not those made with
+        // Kilohearts Bank Maker. These fields is not well understood
+        // so these options are hidden.
+        .arg(
+            Arg::new("version")
+                .help("Version number of the new bank")
+                .long("version")
+                .value_parser(value_parser!(u32))
+                .num_args(1)
+                .hide(true),
+        )
+        .arg(
+            Arg::new("only")
+                .help("Only add files of this kind, such as Sample")
+                .long("only")
+                .value_name("KIND")
+                .value_parser(|kind: &str| kind.parse::<ItemKind>())
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("skip")
+                .help("Do not add files of this kind, such as PhasePlantPreset")
+                .long("skip")
+                .value_name("KIND")
+                .value_parser(|kind: &str| kind.parse::<ItemKind>())
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("deny-skips")
+                .help("Fail if any of the files cannot be added to the bank")
+                .long("deny-skips")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("hash")
+                .help("Hash digest for new bank in hex, 160 bits")
+                .long("hash")
+                .num_args(1)
+                .hide(true),
+        )
+        //
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of new bank")
+                .value_hint(ValueHint::AnyPath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+        .arg(
+            Arg::new("IN_FILES")
+                .help("Files and directories to add to the bank")
+                .value_hint(ValueHint::AnyPath)
+                .value_parser(OsStringValueParser::new())
+                .num_args(1..)
+                .required(true),
+        )
+}
+
+/// Options of `create` for the metadata of the new bank.
+fn metadata_args() -> Vec<Arg> {
+    vec![
+        Arg::new("name")
+            .help("Title of the new bank")
+            .long("name")
+            .short('n')
+            .num_args(1),
+        Arg::new("author")
+            .help("Creator of the new bank")
+            .long("author")
+            .short('a')
+            .num_args(1),
+        Arg::new("description")
+            .help("Overview of the new bank")
+            .long("description")
+            .alias("desc")
+            .short('d')
+            .num_args(1),
+        Arg::new("id")
+            .help("Unique identifier for the new bank")
+            .long("id")
+            .short('i')
+            .hide(true)
+            .num_args(1),
+        Arg::new("id-on-empty")
+            .help("Fail, or generate an ID from the files, when there is no ID, author or name")
+            .long("id-on-empty")
+            .value_parser(["error", "uuid", "hash"]),
+        Arg::new("id-template")
+            .help("Template for the ID, like \"{author}.{name}.{year}\"")
+            .long("id-template")
+            .num_args(1),
+        Arg::new("name-template")
+            .help("Template for the name, like \"{name} {version}\"")
+            .long("name-template")
+            .num_args(1),
+        Arg::new("description-template")
+            .help("Template for the description, like \"{description} ({date})\"")
+            .long("description-template")
+            .num_args(1),
+    ]
+}
+
+/// Options and arguments of `build`.
+pub fn build_command() -> Command {
+    Command::new("build")
+        .about("Build the banks of a project file")
+        .arg(
+            Arg::new("project")
+                .help("Project file, instead of kibank.toml in the current directory")
+                .long("project")
+                .short('p')
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("bank")
+                .help("Name of a bank in the project to build, instead of all of them")
+                .long("bank")
+                .short('b')
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("no-hooks")
+                .help("Do not run the post-build hooks of the project")
+                .long("no-hooks")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+/// Options and arguments of `release`.
+pub fn release_command() -> Command {
+    Command::new("release")
+        .about("Prepare a bank for release by updating the version and hash")
+        .arg(
+            Arg::new("bump")
+                .help("Increment the version of the bank")
+                .long("bump")
+                .short('b')
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("tag")
+                .help("Add the version to the file name of the output")
+                .long("tag")
+                .short('t')
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("output")
+                .help("File name of the released bank, instead of replacing the bank")
+                .long("output")
+                .short('o')
+                .value_hint(ValueHint::AnyPath)
+                .value_parser(OsStringValueParser::new())
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options of `create` that need the `image` feature.
+#[cfg(feature = "image")]
+fn create_image_args() -> Vec<Arg> {
+    vec![Arg::new("generate-background")
+        .help("Draw the name of the bank on a solid color if there is no background image")
+        .long("generate-background")
+        .action(ArgAction::SetTrue)]
+}
+
+#[cfg(not(feature = "image"))]
+fn create_image_args() -> Vec<Arg> {
+    Vec::new()
+}
+
+/// Draw a placeholder background with the name of the bank, or the name of
+/// the bank file when the metadata has no name, if asked to. Returns whether
+/// a background was added.
+#[cfg(feature = "image")]
+fn generate_background(
+    args: &ArgMatches,
+    merged: &Merged,
+    bank_path: &Path,
+    writer: &mut BankWriter<impl Write>,
+) -> Result<bool> {
+    use kibank::background::placeholder;
+
+    if !args.get_flag("generate-background") {
+        return Ok(false);
+    }
+
+    let name = match &merged.metadata {
+        Some(metadata) => metadata.name.clone(),
+        None => merged.classified.metadata()?.name,
+    };
+    let name = if name.trim().is_empty() {
+        bank_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    } else {
+        name
+    };
+    info!("Generating a background with the name {name}");
+    let image = placeholder::render(&name, placeholder::WIDTH, placeholder::HEIGHT)?;
+    let mut file_name = OsString::from(kibank::BACKGROUND_FILE_STEM);
+    file_name.push(".png");
+    writer.add(ItemKind::Background, &file_name, image)?;
+    Ok(true)
+}
+
+#[cfg(not(feature = "image"))]
+fn generate_background(
+    _args: &ArgMatches,
+    _merged: &Merged,
+    _bank_path: &Path,
+    _writer: &mut BankWriter<impl Write>,
+) -> Result<bool> {
+    Ok(false)
+}
+
+/// What was written by `create`, shown once the bank is written.
+#[derive(Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreateSummary {
+    bank: PathBuf,
+    id: String,
+
+    /// Number of files of each kind.
+    items: BTreeMap<String, usize>,
+
+    /// Size of the bank in bytes.
+    size: u64,
+
+    fingerprint: String,
+    skipped: Vec<Skipped>,
+
+    /// How the options changed the metadata file, if there was one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata_changes: Option<MetadataChanges>,
+}
+
+/// Make a new bank.
+pub fn create(args: &ArgMatches) -> Result<()> {
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    if !args.get_flag("show-diff") {
+        bank::check_writable(bank_file_name)?;
+    }
+
+    let classified = classify(args, bank_file_name)?;
+
+    // With --show-diff the bank is only written to memory to compare it with
+    // the bank it would replace.
+    let show_diff = args.get_flag("show-diff");
+    let mut new_bank = Vec::new();
+    // The bank only replaces any file at its path once it is complete, so
+    // failing or pressing Ctrl-C part way through leaves nothing behind.
+    let mut atomic_file = None;
+    let mut volume_writer = None;
+    let bank_file: Box<dyn Write + '_> = if show_diff {
+        Box::new(&mut new_bank)
+    } else {
+        match args.get_one::<u64>("volume-size") {
+            Some(volume_size) => {
+                Box::new(volume_writer.insert(VolumeWriter::new(bank_file_name, *volume_size)?))
+            }
+            None => Box::new(
+                atomic_file.insert(AtomicFile::create(bank_file_name).with_context(|| {
+                    format!("Cannot create bank {}", bank_file_name.to_string_lossy())
+                })?),
+            ),
+        }
+    };
+    let options = BankWriterOptions {
+        preserve_order: args.get_flag("preserve-input-order"),
+        natural_order: args.get_flag("natural-order"),
+        x_compress_items: args.get_flag("x-compress"),
+        cancel: Some(cancel_on_ctrl_c()?),
+        id_on_empty: match args.get_one::<String>("id-on-empty").map(String::as_str) {
+            Some("error") => Some(IdOnEmpty::Error),
+            Some("uuid") => Some(IdOnEmpty::Uuid),
+            Some("hash") => Some(IdOnEmpty::Hash),
+            _ => None,
+        },
+        stamp: args.get_flag("stamp"),
+        hash_threads: args
+            .get_one::<usize>("threads")
+            .copied()
+            .unwrap_or_default(),
+    };
+    let mut writer = BankWriter::with_options(bank_file, options);
+
+    // Attributes of the files, by their path in the bank.
+    let preserve_times = args.get_flag("preserve-times");
+    let mut attributes = Attributes::default();
+
+    let (merged, metadata_changes) = merge_metadata(args, classified, Path::new(bank_file_name))?;
+
+    if merged.classified.background.is_none()
+        && !generate_background(args, &merged, Path::new(bank_file_name), &mut writer)?
+    {
+        if args.get_flag("require-background") {
+            return Err(anyhow!("No background image was found"));
+        }
+        // Warnings are written to stdout, which would break a JSON summary.
+        if args.get_one::<String>("summary").map(String::as_str) != Some("json") {
+            warn!("No background image was found, the default will be shown");
+        }
+    }
+
+    for (bank_path, path) in merged.add_to(&mut writer)? {
+        if preserve_times {
+            let file_attributes = FileAttributes::from_path(&path)
+                .with_context(|| format!("Cannot read the attributes of {}", path.display()))?;
+            attributes.files.insert(bank_path, file_attributes);
+        }
+    }
+
+    if preserve_times {
+        writer.add_with_path(
+            ItemKind::Metadata,
+            OsStr::new(attributes::FILE_NAME),
+            attributes.to_json()?,
+        )?;
+    }
+
+    writer.write()?;
+    drop(writer);
+    if let Some(atomic_file) = atomic_file {
+        atomic_file
+            .commit()
+            .with_context(|| format!("Cannot write bank {}", bank_file_name.to_string_lossy()))?;
+    }
+    if let Some(volume_writer) = volume_writer {
+        volume_writer.commit().with_context(|| {
+            format!(
+                "Cannot write the volumes of bank {}",
+                bank_file_name.to_string_lossy()
+            )
+        })?;
+    }
+
+    if show_diff {
+        return show_create_diff(args, Path::new(bank_file_name), new_bank);
+    }
+
+    match args.get_one::<String>("summary").map(String::as_str) {
+        Some("json") => {
+            let summary = create_summary(
+                args,
+                Path::new(bank_file_name),
+                merged.classified.skipped,
+                metadata_changes,
+            )?;
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+        }
+        Some("text") => {
+            let summary = create_summary(
+                args,
+                Path::new(bank_file_name),
+                merged.classified.skipped,
+                metadata_changes,
+            )?;
+            print_create_summary(&summary);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Collect and classify the files to add to the bank, leaving out the kinds
+/// that weren't asked for.
+fn classify(args: &ArgMatches, bank_file_name: &OsStr) -> Result<Classified> {
+    // Collect files to include before the bank is created so it isn't
+    // skipped when it is written to one of the directories.
+    let mut collected = Collected::new(args.get_many::<OsString>("IN_FILES").unwrap_or_default());
+
+    // Select the kinds of files asked for.
+    let only = args
+        .get_many::<ItemKind>("only")
+        .map(Iterator::collect::<Vec<_>>);
+    let skip = args
+        .get_many::<ItemKind>("skip")
+        .unwrap_or_default()
+        .collect::<Vec<_>>();
+    collected.collection.files.retain(|item| {
+        let selected = only
+            .as_ref()
+            .map_or(true, |only| only.contains(&&item.kind))
+            && !skip.contains(&&item.kind);
+        if !selected {
+            debug!("Leaving out {:?} {}", item.kind, item.path.display());
+        }
+        selected
+    });
+    if args.get_flag("deny-skips") && !collected.collection.skipped.is_empty() {
+        let skipped = collected
+            .collection
+            .skipped
+            .iter()
+            .map(|skipped| format!("{} ({})", skipped.path.display(), skipped.reason))
+            .collect::<Vec<_>>();
+        return Err(anyhow!("Files were skipped: {}", skipped.join(", ")));
+    }
+    debug!(
+        "Creating bank {} from {} items",
+        bank_file_name.to_string_lossy(),
+        collected.collection.files.len()
+    );
+    let mut classified = collected.classify();
+    if let Some(changelog) = args.get_one::<OsString>("changelog") {
+        let path = PathBuf::from(changelog);
+        if !path
+            .extension()
+            .is_some_and(|extension| ItemKind::Changelog.has_extension(extension))
+        {
+            return Err(anyhow!(
+                "Changelog {} must be a {} file",
+                path.display(),
+                ItemKind::Changelog.extensions().join(" or ")
+            ));
+        }
+        let file = SourceFile {
+            path,
+            kind: ItemKind::Changelog,
+        };
+        if let Some(replaced) = classified.changelog.replace(file) {
+            debug!("Leaving out changelog {}", replaced.path.display());
+        }
+    }
+    if let Some(previews_dir) = args.get_one::<OsString>("previews") {
+        let selected = only
+            .as_ref()
+            .map_or(true, |only| only.contains(&&ItemKind::Preview))
+            && !skip.contains(&&ItemKind::Preview);
+        if selected {
+            classified.add_previews(previews_dir);
+        }
+    }
+    Ok(classified)
+}
+
+/// Merge the metadata given on the command line and from the files, with
+/// the changes made to the metadata file if there is one.
+fn merge_metadata(
+    args: &ArgMatches,
+    classified: Classified,
+    bank_path: &Path,
+) -> Result<(Merged, Option<MetadataChanges>)> {
+    // Leave the original metadata file untouched if there are no options
+    // supplied.
+    let multiple_metadata = classified
+        .ignored
+        .iter()
+        .any(|file| file.kind == ItemKind::Metadata);
+    let metadata_from_file = |classified: &Classified| {
+        classified.metadata().with_context(|| {
+            format!(
+                "Cannot read {} as a metadata JSON file",
+                classified
+                    .metadata_file
+                    .as_ref()
+                    .map(|file| file.path.display().to_string())
+                    .unwrap_or_default()
+            )
+        })
+    };
+
+    let cli_author = args.get_one::<String>("author");
+    let cli_name = args.get_one::<String>("name");
+    let cli_description = args.get_one::<String>("description");
+    let cli_id = args.get_one::<String>("id");
+    let cli_version = args.get_one::<u32>("version");
+    let cli_hash = args.get_one::<String>("hash");
+    let metadata_from_cli = cli_author.is_some()
+        || cli_name.is_some()
+        || cli_description.is_some()
+        || cli_id.is_some()
+        || cli_version.is_some()
+        || cli_hash.is_some();
+    let id_template = args.get_one::<String>("id-template");
+    let name_template = args.get_one::<String>("name-template");
+    let description_template = args.get_one::<String>("description-template");
+    let templates =
+        id_template.is_some() || name_template.is_some() || description_template.is_some();
+
+    let mut metadata_changes = None;
+    let merged = if multiple_metadata || metadata_from_cli || templates {
+        let metadata_from_file = metadata_from_file(&classified)?;
+        let original = metadata_from_file.clone();
+
+        let mut metadata = Metadata {
+            author: cli_author.cloned().unwrap_or(metadata_from_file.author),
+            name: cli_name.cloned().unwrap_or(metadata_from_file.name),
+            description: cli_description
+                .cloned()
+                .unwrap_or(metadata_from_file.description),
+            id: cli_id.cloned().unwrap_or(metadata_from_file.id),
+            version: cli_version.copied().or(metadata_from_file.version),
+            hash: cli_hash.cloned().or(metadata_from_file.hash),
+            ..metadata_from_file
+        };
+
+        // Every template sees the values from before any template is applied.
+        let value = |placeholder: &str| template::metadata_value(&metadata, placeholder);
+        let id = id_template
+            .map(|id_template| template::render(id_template, value))
+            .transpose()?;
+        let name = name_template
+            .map(|name_template| template::render(name_template, value))
+            .transpose()?;
+        let description = description_template
+            .map(|description_template| template::render(description_template, value))
+            .transpose()?;
+        if let Some(id) = id {
+            metadata.id = Metadata::sanitize_id(&id);
+        }
+        if let Some(name) = name {
+            metadata.name = name;
+        }
+        if let Some(description) = description {
+            metadata.description = description;
+        }
+
+        if let Some(file) = &classified.metadata_file {
+            let changes = metadata.changes_from(&original);
+            log_metadata_changes(&file.path, &changes);
+            metadata_changes = Some(changes);
+        }
+
+        check_id(args, &metadata, bank_path)?;
+        check_metadata(args, &metadata)?;
+        classified.with_metadata(metadata)
+    } else {
+        // Leave the original metadata file untouched if there is just one.
+        if classified.metadata_file.is_none()
+            || args.contains_id("check-id")
+            || args.get_flag("strict-metadata")
+        {
+            let metadata = metadata_from_file(&classified)?;
+            check_id(args, &metadata, bank_path)?;
+            check_metadata(args, &metadata)?;
+        }
+        classified.keep_metadata()
+    };
+    Ok((merged, metadata_changes))
+}
+
+/// Display the files that creating the bank would add, remove and change in
+/// the bank already at the path, leaving it untouched.
+fn show_create_diff(args: &ArgMatches, bank_path: &Path, new_bank: Vec<u8>) -> Result<()> {
+    let mut new = BankReader::new(Cursor::new(new_bank))?;
+    let diff = if bank_path.exists() {
+        let mut old = open_bank(args, bank_path, BankReaderOptions::default())?;
+        BankDiff::between(&mut old, &mut new)
+            .with_context(|| format!("Cannot compare with bank {}", bank_path.display()))?
+    } else {
+        BankDiff::of_new_bank(&new)
+    };
+    print_bank_diff(&diff);
+    Ok(())
+}
+
+/// Describe the bank that was just created.
+fn create_summary(
+    args: &ArgMatches,
+    bank_path: &Path,
+    skipped: Vec<Skipped>,
+    metadata_changes: Option<MetadataChanges>,
+) -> Result<CreateSummary> {
+    let mut reader = open_bank(args, bank_path, BankReaderOptions::default())?;
+    let metadata = read_metadata(&mut reader, bank_path)?;
+    let items = reader
+        .items_by_kind()
+        .iter()
+        .map(|(kind, items)| (format!("{kind:?}"), items.len()))
+        .collect();
+    let size = match volume::find_index(bank_path) {
+        Some(index_path) => {
+            let index: VolumeIndex = serde_json::from_slice(&fs::read(index_path)?)?;
+            index.size()
+        }
+        None => fs::metadata(bank_path)?.len(),
+    };
+    let fingerprint = hash::content_hash(&mut reader)
+        .with_context(|| format!("Cannot fingerprint bank {}", bank_path.display()))?;
+
+    Ok(CreateSummary {
+        bank: bank_path.to_owned(),
+        id: metadata.effective_id(),
+        items,
+        size,
+        fingerprint,
+        skipped,
+        metadata_changes,
+    })
+}
+
+fn print_create_summary(summary: &CreateSummary) {
+    let bank = summary.bank.display().to_string();
+    println!("{}", Message::BankCreated { bank: &bank }.text());
+    println!("{}: {}", Message::Id.text(), summary.id);
+    println!(
+        "{}: {}",
+        Message::Items.text(),
+        summary.items.values().sum::<usize>()
+    );
+    for (kind, count) in &summary.items {
+        println!("  {kind}: {count}");
+    }
+    println!("{}: {}", Message::Size.text(), summary.size);
+    println!("{}: {}", Message::Fingerprint.text(), summary.fingerprint);
+    for skipped in &summary.skipped {
+        let reason = match &skipped.reason {
+            SkipReason::UnknownType => Message::UnknownType.text(),
+            SkipReason::Empty => Message::EmptyFile.text(),
+            SkipReason::Unreadable { error } => Message::Unreadable { error }.text(),
+        };
+        let message = Message::Skipped {
+            path: &skipped.path.display().to_string(),
+            reason: &reason,
+        };
+        println!("{}", message.text());
+    }
+    if let Some(changes) = &summary.metadata_changes {
+        for (field, change) in &changes.changed {
+            let message = Message::MetadataChanged {
+                field,
+                from: &change.from.to_string(),
+                to: &change.to.to_string(),
+            };
+            println!("{}", message.text());
+        }
+        if !changes.extra_kept.is_empty() {
+            println!(
+                "{}: {}",
+                Message::ExtraMetadataKept.text(),
+                changes.extra_kept.join(", ")
+            );
+        }
+        if !changes.extra_dropped.is_empty() {
+            println!(
+                "{}: {}",
+                Message::ExtraMetadataDropped.text(),
+                changes.extra_dropped.join(", ")
+            );
+        }
+    }
+}
+
+/// Log how the metadata from the file was changed, warning when a value in
+/// the file is replaced or removed so it is never a surprise.
+fn log_metadata_changes(path: &Path, changes: &MetadataChanges) {
+    for (field, change) in &changes.changed {
+        let was_set =
+            !matches!(&change.from, serde_json::Value::Null) && change.from.as_str() != Some("");
+        if was_set {
+            warn!(
+                "Metadata {field} from {} is replaced, {} becomes {}",
+                path.display(),
+                change.from,
+                change.to
+            );
+        } else {
+            info!("Metadata {field} is set to {}", change.to);
+        }
+    }
+    if !changes.extra_kept.is_empty() {
+        info!(
+            "Keeping the extra metadata {} from {}",
+            changes.extra_kept.join(", "),
+            path.display()
+        );
+    }
+    if !changes.extra_dropped.is_empty() {
+        warn!(
+            "Removing the extra metadata {} from {}",
+            changes.extra_dropped.join(", "),
+            path.display()
+        );
+    }
+}
+
+/// Fail if `--strict-metadata` is given and the metadata of the bank being
+/// created breaks any of the rules followed by Kilohearts.
+fn check_metadata(args: &ArgMatches, metadata: &Metadata) -> Result<()> {
+    if !args.get_flag("strict-metadata") {
+        return Ok(());
+    }
+
+    // The writer makes the ID from the author and name when there isn't one.
+    let metadata = Metadata {
+        id: metadata.effective_id(),
+        ..metadata.clone()
+    };
+    let issues = metadata
+        .validate()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("The metadata is not valid: {}", issues.join(", ")))
+    }
+}
+
+/// Fail if the ID of the bank being created is already used by a bank in the
+/// directories or catalog files given with `--check-id`. Duplicate IDs cause
+/// banks to hide each other in the Kilohearts browser.
+fn check_id(args: &ArgMatches, metadata: &Metadata, bank_path: &Path) -> Result<()> {
+    let id = metadata.effective_id();
+    for source in args.get_many::<OsString>("check-id").unwrap_or_default() {
+        let source = Path::new(source);
+        let catalog = if source.is_dir() {
+            Catalog::scan(source)
+        } else {
+            Catalog::load(source)
+        }
+        .with_context(|| format!("Cannot read the banks in {}", source.display()))?;
+
+        // The bank being replaced is not a collision.
+        let bank_path = bank_path.canonicalize().ok();
+        let collisions = catalog
+            .with_id(&id)
+            .into_iter()
+            .filter(|bank| bank.path.canonicalize().ok() != bank_path)
+            .collect::<Vec<_>>();
+        for bank in &collisions {
+            warn!("Bank {} already has the ID {id}", bank.path.display());
+        }
+        if !collisions.is_empty() {
+            return Err(anyhow!(
+                "The ID {id} is already used in {}",
+                source.display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Build every bank of a project, or only those asked for, and run the
+/// post-build hooks of each.
+pub fn build(args: &ArgMatches) -> Result<()> {
+    let project_path = args
+        .get_one::<OsString>("project")
+        .map_or_else(|| PathBuf::from(PROJECT_FILE_NAME), PathBuf::from);
+    let text = fs::read_to_string(&project_path)
+        .with_context(|| format!("Cannot read {}", project_path.display()))?;
+    let project: Project = toml::from_str(&text)
+        .with_context(|| format!("Cannot parse project {}", project_path.display()))?;
+    project
+        .validate()
+        .with_context(|| format!("Invalid project {}", project_path.display()))?;
+
+    let banks = match args.get_many::<String>("bank") {
+        Some(names) => names
+            .map(|name| {
+                project
+                    .bank(name)
+                    .with_context(|| format!("No bank named {name} in {}", project_path.display()))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => project.banks.iter().collect(),
+    };
+
+    // Paths in the project are relative to the project file.
+    let dir = project_path.parent().unwrap_or(Path::new(""));
+    for bank in banks {
+        let out_path = dir.join(&bank.output);
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Cannot create directory {}", parent.display()))?;
+        }
+        save_bank(&out_path, |writer| {
+            let files = project
+                .add_to(bank, dir, writer)
+                .with_context(|| format!("Cannot build bank {}", bank.name))?;
+            debug!("Added {files} files to bank {}", bank.name);
+            Ok(())
+        })?;
+        let created = out_path.display().to_string();
+        println!("{}", Message::BankCreated { bank: &created }.text());
+
+        if !args.get_flag("no-hooks") {
+            project
+                .post_build(bank, &out_path, dir)
+                .with_context(|| format!("A post-build hook of bank {} failed", bank.name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Update the version and hash of a bank so it is ready to be distributed.
+pub fn release(args: &ArgMatches) -> Result<()> {
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+    let mut reader = BankReader::open(bank_path)
+        .with_context(|| format!("Cannot open bank {}", bank_path.display()))?;
+
+    let mut metadata = read_metadata(&mut reader, bank_path)?;
+    if args.get_flag("bump") {
+        let version = metadata.bump_version();
+        info!("Version of bank {} is now {version}", bank_path.display());
+    }
+    metadata.hash = Some(
+        hash::content_hash(&mut reader)
+            .with_context(|| format!("Cannot hash bank {}", bank_path.display()))?,
+    );
+
+    let mut out_path = args
+        .get_one::<OsString>("output")
+        .map_or_else(|| bank_path.to_owned(), PathBuf::from);
+    if args.get_flag("tag") {
+        out_path = versioned_path(&out_path, metadata.version.unwrap_or_default());
+    }
+
+    save_bank(&out_path, |writer| {
+        writer.add_metadata(&metadata)?;
+        copy_items(&mut reader, writer)
+    })
+}
+
+/// Add the version to the file name, so `pack.bank` becomes `pack-v2.bank`.
+fn versioned_path(path: &Path, version: u32) -> PathBuf {
+    let mut file_name = path.file_stem().unwrap_or_default().to_owned();
+    file_name.push(format!("-v{version}"));
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    path.with_file_name(file_name)
+}
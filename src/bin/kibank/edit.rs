@@ -0,0 +1,864 @@
+//! Commands that change the contents of an existing bank.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::builder::{ArgAction, OsStringValueParser};
+use clap::{value_parser, Arg, ArgGroup, ArgMatches, Command, ValueHint};
+use log::{info, warn};
+use os_str_bytes::OsStrBytes;
+
+use crate::messages::Message;
+use crate::{
+    copy_items, open_bank, print_bank_diff, read_metadata, save_bank, save_bank_with,
+    write_bank_file, x_compress_arg, x_decompress_arg,
+};
+use kibank::bank::{self, Bank};
+use kibank::diff::BankDiff;
+use kibank::merge::MergePlan;
+use kibank::names::{lowercase_extension, NameRules};
+use kibank::read::{BankReader, BankReaderOptions};
+use kibank::write::{BankWriter, BankWriterOptions, ContentSource};
+use kibank::{catalog, compress, patch, repair, ItemKind, Metadata, PATH_SEPARATOR};
+
+/// Options and arguments of `add`.
+pub fn add_command() -> Command {
+    Command::new("add")
+        .about("Add files to an existing bank")
+        .arg(
+            Arg::new("output")
+                .help("File name of the new bank, instead of replacing the bank")
+                .long("output")
+                .short('o')
+                .value_hint(ValueHint::AnyPath)
+                .value_parser(OsStringValueParser::new())
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+        .arg(
+            Arg::new("FILE")
+                .help("Files to add, such as presets, samples or a background")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .num_args(1..)
+                .required(true),
+        )
+        .arg(
+            Arg::new("show-diff")
+                .help("Show what would change in the bank without writing it")
+                .long("show-diff")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+/// Options and arguments of `remove`.
+pub fn remove_command() -> Command {
+    Command::new("remove")
+        .about("Remove files and directories from an existing bank")
+        .arg(
+            Arg::new("output")
+                .help("File name of the new bank, instead of replacing the bank")
+                .long("output")
+                .short('o')
+                .value_hint(ValueHint::AnyPath)
+                .value_parser(OsStringValueParser::new())
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+        .arg(
+            Arg::new("PATH")
+                .help("Paths in the bank to remove, where * and ? match any characters except /")
+                .num_args(1..)
+                .required(true),
+        )
+        .arg(
+            Arg::new("show-diff")
+                .help("Show what would change in the bank without writing it")
+                .long("show-diff")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+/// Options and arguments of `patch`.
+pub fn patch_command() -> Command {
+    Command::new("patch")
+        .about("Make an update that turns one version of a bank into another")
+        .arg(
+            Arg::new("output")
+                .help("File name of the patch, instead of the new bank with .kbpatch")
+                .long("output")
+                .short('o')
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new()),
+        )
+        .arg(
+            Arg::new("OLD_BANK_FILE")
+                .help("File name of the old version of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+        .arg(
+            Arg::new("NEW_BANK_FILE")
+                .help("File name of the new version of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `apply`.
+pub fn apply_command() -> Command {
+    Command::new("apply")
+        .about("Apply a patch to the old version of a bank to make the new version")
+        .arg(
+            Arg::new("output")
+                .help("File name of the new version of the bank")
+                .long("output")
+                .short('o')
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+        .arg(
+            Arg::new("OLD_BANK_FILE")
+                .help("File name of the old version of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+        .arg(
+            Arg::new("PATCH_FILE")
+                .help("File name of the patch")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `repair`.
+pub fn repair_command() -> Command {
+    Command::new("repair")
+        .about("Fix damage in banks from older export tools")
+        .arg(
+            Arg::new("output")
+                .help("File name of the repaired bank")
+                .long("output")
+                .short('o')
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the damaged bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `repack`.
+pub fn repack_command() -> Command {
+    Command::new("repack")
+        .about("Rewrite a bank with its items in the usual order")
+        .arg(
+            Arg::new("fix-case")
+                .help("Change the extensions of file names to lowercase")
+                .long("fix-case")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(x_compress_arg())
+        .arg(x_decompress_arg().conflicts_with("x-compress"))
+        .arg(
+            Arg::new("output")
+                .help("File name of the repacked bank, instead of replacing the bank")
+                .long("output")
+                .short('o')
+                .value_hint(ValueHint::AnyPath)
+                .value_parser(OsStringValueParser::new())
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `rebrand`.
+pub fn rebrand_command() -> Command {
+    Command::new("rebrand")
+        .about("Change the author and ID of every bank in a directory")
+        .arg(
+            Arg::new("author")
+                .help("New creator of the banks")
+                .long("author")
+                .short('a')
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("id-prefix")
+                .help("New first part of the IDs of the banks, such as newvendor")
+                .long("id-prefix")
+                .num_args(1),
+        )
+        .group(
+            ArgGroup::new("changes")
+                .args(["author", "id-prefix"])
+                .multiple(true)
+                .required(true),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .help("Show the changes without changing any banks")
+                .long("dry-run")
+                .short('n')
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("DIR")
+                .help("Directory to search for banks")
+                .value_hint(ValueHint::DirPath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `normalize-names`.
+pub fn normalize_names_command() -> Command {
+    Command::new("normalize-names")
+        .about("Rename the presets in a bank to follow naming rules")
+        .arg(
+            Arg::new("title-case")
+                .help("Capitalize the first letter of every word")
+                .long("title-case")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("collapse-spaces")
+                .help("Replace repeated spaces with a single space")
+                .long("collapse-spaces")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("numbering")
+                .help("Start every name with a number, like \"01 - Name\"")
+                .long("numbering")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("max-length")
+                .help("Maximum number of characters in a name")
+                .long("max-length")
+                .value_parser(value_parser!(usize))
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("output")
+                .help("File name of the renamed bank, instead of replacing the bank")
+                .long("output")
+                .short('o')
+                .value_hint(ValueHint::AnyPath)
+                .value_parser(OsStringValueParser::new())
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `merge`.
+pub fn merge_command() -> Command {
+    Command::new("merge")
+        .about("Combine several banks into one")
+        .arg(
+            Arg::new("interactive")
+                .help("Ask which version to keep of each file or metadata field that is different in more than one bank, instead of keeping the first")
+                .long("interactive")
+                .short('i')
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("output")
+                .help("File name of the merged bank")
+                .long("output")
+                .short('o')
+                .value_hint(ValueHint::AnyPath)
+                .value_parser(OsStringValueParser::new())
+                .num_args(1)
+                .required(true),
+        )
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File names of the banks, with the first winning any conflicts")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .num_args(2..)
+                .required(true),
+        )
+}
+
+/// Display the files that saving the bank would add, remove and change in the
+/// bank already at the path, leaving it untouched. Files of the bank without
+/// contents are copied from the bank the reader reads, like
+/// [`Bank::copy_to`], into a temporary file for comparing.
+fn show_save_diff<ReaderType: Read + Seek + BufRead>(
+    bank_path: &Path,
+    reader: &mut BankReader<ReaderType>,
+    bank: Bank,
+) -> Result<()> {
+    let mut new_bank = tempfile::tempfile().with_context(|| "Cannot create a temporary file")?;
+    let mut writer = BankWriter::new(BufWriter::new(&mut new_bank));
+    bank.copy_to(reader, &mut writer)?;
+    writer.write()?;
+    drop(writer);
+    new_bank.rewind()?;
+    let mut new = BankReader::new(BufReader::new(new_bank))?;
+    let diff = if bank_path.exists() {
+        let mut old = BankReader::open(bank_path)
+            .with_context(|| format!("Cannot open bank {}", bank_path.display()))?;
+        BankDiff::between(&mut old, &mut new)
+            .with_context(|| format!("Cannot compare with bank {}", bank_path.display()))?
+    } else {
+        BankDiff::of_new_bank(&new)
+    };
+    print_bank_diff(&diff);
+    Ok(())
+}
+
+/// Make a patch from the differences between two versions of a bank.
+pub fn patch(args: &ArgMatches) -> Result<()> {
+    let old_path = Path::new(
+        args.get_one::<OsString>("OLD_BANK_FILE")
+            .with_context(|| "Expected an old bank file name")?,
+    );
+    let new_path = Path::new(
+        args.get_one::<OsString>("NEW_BANK_FILE")
+            .with_context(|| "Expected a new bank file name")?,
+    );
+    let out_path = match args.get_one::<OsString>("output") {
+        Some(output) => PathBuf::from(output),
+        None => new_path.with_extension(patch::EXTENSION),
+    };
+
+    let mut old = open_bank(args, old_path, BankReaderOptions::default())?;
+    let mut new = open_bank(args, new_path, BankReaderOptions::default())?;
+    let out = File::create(&out_path)
+        .with_context(|| format!("Cannot create patch {}", out_path.display()))?;
+    let header = patch::diff(&mut old, &mut new, BufWriter::new(out))
+        .with_context(|| format!("Cannot write patch {}", out_path.display()))?;
+    info!(
+        "Patch has {} changed and {} removed items",
+        header.changed(),
+        header.removed.len()
+    );
+    Ok(())
+}
+
+/// Add files to a bank, keeping everything already in it.
+pub fn add(args: &ArgMatches) -> Result<()> {
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+    let out_path = args
+        .get_one::<OsString>("output")
+        .map_or_else(|| bank_path.to_owned(), PathBuf::from);
+    let mut reader = BankReader::open(bank_path)
+        .with_context(|| format!("Cannot open bank {}", bank_path.display()))?;
+    let mut bank = Bank::paths_from_reader(&mut reader)
+        .with_context(|| format!("Cannot read bank {}", bank_path.display()))?;
+
+    let mut added = Vec::new();
+    for file in args.get_many::<OsString>("FILE").unwrap_or_default() {
+        let file = Path::new(file);
+        let path = bank
+            .add_file(file)
+            .with_context(|| format!("Cannot add {}", file.display()))?;
+        added.push(path);
+    }
+
+    if args.get_flag("show-diff") {
+        return show_save_diff(&out_path, &mut reader, bank);
+    }
+    save_bank(&out_path, |writer| Ok(bank.copy_to(&mut reader, writer)?))?;
+    for path in &added {
+        println!("{}", Message::Added { path }.text());
+    }
+    Ok(())
+}
+
+/// Rewrite a bank without the files and directories matching the patterns.
+pub fn remove(args: &ArgMatches) -> Result<()> {
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+    let out_path = args
+        .get_one::<OsString>("output")
+        .map_or_else(|| bank_path.to_owned(), PathBuf::from);
+    let mut reader = BankReader::open(bank_path)
+        .with_context(|| format!("Cannot open bank {}", bank_path.display()))?;
+    let mut bank = Bank::paths_from_reader(&mut reader)
+        .with_context(|| format!("Cannot read bank {}", bank_path.display()))?;
+
+    let mut removed = Vec::new();
+    for pattern in args.get_many::<String>("PATH").unwrap_or_default() {
+        let paths = bank.remove_matching(pattern);
+        if paths.is_empty() {
+            return Err(anyhow!(
+                "Nothing in bank {} matches {pattern}",
+                bank_path.display()
+            ));
+        }
+        removed.extend(paths);
+    }
+
+    if args.get_flag("show-diff") {
+        return show_save_diff(&out_path, &mut reader, bank);
+    }
+    save_bank(&out_path, |writer| Ok(bank.copy_to(&mut reader, writer)?))?;
+    for path in &removed {
+        println!("{}", Message::Removed { path }.text());
+    }
+    Ok(())
+}
+
+/// Make the new version of a bank by applying a patch to the old version.
+pub fn apply(args: &ArgMatches) -> Result<()> {
+    let old_path = Path::new(
+        args.get_one::<OsString>("OLD_BANK_FILE")
+            .with_context(|| "Expected an old bank file name")?,
+    );
+    let patch_path = Path::new(
+        args.get_one::<OsString>("PATCH_FILE")
+            .with_context(|| "Expected a patch file name")?,
+    );
+    let out_path = Path::new(
+        args.get_one::<OsString>("output")
+            .with_context(|| "Expected an output file name")?,
+    );
+    bank::check_writable(out_path)?;
+
+    let mut old = open_bank(args, old_path, BankReaderOptions::default())?;
+    let patch_file = File::open(patch_path)
+        .with_context(|| format!("Cannot open patch {}", patch_path.display()))?;
+    write_bank_file(out_path, |out| {
+        patch::apply(&mut old, BufReader::new(patch_file), out)
+            .with_context(|| format!("Cannot apply patch {}", patch_path.display()))
+    })?;
+    Ok(())
+}
+
+/// Rename the presets in a bank according to the naming rules.
+pub fn normalize_names(args: &ArgMatches) -> Result<()> {
+    let rules = NameRules {
+        title_case: args.get_flag("title-case"),
+        collapse_spaces: args.get_flag("collapse-spaces"),
+        numbering: args.get_flag("numbering"),
+        max_length: args.get_one::<usize>("max-length").copied(),
+    };
+    if rules.is_empty() {
+        return Err(anyhow!("No naming rules given (-h for help)"));
+    }
+
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+    let mut reader = BankReader::open(bank_path)
+        .with_context(|| format!("Cannot open bank {}", bank_path.display()))?;
+    let out_path = args
+        .get_one::<OsString>("output")
+        .map_or_else(|| bank_path.to_owned(), PathBuf::from);
+
+    save_bank(&out_path, |writer| {
+        // Presets are numbered by their position amongst presets of the same kind.
+        let mut positions = HashMap::new();
+        let mut new_paths = HashSet::new();
+        for item in reader.items() {
+            if !item.is_file() {
+                continue;
+            }
+
+            let path = Path::assert_from_raw_bytes(item.path_bytes()).into_owned();
+            let Some(kind) = ItemKind::from(&path) else {
+                warn!(
+                    "Skipping {} because it is an unknown type of file",
+                    item.file_name_lossy()
+                );
+                continue;
+            };
+
+            let new_path = match (kind.is_preset(), path.file_stem()) {
+                (true, Some(stem)) => {
+                    let position = positions.entry(kind).or_insert(0);
+                    *position += 1;
+
+                    // Keep the directory as it appears in the bank.
+                    let dir_len = item
+                        .path_bytes()
+                        .iter()
+                        .rposition(|c| *c == PATH_SEPARATOR as u8)
+                        .map_or(0, |index| index + 1);
+                    let mut new_path =
+                        OsStr::assert_from_raw_bytes(&item.path_bytes()[..dir_len]).into_owned();
+                    new_path.push(rules.apply(&stem.to_string_lossy(), *position));
+                    if let Some(extension) = path.extension() {
+                        new_path.push(".");
+                        new_path.push(extension);
+                    }
+                    new_path
+                }
+                _ => path.clone().into_os_string(),
+            };
+
+            if new_path != path.as_os_str() {
+                info!(
+                    "Renaming {} to {}",
+                    path.display(),
+                    new_path.to_string_lossy()
+                );
+            }
+            if !new_paths.insert(new_path.clone()) {
+                return Err(anyhow!(
+                    "More than one preset would be named {}",
+                    new_path.to_string_lossy()
+                ));
+            }
+
+            let contents = reader
+                .content_source(&item)
+                .with_context(|| format!("Cannot read {}", item.file_name_lossy()))?;
+            writer.add_source_with_path(kind, &new_path, contents)?;
+        }
+        Ok(())
+    })
+}
+
+/// Change the author and the first part of the ID of every bank in a
+/// directory, showing each change.
+pub fn rebrand(args: &ArgMatches) -> Result<()> {
+    let dir = Path::new(
+        args.get_one::<OsString>("DIR")
+            .with_context(|| "Expected a directory")?,
+    );
+    let author = args.get_one::<String>("author");
+    let id_prefix = args.get_one::<String>("id-prefix");
+    let dry_run = args.get_flag("dry-run");
+
+    let bank_paths = catalog::bank_paths(dir)
+        .with_context(|| format!("Cannot search {} for banks", dir.display()))?;
+    let mut failures = 0;
+    for bank_path in bank_paths {
+        let result = rebrand_bank(&bank_path, author, id_prefix, dry_run);
+        if let Err(error) = result {
+            let message = Message::CannotRead {
+                path: &bank_path.display().to_string(),
+                error: &format!("{error:#}"),
+            };
+            eprintln!("{}", message.text());
+            failures += 1;
+        }
+    }
+
+    if failures > 0 {
+        Err(anyhow!("Cannot rebrand {failures} bank(s)"))
+    } else {
+        Ok(())
+    }
+}
+
+fn rebrand_bank(
+    bank_path: &Path,
+    author: Option<&String>,
+    id_prefix: Option<&String>,
+    dry_run: bool,
+) -> Result<()> {
+    let mut reader = BankReader::open(bank_path)
+        .with_context(|| format!("Cannot open bank {}", bank_path.display()))?;
+    let mut metadata = read_metadata(&mut reader, bank_path)?;
+
+    let mut changes = Vec::new();
+    if let Some(prefix) = id_prefix {
+        // The author is part of the ID when there isn't one.
+        let old_id = metadata.effective_id();
+        let name = old_id
+            .split_once('.')
+            .map_or(old_id.as_str(), |(_, name)| name);
+        let id = Metadata::sanitize_id(&format!("{prefix}.{name}"));
+        if id != old_id {
+            changes.push((Message::Id, old_id.clone(), id.clone()));
+            metadata.id = id;
+        }
+    }
+    if let Some(author) = author.filter(|author| **author != metadata.author) {
+        changes.push((Message::Author, metadata.author.clone(), author.clone()));
+        metadata.author = author.clone();
+    }
+
+    for (field, old, new) in &changes {
+        println!("{}: {} {old} -> {new}", bank_path.display(), field.text());
+    }
+    if changes.is_empty() || dry_run {
+        return Ok(());
+    }
+    save_bank(bank_path, |writer| {
+        writer.add_metadata(&metadata)?;
+        copy_items(&mut reader, writer)
+    })
+}
+
+/// Write a corrected copy of a damaged bank and show what was fixed.
+pub fn repair(args: &ArgMatches) -> Result<()> {
+    let bank_path = Path::new(
+        args.get_one::<OsString>("BANK_FILE")
+            .with_context(|| "Expected a bank file name")?,
+    );
+    let out_path = Path::new(
+        args.get_one::<OsString>("output")
+            .with_context(|| "Expected an output file name")?,
+    );
+    bank::check_writable(out_path)?;
+
+    let bank_file =
+        File::open(bank_path).with_context(|| format!("Cannot open {}", bank_path.display()))?;
+    let fixes = write_bank_file(out_path, |out| {
+        repair::repair(BufReader::new(bank_file), out)
+            .with_context(|| format!("Cannot repair bank {}", bank_path.display()))
+    })?;
+    for fix in &fixes {
+        println!("{fix}");
+    }
+    Ok(())
+}
+
+/// Rewrite a bank, optionally with lowercase extensions.
+pub fn repack(args: &ArgMatches) -> Result<()> {
+    let fix_case = args.get_flag("fix-case");
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+    let mut reader = BankReader::open(bank_path)
+        .with_context(|| format!("Cannot open bank {}", bank_path.display()))?;
+    let out_path = args
+        .get_one::<OsString>("output")
+        .map_or_else(|| bank_path.to_owned(), PathBuf::from);
+
+    // Compressed items are decompressed before being compressed again so
+    // they are never compressed twice.
+    let compressed = compress::x_bank_has_compressed_items(&mut reader);
+    if args.get_flag("x-decompress") && !compressed {
+        return Err(anyhow!(
+            "The items of bank {} are not compressed",
+            bank_path.display()
+        ));
+    }
+    let decompress = compressed && (args.get_flag("x-decompress") || args.get_flag("x-compress"));
+    let options = BankWriterOptions {
+        x_compress_items: args.get_flag("x-compress"),
+        ..BankWriterOptions::default()
+    };
+
+    save_bank_with(&out_path, options, |writer| {
+        let mut new_paths = HashSet::new();
+        for item in reader.items() {
+            let path = OsStr::assert_from_raw_bytes(item.path_bytes()).into_owned();
+            if item.is_unknown_directory() {
+                writer.add_directory(&path)?;
+                continue;
+            } else if !item.is_file() {
+                continue;
+            }
+
+            let Some(kind) = ItemKind::from(Path::new(&path)) else {
+                warn!(
+                    "Skipping {} because it is an unknown type of file",
+                    item.file_name_lossy()
+                );
+                continue;
+            };
+
+            let new_path = match lowercase_extension(item.path_bytes()) {
+                Some(lowercase) if fix_case => {
+                    let new_path = OsStr::assert_from_raw_bytes(&lowercase).into_owned();
+                    info!(
+                        "Renaming {} to {}",
+                        item.file_name_lossy(),
+                        new_path.to_string_lossy()
+                    );
+                    new_path
+                }
+                _ => path,
+            };
+            if !new_paths.insert(new_path.to_ascii_lowercase()) {
+                return Err(anyhow!(
+                    "More than one file would be named {}",
+                    new_path.to_string_lossy()
+                ));
+            }
+
+            let contents = if decompress {
+                compress::x_read_contents(&mut reader, &item).map(ContentSource::Bytes)
+            } else {
+                reader.content_source(&item)
+            }
+            .with_context(|| format!("Cannot read {}", item.file_name_lossy()))?;
+            writer.add_source_with_path(kind, &new_path, contents)?;
+        }
+        Ok(())
+    })
+}
+
+/// Combine several banks into one. The first bank wins each conflict unless
+/// another version is chosen interactively.
+pub fn merge(args: &ArgMatches) -> Result<()> {
+    let bank_paths = args
+        .get_many::<OsString>("BANK_FILE")
+        .with_context(|| "Expected bank file names")?
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    let out_path = args
+        .get_one::<OsString>("output")
+        .map(PathBuf::from)
+        .with_context(|| "Expected a file name for the merged bank")?;
+    let mut readers = bank_paths
+        .iter()
+        .map(|path| {
+            BankReader::open(path).with_context(|| format!("Cannot open bank {}", path.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut plan = MergePlan::new(&mut readers).with_context(|| "Cannot compare the banks")?;
+
+    let interactive = args.get_flag("interactive");
+    let mut input = io::stdin().lock();
+    for conflict in plan.item_conflicts.clone() {
+        if !interactive {
+            warn!(
+                "Keeping {} from {}",
+                conflict.path,
+                bank_paths[conflict.candidates[0].bank].display()
+            );
+            continue;
+        }
+        println!(
+            "{}",
+            Message::ItemConflict {
+                path: &conflict.path
+            }
+            .text()
+        );
+        let choices = conflict
+            .candidates
+            .iter()
+            .map(|candidate| {
+                format!(
+                    "{}  {} bytes  {}",
+                    bank_paths[candidate.bank].display(),
+                    candidate.size,
+                    candidate.digest
+                )
+            })
+            .collect::<Vec<_>>();
+        let bank = conflict.candidates[choose(&mut input, &choices)?].bank;
+        plan.choose_item(&conflict.path, bank)?;
+    }
+    for conflict in plan.field_conflicts.clone() {
+        if !interactive {
+            warn!(
+                "Keeping metadata {} from {}",
+                conflict.field,
+                bank_paths[conflict.values[0].0].display()
+            );
+            continue;
+        }
+        println!(
+            "{}",
+            Message::FieldConflict {
+                field: &conflict.field
+            }
+            .text()
+        );
+        let choices = conflict
+            .values
+            .iter()
+            .map(|(bank, value)| format!("{}  {value}", bank_paths[*bank].display()))
+            .collect::<Vec<_>>();
+        let (_, value) = &conflict.values[choose(&mut input, &choices)?];
+        plan.choose_field(&conflict.field, value.clone())?;
+    }
+
+    let metadata = plan.metadata()?;
+    save_bank(&out_path, |writer| {
+        writer.add_metadata(&metadata)?;
+        for (bank, item) in plan.files.values() {
+            let path = OsStr::assert_from_raw_bytes(item.path_bytes());
+            let Some(kind) = ItemKind::from(&path) else {
+                warn!(
+                    "Skipping {} because it is an unknown type of file",
+                    item.file_name_lossy()
+                );
+                continue;
+            };
+            let contents = readers[*bank]
+                .content_source(item)
+                .with_context(|| format!("Cannot read {}", item.file_name_lossy()))?;
+            writer.add_source_with_path(kind, &path, contents)?;
+        }
+        Ok(())
+    })
+}
+
+/// Show the numbered choices and read the number of one, or an empty line for
+/// the first. Returns the position of the choice.
+fn choose<R: BufRead>(input: &mut R, choices: &[String]) -> Result<usize> {
+    for (number, choice) in choices.iter().enumerate() {
+        println!("  {}) {choice}", number + 1);
+    }
+    loop {
+        print!(
+            "{} ",
+            Message::KeepWhich {
+                count: choices.len()
+            }
+            .text()
+        );
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Err(anyhow!("No choice was given"));
+        }
+        match line.trim() {
+            "" => return Ok(0),
+            answer => match answer.parse::<usize>() {
+                Ok(number) if (1..=choices.len()).contains(&number) => return Ok(number - 1),
+                _ => continue,
+            },
+        }
+    }
+}
@@ -0,0 +1,501 @@
+//! Commands that copy the contents of banks out to files.
+
+use std::ffi::OsString;
+use std::fs;
+use std::fs::File;
+use std::io::{BufRead, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::builder::{ArgAction, OsStringValueParser};
+use clap::{value_parser, Arg, ArgMatches, Command, ValueHint};
+use log::{debug, info, warn};
+
+use crate::messages::Message;
+use crate::{
+    copy_items, open_bank, parse_mode, parse_sha256, parse_size, read_metadata, save_bank,
+    x_decompress_arg,
+};
+use kibank::attributes::Attributes;
+use kibank::extract::ExtractOptions;
+use kibank::read::{BankReader, BankReaderOptions};
+use kibank::{attributes, catalog, extract};
+
+/// Options and arguments of `export`.
+pub fn export_command() -> Command {
+    Command::new("export")
+        .about("Copy a bank without some of its metadata, such as for customers")
+        .arg(
+            Arg::new("strip")
+                .help("Metadata fields to remove, such as hash,version,extra")
+                .long("strip")
+                .short('s')
+                .value_delimiter(',')
+                .action(ArgAction::Append)
+                .required(true),
+        )
+        .arg(
+            Arg::new("output")
+                .help("File name of the exported bank, instead of replacing the bank")
+                .long("output")
+                .short('o')
+                .value_hint(ValueHint::AnyPath)
+                .value_parser(OsStringValueParser::new())
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `extract`.
+pub fn extract_command() -> Command {
+    Command::new("extract")
+        .about("Extract the contents of a bank")
+        .visible_alias("x")
+        .arg(
+            Arg::new("dest")
+                .long("dest")
+                .short('d')
+                .value_hint(ValueHint::DirPath)
+                .value_parser(OsStringValueParser::new())
+                .help("Destination directory")
+                .required(false),
+        )
+        .arg(
+            Arg::new("hash")
+                .help("Only extract files with this SHA-256 digest, given more than once for any of several")
+                .long("hash")
+                .value_name("SHA256")
+                .action(ArgAction::Append)
+                .value_parser(parse_sha256),
+        )
+        .arg(
+            Arg::new("keep-going")
+                .help("Extract the other items when an item cannot be extracted")
+                .long("keep-going")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("max-size")
+                .help("Only extract files of at most this size, such as 10M")
+                .long("max-size")
+                .value_parser(parse_size),
+        )
+        .arg(
+            Arg::new("min-size")
+                .help("Only extract files of at least this size, such as 512K")
+                .long("min-size")
+                .value_parser(parse_size),
+        )
+        .arg(
+            Arg::new("no-preflight")
+                .help("Skip checking for enough free space and permission to write")
+                .long("no-preflight")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("preserve-times")
+                .help("Restore the modification times and permissions recorded in the bank")
+                .long("preserve-times")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("rename-template")
+                .help("Path to extract each file to, such as {kind}/{stem}_{bankid}.{ext}, with placeholders {path}, {dir}, {name}, {stem}, {ext}, {kind}, {bankid}, {bankname} and {bankauthor}")
+                .long("rename-template")
+                .value_name("TEMPLATE")
+                .num_args(1)
+                .conflicts_with("preserve-times"),
+        )
+        .arg(
+            Arg::new("tag-origin")
+                .help("Write the ID and name of the bank next to each preset, in a file ending with .origin.json")
+                .long("tag-origin")
+                .action(ArgAction::SetTrue),
+        )
+        .args(extract_file_args())
+        .mut_arg("mode", |arg| arg.conflicts_with("preserve-times"))
+        .arg(x_decompress_arg())
+        .arg(
+            Arg::new("BANK_FILE")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .help("File name of the bank")
+                .required(true),
+        )
+}
+
+/// Options and arguments of `extract-all`.
+pub fn extract_all_command() -> Command {
+    Command::new("extract-all")
+        .about("Extract every bank in a directory, each into a directory named by its ID")
+        .arg(
+            Arg::new("dest")
+                .long("dest")
+                .short('d')
+                .value_hint(ValueHint::DirPath)
+                .value_parser(OsStringValueParser::new())
+                .help("Destination directory")
+                .required(false),
+        )
+        .arg(
+            Arg::new("keep-going")
+                .help("Extract the other items of a bank when an item cannot be extracted")
+                .long("keep-going")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("tag-origin")
+                .help("Write the ID and name of the bank next to each preset, in a file ending with .origin.json")
+                .long("tag-origin")
+                .action(ArgAction::SetTrue),
+        )
+        .args(extract_file_args())
+        .arg(
+            Arg::new("threads")
+                .help("Number of banks to extract at once, 0 for one per CPU")
+                .long("threads")
+                .value_parser(value_parser!(usize))
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("DIR")
+                .help("Directory with the banks, which is searched recursively")
+                .value_hint(ValueHint::DirPath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Number of bytes read at a time when extracting many small items.
+const EXTRACT_READAHEAD_BYTES: usize = 4 * 1024 * 1024;
+
+/// Commands that need the `fuse` feature.
+#[cfg(all(feature = "fuse", target_os = "linux"))]
+pub fn mount_commands() -> Vec<Command> {
+    vec![Command::new("mount")
+        .about("Mount a bank as a read-only filesystem until Ctrl-C is pressed")
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("Bank file to mount")
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+        .arg(
+            Arg::new("MOUNTPOINT")
+                .help("Existing directory to mount the bank on")
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )]
+}
+
+#[cfg(not(all(feature = "fuse", target_os = "linux")))]
+pub fn mount_commands() -> Vec<Command> {
+    Vec::new()
+}
+
+/// Present a bank as a read-only filesystem, so its contents can be
+/// auditioned without extracting them.
+#[cfg(all(feature = "fuse", target_os = "linux"))]
+pub fn mount(args: &ArgMatches) -> Result<()> {
+    let bank_path = Path::new(args.get_one::<OsString>("BANK_FILE").expect("required"));
+    let mountpoint = Path::new(args.get_one::<OsString>("MOUNTPOINT").expect("required"));
+    let mount = kibank::fuse::mount(bank_path, mountpoint).with_context(|| {
+        format!(
+            "Cannot mount bank {} on {}",
+            bank_path.display(),
+            mountpoint.display()
+        )
+    })?;
+
+    // Unmounting makes serve return.
+    let handler_mountpoint = mountpoint.to_owned();
+    ctrlc::set_handler(move || {
+        if let Err(error) = kibank::fuse::unmount(&handler_mountpoint) {
+            eprintln!("Cannot unmount {}: {error}", handler_mountpoint.display());
+        }
+    })
+    .with_context(|| "Cannot handle Ctrl-C")?;
+
+    println!(
+        "{}",
+        Message::Mounted {
+            bank: &bank_path.display().to_string(),
+            mountpoint: &mountpoint.display().to_string(),
+        }
+        .text()
+    );
+    mount
+        .serve()
+        .with_context(|| format!("Cannot serve bank {}", bank_path.display()))
+}
+
+/// Options of the commands that extract files for how they are written.
+fn extract_file_args() -> Vec<Arg> {
+    vec![
+        Arg::new("fsync")
+            .help("Flush each file to the disk before extracting the next")
+            .long("fsync")
+            .action(ArgAction::SetTrue),
+        Arg::new("gid")
+            .help("Group ID to own the extracted files, on Unix")
+            .long("gid")
+            .value_parser(value_parser!(u32)),
+        Arg::new("mode")
+            .help("Permissions of the extracted files in octal, such as 644, on Unix")
+            .long("mode")
+            .value_parser(parse_mode),
+        Arg::new("uid")
+            .help("User ID to own the extracted files, on Unix, which usually needs root")
+            .long("uid")
+            .value_parser(value_parser!(u32)),
+    ]
+}
+
+/// Extract the contents of the bank. Existing files will be overwritten.
+pub fn extract(args: &ArgMatches) -> Result<()> {
+    // Default destination is the current directory
+    let dest_dir = match args.get_one::<OsString>("dest") {
+        None => std::env::current_dir()?,
+        Some(osstr) => PathBuf::from(osstr),
+    };
+    info!("Destination dir is {}", dest_dir.display());
+
+    // Open the bank
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+    let options = BankReaderOptions {
+        readahead_bytes: EXTRACT_READAHEAD_BYTES,
+        ..BankReaderOptions::default()
+    };
+    let mut reader = open_bank(args, bank_path, options)?;
+    if !args.get_flag("no-preflight") {
+        preflight(&reader, &dest_dir)?;
+    }
+
+    // The attributes are applied after every file is extracted.
+    let preserve_times = args.get_flag("preserve-times");
+    let mut options = ExtractOptions {
+        keep_going: args.get_flag("keep-going"),
+        rename_template: args.get_one::<String>("rename-template").cloned(),
+        tag_origin: args.get_flag("tag-origin"),
+        min_size: args.get_one::<u64>("min-size").copied(),
+        max_size: args.get_one::<u64>("max-size").copied(),
+        sha256: args
+            .get_many::<String>("hash")
+            .unwrap_or_default()
+            .cloned()
+            .collect(),
+        file_mode: args.get_one::<u32>("mode").copied(),
+        uid: args.get_one::<u32>("uid").copied(),
+        gid: args.get_one::<u32>("gid").copied(),
+        fsync: args.get_flag("fsync"),
+        x_decompress: args.get_flag("x-decompress"),
+        ..ExtractOptions::default()
+    };
+    if preserve_times {
+        options.skip.push(attributes::FILE_NAME.to_string());
+    }
+    let report = extract::extract_all(&mut reader, &dest_dir, &options).with_context(|| {
+        format!(
+            "Cannot extract bank {} to {}",
+            bank_path.display(),
+            dest_dir.display()
+        )
+    })?;
+    debug!("Extracted {} files", report.extracted.len());
+
+    if preserve_times {
+        match reader.get(attributes::FILE_NAME).cloned() {
+            None => warn!("No attributes were recorded in the bank"),
+            Some(item) => {
+                let json = reader.read_contents(&item)?;
+                let attributes = Attributes::parse(&json)
+                    .with_context(|| format!("Cannot read {}", attributes::FILE_NAME))?;
+                for (bank_path, file_attributes) in &attributes.files {
+                    // Only files that were extracted are changed.
+                    if reader.get(bank_path).is_none() {
+                        warn!("Skipping attributes of {bank_path} which is not in the bank");
+                        continue;
+                    }
+                    // Files that failed or were left out by the filters were
+                    // never written.
+                    let dest_path = extract::item_path(&dest_dir, bank_path.as_bytes())?;
+                    if !report.extracted.contains(&dest_path) {
+                        continue;
+                    }
+                    debug!("Restoring attributes of {}", dest_path.display());
+                    file_attributes.apply(&dest_path).with_context(|| {
+                        format!("Cannot restore the attributes of {}", dest_path.display())
+                    })?;
+                }
+            }
+        }
+    }
+
+    if !report.is_complete() {
+        for failure in &report.failed {
+            eprintln!(
+                "{}",
+                Message::CannotExtract {
+                    path: &failure.path,
+                    error: &failure.error.to_string(),
+                }
+                .text()
+            );
+        }
+        return Err(anyhow!(
+            "{} of {} items could not be extracted",
+            report.failed.len(),
+            report.failed.len() + report.extracted.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Extract every bank in a directory into its own directory.
+pub fn extract_all(args: &ArgMatches) -> Result<()> {
+    let dest_dir = match args.get_one::<OsString>("dest") {
+        None => std::env::current_dir()?,
+        Some(osstr) => PathBuf::from(osstr),
+    };
+    let dir = Path::new(
+        args.get_one::<OsString>("DIR")
+            .with_context(|| "Expected a directory")?,
+    );
+    let bank_paths = catalog::bank_paths(dir)
+        .with_context(|| format!("Cannot find the banks in {}", dir.display()))?;
+    info!(
+        "Extracting {} banks to {}",
+        bank_paths.len(),
+        dest_dir.display()
+    );
+
+    let options = ExtractOptions {
+        keep_going: args.get_flag("keep-going"),
+        tag_origin: args.get_flag("tag-origin"),
+        file_mode: args.get_one::<u32>("mode").copied(),
+        uid: args.get_one::<u32>("uid").copied(),
+        gid: args.get_one::<u32>("gid").copied(),
+        fsync: args.get_flag("fsync"),
+        ..ExtractOptions::default()
+    };
+    let threads = args
+        .get_one::<usize>("threads")
+        .copied()
+        .unwrap_or_default();
+    let extractions = extract::extract_banks(&bank_paths, &dest_dir, &options, threads)?;
+
+    let mut failed = 0;
+    for extraction in &extractions {
+        let bank = extraction.bank_path.display().to_string();
+        match &extraction.result {
+            Ok(report) => {
+                for failure in &report.failed {
+                    eprintln!(
+                        "{}",
+                        Message::CannotExtract {
+                            path: &format!("{bank}: {}", failure.path),
+                            error: &failure.error.to_string(),
+                        }
+                        .text()
+                    );
+                }
+                if !report.is_complete() {
+                    failed += 1;
+                }
+                let dir = extraction.dest_dir.display().to_string();
+                println!(
+                    "{}",
+                    Message::BankExtracted {
+                        bank: &bank,
+                        dir: &dir
+                    }
+                    .text()
+                );
+            }
+            Err(error) => {
+                failed += 1;
+                eprintln!(
+                    "{}",
+                    Message::CannotExtract {
+                        path: &bank,
+                        error: &error.to_string(),
+                    }
+                    .text()
+                );
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!(
+            "{failed} of {} banks could not be extracted",
+            extractions.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Copy a bank with some of the fields of its metadata removed.
+pub fn export(args: &ArgMatches) -> Result<()> {
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+    let mut reader = open_bank(args, bank_path, BankReaderOptions::default())?;
+
+    let mut metadata = read_metadata(&mut reader, bank_path)?;
+    for field in args.get_many::<String>("strip").unwrap_or_default() {
+        metadata
+            .strip(field.trim())
+            .with_context(|| format!("Cannot strip {field} from the metadata"))?;
+    }
+
+    let out_path = args
+        .get_one::<OsString>("output")
+        .map_or_else(|| bank_path.to_owned(), PathBuf::from);
+    save_bank(&out_path, |writer| {
+        writer.add_metadata(&metadata)?;
+        copy_items(&mut reader, writer)
+    })
+}
+
+/// Fail before anything is extracted if the destination directory cannot be
+/// written to or doesn't have enough free space for every file in the bank,
+/// rather than partway through extracting a large bank.
+fn preflight<ReaderType: Read + Seek + BufRead>(
+    reader: &BankReader<ReaderType>,
+    dest_dir: &Path,
+) -> Result<()> {
+    let needed = reader
+        .items()
+        .iter()
+        .filter(|item| item.is_file())
+        .map(|item| item.size())
+        .sum::<u64>();
+
+    fs::create_dir_all(dest_dir)
+        .with_context(|| format!("Cannot create directory {}", dest_dir.display()))?;
+    let probe_path = dest_dir.join(format!(".kibank-preflight-{}", std::process::id()));
+    File::create(&probe_path)
+        .and_then(|_| fs::remove_file(&probe_path))
+        .with_context(|| format!("Cannot write to {}", dest_dir.display()))?;
+
+    let available = fs4::available_space(dest_dir)
+        .with_context(|| format!("Cannot find the free space of {}", dest_dir.display()))?;
+    debug!("Extracting {needed} bytes with {available} bytes free");
+    if needed > available {
+        return Err(anyhow!(
+            "Extracting needs {needed} bytes but {} only has {available} bytes free",
+            dest_dir.display()
+        ));
+    }
+    Ok(())
+}
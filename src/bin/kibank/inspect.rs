@@ -0,0 +1,1122 @@
+//! Commands that display or check banks without changing them.
+
+use std::ffi::OsString;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{anyhow, Context, Result};
+use clap::builder::{ArgAction, OsStringValueParser};
+use clap::{Arg, ArgMatches, Command, ValueHint};
+use log::{debug, warn};
+use serde::Serialize;
+
+#[cfg(feature = "schema")]
+use crate::create::CreateSummary;
+use crate::messages::{Lang, Message};
+use crate::{algo_arg, algorithm, open_bank, read_metadata, save_stats, stats_file_arg};
+use kibank::annotations::Annotations;
+use kibank::attributes::{Attributes, FileAttributes};
+use kibank::background::BackgroundDetails;
+use kibank::catalog::{BankSummary, Catalog, Duplicate};
+use kibank::compat::Version;
+use kibank::diff::{self, BankDiff};
+use kibank::hash::{Algorithm, Checksums};
+use kibank::inspect::InspectOptions;
+use kibank::lint::{Level, LintConfig};
+use kibank::names::natural_cmp;
+use kibank::previews::Previews;
+use kibank::read::{BankReader, BankReaderOptions, Item};
+use kibank::stats::{Outcome, Stats};
+use kibank::verify::VerifyOptions;
+#[cfg(feature = "schema")]
+use kibank::Metadata;
+use kibank::{
+    attributes, catalog, compat, extract, inspect, lint, verify, ItemKind, Product, Provenance,
+    PATH_SEPARATOR,
+};
+
+/// Options and arguments of `checksum`.
+pub fn checksum_command() -> Command {
+    Command::new("checksum")
+        .about("Show the digest of every file in a bank")
+        .arg(algo_arg())
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `compat`.
+pub fn compat_command() -> Command {
+    Command::new("compat")
+        .about("List the presets of a bank saved with a newer version than a target")
+        .arg(
+            Arg::new("against")
+                .help("Version of the Kilohearts products to compare with, such as 2.1")
+                .long("against")
+                .value_name("VERSION")
+                .value_parser(|text: &str| text.parse::<Version>())
+                .required(true),
+        )
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `diff`.
+pub fn diff_command() -> Command {
+    Command::new("diff")
+        .about("Compare a bank with another bank, or with a directory of files such as where it was extracted")
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+        .arg(
+            Arg::new("OTHER")
+                .help("Bank or directory to compare the bank with")
+                .value_hint(ValueHint::AnyPath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `info`.
+pub fn info_command() -> Command {
+    Command::new("info")
+        .about("Display the details of a bank")
+        .visible_alias("i")
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::AnyPath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+        .arg(
+            Arg::new("changelog")
+                .help("Display the changelog of the bank after the details")
+                .long("changelog")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("bank-lang")
+                .help("Language tag of the translation of the name and description to show, such as ja, instead of the language of the output")
+                .long("bank-lang")
+                .value_name("TAG"),
+        )
+        .arg(
+            Arg::new("raw")
+                .help("Display the metadata file exactly as it is stored instead")
+                .long("raw")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("changelog"),
+        )
+}
+
+/// Options and arguments of `inspect`.
+pub fn inspect_command() -> Command {
+    Command::new("inspect")
+        .about("Check a bank from an untrusted source for anything harmful")
+        .arg(
+            Arg::new("sandbox")
+                .help("Limit the size of the bank and its items and only read plain banks")
+                .long("sandbox")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `kinds`.
+pub fn kinds_command() -> Command {
+    Command::new("kinds")
+        .about("List the types of files that can be in a bank")
+        .arg(
+            Arg::new("json")
+                .help("Write the types as JSON")
+                .long("json")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+/// Options and arguments of `lint`.
+pub fn lint_command() -> Command {
+    Command::new("lint")
+        .about("Check a bank against policies for its contents")
+        .arg(
+            Arg::new("config")
+                .help("TOML file with the levels of rules and limits")
+                .long("config")
+                .short('c')
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `list`.
+pub fn list_command() -> Command {
+    Command::new("list")
+        .about("Display the contents of a bank")
+        .visible_alias("l")
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File name of the bank")
+                .value_hint(ValueHint::AnyPath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+        .arg(
+            Arg::new("natural-order")
+                .help("Sort the items in natural order, with Preset 2 before Preset 10")
+                .long("natural-order")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("annotations")
+                .help("Show the annotations of each item from the bank or the file next to it")
+                .long("annotations")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("previews")
+                .help("Only list the presets, each with its preview")
+                .long("previews")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["natural-order", "annotations"]),
+        )
+}
+
+/// Options and arguments of `scan`.
+pub fn scan_command() -> Command {
+    Command::new("scan")
+        .about("Summarize every bank in a directory")
+        .arg(
+            Arg::new("table")
+                .help("Display a table instead of JSON")
+                .long("table")
+                .short('t')
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("sort")
+                .help("Order of the banks")
+                .long("sort")
+                .short('s')
+                .value_parser(["path", "name", "author", "id", "items", "size"])
+                .default_value("path"),
+        )
+        .arg(
+            Arg::new("filter")
+                .help("Only include banks where the field contains the text, like author=name")
+                .long("filter")
+                .short('f')
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("find-duplicates")
+                .help("Report banks with the same contents or the same ID")
+                .long("find-duplicates")
+                .short('D')
+                .action(ArgAction::SetTrue),
+        )
+        .args(scan_image_args())
+        .args(scan_sqlite_args())
+        .arg(stats_file_arg())
+        .arg(
+            Arg::new("DIR")
+                .help("Directory to search for banks")
+                .value_hint(ValueHint::DirPath)
+                .value_parser(OsStringValueParser::new())
+                .required(true),
+        )
+}
+
+/// Options and arguments of `verify`.
+pub fn verify_command() -> Command {
+    Command::new("verify")
+        .about("Check that a bank is well formed")
+        .visible_alias("v")
+        .arg(
+            Arg::new("deep")
+                .help("Read the contents of every item")
+                .long("deep")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("roundtrip")
+                .help("Rebuild the bank in memory and report any differences")
+                .long("roundtrip")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("strict-metadata")
+                .help("Fail if the metadata breaks the rules followed by Kilohearts")
+                .long("strict-metadata")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("recursive")
+                .help("Verify every bank in the directories and their subdirectories")
+                .long("recursive")
+                .short('r')
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("fail-fast")
+                .help("Stop at the first bank that is not valid")
+                .long("fail-fast")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("checksums")
+                .help("Compare every file with the digests written by checksum")
+                .long("checksums")
+                .value_name("CHECKSUMS_FILE")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new()),
+        )
+        .arg(algo_arg().requires("checksums"))
+        .arg(stats_file_arg())
+        .arg(
+            Arg::new("BANK_FILE")
+                .help("File names of the banks, or directories with --recursive")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .num_args(1..)
+                .required(true),
+        )
+}
+
+/// Names of the JSON written by the commands that have a schema.
+#[cfg(feature = "schema")]
+const SCHEMA_NAMES: [&str; 4] = ["catalog", "create-summary", "metadata", "stats"];
+
+/// Commands that need the `schema` feature.
+#[cfg(feature = "schema")]
+pub fn schema_commands() -> Vec<Command> {
+    vec![Command::new("schema")
+        .about("Display the JSON Schema of JSON written by other commands")
+        .arg(
+            Arg::new("NAME")
+                .help("catalog for scan, create-summary for create --summary json, metadata for index.json, or stats for --stats-file")
+                .value_parser(SCHEMA_NAMES)
+                .required(true),
+        )]
+}
+
+#[cfg(not(feature = "schema"))]
+pub fn schema_commands() -> Vec<Command> {
+    Vec::new()
+}
+
+/// Display the JSON Schema of the JSON written by a command, so other
+/// programs can check what they read.
+#[cfg(feature = "schema")]
+pub fn schema(args: &ArgMatches) -> Result<()> {
+    let schema = match args.get_one::<String>("NAME").map(String::as_str) {
+        Some("catalog") => schemars::schema_for!(Catalog),
+        Some("create-summary") => schemars::schema_for!(CreateSummary),
+        Some("metadata") => schemars::schema_for!(Metadata),
+        Some("stats") => schemars::schema_for!(Stats),
+        _ => return Err(anyhow!("Expected the name of a schema")),
+    };
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
+
+/// Options of `scan` that need the `image` feature.
+#[cfg(feature = "image")]
+fn scan_image_args() -> Vec<Arg> {
+    vec![Arg::new("thumbnails")
+        .help("Write thumbnails of the backgrounds to the directory and list them in the catalog")
+        .long("thumbnails")
+        .value_name("DIR")
+        .value_hint(ValueHint::DirPath)
+        .value_parser(OsStringValueParser::new())
+        .conflicts_with_all(["table", "find-duplicates"])]
+}
+
+#[cfg(not(feature = "image"))]
+fn scan_image_args() -> Vec<Arg> {
+    Vec::new()
+}
+
+/// Options of `scan` that need the `sqlite` feature.
+#[cfg(feature = "sqlite")]
+fn scan_sqlite_args() -> Vec<Arg> {
+    vec![Arg::new("sqlite")
+        .help("Write the banks and their items to a SQLite database instead")
+        .long("sqlite")
+        .value_name("DB_FILE")
+        .value_hint(ValueHint::FilePath)
+        .value_parser(OsStringValueParser::new())
+        .conflicts_with_all(["table", "find-duplicates"])]
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn scan_sqlite_args() -> Vec<Arg> {
+    Vec::new()
+}
+
+/// Show the digest of every file in a bank.
+pub fn checksum(args: &ArgMatches) -> Result<()> {
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+    let mut reader = open_bank(args, bank_path, BankReaderOptions::default())?;
+    let checksums = Checksums::of_bank(&mut reader, algorithm(args)?)
+        .with_context(|| format!("Cannot read bank {}", bank_path.display()))?;
+    print!("{checksums}");
+    Ok(())
+}
+
+/// List the presets saved with a version newer than the target. Only the
+/// versions written as text in the headers of the presets are found.
+pub fn compat(args: &ArgMatches) -> Result<()> {
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+    let target = args
+        .get_one::<Version>("against")
+        .copied()
+        .with_context(|| "Expected a version")?;
+    let mut reader = open_bank(args, bank_path, BankReaderOptions::default())?;
+    let presets = compat::preset_versions(&mut reader)
+        .with_context(|| format!("Cannot read bank {}", bank_path.display()))?;
+
+    let newer = presets
+        .iter()
+        .filter(|preset| preset.is_newer_than(target))
+        .collect::<Vec<_>>();
+    for preset in &newer {
+        if let Some(version) = preset.version {
+            println!("{} {version}", preset.path);
+        }
+    }
+    let summary = Message::PresetsNewer {
+        count: newer.len(),
+        target: &target.to_string(),
+        unknown: presets
+            .iter()
+            .filter(|preset| preset.version.is_none())
+            .count(),
+    };
+    println!("{}", summary.text());
+    Ok(())
+}
+
+/// Display the files that are only in the bank, only in the directory or
+/// different, and for those that are different whether the bank or the
+/// directory is newer. Nothing is changed.
+pub fn diff(args: &ArgMatches) -> Result<()> {
+    let bank_path = Path::new(
+        args.get_one::<OsString>("BANK_FILE")
+            .with_context(|| "Expected a bank file name")?,
+    );
+    let other = Path::new(
+        args.get_one::<OsString>("OTHER")
+            .with_context(|| "Expected a bank or a directory")?,
+    );
+    let mut reader = open_bank(args, bank_path, BankReaderOptions::default())?;
+    if !other.is_dir() {
+        return diff_banks(args, &mut reader, bank_path, other);
+    }
+    let dir = other;
+    let diff = BankDiff::with_directory(&mut reader, dir).with_context(|| {
+        format!(
+            "Cannot compare bank {} with {}",
+            bank_path.display(),
+            dir.display()
+        )
+    })?;
+
+    // The bank only knows when each item was modified if it was created with
+    // --preserve-times, otherwise the bank itself was modified then.
+    let attributes = match reader.get(attributes::FILE_NAME).cloned() {
+        Some(item) => Attributes::parse(&reader.read_contents(&item)?)
+            .with_context(|| format!("Cannot read {}", attributes::FILE_NAME))?,
+        None => Attributes::default(),
+    };
+    let bank_modified = FileAttributes::from_path(bank_path)
+        .ok()
+        .and_then(|file_attributes| file_attributes.modified);
+
+    for (sign, path) in diff.entries() {
+        print!("{sign} {path}");
+        if sign == '~' {
+            let item_modified = attributes
+                .files
+                .get(path)
+                .and_then(|file_attributes| file_attributes.modified)
+                .or(bank_modified);
+            let file_modified =
+                FileAttributes::from_path(extract::item_path(dir, path.as_bytes())?)
+                    .ok()
+                    .and_then(|file_attributes| file_attributes.modified);
+            match (item_modified, file_modified) {
+                (Some(item), Some(file)) if item > file => {
+                    print!(" ({})", Message::NewerInBank.text());
+                }
+                (Some(item), Some(file)) if file > item => {
+                    print!(" ({})", Message::NewerInDirectory.text());
+                }
+                _ => {}
+            }
+        }
+        println!();
+    }
+    let summary = Message::DiffSummary {
+        added: diff.added.len(),
+        removed: diff.removed.len(),
+        changed: diff.changed.len(),
+        unchanged: diff.unchanged,
+    };
+    println!("{}", summary.text());
+    Ok(())
+}
+
+/// Compare the items and metadata of two banks, such as a rebuilt bank with
+/// the original release.
+fn diff_banks<ReaderType: Read + Seek + BufRead>(
+    args: &ArgMatches,
+    reader: &mut BankReader<ReaderType>,
+    bank_path: &Path,
+    other_path: &Path,
+) -> Result<()> {
+    let mut other = open_bank(args, other_path, BankReaderOptions::default())?;
+    let context = || {
+        format!(
+            "Cannot compare bank {} with {}",
+            bank_path.display(),
+            other_path.display()
+        )
+    };
+    let diff = BankDiff::between(reader, &mut other).with_context(context)?;
+    let differences = diff::metadata_differences(reader, &mut other).with_context(context)?;
+
+    print!("{diff}");
+    for (field, change) in &differences {
+        let message = Message::MetadataChanged {
+            field,
+            from: &change.from.to_string(),
+            to: &change.to.to_string(),
+        };
+        println!("{}", message.text());
+    }
+    let summary = Message::DiffSummary {
+        added: diff.added.len(),
+        removed: diff.removed.len(),
+        changed: diff.changed.len(),
+        unchanged: diff.unchanged,
+    };
+    println!("{}", summary.text());
+    Ok(())
+}
+
+/// Display the bank metadata.
+pub fn info(args: &ArgMatches) -> Result<()> {
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+    let mut reader = open_bank(args, bank_path, BankReaderOptions::default())?;
+
+    if args.get_flag("raw") {
+        let item = reader
+            .items()
+            .into_iter()
+            .find(Item::is_metadata_file)
+            .with_context(|| Message::NoMetadataItem.text())?;
+        let json = reader.read_metadata_raw(&item).with_context(|| {
+            format!("Cannot read the metadata for bank {}", bank_path.display())
+        })?;
+        io::stdout().write_all(&json)?;
+        return Ok(());
+    }
+
+    // The name and description are shown in the language asked for, or
+    // else the language of the output, when the bank has a translation.
+    let metadata = read_metadata(&mut reader, bank_path)?;
+    let lang = Lang::current().tag();
+    let bank_lang = args.get_one::<String>("bank-lang").map(String::as_str);
+    let name = bank_lang
+        .map(|tag| metadata.localized_name(tag))
+        .filter(|name| *name != metadata.name)
+        .unwrap_or_else(|| metadata.localized_name(lang));
+    let description = bank_lang
+        .map(|tag| metadata.localized_description(tag))
+        .filter(|description| *description != metadata.description)
+        .unwrap_or_else(|| metadata.localized_description(lang));
+    if !reader.items().iter().any(Item::is_metadata_file) {
+        println!("{}", Message::NoMetadataItem.text());
+    }
+    println!("{}: {}", Message::Id.text(), metadata.id);
+    println!("{}: {name}", Message::Name.text());
+    println!("{}: {}", Message::Author.text(), metadata.author);
+    println!("{}: {description}", Message::Description.text());
+    println!(
+        "{}: {}",
+        Message::Version.text(),
+        metadata.version.unwrap_or_default()
+    );
+    println!(
+        "{}: {}",
+        Message::Hash.text(),
+        metadata.hash.as_deref().unwrap_or_default()
+    );
+    let presets = Product::count_presets(
+        reader
+            .items()
+            .iter()
+            .filter(|item| item.is_file())
+            .filter_map(|item| ItemKind::from(Path::new(&item.file_name_lossy()))),
+    );
+    if !presets.is_empty() {
+        let counts = presets
+            .iter()
+            .map(|(product, count)| format!("{product}: {count}"))
+            .collect::<Vec<_>>();
+        println!("{}: {}", Message::Presets.text(), counts.join(", "));
+    }
+    if let Some(provenance) = metadata.provenance() {
+        let created = Message::Created {
+            time: &provenance.created_utc().to_string(),
+            tool: &provenance.tool,
+            version: &provenance.tool_version,
+        };
+        println!("{}", created.text());
+        println!("{}: {}", Message::SourceHash.text(), provenance.source_hash);
+    }
+    for extra in metadata.extra {
+        if extra.0 != Provenance::KEY {
+            println!("{}: {}: {}", Message::Extra.text(), extra.0, extra.1);
+        }
+    }
+    match BackgroundDetails::of_bank(&mut reader)
+        .with_context(|| format!("Cannot read the background of {}", bank_path.display()))?
+    {
+        None => println!("{}: {}", Message::Background.text(), Message::No.text()),
+        Some(background) => {
+            println!("{}: {}", Message::Background.text(), background.path);
+            let format = background
+                .format
+                .map_or_else(|| Message::UnknownType.text(), |format| format.to_string());
+            println!("{}: {format}", Message::BackgroundFormat.text());
+            if let Some((width, height)) = background.dimensions {
+                println!("{}: {width}x{height}", Message::BackgroundDimensions.text());
+            }
+            println!(
+                "{}: {} bytes",
+                Message::BackgroundSize.text(),
+                background.size
+            );
+        }
+    }
+
+    if args.get_flag("changelog") {
+        let item = reader
+            .items()
+            .into_iter()
+            .find(Item::is_changelog_file)
+            .with_context(|| format!("Bank {} has no changelog", bank_path.display()))?;
+        let contents = reader.read_contents(&item)?;
+        println!();
+        print!("{}", String::from_utf8_lossy(&contents));
+    }
+    Ok(())
+}
+
+/// Report anything in a bank that could harm the tools that read it. In the
+/// sandbox the bank is read within limits and compressed, encrypted and
+/// split banks are not read at all.
+pub fn inspect(args: &ArgMatches) -> Result<()> {
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+
+    let report = if args.get_flag("sandbox") {
+        inspect::inspect_file(bank_path, &InspectOptions::sandbox())
+            .with_context(|| format!("Cannot open bank {}", bank_path.display()))?
+    } else {
+        let reader = open_bank(args, bank_path, BankReaderOptions::default())?;
+        inspect::inspect_bank(&reader, &InspectOptions::default())
+    };
+    for issue in &report.issues {
+        println!("{issue}");
+    }
+
+    let bank = bank_path.display().to_string();
+    if report.is_ok() {
+        println!("{}", Message::BankOk { bank: &bank }.text());
+        Ok(())
+    } else {
+        Err(anyhow!(Message::BankNotValid { bank: &bank }.text()))
+    }
+}
+
+/// A type of file that can be in a bank, as listed by `kinds --json`.
+#[derive(Serialize)]
+struct KindSummary {
+    kind: String,
+    label: &'static str,
+    extensions: Vec<&'static str>,
+    directory: Option<&'static str>,
+}
+
+/// List every type of file that can be in a bank, so people can check that
+/// their files will be included by `create`.
+pub fn kinds(args: &ArgMatches) -> Result<()> {
+    let kinds = ItemKind::all()
+        .into_iter()
+        .map(|kind| KindSummary {
+            kind: format!("{kind:?}"),
+            label: kind.label(),
+            extensions: kind.extensions(),
+            directory: kind.directory(),
+        })
+        .collect::<Vec<_>>();
+    if args.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&kinds)?);
+        return Ok(());
+    }
+
+    let rows = kinds
+        .into_iter()
+        .map(|kind| {
+            vec![
+                kind.kind,
+                kind.label.to_string(),
+                kind.extensions.join(", "),
+                kind.directory.unwrap_or_default().to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let headings = [
+        Message::Kind,
+        Message::Label,
+        Message::Extensions,
+        Message::Directory,
+    ]
+    .map(|heading| heading.text());
+    let headings = headings.iter().map(String::as_str).collect::<Vec<_>>();
+    print_table(&headings, &rows);
+    Ok(())
+}
+
+/// Check the bank against the lint rules. Fails if any rule at the error level is broken.
+pub fn lint(args: &ArgMatches) -> Result<()> {
+    let config = match args.get_one::<OsString>("config") {
+        Some(config_path) => {
+            let config_path = Path::new(config_path);
+            let text = fs::read_to_string(config_path)
+                .with_context(|| format!("Cannot read {}", config_path.display()))?;
+            toml::from_str(&text)
+                .with_context(|| format!("Cannot parse lint config {}", config_path.display()))?
+        }
+        None => LintConfig::default(),
+    };
+
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+    let mut reader = open_bank(args, bank_path, BankReaderOptions::default())?;
+
+    let findings = lint::lint(&mut reader, &config)
+        .with_context(|| format!("Cannot lint bank {}", bank_path.display()))?;
+    for finding in &findings {
+        println!("{finding}");
+    }
+
+    let error_count = findings
+        .iter()
+        .filter(|finding| finding.level == Level::Error)
+        .count();
+    if error_count > 0 {
+        let message = Message::LintErrors {
+            bank: &bank_path.display().to_string(),
+            count: error_count,
+        };
+        Err(anyhow!(message.text()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Display the contents of the bank including directories.
+pub fn list(args: &ArgMatches) -> Result<()> {
+    let bank_file_name = args
+        .get_one::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?;
+    let bank_path = Path::new(bank_file_name);
+    let mut reader = open_bank(args, bank_path, BankReaderOptions::default())?;
+    let annotations = if args.get_flag("annotations") {
+        read_annotations(&mut reader, bank_path)?
+    } else {
+        None
+    };
+
+    if args.get_flag("previews") {
+        let previews = Previews::pair(&reader.items());
+        for (preset, preview) in &previews.presets {
+            print!("{}", preset.file_name_lossy());
+            if let Some(preview) = preview {
+                print!("  {}", preview.file_name_lossy());
+            }
+            println!();
+        }
+        for preview in &previews.unmatched {
+            warn!(
+                "Preview {} is not named after any preset",
+                preview.file_name_lossy()
+            );
+        }
+        return Ok(());
+    }
+
+    let mut items = reader.items();
+    if args.get_flag("natural-order") {
+        items.sort_by(|a, b| natural_cmp(a.path_bytes(), b.path_bytes()));
+    }
+    for item in &items {
+        let path = item.file_name_lossy();
+        print!("{path}");
+        if item.is_directory() {
+            // Add a trailing slash that matches what is found in the banks,
+            // not what's used by the operating system.
+            print!("{PATH_SEPARATOR}");
+        }
+        if let Some(annotation) = annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(&path))
+        {
+            print!("  {annotation}");
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+/// The annotations in the bank, or those in the file next to the bank if the
+/// bank has none.
+fn read_annotations<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    bank_path: &Path,
+) -> Result<Option<Annotations>> {
+    if let Some(annotations) = Annotations::read(reader).with_context(|| {
+        format!(
+            "Cannot read the annotations in bank {}",
+            bank_path.display()
+        )
+    })? {
+        return Ok(Some(annotations));
+    }
+    let sidecar_path = Annotations::sidecar_path(bank_path);
+    if !sidecar_path.exists() {
+        warn!("No annotations were found for {}", bank_path.display());
+        return Ok(None);
+    }
+    debug!("Reading annotations from {}", sidecar_path.display());
+    let json = fs::read(&sidecar_path)
+        .with_context(|| format!("Cannot read {}", sidecar_path.display()))?;
+    let annotations = Annotations::parse(&json)
+        .with_context(|| format!("Cannot read annotations {}", sidecar_path.display()))?;
+    Ok(Some(annotations))
+}
+
+/// Summarize every bank in a directory.
+pub fn scan(args: &ArgMatches) -> Result<()> {
+    let dir = Path::new(
+        args.get_one::<OsString>("DIR")
+            .with_context(|| "Expected a directory")?,
+    );
+    let mut stats = args.contains_id("stats-file").then(Stats::default);
+    let mut catalog = Catalog::scan_with(dir, stats.as_mut())
+        .with_context(|| format!("Cannot scan {}", dir.display()))?;
+    save_stats(args, stats.as_ref())?;
+
+    // Filters are case-insensitive.
+    for filter in args.get_many::<String>("filter").unwrap_or_default() {
+        let (field, text) = filter
+            .split_once('=')
+            .with_context(|| format!("Filter {filter} is not in the form field=text"))?;
+        let text = text.to_lowercase();
+        let field_value = match field {
+            "name" => |bank: &BankSummary| bank.name.clone(),
+            "author" => |bank: &BankSummary| bank.author.clone(),
+            "id" => |bank: &BankSummary| bank.id.clone(),
+            "description" => |bank: &BankSummary| bank.description.clone(),
+            _ => return Err(anyhow!("Cannot filter by unknown field {field}")),
+        };
+        catalog
+            .banks
+            .retain(|bank| field_value(bank).to_lowercase().contains(&text));
+    }
+
+    match args.get_one::<String>("sort").map(String::as_str) {
+        Some("name") => catalog.banks.sort_by_key(|bank| bank.name.to_lowercase()),
+        Some("author") => catalog.banks.sort_by_key(|bank| bank.author.to_lowercase()),
+        Some("id") => catalog.banks.sort_by(|a, b| a.id.cmp(&b.id)),
+        Some("items") => catalog.banks.sort_by_key(|bank| bank.item_count),
+        Some("size") => catalog.banks.sort_by_key(|bank| bank.size),
+        _ => catalog.banks.sort_by(|a, b| a.path.cmp(&b.path)),
+    }
+
+    #[cfg(feature = "image")]
+    if let Some(thumbnails_dir) = args.get_one::<OsString>("thumbnails") {
+        let thumbnails_dir = Path::new(thumbnails_dir);
+        catalog
+            .add_thumbnails(thumbnails_dir, kibank::background::thumbnail::SIZE)
+            .with_context(|| format!("Cannot write thumbnails to {}", thumbnails_dir.display()))?;
+    }
+
+    #[cfg(feature = "sqlite")]
+    if let Some(db_path) = args.get_one::<OsString>("sqlite") {
+        let db_path = Path::new(db_path);
+        kibank::sqlite::export(&catalog, db_path)
+            .with_context(|| format!("Cannot write the catalog to {}", db_path.display()))?;
+        return Ok(());
+    }
+
+    if args.get_flag("find-duplicates") {
+        // The fingerprints are only compared with each other so the fastest
+        // hash function is used.
+        catalog
+            .fingerprint_with(Algorithm::Blake3)
+            .with_context(|| format!("Cannot fingerprint the banks in {}", dir.display()))?;
+        for duplicate in catalog.duplicates() {
+            let (description, paths) = match duplicate {
+                Duplicate::SameContents { paths, .. } => (Message::SameContents.text(), paths),
+                Duplicate::SameId { id, paths } => (Message::SameId { id: &id }.text(), paths),
+            };
+            println!("{description}:");
+            for path in paths {
+                println!("  {}", path.display());
+            }
+        }
+    } else if args.get_flag("table") {
+        let rows = catalog
+            .banks
+            .iter()
+            .map(|bank| {
+                vec![
+                    bank.name.clone(),
+                    bank.author.clone(),
+                    bank.id.clone(),
+                    bank.item_count.to_string(),
+                    bank.size.to_string(),
+                    if bank.hash.is_some() {
+                        Message::Yes
+                    } else {
+                        Message::No
+                    }
+                    .text(),
+                    bank.path.display().to_string(),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let headings = [
+            Message::Name,
+            Message::Author,
+            Message::Id,
+            Message::Items,
+            Message::Size,
+            Message::Hash,
+            Message::Path,
+        ]
+        .map(|heading| heading.text());
+        let headings = headings.iter().map(String::as_str).collect::<Vec<_>>();
+        print_table(&headings, &rows);
+        for unreadable in &catalog.unreadable {
+            let message = Message::CannotRead {
+                path: &unreadable.path.display().to_string(),
+                error: &unreadable.error,
+            };
+            eprintln!("{}", message.text());
+        }
+    } else {
+        println!("{}", serde_json::to_string_pretty(&catalog)?);
+    }
+    Ok(())
+}
+
+/// Check a bank is well formed. Fails if there are any errors.
+pub fn verify(args: &ArgMatches) -> Result<()> {
+    let mut bank_paths = Vec::new();
+    for bank_file_name in args
+        .get_many::<OsString>("BANK_FILE")
+        .with_context(|| "Expected a bank file name")?
+    {
+        let path = PathBuf::from(bank_file_name);
+        if args.get_flag("recursive") {
+            let paths = catalog::bank_paths(&path)
+                .with_context(|| format!("Cannot find the banks in {}", path.display()))?;
+            bank_paths.extend(paths);
+        } else {
+            bank_paths.push(path);
+        }
+    }
+    let batch = args.get_flag("recursive") || bank_paths.len() > 1;
+    let checksums = match args.get_one::<OsString>("checksums") {
+        Some(checksums_path) => {
+            let checksums_path = Path::new(checksums_path);
+            let text = fs::read_to_string(checksums_path)
+                .with_context(|| format!("Cannot read {}", checksums_path.display()))?;
+            let checksums = Checksums::parse(&text, algorithm(args)?)
+                .with_context(|| format!("Cannot read {}", checksums_path.display()))?;
+            Some(checksums)
+        }
+        None => None,
+    };
+    let options = VerifyOptions {
+        checksums,
+        deep: args.get_flag("deep"),
+        roundtrip: args.get_flag("roundtrip"),
+        strict_metadata: args.get_flag("strict-metadata"),
+        ..VerifyOptions::default()
+    };
+
+    // Every bank is verified even when one cannot be read, unless there is
+    // only one or verification stops at the first failure.
+    let mut stats = args.contains_id("stats-file").then(Stats::default);
+    let mut passed = 0;
+    let mut invalid = Vec::new();
+    for bank_path in &bank_paths {
+        let start = Instant::now();
+        let result = verify_bank(args, bank_path, &options);
+        if let Some(stats) = stats.as_mut() {
+            let (outcome, error) = match &result {
+                Ok(true) => (Outcome::Ok, None),
+                Ok(false) => (Outcome::Invalid, None),
+                Err(error) => (Outcome::Unreadable, Some(format!("{error:#}"))),
+            };
+            stats.record(bank_path, start.elapsed(), outcome, error);
+        }
+        match result {
+            Ok(true) => passed += 1,
+            Ok(false) => invalid.push(bank_path.display().to_string()),
+            Err(error) if !batch => {
+                save_stats(args, stats.as_ref())?;
+                return Err(error);
+            }
+            Err(error) => {
+                eprintln!("{error:#}");
+                invalid.push(bank_path.display().to_string());
+            }
+        }
+        if !invalid.is_empty() && args.get_flag("fail-fast") {
+            break;
+        }
+    }
+    save_stats(args, stats.as_ref())?;
+
+    if batch {
+        let summary = Message::VerifySummary {
+            passed,
+            failed: invalid.len(),
+        };
+        println!("{}", summary.text());
+        for bank in &invalid {
+            println!("  {bank}");
+        }
+    }
+
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        let bank = invalid.join(", ");
+        Err(anyhow!(Message::BankNotValid { bank: &bank }.text()))
+    }
+}
+
+/// Verify one bank and show its issues. Returns whether the bank is valid.
+fn verify_bank(args: &ArgMatches, bank_path: &Path, options: &VerifyOptions) -> Result<bool> {
+    let mut reader = match open_bank(args, bank_path, BankReaderOptions::default()) {
+        Ok(reader) => reader,
+        Err(error) => {
+            // The error only has the first problem with the header, so show
+            // every problem when there is more than one.
+            if let Ok(file) = File::open(bank_path) {
+                let (_, issues) = BankReader::new_collecting(BufReader::new(file));
+                if issues.len() > 1 {
+                    for issue in issues {
+                        println!("{}: {issue}", Level::Error);
+                    }
+                }
+            }
+            return Err(error);
+        }
+    };
+    let report = verify::verify(&mut reader, options)
+        .with_context(|| format!("Cannot verify bank {}", bank_path.display()))?;
+    for issue in &report.issues {
+        println!("{issue}");
+    }
+    if let Some(fingerprint) = &report.fingerprint {
+        println!("{}: {fingerprint}", Message::Fingerprint.text());
+    }
+
+    if report.is_ok() {
+        let bank = bank_path.display().to_string();
+        println!("{}", Message::BankOk { bank: &bank }.text());
+    }
+    Ok(report.is_ok())
+}
+
+/// Print rows of text in columns aligned to the widest value.
+fn print_table(headings: &[&str], rows: &[Vec<String>]) {
+    let mut widths = headings
+        .iter()
+        .map(|heading| heading.chars().count())
+        .collect::<Vec<_>>();
+    for row in rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+
+    let print_row = |values: &mut dyn Iterator<Item = &str>| {
+        let line = values
+            .zip(&widths)
+            .map(|(value, width)| format!("{value:width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    };
+    print_row(&mut headings.iter().copied());
+    for row in rows {
+        print_row(&mut row.iter().map(String::as_str));
+    }
+}
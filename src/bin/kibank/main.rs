@@ -0,0 +1,476 @@
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use clap::builder::{ArgAction, OsStringValueParser};
+use clap::{
+    crate_authors, crate_description, crate_name, crate_version, Arg, ArgMatches, Command,
+    ValueHint,
+};
+use log::{debug, info, warn, LevelFilter};
+use os_str_bytes::OsStrBytes;
+use simplelog::{ColorChoice, ConfigBuilder, TermLogger, TerminalMode};
+
+use crate::messages::{Lang, Message};
+use kibank::bank;
+use kibank::cancel::CancellationToken;
+use kibank::compress::Compression;
+use kibank::diff::BankDiff;
+use kibank::hash::Algorithm;
+use kibank::read::{BankReader, BankReaderOptions};
+use kibank::stats::Stats;
+use kibank::volume::VolumeReader;
+use kibank::write::{AtomicFile, BankWriter, BankWriterOptions};
+
+mod archive;
+mod create;
+mod edit;
+mod extract;
+mod inspect;
+mod messages;
+
+use kibank::{compress, encrypt, volume, zip, ItemKind, Metadata};
+
+fn main() -> Result<()> {
+    // Command line arguments
+    let app = Command::new(crate_name!())
+        .version(crate_version!())
+        .author(crate_authors!())
+        .about(crate_description!())
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .action(ArgAction::Count),
+        )
+        .arg(
+            Arg::new("lang")
+                .help("Language of the output")
+                .long("lang")
+                .env("KIBANK_LANG")
+                .value_parser(Lang::TAGS)
+                .global(true),
+        )
+        .arg(
+            Arg::new("password")
+                .help("Password of an encrypted bank")
+                .long("password")
+                .env("KIBANK_PASSWORD")
+                .hide_env_values(true)
+                .global(true)
+                .conflicts_with("keyfile"),
+        )
+        .arg(
+            Arg::new("keyfile")
+                .help("File containing the password of an encrypted bank")
+                .long("keyfile")
+                .value_hint(ValueHint::FilePath)
+                .value_parser(OsStringValueParser::new())
+                .global(true),
+        )
+        .subcommand(create::create_command())
+        .subcommand(edit::add_command())
+        .subcommand(edit::remove_command())
+        .subcommand(inspect::checksum_command())
+        .subcommand(inspect::compat_command())
+        .subcommand(archive::compress_command())
+        .subcommand(archive::decompress_command())
+        .subcommand(inspect::diff_command())
+        .subcommand(archive::encrypt_command())
+        .subcommand(extract::export_command())
+        .subcommand(extract::extract_command())
+        .subcommand(extract::extract_all_command())
+        .subcommand(inspect::info_command())
+        .subcommand(inspect::inspect_command())
+        .subcommand(inspect::kinds_command())
+        .subcommand(inspect::lint_command())
+        .subcommand(inspect::list_command())
+        .subcommand(edit::merge_command())
+        .subcommand(edit::normalize_names_command())
+        .subcommand(inspect::scan_command())
+        .subcommand(archive::split_command())
+        .subcommand(inspect::verify_command())
+        .subcommand(edit::patch_command())
+        .subcommand(edit::apply_command())
+        .subcommand(edit::repair_command())
+        .subcommand(edit::repack_command())
+        .subcommand(edit::rebrand_command())
+        .subcommand(create::release_command())
+        .subcommand(create::build_command())
+        .subcommands(inspect::schema_commands())
+        .subcommands(extract::mount_commands());
+    let cli_matches = app.get_matches();
+
+    let log_level_filter = [
+        LevelFilter::Off,
+        LevelFilter::Error,
+        LevelFilter::Warn,
+        LevelFilter::Info, // One --verbose given
+        LevelFilter::Debug,
+        LevelFilter::Trace,
+    ]
+    .get(*cli_matches.get_one::<u8>("verbose").unwrap() as usize + 2)
+    .unwrap_or(&LevelFilter::Trace);
+
+    // Logging
+    let log_config = ConfigBuilder::new()
+        .set_time_level(LevelFilter::Debug)
+        .set_thread_level(LevelFilter::Trace)
+        .set_target_level(*log_level_filter)
+        .build();
+
+    TermLogger::init(
+        *log_level_filter,
+        log_config,
+        TerminalMode::Mixed,
+        ColorChoice::Auto,
+    )?;
+
+    if let Some(lang) = cli_matches
+        .get_one::<String>("lang")
+        .and_then(|tag| Lang::from_tag(tag))
+    {
+        lang.set();
+    }
+
+    match cli_matches.subcommand() {
+        Some(("create", args)) => create::create(args),
+        Some(("add", args)) => edit::add(args),
+        Some(("apply", args)) => edit::apply(args),
+        Some(("build", args)) => create::build(args),
+        Some(("checksum", args)) => inspect::checksum(args),
+        Some(("compat", args)) => inspect::compat(args),
+        Some(("compress", args)) => archive::compress(args),
+        Some(("decompress", args)) => archive::decompress(args),
+        Some(("diff", args)) => inspect::diff(args),
+        Some(("encrypt", args)) => archive::encrypt(args),
+        Some(("export", args)) => extract::export(args),
+        Some(("extract", args)) => extract::extract(args),
+        Some(("extract-all", args)) => extract::extract_all(args),
+        Some(("info", args)) => inspect::info(args),
+        Some(("kinds", args)) => inspect::kinds(args),
+        Some(("lint", args)) => inspect::lint(args),
+        Some(("inspect", args)) => inspect::inspect(args),
+        Some(("list", args)) => inspect::list(args),
+        Some(("merge", args)) => edit::merge(args),
+        #[cfg(all(feature = "fuse", target_os = "linux"))]
+        Some(("mount", args)) => extract::mount(args),
+        Some(("normalize-names", args)) => edit::normalize_names(args),
+        Some(("patch", args)) => edit::patch(args),
+        Some(("rebrand", args)) => edit::rebrand(args),
+        Some(("release", args)) => create::release(args),
+        Some(("remove", args)) => edit::remove(args),
+        Some(("repair", args)) => edit::repair(args),
+        Some(("repack", args)) => edit::repack(args),
+        Some(("scan", args)) => inspect::scan(args),
+        #[cfg(feature = "schema")]
+        Some(("schema", args)) => inspect::schema(args),
+        Some(("split", args)) => archive::split(args),
+        Some(("verify", args)) => inspect::verify(args),
+        _ => Err(anyhow!(Message::MissingCommand.text())),
+    }
+}
+
+/// Option of the commands that write banks to compress each item, which
+/// Kilohearts products cannot read.
+fn x_compress_arg() -> Arg {
+    Arg::new("x-compress")
+        .help("Experimental: compress each item, making a bank only kibank can read")
+        .long("x-compress")
+        .action(ArgAction::SetTrue)
+}
+
+/// Option of the commands that read banks written with `--x-compress`.
+fn x_decompress_arg() -> Arg {
+    Arg::new("x-decompress")
+        .help("Experimental: decompress the items of a bank written with --x-compress")
+        .long("x-decompress")
+        .action(ArgAction::SetTrue)
+}
+
+/// Option of the commands that choose the hash function of digests.
+fn algo_arg() -> Arg {
+    Arg::new("algo")
+        .help("Hash function of the digests")
+        .long("algo")
+        .value_parser(Algorithm::NAMES)
+        .default_value("blake3")
+}
+
+/// The hash function chosen with `--algo`.
+fn algorithm(args: &ArgMatches) -> Result<Algorithm> {
+    args.get_one::<String>("algo")
+        .and_then(|name| Algorithm::from_name(name))
+        .with_context(|| "Expected the name of a hash function")
+}
+
+/// Option of the commands that process many banks to record how each bank
+/// went, to find slow or problematic banks afterwards.
+fn stats_file_arg() -> Arg {
+    Arg::new("stats-file")
+        .help("Write the time, size and outcome of each bank to a JSON file")
+        .long("stats-file")
+        .value_name("STATS_FILE")
+        .value_hint(ValueHint::FilePath)
+        .value_parser(OsStringValueParser::new())
+}
+
+/// Write the statistics if they were asked for.
+fn save_stats(args: &ArgMatches, stats: Option<&Stats>) -> Result<()> {
+    if let (Some(path), Some(stats)) = (args.get_one::<OsString>("stats-file"), stats) {
+        let path = Path::new(path);
+        info!("Writing statistics to {}", path.display());
+        stats
+            .save(path)
+            .with_context(|| format!("Cannot write statistics to {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Display the changes between two banks followed by how many files changed.
+fn print_bank_diff(diff: &BankDiff) {
+    print!("{diff}");
+    let summary = Message::DiffSummary {
+        added: diff.added.len(),
+        removed: diff.removed.len(),
+        changed: diff.changed.len(),
+        unchanged: diff.unchanged,
+    };
+    println!("{}", summary.text());
+}
+
+/// Anything a bank can be read from.
+trait BankSource: Read + Seek + BufRead {}
+
+impl<T: Read + Seek + BufRead> BankSource for T {}
+
+/// Open a bank for reading, decompressing or decrypting it first if needed.
+/// Banks split into volumes are read from every volume.
+fn open_bank(
+    args: &ArgMatches,
+    bank_path: &Path,
+    options: BankReaderOptions,
+) -> Result<BankReader<'static, Box<dyn BankSource>>> {
+    if let Some(index_path) = volume::find_index(bank_path) {
+        debug!("Reading volumes listed in {}", index_path.display());
+        let volumes = VolumeReader::open(&index_path)
+            .with_context(|| format!("Cannot open the volumes of {}", bank_path.display()))?;
+        let source: Box<dyn BankSource> = Box::new(BufReader::new(volumes));
+        return BankReader::with_options(source, options)
+            .with_context(|| format!("Cannot open bank {}", bank_path.display()));
+    }
+
+    let file = File::open(bank_path)
+        .with_context(|| format!("Cannot open bank {}", bank_path.display()))?;
+    let mut file = BufReader::new(file);
+    let header = file.fill_buf()?;
+    let plain = Compression::detect(header).is_none()
+        && !encrypt::is_encrypted(header)
+        && !zip::is_zip(header);
+    let source: Box<dyn BankSource> = if let Some(compression) = Compression::detect(header) {
+        // Banks must be seekable so the bank is decompressed to a file.
+        debug!("Decompressing {} with {compression}", bank_path.display());
+        let bank = compress::decompress_to_temp(file, compression, options.max_bank_size)
+            .with_context(|| format!("Cannot decompress bank {}", bank_path.display()))?;
+        Box::new(BufReader::new(bank))
+    } else if encrypt::is_encrypted(header) {
+        let password = password(args)?.with_context(|| {
+            format!(
+                "Bank {} is encrypted, use --password or --keyfile",
+                bank_path.display()
+            )
+        })?;
+        let mut encrypted = Vec::new();
+        file.read_to_end(&mut encrypted)?;
+        debug!("Decrypting {}", bank_path.display());
+        let bank = encrypt::decrypt(&encrypted, &password)
+            .with_context(|| format!("Cannot decrypt bank {}", bank_path.display()))?;
+        Box::new(Cursor::new(bank))
+    } else if zip::is_zip(header) {
+        let (name, bank) = zip::open_bank(file)
+            .with_context(|| format!("Cannot read a bank in {}", bank_path.display()))?;
+        info!("Reading {name} in {}", bank_path.display());
+        Box::new(BufReader::new(bank))
+    } else {
+        Box::new(file)
+    };
+    let reader = BankReader::with_options(source, options)
+        .with_context(|| format!("Cannot open bank {}", bank_path.display()))?;
+
+    // Items of a plain bank file can be read from it again when copied.
+    Ok(if plain {
+        reader.with_path(bank_path)
+    } else {
+        reader
+    })
+}
+
+/// The password given with `--password` or read from the `--keyfile`.
+fn password(args: &ArgMatches) -> Result<Option<Vec<u8>>> {
+    if let Some(password) = args.get_one::<String>("password") {
+        Ok(Some(password.as_bytes().to_owned()))
+    } else if let Some(keyfile) = args.get_one::<OsString>("keyfile") {
+        let key = fs::read(keyfile)
+            .with_context(|| format!("Cannot read {}", keyfile.to_string_lossy()))?;
+        Ok(Some(key))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Write a new bank using the closure to add the contents. The output may
+/// replace the bank being read, since it is only replaced once the new bank
+/// is complete.
+fn save_bank<F>(out_path: &Path, add_contents: F) -> Result<()>
+where
+    F: FnOnce(&mut BankWriter<&mut BankFileWriter>) -> Result<()>,
+{
+    save_bank_with(out_path, BankWriterOptions::default(), add_contents)
+}
+
+/// Like [`save_bank`] with options for the writer.
+fn save_bank_with<F>(out_path: &Path, options: BankWriterOptions, add_contents: F) -> Result<()>
+where
+    F: FnOnce(&mut BankWriter<&mut BankFileWriter>) -> Result<()>,
+{
+    bank::check_writable(out_path)?;
+    write_bank_file(out_path, |out| {
+        let mut writer = BankWriter::with_options(out, options);
+        add_contents(&mut writer)?;
+        Ok(writer.write()?)
+    })
+}
+
+/// Where a bank is written by [`write_bank_file`].
+type BankFileWriter<'a> = BufWriter<&'a mut AtomicFile>;
+
+/// Replace the file at the path with the bank written by the closure, leaving
+/// the file as it was if the bank cannot be completely written. The bank is
+/// written to a temporary file next to the destination, so items can still
+/// be read from the file being replaced.
+fn write_bank_file<F, T>(out_path: &Path, write: F) -> Result<T>
+where
+    F: FnOnce(&mut BankFileWriter) -> Result<T>,
+{
+    info!("Writing bank to {}", out_path.display());
+    let mut file = AtomicFile::create(out_path)
+        .with_context(|| format!("Cannot create bank {}", out_path.display()))?;
+    let mut out = BufWriter::new(&mut file);
+    let result = write(&mut out)?;
+    out.into_inner()
+        .map_err(io::IntoInnerError::into_error)
+        .with_context(|| format!("Cannot write bank {}", out_path.display()))?;
+    file.commit()
+        .with_context(|| format!("Cannot write bank {}", out_path.display()))?;
+    Ok(result)
+}
+
+/// A token cancelled when Ctrl-C is pressed, so writing stops before the next
+/// item and the partial output is removed instead of the program ending
+/// immediately.
+fn cancel_on_ctrl_c() -> Result<CancellationToken> {
+    let token = CancellationToken::new();
+    let handler_token = token.clone();
+    ctrlc::set_handler(move || {
+        eprintln!("{}", Message::Cancelling.text());
+        handler_token.cancel();
+    })
+    .with_context(|| "Cannot handle Ctrl-C")?;
+    Ok(token)
+}
+
+/// The metadata of a bank, or the default metadata if the bank doesn't have any.
+fn read_metadata<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    bank_path: &Path,
+) -> Result<Metadata> {
+    match reader.items().iter().find(|item| item.is_metadata_file()) {
+        Some(item) => reader
+            .read_metadata(item)
+            .with_context(|| format!("Cannot read the metadata for bank {}", bank_path.display())),
+        None => Ok(Metadata::default()),
+    }
+}
+
+/// Copy every file except the metadata from one bank to another.
+fn copy_items<ReaderType: Read + Seek + BufRead, WriterType: Write>(
+    reader: &mut BankReader<ReaderType>,
+    writer: &mut BankWriter<WriterType>,
+) -> Result<()> {
+    for item in reader.items() {
+        let path = OsStr::assert_from_raw_bytes(item.path_bytes());
+        if item.is_unknown_directory() {
+            writer.add_directory(&path)?;
+        }
+        if !item.is_file() || item.is_metadata_file() {
+            continue;
+        }
+
+        if let Some(kind) = ItemKind::from(&path) {
+            let contents = reader
+                .content_source(&item)
+                .with_context(|| format!("Cannot read {}", item.file_name_lossy()))?;
+            writer.add_source_with_path(kind, &path, contents)?;
+        } else {
+            warn!(
+                "Skipping {} because it is an unknown type of file",
+                item.file_name_lossy()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// A SHA-256 digest given as hex, in lowercase.
+fn parse_sha256(value: &str) -> Result<String> {
+    let value = value.trim();
+    if value.len() != Algorithm::Sha256.hex_len() || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("{value} is not a SHA-256 digest in hex"));
+    }
+    Ok(value.to_ascii_lowercase())
+}
+
+/// Parse permissions in octal, such as `644` or `0o755`.
+fn parse_mode(value: &str) -> Result<u32> {
+    let value = value.trim();
+    let digits = value.strip_prefix("0o").unwrap_or(value);
+    let mode = u32::from_str_radix(digits, 8)
+        .with_context(|| format!("Cannot read {value} as permissions in octal"))?;
+    if mode > 0o7777 {
+        return Err(anyhow!("Permissions {value} are more than 7777"));
+    }
+    Ok(mode)
+}
+
+/// Parse a size in bytes with an optional suffix of K, M, G or T for
+/// multiples of 1024, such as `2G`.
+fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((pos, suffix)) if suffix.is_ascii_alphabetic() => {
+            let exponent = match suffix.to_ascii_uppercase() {
+                'K' => 1,
+                'M' => 2,
+                'G' => 3,
+                'T' => 4,
+                _ => return Err(anyhow!("Unknown size suffix {suffix}")),
+            };
+            (&value[..pos], 1024_u64.pow(exponent))
+        }
+        _ => (value, 1),
+    };
+    let size = number
+        .trim()
+        .parse::<u64>()
+        .with_context(|| format!("Cannot read {value} as a size"))?
+        .checked_mul(multiplier)
+        .with_context(|| format!("Size {value} is too large"))?;
+    if size == 0 {
+        return Err(anyhow!("Size must be more than zero"));
+    }
+    Ok(size)
+}
@@ -0,0 +1,363 @@
+//! Text shown to people using the application, in each supported language.
+//! Log messages and errors from the library are always in English.
+
+use std::sync::OnceLock;
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// Languages the output of the application can be shown in.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Lang {
+    #[default]
+    English,
+    German,
+}
+
+impl Lang {
+    /// Tags accepted by the `--lang` option.
+    pub const TAGS: [&'static str; 2] = ["en", "de"];
+
+    /// Find the language from a tag such as `de`, `de-DE` or `de_DE.UTF-8`.
+    pub fn from_tag(tag: &str) -> Option<Lang> {
+        let primary = tag.split(['-', '_', '.']).next().unwrap_or_default();
+        match primary.to_ascii_lowercase().as_str() {
+            "en" => Some(Lang::English),
+            "de" => Some(Lang::German),
+            _ => None,
+        }
+    }
+
+    /// The tag of the language, such as `de`.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Lang::English => "en",
+            Lang::German => "de",
+        }
+    }
+
+    /// Use the language for all messages. Only the first call has any effect.
+    pub fn set(self) {
+        let _ = LANG.set(self);
+    }
+
+    /// The language messages are shown in.
+    pub fn current() -> Lang {
+        LANG.get().copied().unwrap_or_default()
+    }
+}
+
+/// Everything the application shows other than log messages.
+pub enum Message<'a> {
+    Added {
+        path: &'a str,
+    },
+    Author,
+    Background,
+    BackgroundDimensions,
+    BackgroundFormat,
+    BackgroundSize,
+    BankCreated {
+        bank: &'a str,
+    },
+    BankExtracted {
+        bank: &'a str,
+        dir: &'a str,
+    },
+    BankNotValid {
+        bank: &'a str,
+    },
+    BankOk {
+        bank: &'a str,
+    },
+    Cancelling,
+    CannotExtract {
+        path: &'a str,
+        error: &'a str,
+    },
+    CannotRead {
+        path: &'a str,
+        error: &'a str,
+    },
+    Created {
+        time: &'a str,
+        tool: &'a str,
+        version: &'a str,
+    },
+    Description,
+    DiffSummary {
+        added: usize,
+        removed: usize,
+        changed: usize,
+        unchanged: usize,
+    },
+    Directory,
+    EmptyFile,
+    Extensions,
+    Extra,
+    ExtraMetadataDropped,
+    ExtraMetadataKept,
+    FieldConflict {
+        field: &'a str,
+    },
+    Fingerprint,
+    Hash,
+    Id,
+    ItemConflict {
+        path: &'a str,
+    },
+    Items,
+    KeepWhich {
+        count: usize,
+    },
+    Kind,
+    Label,
+    LintErrors {
+        bank: &'a str,
+        count: usize,
+    },
+    MetadataChanged {
+        field: &'a str,
+        from: &'a str,
+        to: &'a str,
+    },
+    MissingCommand,
+    #[cfg(all(feature = "fuse", target_os = "linux"))]
+    Mounted {
+        bank: &'a str,
+        mountpoint: &'a str,
+    },
+    Name,
+    NewerInBank,
+    NewerInDirectory,
+    No,
+    NoMetadataItem,
+    Path,
+    Presets,
+    PresetsNewer {
+        count: usize,
+        target: &'a str,
+        unknown: usize,
+    },
+    Removed {
+        path: &'a str,
+    },
+    SameContents,
+    SameId {
+        id: &'a str,
+    },
+    Size,
+    Skipped {
+        path: &'a str,
+        reason: &'a str,
+    },
+    SourceHash,
+    UnknownType,
+    Unreadable {
+        error: &'a str,
+    },
+    VerifySummary {
+        passed: usize,
+        failed: usize,
+    },
+    Version,
+    Yes,
+}
+
+impl Message<'_> {
+    /// The message in the language set with [`Lang::set`].
+    pub fn text(&self) -> String {
+        self.text_in(Lang::current())
+    }
+
+    /// The message in the language.
+    pub fn text_in(&self, lang: Lang) -> String {
+        match lang {
+            Lang::English => self.english(),
+            Lang::German => self.german(),
+        }
+    }
+
+    fn english(&self) -> String {
+        match self {
+            Message::Added { path } => format!("Added {path}"),
+            Message::Author => "Author".to_string(),
+            Message::Background => "Background".to_string(),
+            Message::BackgroundDimensions => "Background dimensions".to_string(),
+            Message::BackgroundFormat => "Background format".to_string(),
+            Message::BackgroundSize => "Background size".to_string(),
+            Message::BankCreated { bank } => format!("Created {bank}"),
+            Message::BankExtracted { bank, dir } => format!("Extracted {bank} to {dir}"),
+            Message::BankNotValid { bank } => format!("Bank {bank} is not valid"),
+            Message::BankOk { bank } => format!("{bank} is OK"),
+            Message::Cancelling => "Cancelling after the current item".to_string(),
+            Message::CannotExtract { path, error } => format!("Cannot extract {path}: {error}"),
+            Message::CannotRead { path, error } => format!("Cannot read {path}: {error}"),
+            Message::Created {
+                time,
+                tool,
+                version,
+            } => format!("Created: {time} by {tool} {version}"),
+            Message::Description => "Description".to_string(),
+            Message::DiffSummary {
+                added,
+                removed,
+                changed,
+                unchanged,
+            } => format!(
+                "{added} added, {removed} removed, {changed} changed, {unchanged} unchanged"
+            ),
+            Message::Directory => "Directory".to_string(),
+            Message::EmptyFile => "empty file".to_string(),
+            Message::Extensions => "Extensions".to_string(),
+            Message::Extra => "Extra".to_string(),
+            Message::ExtraMetadataDropped => "Extra metadata removed".to_string(),
+            Message::ExtraMetadataKept => "Extra metadata kept".to_string(),
+            Message::FieldConflict { field } => {
+                format!("Metadata {field} is different in more than one bank")
+            }
+            Message::Fingerprint => "Fingerprint".to_string(),
+            Message::Hash => "Hash".to_string(),
+            Message::Id => "ID".to_string(),
+            Message::ItemConflict { path } => format!("{path} is different in more than one bank"),
+            Message::Items => "Items".to_string(),
+            Message::KeepWhich { count } => format!("Keep which (1-{count}, Enter for 1)?"),
+            Message::Kind => "Kind".to_string(),
+            Message::Label => "Label".to_string(),
+            Message::LintErrors { bank, count } => {
+                format!("Bank {bank} has {count} lint error(s)")
+            }
+            Message::MetadataChanged { field, from, to } => {
+                format!("Metadata {field} changed from {from} to {to}")
+            }
+            Message::MissingCommand => "Missing command (-h for help)".to_string(),
+            #[cfg(all(feature = "fuse", target_os = "linux"))]
+            Message::Mounted { bank, mountpoint } => {
+                format!("Mounted bank {bank} on {mountpoint}, press Ctrl-C to unmount")
+            }
+            Message::Name => "Name".to_string(),
+            Message::NewerInBank => "newer in the bank".to_string(),
+            Message::NewerInDirectory => "newer in the directory".to_string(),
+            Message::No => "no".to_string(),
+            Message::NoMetadataItem => "No metadata item".to_string(),
+            Message::Path => "Path".to_string(),
+            Message::Presets => "Presets".to_string(),
+            Message::PresetsNewer {
+                count,
+                target,
+                unknown,
+            } => format!(
+                "{count} preset(s) saved with a version newer than {target}, {unknown} without a version"
+            ),
+            Message::Removed { path } => format!("Removed {path}"),
+            Message::SameContents => "Same contents".to_string(),
+            Message::SameId { id } => format!("Same ID {id} but different contents"),
+            Message::Size => "Size".to_string(),
+            Message::Skipped { path, reason } => format!("Skipped {path}: {reason}"),
+            Message::SourceHash => "Source hash".to_string(),
+            Message::UnknownType => "unknown type of file".to_string(),
+            Message::Unreadable { error } => format!("cannot be read: {error}"),
+            Message::VerifySummary { passed, failed } => {
+                format!("{passed} passed, {failed} failed")
+            }
+            Message::Version => "Version".to_string(),
+            Message::Yes => "yes".to_string(),
+        }
+    }
+
+    fn german(&self) -> String {
+        match self {
+            Message::Added { path } => format!("{path} wurde hinzugefügt"),
+            Message::Author => "Autor".to_string(),
+            Message::Background => "Hintergrund".to_string(),
+            Message::BackgroundDimensions => "Abmessungen des Hintergrunds".to_string(),
+            Message::BackgroundFormat => "Format des Hintergrunds".to_string(),
+            Message::BackgroundSize => "Größe des Hintergrunds".to_string(),
+            Message::BankCreated { bank } => format!("{bank} wurde erstellt"),
+            Message::BankExtracted { bank, dir } => {
+                format!("{bank} wurde nach {dir} entpackt")
+            }
+            Message::BankNotValid { bank } => format!("Bank {bank} ist nicht gültig"),
+            Message::BankOk { bank } => format!("{bank} ist in Ordnung"),
+            Message::Cancelling => "Abbruch nach dem aktuellen Element".to_string(),
+            Message::CannotExtract { path, error } => {
+                format!("{path} kann nicht entpackt werden: {error}")
+            }
+            Message::CannotRead { path, error } => {
+                format!("{path} kann nicht gelesen werden: {error}")
+            }
+            Message::Created {
+                time,
+                tool,
+                version,
+            } => format!("Erstellt: {time} von {tool} {version}"),
+            Message::Description => "Beschreibung".to_string(),
+            Message::DiffSummary {
+                added,
+                removed,
+                changed,
+                unchanged,
+            } => format!(
+                "{added} hinzugefügt, {removed} entfernt, {changed} geändert, {unchanged} unverändert"
+            ),
+            Message::Directory => "Verzeichnis".to_string(),
+            Message::EmptyFile => "leere Datei".to_string(),
+            Message::Extensions => "Dateiendungen".to_string(),
+            Message::Extra => "Zusätzlich".to_string(),
+            Message::ExtraMetadataDropped => "Entfernte zusätzliche Metadaten".to_string(),
+            Message::ExtraMetadataKept => "Beibehaltene zusätzliche Metadaten".to_string(),
+            Message::FieldConflict { field } => {
+                format!("Metadaten {field} unterscheiden sich in mehreren Banken")
+            }
+            Message::Fingerprint => "Fingerabdruck".to_string(),
+            Message::Hash => "Hash".to_string(),
+            Message::Id => "ID".to_string(),
+            Message::ItemConflict { path } => {
+                format!("{path} unterscheidet sich in mehreren Banken")
+            }
+            Message::Items => "Elemente".to_string(),
+            Message::KeepWhich { count } => {
+                format!("Welche behalten (1-{count}, Eingabe für 1)?")
+            }
+            Message::Kind => "Art".to_string(),
+            Message::Label => "Bezeichnung".to_string(),
+            Message::LintErrors { bank, count } => {
+                format!("Bank {bank} hat {count} Lint-Fehler")
+            }
+            Message::MetadataChanged { field, from, to } => {
+                format!("Metadaten {field} von {from} in {to} geändert")
+            }
+            Message::MissingCommand => "Fehlender Befehl (-h für Hilfe)".to_string(),
+            #[cfg(all(feature = "fuse", target_os = "linux"))]
+            Message::Mounted { bank, mountpoint } => {
+                format!("Bank {bank} in {mountpoint} eingehängt, Strg-C zum Aushängen")
+            }
+            Message::Name => "Name".to_string(),
+            Message::NewerInBank => "neuer in der Bank".to_string(),
+            Message::NewerInDirectory => "neuer im Verzeichnis".to_string(),
+            Message::No => "nein".to_string(),
+            Message::NoMetadataItem => "Kein Metadaten-Element".to_string(),
+            Message::Path => "Pfad".to_string(),
+            Message::Presets => "Presets".to_string(),
+            Message::PresetsNewer {
+                count,
+                target,
+                unknown,
+            } => format!(
+                "{count} Preset(s) mit einer neueren Version als {target} gespeichert, {unknown} ohne Version"
+            ),
+            Message::Removed { path } => format!("{path} wurde entfernt"),
+            Message::SameContents => "Gleicher Inhalt".to_string(),
+            Message::SameId { id } => format!("Gleiche ID {id}, aber unterschiedlicher Inhalt"),
+            Message::Size => "Größe".to_string(),
+            Message::Skipped { path, reason } => format!("{path} wurde übersprungen: {reason}"),
+            Message::SourceHash => "Quell-Hash".to_string(),
+            Message::UnknownType => "unbekannter Dateityp".to_string(),
+            Message::Unreadable { error } => format!("nicht lesbar: {error}"),
+            Message::VerifySummary { passed, failed } => {
+                format!("{passed} bestanden, {failed} fehlgeschlagen")
+            }
+            Message::Version => "Version".to_string(),
+            Message::Yes => "ja".to_string(),
+        }
+    }
+}
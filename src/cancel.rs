@@ -0,0 +1,112 @@
+//! Cancelling long-running operations from another thread and following their
+//! progress, such as for a cancel button and a progress bar in an application
+//! with a graphical interface.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Shared by an operation and whoever started it. Cancelling stops the
+/// operation before it starts on the next item. The operation records how
+/// many of its items are finished so progress can be shown while it runs.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    cancelled: AtomicBool,
+    done: AtomicUsize,
+    total: AtomicUsize,
+}
+
+impl CancellationToken {
+    #[must_use]
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Stop the operation using this token, and any later operation.
+    pub fn cancel(&self) {
+        self.shared.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// How far through its items the operation is.
+    #[must_use]
+    pub fn progress(&self) -> Progress {
+        Progress {
+            done: self.shared.done.load(Ordering::Relaxed),
+            total: self.shared.total.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Start counting the items of an operation.
+    pub(crate) fn start(&self, total: usize) {
+        self.shared.done.store(0, Ordering::Relaxed);
+        self.shared.total.store(total, Ordering::Relaxed);
+    }
+
+    /// Returns the [`Cancelled`] error if the operation has been cancelled.
+    pub(crate) fn check(&self) -> io::Result<()> {
+        if self.is_cancelled() {
+            Err(io::Error::other(Cancelled {
+                progress: self.progress(),
+            }))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Count another item as finished.
+    pub(crate) fn item_done(&self) {
+        self.shared.done.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Number of items finished out of the total number of items.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+/// The error inside the [`io::Error`] returned by a cancelled operation, with
+/// how far the operation got before it stopped.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cancelled {
+    pub progress: Progress,
+}
+
+impl Cancelled {
+    /// The cancellation inside the error, if the error is because the
+    /// operation was cancelled.
+    #[must_use]
+    pub fn from_error(error: &io::Error) -> Option<&Cancelled> {
+        error.get_ref()?.downcast_ref::<Cancelled>()
+    }
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cancelled after {} of {} items",
+            self.progress.done, self.progress.total
+        )
+    }
+}
+
+impl error::Error for Cancelled {}
+
+/// Check for cancellation with a token that may not have been given.
+pub(crate) fn check(cancel: Option<&CancellationToken>) -> io::Result<()> {
+    cancel.map_or(Ok(()), CancellationToken::check)
+}
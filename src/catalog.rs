@@ -0,0 +1,326 @@
+//! Summaries of every bank found in a directory, such as a library of content.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "image")]
+use crate::background::thumbnail;
+use crate::hash;
+use crate::hash::Algorithm;
+use crate::read::BankReader;
+use crate::stats::{Outcome, Stats};
+use crate::{ItemKind, Metadata, Product};
+
+/// File name extension of banks, without the leading dot.
+pub const BANK_EXTENSION: &str = "bank";
+
+/// The details of one bank in a catalog.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BankSummary {
+    /// Location of the bank file.
+    pub path: PathBuf,
+
+    pub id: String,
+    pub name: String,
+    pub author: String,
+    pub description: String,
+    pub version: Option<u32>,
+    pub hash: Option<String>,
+
+    /// Number of files in the bank, not including directories.
+    pub item_count: usize,
+
+    /// Size of the bank file in bytes.
+    pub size: u64,
+
+    /// Number of presets for each product.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub presets: BTreeMap<Product, usize>,
+
+    /// Hash of the contents of the bank, excluding the metadata, when it has
+    /// been calculated. See [`hash::content_hash`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+
+    /// Location of a small copy of the background of the bank, when
+    /// thumbnails have been made. See [`Catalog::add_thumbnails`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<PathBuf>,
+}
+
+impl BankSummary {
+    /// Summarize the bank at the path.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file is not a readable bank.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<BankSummary> {
+        let path = path.as_ref();
+        let size = fs::metadata(path)?.len();
+        let mut reader = BankReader::open(path)?;
+        let items = reader.items();
+        let metadata = match items.iter().find(|item| item.is_metadata_file()) {
+            Some(item) => reader.read_metadata(item)?,
+            None => Metadata::default(),
+        };
+
+        Ok(BankSummary {
+            path: path.to_owned(),
+            id: metadata.id,
+            name: metadata.name,
+            author: metadata.author,
+            description: metadata.description,
+            version: metadata.version,
+            hash: metadata.hash.filter(|hash| !hash.is_empty()),
+            item_count: items.iter().filter(|item| item.is_file()).count(),
+            size,
+            presets: Product::count_presets(
+                items
+                    .iter()
+                    .filter(|item| item.is_file())
+                    .filter_map(|item| ItemKind::from(Path::new(&item.file_name_lossy()))),
+            ),
+            fingerprint: None,
+            thumbnail: None,
+        })
+    }
+}
+
+/// Banks that are likely to be confused with each other.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum Duplicate {
+    /// The banks have the same contents, regardless of their metadata.
+    SameContents {
+        fingerprint: String,
+        paths: Vec<PathBuf>,
+    },
+
+    /// The banks have the same ID but different contents, so one will hide
+    /// the other when both are installed.
+    SameId { id: String, paths: Vec<PathBuf> },
+}
+
+/// A file that looked like a bank but could not be read.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Unreadable {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// Every bank found in a directory.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Catalog {
+    pub banks: Vec<BankSummary>,
+    pub unreadable: Vec<Unreadable>,
+}
+
+impl Catalog {
+    /// Summarize every file with the bank extension in the directory and its
+    /// subdirectories. Banks that cannot be read are listed as unreadable
+    /// rather than stopping the scan.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the directory cannot be read.
+    pub fn scan<P: AsRef<Path>>(dir: P) -> io::Result<Catalog> {
+        Self::scan_with(dir, None)
+    }
+
+    /// Like [`Catalog::scan`] and also records how long each bank took to
+    /// summarize in the statistics, if given.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the directory cannot be read.
+    pub fn scan_with<P: AsRef<Path>>(dir: P, mut stats: Option<&mut Stats>) -> io::Result<Catalog> {
+        let mut catalog = Catalog::default();
+        for path in bank_paths(dir.as_ref())? {
+            debug!("Scanning {}", path.display());
+            let start = Instant::now();
+            let summary = BankSummary::from_path(&path);
+            if let Some(stats) = stats.as_deref_mut() {
+                let (outcome, error) = match &summary {
+                    Ok(_) => (Outcome::Ok, None),
+                    Err(error) => (Outcome::Unreadable, Some(error.to_string())),
+                };
+                stats.record(&path, start.elapsed(), outcome, error);
+            }
+            match summary {
+                Ok(summary) => catalog.banks.push(summary),
+                Err(error) => {
+                    warn!("Cannot read bank {}: {error}", path.display());
+                    catalog.unreadable.push(Unreadable {
+                        path,
+                        error: error.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(catalog)
+    }
+
+    /// Read a catalog previously saved as JSON, such as the output of `kibank scan`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be read or is not a catalog.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Catalog> {
+        let json = fs::read(path)?;
+        serde_json::from_slice(&json).map_err(Into::into)
+    }
+
+    /// Banks with the ID, ignoring case.
+    #[must_use]
+    pub fn with_id(&self, id: &str) -> Vec<&BankSummary> {
+        self.banks
+            .iter()
+            .filter(|bank| bank.id.eq_ignore_ascii_case(id))
+            .collect()
+    }
+
+    /// Calculate the fingerprint of every bank that doesn't have one yet.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a bank cannot be read.
+    pub fn fingerprint(&mut self) -> io::Result<()> {
+        self.fingerprint_with(Algorithm::Sha1)
+    }
+
+    /// The same as [`Catalog::fingerprint`] but with the algorithm given.
+    /// Fingerprints made with different algorithms never match, so use the
+    /// same algorithm for every bank compared.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a bank cannot be read.
+    pub fn fingerprint_with(&mut self, algorithm: Algorithm) -> io::Result<()> {
+        for bank in self
+            .banks
+            .iter_mut()
+            .filter(|bank| bank.fingerprint.is_none())
+        {
+            let mut reader = BankReader::open(&bank.path)?;
+            bank.fingerprint = Some(hash::content_hash_using(&mut reader, algorithm, None)?);
+        }
+        Ok(())
+    }
+
+    /// Write a thumbnail of the background of every bank with one to the
+    /// directory, as a PNG image with the longest side at most `size` pixels,
+    /// and record where it was written. Thumbnails are named after the hash
+    /// of the background so banks sharing a background share a thumbnail.
+    /// Backgrounds that cannot be decoded, such as JPEG images, are skipped
+    /// with a warning.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a bank cannot be read or a thumbnail cannot be
+    /// written.
+    #[cfg(feature = "image")]
+    pub fn add_thumbnails(&mut self, dir: &Path, size: u32) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for bank in &mut self.banks {
+            let mut reader = BankReader::open(&bank.path)?;
+            let Some(item) = reader
+                .items()
+                .into_iter()
+                .find(|item| item.is_background_file())
+            else {
+                continue;
+            };
+            let image = reader.read_contents(&item)?;
+            let thumbnail = match thumbnail::thumbnail(&image, size) {
+                Ok(thumbnail) => thumbnail,
+                Err(error) => {
+                    warn!(
+                        "Cannot make a thumbnail of the background of {}: {error}",
+                        bank.path.display()
+                    );
+                    continue;
+                }
+            };
+            let path = dir.join(format!("{}.png", hash::digest(image.as_slice())?));
+            fs::write(&path, thumbnail)?;
+            bank.thumbnail = Some(path);
+        }
+        Ok(())
+    }
+
+    /// Find banks with the same contents and banks with the same ID but
+    /// different contents. Only banks with a fingerprint, calculated with
+    /// [`Catalog::fingerprint`], are compared.
+    #[must_use]
+    pub fn duplicates(&self) -> Vec<Duplicate> {
+        let mut by_fingerprint = BTreeMap::<&str, Vec<&BankSummary>>::new();
+        let mut by_id = BTreeMap::<&str, Vec<&BankSummary>>::new();
+        for bank in &self.banks {
+            if let Some(fingerprint) = &bank.fingerprint {
+                by_fingerprint.entry(fingerprint).or_default().push(bank);
+                if !bank.id.is_empty() {
+                    by_id.entry(&bank.id).or_default().push(bank);
+                }
+            }
+        }
+
+        let paths = |banks: &[&BankSummary]| banks.iter().map(|bank| bank.path.clone()).collect();
+        let mut duplicates = by_fingerprint
+            .into_iter()
+            .filter(|(_, banks)| banks.len() > 1)
+            .map(|(fingerprint, banks)| Duplicate::SameContents {
+                fingerprint: fingerprint.to_string(),
+                paths: paths(&banks),
+            })
+            .collect::<Vec<_>>();
+        for (id, banks) in by_id {
+            let fingerprints = banks
+                .iter()
+                .map(|bank| &bank.fingerprint)
+                .collect::<BTreeSet<_>>();
+            if banks.len() > 1 && fingerprints.len() > 1 {
+                duplicates.push(Duplicate::SameId {
+                    id: id.to_string(),
+                    paths: paths(&banks),
+                });
+            }
+        }
+        duplicates
+    }
+}
+
+/// Paths of the files with the bank extension in the directory and its
+/// subdirectories, sorted so the results are consistent between platforms.
+///
+/// # Errors
+///
+/// Will return `Err` if the directory cannot be read.
+pub fn bank_paths(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case(BANK_EXTENSION))
+            {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
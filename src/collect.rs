@@ -0,0 +1,161 @@
+//! Find the files to include in a new bank, keeping track of the files that
+//! cannot be included and why.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, info, warn};
+use serde::Serialize;
+
+use crate::{attributes, ItemKind};
+
+/// A file to include in a bank.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub kind: ItemKind,
+}
+
+/// Why a file was not included.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case", tag = "reason")]
+pub enum SkipReason {
+    /// The file name extension is not used by any kind of item.
+    UnknownType,
+
+    /// The file has no contents.
+    Empty,
+
+    /// The file or directory cannot be read.
+    Unreadable { error: String },
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::UnknownType => write!(f, "unknown type of file"),
+            SkipReason::Empty => write!(f, "empty file"),
+            SkipReason::Unreadable { error } => write!(f, "cannot be read: {error}"),
+        }
+    }
+}
+
+/// A file that was not included.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Skipped {
+    pub path: PathBuf,
+
+    #[serde(flatten)]
+    pub reason: SkipReason,
+}
+
+/// The files found by [`collect`].
+#[derive(Clone, Debug, Default)]
+pub struct Collection {
+    /// Files to include, each only once.
+    pub files: Vec<SourceFile>,
+
+    pub skipped: Vec<Skipped>,
+}
+
+impl Collection {
+    fn skip(&mut self, path: &Path, reason: SkipReason) {
+        self.skipped.push(Skipped {
+            path: path.to_owned(),
+            reason,
+        });
+    }
+}
+
+/// Find the files to include from the files and directories, including the
+/// files in every subdirectory. The contents of each directory are visited
+/// in order of their names so the results are consistent between platforms.
+/// Attribute files written by [`attributes`] are left out without being
+/// counted as skipped. Links to files are followed but links to directories
+/// inside the directories are not, so a link back up the tree cannot make
+/// the search go round in circles.
+pub fn collect<I, P>(paths: I) -> Collection
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let mut collection = Collection::default();
+    let mut seen = HashSet::new();
+    for path in paths {
+        visit(path.as_ref(), &mut collection, &mut seen, true);
+    }
+    collection
+}
+
+/// Add the file or the files in the directory. Links to directories are only
+/// followed for the paths given to [`collect`].
+fn visit(path: &Path, collection: &mut Collection, seen: &mut HashSet<PathBuf>, is_given: bool) {
+    if !is_given && path.is_symlink() && path.is_dir() {
+        info!("Not following the link to the directory {}", path.display());
+        return;
+    }
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) => {
+            warn!("Cannot read {}: {error}", path.display());
+            collection.skip(
+                path,
+                SkipReason::Unreadable {
+                    error: error.to_string(),
+                },
+            );
+            return;
+        }
+    };
+
+    if metadata.is_dir() {
+        let entries = fs::read_dir(path).and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()
+        });
+        match entries {
+            Ok(mut entries) => {
+                entries.sort();
+                for entry in entries {
+                    visit(&entry, collection, seen, false);
+                }
+            }
+            Err(error) => {
+                warn!("Cannot read directory {}: {error}", path.display());
+                collection.skip(
+                    path,
+                    SkipReason::Unreadable {
+                        error: error.to_string(),
+                    },
+                );
+            }
+        }
+    } else if path
+        .file_name()
+        .is_some_and(|name| name == attributes::FILE_NAME)
+    {
+        debug!("Skipping attributes from {}", path.display());
+    } else if let Some(kind) = ItemKind::from(path) {
+        if metadata.len() == 0 {
+            info!("Skipping {} because it is empty", path.display());
+            collection.skip(path, SkipReason::Empty);
+        } else if seen.insert(path.to_owned()) {
+            debug!("Adding {kind:?} from {}", path.display());
+            collection.files.push(SourceFile {
+                path: path.to_owned(),
+                kind,
+            });
+        }
+    } else {
+        info!(
+            "Skipping {} because it is an unknown type of file",
+            path.display()
+        );
+        collection.skip(path, SkipReason::UnknownType);
+    }
+}
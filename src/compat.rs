@@ -0,0 +1,123 @@
+//! Versions of the Kilohearts products that saved the presets of a bank, to
+//! find the presets that need a newer version than the one a vendor states.
+//!
+//! The preset formats are not documented, so a preset only has a version when
+//! one is written as text in its header, such as `2.1.0`. Presets without one
+//! are reported as unknown rather than guessed.
+
+use std::fmt;
+use std::io;
+use std::io::{BufRead, Error, ErrorKind, Read, Seek};
+use std::str::FromStr;
+
+use crate::read::BankReader;
+use crate::ItemKind;
+
+/// Number of bytes at the start of a preset searched for its version.
+pub const HEADER_SIZE: u64 = 256;
+
+/// Version of a product, such as `2.1` or `2.1.3`. A missing patch is zero.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for Version {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            let msg = format!("Version {text} is not of the form 2.1 or 2.1.3");
+            Error::new(ErrorKind::InvalidInput, msg)
+        };
+        let mut parts = text.split('.').map(|part| {
+            if part.is_empty() || !part.bytes().all(|byte| byte.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            part.parse::<u32>().map_err(|_| invalid())
+        });
+        let major = parts.next().ok_or_else(invalid)??;
+        let minor = parts.next().ok_or_else(invalid)??;
+        let patch = parts.next().transpose()?.unwrap_or_default();
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(Version {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+/// The version a preset was saved with, if its header has one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PresetVersion {
+    /// Path of the preset in the bank.
+    pub path: String,
+    pub kind: ItemKind,
+    pub version: Option<Version>,
+}
+
+impl PresetVersion {
+    /// Returns `true` if the preset was saved with a version newer than the
+    /// target. Presets without a version are not newer.
+    #[must_use]
+    pub fn is_newer_than(&self, target: Version) -> bool {
+        self.version.is_some_and(|version| version > target)
+    }
+}
+
+/// The first version written as text in the header, two or three numbers of
+/// up to four digits separated by dots that aren't part of a longer run of
+/// digits and dots.
+#[must_use]
+pub fn find_version(header: &[u8]) -> Option<Version> {
+    let is_version_byte = |byte: &u8| byte.is_ascii_digit() || *byte == b'.';
+    header
+        .split(|byte| !is_version_byte(byte))
+        .filter(|run| run.first().is_some_and(u8::is_ascii_digit))
+        .filter(|run| run.split(|byte| *byte == b'.').all(|part| part.len() <= 4))
+        .find_map(|run| std::str::from_utf8(run).ok()?.parse().ok())
+}
+
+/// The version of every preset in the bank, in the order of their kinds and
+/// then as they are in the bank. Only the header of each preset is read.
+///
+/// # Errors
+///
+/// Will return `Err` on read or seek failure.
+pub fn preset_versions<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+) -> io::Result<Vec<PresetVersion>> {
+    let presets = reader
+        .items_by_kind()
+        .iter()
+        .filter(|(kind, _)| kind.is_preset())
+        .flat_map(|(kind, items)| items.iter().map(|item| (*kind, item.clone())))
+        .collect::<Vec<_>>();
+
+    let mut versions = Vec::with_capacity(presets.len());
+    let mut header = Vec::new();
+    for (kind, item) in presets {
+        header.clear();
+        reader
+            .contents_reader(&item)?
+            .take(HEADER_SIZE)
+            .read_to_end(&mut header)?;
+        versions.push(PresetVersion {
+            path: item.file_name_lossy(),
+            kind,
+            version: find_version(&header),
+        });
+    }
+    Ok(versions)
+}
@@ -0,0 +1,229 @@
+//! Banks compressed with gzip or Zstandard for distribution. Banks of presets
+//! are mostly JSON and compress well.
+//!
+//! Banks can also have the contents of each item compressed, an experimental
+//! and non-standard form that only kibank can read. See
+//! [`X_ITEM_COMPRESSION_KEY`].
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde_json::Value;
+
+use crate::read::{BankReader, Item};
+use crate::Metadata;
+
+/// Key in the extra metadata of banks with the contents of every item other
+/// than the metadata compressed with Zstandard, written with
+/// [`crate::write::BankWriterOptions::x_compress_items`]. Kilohearts products
+/// cannot read these banks, so they are only for exchanging work in progress
+/// between people using kibank.
+pub const X_ITEM_COMPRESSION_KEY: &str = "kibank.x-item-compression";
+
+/// Value stored under [`X_ITEM_COMPRESSION_KEY`].
+const X_ITEM_COMPRESSION: &str = "zstd";
+
+/// Formats banks can be compressed with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Find the format from the first bytes of the compressed bank,
+    /// regardless of the file name extension.
+    #[must_use]
+    pub fn detect(bytes: &[u8]) -> Option<Compression> {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+
+    /// Find the format from the file name extension, such as `pack.bank.zst`.
+    #[must_use]
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Compression> {
+        let extension = path.as_ref().extension()?;
+        [Compression::Gzip, Compression::Zstd]
+            .into_iter()
+            .find(|compression| extension.eq_ignore_ascii_case(compression.extension()))
+    }
+
+    /// File name extension of files compressed with this format, without the
+    /// leading dot.
+    #[must_use]
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gz",
+            Compression::Zstd => "zst",
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compression::Gzip => write!(f, "gzip"),
+            Compression::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+/// A reader of the decompressed contents of the compressed source.
+///
+/// # Errors
+///
+/// Will return `Err` if the decompressor cannot be created.
+pub fn decoder<'a, R: Read + 'a>(
+    source: R,
+    compression: Compression,
+) -> io::Result<Box<dyn Read + 'a>> {
+    Ok(match compression {
+        Compression::Gzip => Box::new(GzDecoder::new(source)),
+        Compression::Zstd => Box::new(zstd::Decoder::new(source)?),
+    })
+}
+
+/// Compress everything read from the source into the destination, a piece
+/// at a time. Returns the number of bytes read from the source.
+///
+/// # Errors
+///
+/// Will return `Err` on read or write failure.
+pub fn compress<R: Read, W: Write>(
+    mut source: R,
+    dest: W,
+    compression: Compression,
+) -> io::Result<u64> {
+    match compression {
+        Compression::Gzip => {
+            let mut encoder = GzEncoder::new(dest, flate2::Compression::best());
+            let len = io::copy(&mut source, &mut encoder)?;
+            encoder.finish()?.flush()?;
+            Ok(len)
+        }
+        Compression::Zstd => {
+            let mut encoder = zstd::Encoder::new(dest, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+            let len = io::copy(&mut source, &mut encoder)?;
+            encoder.finish()?.flush()?;
+            Ok(len)
+        }
+    }
+}
+
+/// Decompress a bank into an anonymous temporary file, which is removed when
+/// it is closed, so it can be read like any other bank without being kept in
+/// memory. Fails once more than `max_len` bytes have been decompressed, so a
+/// small compressed file cannot fill the disk.
+///
+/// # Errors
+///
+/// Will return `Err` if the temporary file cannot be written, the source is
+/// corrupt or it decompresses to more than `max_len` bytes.
+pub fn decompress_to_temp<R: Read>(
+    source: R,
+    compression: Compression,
+    max_len: Option<u64>,
+) -> io::Result<File> {
+    let mut temp = tempfile::tempfile()?;
+    let limit = max_len.unwrap_or(u64::MAX);
+    let len = io::copy(
+        &mut decoder(source, compression)?.take(limit.saturating_add(1)),
+        &mut temp,
+    )?;
+    if len > limit {
+        let msg = format!("Decompresses to more than the limit of {limit} bytes");
+        return Err(Error::new(ErrorKind::InvalidData, msg));
+    }
+    temp.seek(SeekFrom::Start(0))?;
+    Ok(temp)
+}
+
+/// Decompress everything read from the compressed source into the
+/// destination, a piece at a time. Returns the number of bytes written.
+///
+/// # Errors
+///
+/// Will return `Err` on read or write failure, or if the source is corrupt.
+pub fn decompress<R: Read, W: Write>(
+    source: R,
+    mut dest: W,
+    compression: Compression,
+) -> io::Result<u64> {
+    let len = io::copy(&mut decoder(source, compression)?, &mut dest)?;
+    dest.flush()?;
+    Ok(len)
+}
+
+/// Mark the metadata as belonging to a bank with compressed items.
+///
+/// # Errors
+///
+/// Will return `Err` if the metadata cannot be changed.
+pub fn x_mark_compressed_items(metadata: &mut Metadata) -> io::Result<()> {
+    metadata.extra_set(X_ITEM_COMPRESSION_KEY, &X_ITEM_COMPRESSION)
+}
+
+/// Returns `true` if the metadata is of a bank with compressed items.
+#[must_use]
+pub fn x_has_compressed_items(metadata: &Metadata) -> bool {
+    metadata
+        .extra
+        .get(X_ITEM_COMPRESSION_KEY)
+        .and_then(Value::as_str)
+        == Some(X_ITEM_COMPRESSION)
+}
+
+/// Returns `true` if the bank has compressed items. Banks without metadata,
+/// or with metadata that cannot be read, are treated as standard banks.
+pub fn x_bank_has_compressed_items<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+) -> bool {
+    reader
+        .items()
+        .iter()
+        .find(|item| item.is_metadata_file())
+        .and_then(|item| reader.read_metadata(item).ok())
+        .is_some_and(|metadata| x_has_compressed_items(&metadata))
+}
+
+/// Compress the contents of an item.
+///
+/// # Errors
+///
+/// Will return `Err` if the compressor fails.
+pub fn x_compress_item(contents: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::encode_all(contents, zstd::DEFAULT_COMPRESSION_LEVEL)
+}
+
+/// The contents of an item of a bank with compressed items, as they were
+/// before being compressed. The metadata is returned without the mark so the
+/// result can be written to a standard bank.
+///
+/// # Errors
+///
+/// Will return `Err` on read failure or if the contents are not compressed.
+pub fn x_read_contents<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    item: &Item,
+) -> io::Result<Vec<u8>> {
+    if item.is_metadata_file() {
+        let mut metadata = reader.read_metadata(item)?;
+        metadata.extra_remove(X_ITEM_COMPRESSION_KEY);
+        return Ok(serde_json::to_vec_pretty(&metadata)?);
+    }
+    let compressed = reader.read_contents(item)?;
+    zstd::decode_all(compressed.as_slice()).map_err(|error| {
+        let msg = format!("Cannot decompress {}: {error}", item.file_name_lossy());
+        Error::new(ErrorKind::InvalidData, msg)
+    })
+}
@@ -0,0 +1,124 @@
+//! Files that contain several banks one after another, as made by some
+//! distribution tools. Each bank after the first starts right after the
+//! contents of the last item of the bank before it.
+
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Error, ErrorKind, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+use log::{debug, warn};
+
+use crate::read::BankReader;
+
+/// Part of a stream that is read as if it were the whole stream, so a bank
+/// that doesn't start at the beginning of the stream can be read.
+pub struct Section<R> {
+    inner: R,
+    start: u64,
+    len: u64,
+    position: u64,
+}
+
+impl<R: Read + Seek> Section<R> {
+    /// The bytes of the stream in the range.
+    pub fn new(inner: R, range: Range<u64>) -> Section<R> {
+        Section {
+            inner,
+            start: range.start,
+            len: range.end.saturating_sub(range.start),
+            position: 0,
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for Section<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len.saturating_sub(self.position);
+        if remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+
+        // The stream may be shared so always seek before reading.
+        self.inner
+            .seek(SeekFrom::Start(self.start + self.position))?;
+        let len = usize::try_from(remaining)
+            .unwrap_or(usize::MAX)
+            .min(buf.len());
+        let read = self.inner.read(&mut buf[..len])?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Seek for Section<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        }
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "Cannot seek before the start of the bank",
+            )
+        })?;
+        self.position = position;
+        Ok(position)
+    }
+}
+
+/// Where each bank is in a stream of banks written one after another. A
+/// stream with a single bank has one range. Anything after the last bank
+/// that is not a bank is ignored.
+///
+/// # Errors
+///
+/// Will return `Err` if the stream does not start with a bank, or on read or
+/// seek failure.
+pub fn bank_ranges<R: Read + Seek>(inner: &mut R) -> io::Result<Vec<Range<u64>>> {
+    let stream_len = inner.seek(SeekFrom::End(0))?;
+    let mut ranges: Vec<Range<u64>> = Vec::new();
+    let mut start = 0;
+    while start < stream_len {
+        let section = Section::new(&mut *inner, start..stream_len);
+        let size = match BankReader::new(BufReader::new(section)) {
+            Ok(reader) => reader.size(),
+            Err(error) if ranges.is_empty() => return Err(error),
+            Err(error) => {
+                warn!(
+                    "Ignoring {} bytes after the last bank: {error}",
+                    stream_len - start
+                );
+                break;
+            }
+        };
+        debug!("Bank {} is {size} bytes at {start}", ranges.len() + 1);
+        ranges.push(start..start + size);
+        start += size;
+    }
+    Ok(ranges)
+}
+
+/// Open every bank in a file of banks written one after another. Each reader
+/// has its own handle to the file so they can be used independently.
+///
+/// # Errors
+///
+/// Will return `Err` if the file cannot be opened, or if it does not start
+/// with a bank.
+pub fn open_all<P: AsRef<Path>>(
+    path: P,
+) -> io::Result<Vec<BankReader<'static, BufReader<Section<File>>>>> {
+    let path = path.as_ref();
+    let ranges = bank_ranges(&mut File::open(path)?)?;
+    ranges
+        .into_iter()
+        .map(|range| {
+            let section = Section::new(File::open(path)?, range);
+            BankReader::new(BufReader::new(section))
+        })
+        .collect()
+}
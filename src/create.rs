@@ -0,0 +1,324 @@
+//! The steps of creating a bank from files and directories: collecting the
+//! files, classifying them by kind, merging the metadata and writing the bank.
+//! Each step returns the next stage so an application can show what was found,
+//! such as the number of presets and samples, and ask before continuing.
+//! [`run`] goes through every step and reports each one as an [`Event`].
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use log::{debug, warn};
+
+use crate::annotations::Annotations;
+use crate::collect::{Collection, SourceFile};
+use crate::read::BankReader;
+use crate::write::BankWriter;
+use crate::{
+    annotations, collect, ItemKind, Metadata, BACKGROUND_FILE_STEM, CHANGELOG_FILE_STEM,
+    PATH_SEPARATOR,
+};
+
+/// The files found, before they are classified.
+#[derive(Clone, Debug, Default)]
+pub struct Collected {
+    pub collection: Collection,
+}
+
+impl Collected {
+    /// Find the files to include with [`collect::collect`].
+    pub fn new<I, P>(paths: I) -> Collected
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        Collected {
+            collection: collect::collect(paths),
+        }
+    }
+
+    /// Separate the background, the metadata file, the changelog and the
+    /// annotations from the other items.
+    #[must_use]
+    pub fn classify(self) -> Classified {
+        let mut classified = Classified {
+            skipped: self.collection.skipped,
+            ..Classified::default()
+        };
+        for file in self.collection.files {
+            let is_annotations = file
+                .path
+                .file_name()
+                .is_some_and(|name| name.eq_ignore_ascii_case(annotations::FILE_NAME));
+            let slot = match file.kind {
+                ItemKind::Metadata if is_annotations => &mut classified.annotations,
+                ItemKind::Background => &mut classified.background,
+                ItemKind::Metadata => &mut classified.metadata_file,
+                ItemKind::Changelog => &mut classified.changelog,
+                _ => {
+                    classified.items.push(file);
+                    continue;
+                }
+            };
+            if slot.is_some() {
+                warn!("More than one {:?} found", file.kind);
+                classified.ignored.push(file);
+            } else {
+                *slot = Some(file);
+            }
+        }
+        classified
+    }
+}
+
+/// The files found, by what they are used for in the bank.
+#[derive(Clone, Debug, Default)]
+pub struct Classified {
+    pub background: Option<SourceFile>,
+    pub metadata_file: Option<SourceFile>,
+    pub changelog: Option<SourceFile>,
+
+    /// Curation notes about the items, see [`annotations`].
+    pub annotations: Option<SourceFile>,
+
+    /// Every other file to include.
+    pub items: Vec<SourceFile>,
+
+    /// Backgrounds, metadata files, changelogs and annotations after the
+    /// first of each, which are not included.
+    pub ignored: Vec<SourceFile>,
+
+    pub skipped: Vec<collect::Skipped>,
+}
+
+impl Classified {
+    /// Number of files of each kind that will be included.
+    #[must_use]
+    pub fn counts(&self) -> BTreeMap<ItemKind, usize> {
+        let mut counts = BTreeMap::new();
+        for file in self.files() {
+            *counts.entry(file.kind).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Every file that will be included, in the order they are written.
+    pub fn files(&self) -> impl Iterator<Item = &SourceFile> {
+        self.background
+            .iter()
+            .chain(self.metadata_file.iter())
+            .chain(self.changelog.iter())
+            .chain(self.annotations.iter())
+            .chain(self.items.iter())
+    }
+
+    /// Include the MP3 and FLAC files in the directory and its
+    /// subdirectories as previews of the presets, see [`crate::previews`].
+    /// Other files are left out with a warning.
+    pub fn add_previews<P: AsRef<Path>>(&mut self, dir: P) {
+        let collection = collect::collect([dir]);
+        self.skipped.extend(collection.skipped);
+        for file in collection.files {
+            if file
+                .path
+                .extension()
+                .is_some_and(|extension| ItemKind::Preview.has_extension(extension))
+            {
+                self.items.push(SourceFile {
+                    kind: ItemKind::Preview,
+                    ..file
+                });
+            } else {
+                warn!(
+                    "Leaving out {} since previews must be {} files",
+                    file.path.display(),
+                    ItemKind::Preview.extensions().join(" or ")
+                );
+            }
+        }
+    }
+
+    /// The metadata from the metadata file, or the default metadata when
+    /// there is no metadata file.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the metadata file cannot be read or parsed.
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        match &self.metadata_file {
+            Some(file) => {
+                debug!("Metadata is from the file {}", file.path.display());
+                BankReader::parse_metadata(&fs::read(&file.path)?)
+            }
+            None => Ok(Metadata::default()),
+        }
+    }
+
+    /// Write the metadata to the bank instead of the metadata file.
+    #[must_use]
+    pub fn with_metadata(self, metadata: Metadata) -> Merged {
+        Merged {
+            classified: self,
+            metadata: Some(metadata),
+        }
+    }
+
+    /// Include the metadata file untouched, if there is one.
+    #[must_use]
+    pub fn keep_metadata(self) -> Merged {
+        Merged {
+            classified: self,
+            metadata: None,
+        }
+    }
+}
+
+/// The files and the metadata of the bank, ready to be written.
+#[derive(Clone, Debug)]
+pub struct Merged {
+    pub classified: Classified,
+
+    /// Metadata to write instead of the metadata file.
+    pub metadata: Option<Metadata>,
+}
+
+impl Merged {
+    /// Add the background, the metadata and the other items to the writer
+    /// without writing the bank, so more items can be added. Returns the path
+    /// in the bank of each file that was added with the file it is from.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a file cannot be read or added.
+    pub fn add_to<W: Write>(
+        &self,
+        writer: &mut BankWriter<W>,
+    ) -> io::Result<Vec<(String, PathBuf)>> {
+        let mut added = Vec::new();
+
+        // Background is first.
+        if let Some(file) = &self.classified.background {
+            debug!("Background is from the file {}", file.path.display());
+            match file.path.extension() {
+                Some(extension) if ItemKind::Background.has_extension(extension) => {
+                    let mut file_name = OsString::from(BACKGROUND_FILE_STEM);
+                    file_name.push(".");
+                    file_name.push(extension);
+                    writer.add_file(file.kind, &file_name, &file.path)?;
+                    added.push((file_name.to_string_lossy().into_owned(), file.path.clone()));
+                }
+                Some(extension) => warn!(
+                    "Unsupported type of background file, extension {} is not {}",
+                    extension.to_string_lossy(),
+                    ItemKind::Background.extensions().join(" or ")
+                ),
+                None => warn!(
+                    "Cannot find the extension for the background image {}",
+                    file.path.display()
+                ),
+            }
+        }
+
+        match (&self.metadata, &self.classified.metadata_file) {
+            (Some(metadata), _) => writer.add_metadata(metadata)?,
+            (None, Some(file)) => {
+                writer.add_file(file.kind, Metadata::FILE_NAME.as_ref(), &file.path)?;
+                added.push((Metadata::FILE_NAME.to_string(), file.path.clone()));
+            }
+            (None, None) => {}
+        }
+
+        // The changelog is always at the top of the bank with the same name.
+        if let Some(file) = &self.classified.changelog {
+            let mut file_name = OsString::from(CHANGELOG_FILE_STEM);
+            file_name.push(".");
+            file_name.push(
+                file.path
+                    .extension()
+                    .unwrap_or_default()
+                    .to_ascii_lowercase(),
+            );
+            writer.add_file(file.kind, &file_name, &file.path)?;
+            added.push((file_name.to_string_lossy().into_owned(), file.path.clone()));
+        }
+
+        // The annotations are checked so a mistake is found before release.
+        if let Some(file) = &self.classified.annotations {
+            let contents = fs::read(&file.path)?;
+            Annotations::parse(&contents).map_err(|error| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Cannot read annotations {}: {error}", file.path.display()),
+                )
+            })?;
+            writer.add_with_path(file.kind, annotations::FILE_NAME.as_ref(), contents)?;
+            added.push((annotations::FILE_NAME.to_string(), file.path.clone()));
+        }
+
+        for file in &self.classified.items {
+            let Some(file_name) = file.path.file_name() else {
+                warn!(
+                    "Skipping file {} because the file name cannot be extracted",
+                    file.path.display()
+                );
+                continue;
+            };
+            writer.add(file.kind, file_name, fs::read(&file.path)?)?;
+            let bank_path = match file.kind.directory() {
+                Some(dir) => format!("{dir}{PATH_SEPARATOR}{}", file_name.to_string_lossy()),
+                None => file_name.to_string_lossy().into_owned(),
+            };
+            added.push((bank_path, file.path.clone()));
+        }
+        Ok(added)
+    }
+}
+
+/// A step of [`run`] that has finished, with its result.
+#[derive(Debug)]
+pub enum Event<'a> {
+    Collected(&'a Collection),
+    Classified(&'a Classified),
+    MetadataMerged(&'a Merged),
+
+    /// The bank was written with the number of files added.
+    Written {
+        files: usize,
+    },
+}
+
+/// Create a bank from the files and directories, keeping any metadata file
+/// untouched. The observer is given each step as it finishes and returns
+/// `false` to stop before the next step. Returns whether the bank was
+/// written.
+///
+/// # Errors
+///
+/// Will return `Err` if a file cannot be read or on write failure.
+pub fn run<I, P, W, F>(paths: I, writer: &mut BankWriter<W>, mut observe: F) -> io::Result<bool>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+    W: Write,
+    F: FnMut(Event<'_>) -> bool,
+{
+    let collected = Collected::new(paths);
+    if !observe(Event::Collected(&collected.collection)) {
+        return Ok(false);
+    }
+    let classified = collected.classify();
+    if !observe(Event::Classified(&classified)) {
+        return Ok(false);
+    }
+    let merged = classified.keep_metadata();
+    if !observe(Event::MetadataMerged(&merged)) {
+        return Ok(false);
+    }
+    let added = merged.add_to(writer)?;
+    writer.write()?;
+    observe(Event::Written { files: added.len() });
+    Ok(true)
+}
@@ -0,0 +1,216 @@
+//! What changed between two versions of a bank, such as before replacing a
+//! bank with a new one, or between a bank and a directory of files it was
+//! extracted to.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::read::{BankReader, Item};
+use crate::{hash, FieldChange, Metadata, PATH_SEPARATOR};
+
+/// The files added, removed and changed between two versions of a bank.
+/// Directories are not compared since they are implied by the files.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BankDiff {
+    /// Paths of the files only in the new bank.
+    pub added: Vec<String>,
+
+    /// Paths of the files only in the old bank.
+    pub removed: Vec<String>,
+
+    /// Paths of the files in both banks with different contents.
+    pub changed: Vec<String>,
+
+    /// Number of files that are the same in both banks.
+    pub unchanged: usize,
+}
+
+impl BankDiff {
+    /// Compare the files of the old and new banks by path and contents. The
+    /// contents are only read when the sizes are the same.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` on read or seek failure, or if a bank is truncated.
+    pub fn between<OldType, NewType>(
+        old: &mut BankReader<OldType>,
+        new: &mut BankReader<NewType>,
+    ) -> io::Result<BankDiff>
+    where
+        OldType: Read + Seek + BufRead,
+        NewType: Read + Seek + BufRead,
+    {
+        let mut diff = BankDiff::default();
+        let mut new_files = files(new);
+        for (path, old_item) in files(old) {
+            let Some(new_item) = new_files.remove(&path) else {
+                diff.removed.push(path);
+                continue;
+            };
+            let same = old_item.size() == new_item.size()
+                && hash::item_digest(old, &old_item)? == hash::item_digest(new, &new_item)?;
+            if same {
+                diff.unchanged += 1;
+            } else {
+                diff.changed.push(path);
+            }
+        }
+        diff.added = new_files.into_keys().collect();
+        Ok(diff)
+    }
+
+    /// Compare the files of the bank with the files in the directory and its
+    /// subdirectories, such as a bank and the directory it was extracted to
+    /// that is being worked on. The bank is the old side, so files only in
+    /// the directory are added and files only in the bank are removed. The
+    /// contents are only read when the sizes are the same.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the directory cannot be read, on read or seek
+    /// failure, or if the bank is truncated.
+    pub fn with_directory<ReaderType: Read + Seek + BufRead>(
+        reader: &mut BankReader<ReaderType>,
+        dir: &Path,
+    ) -> io::Result<BankDiff> {
+        let mut diff = BankDiff::default();
+        let mut dir_files = BTreeMap::new();
+        directory_files(dir, "", &mut dir_files)?;
+        for (path, item) in files(reader) {
+            let Some(file_path) = dir_files.remove(&path) else {
+                diff.removed.push(path);
+                continue;
+            };
+            let same = fs::metadata(&file_path)?.len() == item.size()
+                && hash::digest(BufReader::new(File::open(&file_path)?))?
+                    == hash::item_digest(reader, &item)?;
+            if same {
+                diff.unchanged += 1;
+            } else {
+                diff.changed.push(path);
+            }
+        }
+        diff.added = dir_files.into_keys().collect();
+        Ok(diff)
+    }
+
+    /// Every file of a bank that replaces nothing, as added.
+    #[must_use]
+    pub fn of_new_bank<NewType: Read + Seek + BufRead>(new: &BankReader<NewType>) -> BankDiff {
+        BankDiff {
+            added: files(new).into_keys().collect(),
+            ..BankDiff::default()
+        }
+    }
+
+    /// Returns `true` if no files were added, removed or changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// The path of every file that is different with `+` for added, `-` for
+    /// removed and `~` for changed, sorted by path.
+    #[must_use]
+    pub fn entries(&self) -> Vec<(char, &str)> {
+        let mut entries = self
+            .added
+            .iter()
+            .map(|path| ('+', path.as_str()))
+            .chain(self.removed.iter().map(|path| ('-', path.as_str())))
+            .chain(self.changed.iter().map(|path| ('~', path.as_str())))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(_, path)| *path);
+        entries
+    }
+}
+
+/// One line for each of the [`BankDiff::entries`].
+impl fmt::Display for BankDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (sign, path) in self.entries() {
+            writeln!(f, "{sign} {path}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The fields of the metadata with different values in the old and new
+/// banks by their name in the JSON, including fields kibank doesn't know. A
+/// field only in one of the banks is null in the other, and a bank without
+/// a metadata file has the default metadata.
+///
+/// # Errors
+///
+/// Will return `Err` if the metadata of either bank cannot be read.
+pub fn metadata_differences<OldType, NewType>(
+    old: &mut BankReader<OldType>,
+    new: &mut BankReader<NewType>,
+) -> io::Result<BTreeMap<String, FieldChange>>
+where
+    OldType: Read + Seek + BufRead,
+    NewType: Read + Seek + BufRead,
+{
+    let old = metadata(old)?;
+    let new = metadata(new)?;
+    let changes = new.changes_from(&old);
+    let mut differences = changes.changed;
+    for field in changes.extra_dropped {
+        let from = old.extra.get(&field).cloned().unwrap_or(Value::Null);
+        differences.insert(
+            field,
+            FieldChange {
+                from,
+                to: Value::Null,
+            },
+        );
+    }
+    Ok(differences)
+}
+
+fn metadata<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+) -> io::Result<Metadata> {
+    match reader.get(Metadata::FILE_NAME).cloned() {
+        Some(item) => reader.read_metadata(&item),
+        None => Ok(Metadata::default()),
+    }
+}
+
+/// The files of the bank by path.
+fn files<ReaderType: Read + Seek + BufRead>(
+    reader: &BankReader<ReaderType>,
+) -> BTreeMap<String, Item<'static>> {
+    reader
+        .items()
+        .into_iter()
+        .filter(Item::is_file)
+        .map(|item| (item.file_name_lossy(), item.into_owned()))
+        .collect()
+}
+
+/// Add the files in the directory and its subdirectories by their path in a
+/// bank, which starts with the prefix.
+fn directory_files(
+    dir: &Path,
+    prefix: &str,
+    files: &mut BTreeMap<String, PathBuf>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = format!("{prefix}{}", entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            directory_files(&entry.path(), &format!("{path}{PATH_SEPARATOR}"), files)?;
+        } else {
+            files.insert(path, entry.path());
+        }
+    }
+    Ok(())
+}
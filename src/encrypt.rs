@@ -0,0 +1,91 @@
+//! Banks encrypted with a password, for distributing content that must not be
+//! readable by anyone else, such as pre-release content for beta testers.
+//!
+//! An encrypted bank starts with [`MAGIC`], followed by the salt used to derive
+//! the key from the password with Argon2id, the nonce, then the entire bank
+//! encrypted with AES-256-GCM.
+
+use std::io;
+use std::io::{Error, ErrorKind};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+
+/// First bytes of an encrypted bank.
+pub const MAGIC: &[u8] = b"kibankE1";
+
+/// File name extension added to encrypted banks, without the leading dot.
+pub const EXTENSION: &str = "enc";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Returns `true` if the bytes are the start of an encrypted bank.
+#[must_use]
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Encrypt the entire bank with a key derived from the password.
+///
+/// # Errors
+///
+/// Will return `Err` if the key cannot be derived or the bank cannot be encrypted.
+pub fn encrypt(bank: &[u8], password: &[u8]) -> io::Result<Vec<u8>> {
+    let mut salt = [0_u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let cipher = Aes256Gcm::new(&derive_key(password, &salt)?);
+    let ciphertext = cipher
+        .encrypt(&nonce, bank)
+        .map_err(|_| Error::other("Cannot encrypt the bank"))?;
+
+    let mut encrypted = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+    encrypted.extend_from_slice(MAGIC);
+    encrypted.extend_from_slice(&salt);
+    encrypted.extend_from_slice(&nonce);
+    encrypted.extend_from_slice(&ciphertext);
+    Ok(encrypted)
+}
+
+/// Decrypt a bank encrypted with [`encrypt`].
+///
+/// # Errors
+///
+/// Will return `Err` if the bytes are not an encrypted bank, the password is
+/// wrong or the encrypted bank has been changed.
+pub fn decrypt(encrypted: &[u8], password: &[u8]) -> io::Result<Vec<u8>> {
+    if !is_encrypted(encrypted) {
+        return Err(Error::new(ErrorKind::InvalidData, "Not an encrypted bank"));
+    }
+    let header_len = MAGIC.len() + SALT_LEN + NONCE_LEN;
+    if encrypted.len() < header_len {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "Encrypted bank is truncated",
+        ));
+    }
+    let salt = &encrypted[MAGIC.len()..MAGIC.len() + SALT_LEN];
+    let nonce = Nonce::from_slice(&encrypted[MAGIC.len() + SALT_LEN..header_len]);
+
+    let cipher = Aes256Gcm::new(&derive_key(password, salt)?);
+    cipher
+        .decrypt(nonce, &encrypted[header_len..])
+        .map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                "Cannot decrypt the bank, the password is wrong or the bank is corrupt",
+            )
+        })
+}
+
+fn derive_key(password: &[u8], salt: &[u8]) -> io::Result<Key<Aes256Gcm>> {
+    let mut key = Key::<Aes256Gcm>::default();
+    Argon2::default()
+        .hash_password_into(password, salt, &mut key)
+        .map_err(|error| Error::other(format!("Cannot derive the key: {error}")))?;
+    Ok(key)
+}
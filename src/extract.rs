@@ -0,0 +1,650 @@
+//! Write the contents of a bank to a directory.
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, Error, ErrorKind, Read, Seek};
+use std::num::NonZeroUsize;
+use std::path::{Component, Path, PathBuf, MAIN_SEPARATOR};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::{self, CancellationToken};
+#[cfg(feature = "checksums")]
+use crate::hash::{self, Algorithm};
+use crate::read::{BankReader, Item};
+use crate::{template, ItemKind, Metadata, PATH_SEPARATOR};
+
+/// Added to the file name of each extracted preset for the file recording
+/// the bank it came from, with [`ExtractOptions::tag_origin`].
+pub const ORIGIN_SUFFIX: &str = ".origin.json";
+
+/// Settings that change how a bank is extracted.
+#[derive(Clone, Debug, Default)]
+pub struct ExtractOptions {
+    /// Paths of items in the bank that are not extracted, ignoring ASCII case.
+    pub skip: Vec<String>,
+
+    /// Stops extracting before the next item when cancelled.
+    pub cancel: Option<CancellationToken>,
+
+    /// Continue with the other items when an item cannot be extracted,
+    /// instead of stopping at the first failure.
+    pub keep_going: bool,
+
+    /// Template for the path each file is extracted to in the destination
+    /// directory instead of its path in the bank, such as
+    /// `{kind}/{stem}_{bankid}.{ext}`. See [`template::item_value`] for the
+    /// placeholders. Directories are separated by `/` and are only created
+    /// when a file is extracted into them.
+    pub rename_template: Option<String>,
+
+    /// Write an [`Origin`] next to each extracted preset, so presets from
+    /// many banks can be traced back to the bank they came from.
+    pub tag_origin: bool,
+
+    /// Only files of at least this many bytes are extracted.
+    pub min_size: Option<u64>,
+
+    /// Only files of at most this many bytes are extracted.
+    pub max_size: Option<u64>,
+
+    /// Only files whose contents have one of these SHA-256 digests, in hex,
+    /// are extracted, such as to pull a known sample out of a large bank.
+    pub sha256: Vec<String>,
+
+    /// Permissions of each extracted file on Unix, such as `0o644`, instead
+    /// of what the umask of the process leaves.
+    pub file_mode: Option<u32>,
+
+    /// User ID to own each extracted file on Unix, which usually needs root.
+    pub uid: Option<u32>,
+
+    /// Group ID to own each extracted file on Unix.
+    pub gid: Option<u32>,
+
+    /// Flush each file to the disk before extracting the next, so an
+    /// extraction onto a network share is complete when it finishes.
+    pub fsync: bool,
+
+    /// Decompress the contents of the items of a bank written with
+    /// [`crate::write::BankWriterOptions::x_compress_items`]. Those banks
+    /// cannot be extracted without it, and other banks cannot be extracted
+    /// with it.
+    #[cfg(feature = "compression")]
+    pub x_decompress: bool,
+}
+
+impl ExtractOptions {
+    /// Only some files are extracted because of their size or contents.
+    /// Directories are then only created when a file is extracted into them.
+    #[must_use]
+    pub fn filters_files(&self) -> bool {
+        self.min_size.is_some() || self.max_size.is_some() || !self.sha256.is_empty()
+    }
+}
+
+/// An item that could not be extracted.
+#[derive(Debug)]
+pub struct Failure {
+    /// Path of the item in the bank.
+    pub path: String,
+
+    pub error: Error,
+}
+
+/// What was extracted by [`extract_all`].
+#[derive(Debug, Default)]
+pub struct ExtractionReport {
+    /// Where each file was extracted to.
+    pub extracted: Vec<PathBuf>,
+
+    /// Items that could not be extracted, which is only ever not empty when
+    /// [`ExtractOptions::keep_going`] is set.
+    pub failed: Vec<Failure>,
+}
+
+impl ExtractionReport {
+    /// Every item was extracted.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// A bank extracted by [`extract_banks`].
+#[derive(Debug)]
+pub struct BankExtraction {
+    /// Path of the bank file.
+    pub bank_path: PathBuf,
+
+    /// The directory the bank was extracted to, inside the destination.
+    pub dest_dir: PathBuf,
+
+    /// What was extracted, or why the bank could not be extracted.
+    pub result: io::Result<ExtractionReport>,
+}
+
+/// The bank a preset was extracted from, written next to the preset with
+/// [`ExtractOptions::tag_origin`].
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Origin {
+    pub bank_id: String,
+    pub bank_name: String,
+
+    /// Path of the preset in the bank.
+    pub path: String,
+}
+
+impl Origin {
+    /// Where the origin of the extracted preset is written.
+    #[must_use]
+    pub fn sidecar_path<P: AsRef<Path>>(preset_path: P) -> PathBuf {
+        let mut path = preset_path.as_ref().as_os_str().to_owned();
+        path.push(ORIGIN_SUFFIX);
+        PathBuf::from(path)
+    }
+
+    /// The origin written next to the extracted preset.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be read or parsed.
+    pub fn read<P: AsRef<Path>>(preset_path: P) -> io::Result<Origin> {
+        let json = fs::read(Origin::sidecar_path(preset_path))?;
+        serde_json::from_slice(&json).map_err(Into::into)
+    }
+
+    /// Write the origin next to the extracted preset.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` on write failure.
+    pub fn write<P: AsRef<Path>>(&self, preset_path: P) -> io::Result<()> {
+        let sidecar_path = Origin::sidecar_path(preset_path);
+        info!("Writing the origin to {}", sidecar_path.display());
+        fs::write(sidecar_path, serde_json::to_vec_pretty(self)?)
+    }
+}
+
+/// Where the item with the path in the bank is extracted to in the
+/// destination directory.
+///
+/// # Errors
+///
+/// Will return `Err` if the path would be outside the destination directory.
+pub fn item_path(dest_dir: &Path, path_bytes: &[u8]) -> io::Result<PathBuf> {
+    // Banks have a consistent separator that needs to be changed to match
+    // the current platform.
+    let platform_path = path_bytes
+        .iter()
+        .map(|c| {
+            if *c == PATH_SEPARATOR as u8 {
+                MAIN_SEPARATOR as u8
+            } else {
+                *c
+            }
+        })
+        .collect::<Vec<u8>>();
+
+    #[cfg(unix)]
+    let item_path = {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(OsStr::from_bytes(&platform_path))
+    };
+    #[cfg(not(unix))]
+    let item_path = PathBuf::from(OsStr::new(&*String::from_utf8_lossy(&platform_path)));
+
+    // Path::join() replaces the entire path with an absolute path, which
+    // would let the bank write outside the destination. See Rust issue
+    // #16507 at https://github.com/rust-lang/rust/issues/16507
+    let escapes = item_path
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+    if escapes {
+        let msg = format!(
+            "File {} is outside the destination and cannot be extracted",
+            path_bytes.escape_ascii()
+        );
+        return Err(Error::new(ErrorKind::InvalidData, msg));
+    }
+
+    Ok(dest_dir.join(item_path))
+}
+
+/// Write every item of the bank to the destination directory, creating
+/// directories as they are needed. Items are read in the order of their
+/// contents so the bank is read without seeking backwards. Returns the files
+/// that were extracted and, with [`ExtractOptions::keep_going`], the items
+/// that failed.
+///
+/// # Errors
+///
+/// Will return `Err` with [`crate::cancel::Cancelled`] if it is cancelled,
+/// or if the items are compressed and [`ExtractOptions::x_decompress`] is not
+/// set or the other way around, or if the files cannot be renamed with
+/// [`ExtractOptions::rename_template`]. Unless [`ExtractOptions::keep_going`]
+/// is set, will also return `Err` on read or write failure, including of the
+/// [`Origin`] of a preset, or if an item would be outside the destination
+/// directory.
+pub fn extract_all<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    dest_dir: &Path,
+    options: &ExtractOptions,
+) -> io::Result<ExtractionReport> {
+    #[cfg(feature = "compression")]
+    let decompress = {
+        let compressed = crate::compress::x_bank_has_compressed_items(reader);
+        if compressed != options.x_decompress {
+            let msg = if compressed {
+                "The items of the bank are compressed, which needs the experimental decompression"
+            } else {
+                "The items of the bank are not compressed"
+            };
+            return Err(Error::new(ErrorKind::InvalidInput, msg));
+        }
+        compressed
+    };
+    #[cfg(not(feature = "compression"))]
+    let decompress = false;
+
+    let items = reader
+        .items_in_data_order()
+        .into_iter()
+        .filter(|item| {
+            !options
+                .skip
+                .iter()
+                .any(|skip| item.path_bytes().eq_ignore_ascii_case(skip.as_bytes()))
+        })
+        .collect::<Vec<_>>();
+    let items = if options.filters_files() {
+        let mut selected = Vec::new();
+        for item in items {
+            if is_selected(reader, &item, options)? {
+                selected.push(item);
+            }
+        }
+        info!("{} files match the filters", selected.len());
+        selected
+    } else {
+        items
+    };
+    let cancel = options.cancel.as_ref();
+    if let Some(cancel) = cancel {
+        cancel.start(items.len());
+    }
+
+    let metadata = match reader.get(Metadata::FILE_NAME).cloned() {
+        Some(item) if options.rename_template.is_some() || options.tag_origin => {
+            reader.read_metadata(&item)?
+        }
+        _ => Metadata::default(),
+    };
+    let renamed = match &options.rename_template {
+        Some(rename_template) => Some(rename_all(&items, &metadata, rename_template)?),
+        None => None,
+    };
+
+    let mut report = ExtractionReport::default();
+    for (position, item) in items.iter().enumerate() {
+        cancel::check(cancel)?;
+        let extracted = match &renamed {
+            Some(renamed) => match &renamed[position] {
+                Some(path) => {
+                    extract_item(reader, item, path.as_bytes(), dest_dir, options, decompress)
+                }
+                None => Ok(None),
+            },
+            None => extract_item(
+                reader,
+                item,
+                item.path_bytes(),
+                dest_dir,
+                options,
+                decompress,
+            ),
+        };
+        let extracted = extracted.and_then(|dest_path| match dest_path {
+            Some(dest_path) if options.tag_origin && is_preset(item) => {
+                let origin = Origin {
+                    bank_id: metadata.id.clone(),
+                    bank_name: metadata.name.clone(),
+                    path: item.file_name_lossy(),
+                };
+                origin.write(&dest_path)?;
+                Ok(Some(dest_path))
+            }
+            dest_path => Ok(dest_path),
+        });
+        match extracted {
+            Ok(Some(dest_path)) => report.extracted.push(dest_path),
+            Ok(None) => {}
+            Err(error) if options.keep_going => {
+                warn!("Cannot extract {}: {error}", item.file_name_lossy());
+                report.failed.push(Failure {
+                    path: item.file_name_lossy(),
+                    error,
+                });
+            }
+            Err(error) => return Err(error),
+        }
+        if let Some(cancel) = cancel {
+            cancel.item_done();
+        }
+    }
+    Ok(report)
+}
+
+/// Extract each bank into its own directory inside the destination, named by
+/// [`bank_dir_names`]. Extracting is mostly waiting for the disk so up to
+/// `threads` banks are extracted at once, or one for each CPU when `threads`
+/// is zero. The results are in the same order as the paths.
+///
+/// A bank that cannot be extracted doesn't stop the others, and
+/// [`ExtractOptions::keep_going`] applies to the items of each bank.
+/// Cancelling stops before the next bank and progress counts banks.
+///
+/// # Errors
+///
+/// Will return `Err` with [`crate::cancel::Cancelled`] if it is cancelled.
+/// Banks that cannot be read or extracted have an `Err` result.
+pub fn extract_banks<P: AsRef<Path> + Sync>(
+    bank_paths: &[P],
+    dest_dir: &Path,
+    options: &ExtractOptions,
+    threads: usize,
+) -> io::Result<Vec<BankExtraction>> {
+    // The names depend on every bank so they are found before extracting.
+    let ids = bank_paths
+        .iter()
+        .map(|bank_path| {
+            let bank_path = bank_path.as_ref();
+            bank_id(bank_path).unwrap_or_else(|error| {
+                warn!(
+                    "Cannot read the id of bank {}: {error}",
+                    bank_path.display()
+                );
+                String::new()
+            })
+        })
+        .collect::<Vec<_>>();
+    let fallbacks = bank_paths
+        .iter()
+        .map(|bank_path| {
+            bank_path
+                .as_ref()
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect::<Vec<_>>();
+    let names = bank_dir_names(&ids, &fallbacks);
+
+    let cancel = options.cancel.as_ref();
+    if let Some(cancel) = cancel {
+        cancel.start(bank_paths.len());
+    }
+    let bank_options = ExtractOptions {
+        cancel: None,
+        ..options.clone()
+    };
+    let threads = match threads {
+        0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        threads => threads,
+    }
+    .min(bank_paths.len())
+    .max(1);
+
+    // Each thread takes the next bank that hasn't been started.
+    let next = AtomicUsize::new(0);
+    let extract_next = || {
+        let mut extractions = Vec::new();
+        loop {
+            if cancel.is_some_and(CancellationToken::is_cancelled) {
+                return extractions;
+            }
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(bank_path) = bank_paths.get(index) else {
+                return extractions;
+            };
+            let bank_path = bank_path.as_ref();
+            let bank_dest_dir = dest_dir.join(&names[index]);
+            info!(
+                "Extracting bank {} to {}",
+                bank_path.display(),
+                bank_dest_dir.display()
+            );
+            let result = BankReader::open(bank_path)
+                .and_then(|mut reader| extract_all(&mut reader, &bank_dest_dir, &bank_options));
+            extractions.push((
+                index,
+                BankExtraction {
+                    bank_path: bank_path.to_owned(),
+                    dest_dir: bank_dest_dir,
+                    result,
+                },
+            ));
+            if let Some(cancel) = cancel {
+                cancel.item_done();
+            }
+        }
+    };
+    let mut extractions = thread::scope(|scope| {
+        let workers = (1..threads)
+            .map(|_| scope.spawn(extract_next))
+            .collect::<Vec<_>>();
+        let mut extractions = extract_next();
+        for worker in workers {
+            extractions.extend(worker.join().expect("Extracting threads don't panic"));
+        }
+        extractions
+    });
+    cancel::check(cancel)?;
+    extractions.sort_by_key(|(index, _)| *index);
+    Ok(extractions
+        .into_iter()
+        .map(|(_, extraction)| extraction)
+        .collect())
+}
+
+/// Names of the directories to extract banks to, in the same order as their
+/// ids. Each is the id made safe with [`Metadata::sanitize_id`], or the
+/// fallback, such as the name of the bank file, when that leaves nothing
+/// usable. When names are the same ignoring ASCII case the later banks get
+/// `-2`, `-3` and so on added, so every bank has its own directory.
+#[must_use]
+pub fn bank_dir_names(ids: &[String], fallbacks: &[String]) -> Vec<String> {
+    let usable = |name: String| Some(name).filter(|name| !name.chars().all(|c| c == '.'));
+    let mut taken = HashSet::new();
+    let mut names = Vec::with_capacity(ids.len());
+    for (position, id) in ids.iter().enumerate() {
+        let name = usable(Metadata::sanitize_id(id))
+            .or_else(|| {
+                let fallback = fallbacks.get(position)?;
+                usable(Metadata::sanitize_id(fallback))
+            })
+            .unwrap_or_else(|| "bank".to_string());
+        let mut unique = name.clone();
+        let mut count = 1;
+        while !taken.insert(unique.to_ascii_lowercase()) {
+            count += 1;
+            unique = format!("{name}-{count}");
+        }
+        names.push(unique);
+    }
+    names
+}
+
+/// The id in the metadata of the bank, which is empty if it has none.
+fn bank_id(bank_path: &Path) -> io::Result<String> {
+    let mut reader = BankReader::open(bank_path)?;
+    match reader.get(Metadata::FILE_NAME).cloned() {
+        Some(item) => Ok(reader.read_metadata(&item)?.id),
+        None => Ok(String::new()),
+    }
+}
+
+/// The path in the destination of each file rendered from the template, in
+/// the style of a bank, or `None` for directories.
+///
+/// # Errors
+///
+/// Will return `Err` if the template cannot be rendered, or if a file would
+/// have no name or the same name as another file, ignoring ASCII case.
+fn rename_all(
+    items: &[Item],
+    metadata: &Metadata,
+    rename_template: &str,
+) -> io::Result<Vec<Option<String>>> {
+    let mut seen = HashSet::new();
+    let mut renamed = Vec::with_capacity(items.len());
+    for item in items {
+        if item.is_directory() {
+            renamed.push(None);
+            continue;
+        }
+        let path = item.file_name_lossy();
+        let rendered = template::render(rename_template, |placeholder| {
+            template::item_value(&path, metadata, placeholder)
+        })?;
+        // Empty directories, such as from an empty placeholder, are left out.
+        let new_path = rendered
+            .split(PATH_SEPARATOR)
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(&PATH_SEPARATOR.to_string());
+        if new_path.is_empty() {
+            let msg = format!("{path} would be renamed to an empty path");
+            return Err(Error::new(ErrorKind::InvalidInput, msg));
+        }
+        if !seen.insert(new_path.to_ascii_lowercase()) {
+            let msg = format!("More than one file would be extracted to {new_path}");
+            return Err(Error::new(ErrorKind::InvalidInput, msg));
+        }
+        renamed.push(Some(new_path));
+    }
+    Ok(renamed)
+}
+
+/// Extract a single item to the path, in the style of a bank, in the
+/// destination directory. Returns where the item was written if it is a file.
+fn extract_item<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    item: &Item,
+    path_bytes: &[u8],
+    dest_dir: &Path,
+    options: &ExtractOptions,
+    decompress: bool,
+) -> io::Result<Option<PathBuf>> {
+    let dest_path = item_path(dest_dir, path_bytes)?;
+    if item.is_directory() {
+        info!("Creating directory {}", dest_path.display());
+        fs::create_dir_all(&dest_path)?;
+        Ok(None)
+    } else {
+        info!(
+            "Extracting {} to {}",
+            item.file_name_lossy(),
+            dest_path.display()
+        );
+
+        // Create missing intermediate directories
+        if let Some(parent_dir) = dest_path.parent() {
+            fs::create_dir_all(parent_dir)?;
+        }
+        let mut file = File::create(&dest_path)?;
+        if decompress {
+            #[cfg(feature = "compression")]
+            {
+                use std::io::Write;
+                file.write_all(&crate::compress::x_read_contents(reader, item)?)?;
+            }
+        } else {
+            reader.copy_to(item, &mut file)?;
+        }
+        finish_file(&file, options)?;
+        Ok(Some(dest_path))
+    }
+}
+
+/// Give the extracted file the permissions and owner it was asked to have,
+/// and flush it to the disk.
+fn finish_file(file: &File, options: &ExtractOptions) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{fchown, PermissionsExt};
+
+        if let Some(mode) = options.file_mode {
+            file.set_permissions(fs::Permissions::from_mode(mode))?;
+        }
+        if options.uid.is_some() || options.gid.is_some() {
+            fchown(file, options.uid, options.gid)?;
+        }
+    }
+    #[cfg(not(unix))]
+    if options.file_mode.is_some() || options.uid.is_some() || options.gid.is_some() {
+        let msg = "File modes and owners can only be set on Unix";
+        return Err(Error::new(ErrorKind::Unsupported, msg));
+    }
+
+    if options.fsync {
+        file.sync_all()?;
+    }
+    Ok(())
+}
+
+/// The item is a file that passes the size and digest filters. Digests are
+/// only found for files of the right size.
+fn is_selected<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    item: &Item,
+    options: &ExtractOptions,
+) -> io::Result<bool> {
+    let size = item.size();
+    if item.is_directory()
+        || options.min_size.is_some_and(|min_size| size < min_size)
+        || options.max_size.is_some_and(|max_size| size > max_size)
+    {
+        return Ok(false);
+    }
+    if options.sha256.is_empty() {
+        return Ok(true);
+    }
+    matches_sha256(reader, item, &options.sha256)
+}
+
+/// The SHA-256 digest of the contents of the item is one of the digests.
+#[cfg(feature = "checksums")]
+fn matches_sha256<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    item: &Item,
+    digests: &[String],
+) -> io::Result<bool> {
+    let digest = hash::item_digest_using(reader, item, Algorithm::Sha256)?;
+    Ok(digests
+        .iter()
+        .any(|sha256| sha256.eq_ignore_ascii_case(&digest)))
+}
+
+#[cfg(not(feature = "checksums"))]
+fn matches_sha256<ReaderType: Read + Seek + BufRead>(
+    _reader: &mut BankReader<ReaderType>,
+    _item: &Item,
+    _digests: &[String],
+) -> io::Result<bool> {
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        "Matching SHA-256 digests needs the checksums feature",
+    ))
+}
+
+fn is_preset(item: &Item) -> bool {
+    ItemKind::from(Path::new(&item.file_name_lossy())).is_some_and(|kind| kind.is_preset())
+}
@@ -0,0 +1,165 @@
+//! Functions for hosts written in C or other languages, such as plugin hosts.
+//! Items are given to a callback one at a time and contents are read in
+//! pieces into buffers owned by the host, so a host that cannot allocate
+//! large buffers never has to. Build a shared library with
+//! `cargo rustc --lib --release --no-default-features --features ffi --crate-type cdylib`.
+//!
+//! A bank may be used by one thread at a time.
+
+use std::ffi::{c_char, c_void, CStr};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::ptr;
+
+use log::debug;
+
+use crate::read::{BankReader, OwnedItem};
+
+/// A bank opened with [`kibank_open`].
+pub struct KibankBank {
+    file: File,
+    items: Vec<OwnedItem>,
+}
+
+/// An item given to the callback of [`kibank_for_each_item`]. The path is
+/// only valid during the call and is not null terminated.
+#[repr(C)]
+pub struct KibankItem {
+    /// Position of the item, used to read its contents.
+    pub index: usize,
+
+    pub path: *const u8,
+    pub path_len: usize,
+
+    /// Size of the contents in bytes, zero for directories.
+    pub size: u64,
+
+    pub is_directory: bool,
+}
+
+/// Called for each item. Returns `false` to stop before the next item.
+pub type KibankItemCallback =
+    extern "C" fn(item: *const KibankItem, user_data: *mut c_void) -> bool;
+
+/// Open the bank at the path, which is null terminated UTF-8. Returns null if
+/// the bank cannot be opened.
+///
+/// # Safety
+///
+/// The path must be null or a valid null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn kibank_open(path: *const c_char) -> *mut KibankBank {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+    let Ok(path) = CStr::from_ptr(path).to_str() else {
+        return ptr::null_mut();
+    };
+    let path = Path::new(path);
+    let opened = BankReader::open(path).and_then(|reader| {
+        let items = reader.items().into_iter().map(|item| item.into_owned());
+        Ok(KibankBank {
+            file: File::open(path)?,
+            items: items.collect(),
+        })
+    });
+    match opened {
+        Ok(bank) => Box::into_raw(Box::new(bank)),
+        Err(error) => {
+            debug!("Cannot open bank {}: {error}", path.display());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Close a bank opened with [`kibank_open`]. Does nothing if the bank is null.
+///
+/// # Safety
+///
+/// The bank must be null or from [`kibank_open`] and not already closed.
+#[no_mangle]
+pub unsafe extern "C" fn kibank_close(bank: *mut KibankBank) {
+    if !bank.is_null() {
+        drop(Box::from_raw(bank));
+    }
+}
+
+/// Call the callback with each item in the order they are in the bank.
+/// Returns the number of items given to the callback, or -1 if the bank or
+/// the callback is null.
+///
+/// # Safety
+///
+/// The bank must be from [`kibank_open`] and not closed.
+#[no_mangle]
+pub unsafe extern "C" fn kibank_for_each_item(
+    bank: *const KibankBank,
+    callback: Option<KibankItemCallback>,
+    user_data: *mut c_void,
+) -> i64 {
+    let (Some(bank), Some(callback)) = (bank.as_ref(), callback) else {
+        return -1;
+    };
+    let mut count = 0;
+    for (index, item) in bank.items.iter().enumerate() {
+        let path = item.path_bytes();
+        let item = KibankItem {
+            index,
+            path: path.as_ptr(),
+            path_len: path.len(),
+            size: item.size(),
+            is_directory: item.is_directory(),
+        };
+        count += 1;
+        if !callback(&item, user_data) {
+            break;
+        }
+    }
+    count
+}
+
+/// Read up to `len` bytes of the contents of the item, starting `offset`
+/// bytes into the contents, into the buffer. Returns the number of bytes
+/// read, which is zero at the end of the contents, or -1 if the bank or
+/// buffer is null, there is no item at the index or on read failure.
+///
+/// # Safety
+///
+/// The bank must be from [`kibank_open`] and not closed, and the buffer must
+/// be valid for writing `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn kibank_read_chunk(
+    bank: *mut KibankBank,
+    index: usize,
+    offset: u64,
+    buffer: *mut u8,
+    len: usize,
+) -> i64 {
+    let Some(bank) = bank.as_mut() else {
+        return -1;
+    };
+    let Some(item) = bank.items.get(index) else {
+        return -1;
+    };
+    if buffer.is_null() {
+        return -1;
+    }
+    let remaining = item.size().saturating_sub(offset);
+    let len = usize::try_from(remaining).map_or(len, |remaining| remaining.min(len));
+    if len == 0 {
+        return 0;
+    }
+    let buffer = std::slice::from_raw_parts_mut(buffer, len);
+    let read = bank
+        .file
+        .seek(SeekFrom::Start(item.offset() + offset))
+        .and_then(|_| bank.file.read_exact(buffer));
+    match read {
+        Ok(()) => i64::try_from(len).unwrap_or(-1),
+        Err(error) => {
+            debug!("Cannot read item {index}: {error}");
+            -1
+        }
+    }
+}
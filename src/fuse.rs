@@ -0,0 +1,591 @@
+//! A bank as a read-only filesystem on Linux, so DAWs and sample browsers can
+//! audition its contents without extracting them. The FUSE protocol of the
+//! kernel is spoken directly so libfuse is not needed. Mounting needs root or
+//! the `fusermount3` or `fusermount` program that comes with FUSE.
+//!
+//! Contents are read from the bank file at their offset when they are asked
+//! for, so mounting a large bank is quick and takes little memory.
+
+use std::ffi::{c_int, CString, OsStr};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{Error, ErrorKind, Read, Write};
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::ptr;
+use std::time::UNIX_EPOCH;
+
+use log::{debug, info};
+
+use crate::read::BankReader;
+use crate::tree::{BankTree, Inode, Node};
+
+/// Version of the protocol that is spoken, which every kernel since 5.4
+/// understands. Older kernels are answered in their own version.
+const PROTOCOL_MAJOR: u32 = 7;
+const PROTOCOL_MINOR: u32 = 31;
+
+/// Largest request that is written to the filesystem, which is never since
+/// it is read-only, but the buffer must have room for it.
+const MAX_WRITE: u32 = 128 * 1024;
+
+/// Room for the largest request and its headers.
+const BUFFER_SIZE: usize = MAX_WRITE as usize + 4096;
+
+/// How long the kernel may remember names and attributes, in seconds. The
+/// bank never changes while it is mounted.
+const TTL: u64 = 3600;
+
+const BLOCK_SIZE: u32 = 4096;
+
+const IN_HEADER_SIZE: usize = 40;
+const OUT_HEADER_SIZE: usize = 16;
+
+/// Options given to `fusermount` and the kernel.
+const MOUNT_OPTIONS: &str = "ro,nosuid,nodev,fsname=kibank,subtype=kibank";
+
+// Requests from the kernel.
+const LOOKUP: u32 = 1;
+const FORGET: u32 = 2;
+const GETATTR: u32 = 3;
+const OPEN: u32 = 14;
+const READ: u32 = 15;
+const STATFS: u32 = 17;
+const RELEASE: u32 = 18;
+const FLUSH: u32 = 25;
+const INIT: u32 = 26;
+const OPENDIR: u32 = 27;
+const READDIR: u32 = 28;
+const RELEASEDIR: u32 = 29;
+const INTERRUPT: u32 = 36;
+const DESTROY: u32 = 38;
+const BATCH_FORGET: u32 = 42;
+
+/// The kernel may keep the contents of open files cached.
+const FOPEN_KEEP_CACHE: u32 = 2;
+
+/// The reply to a request, or the error number to answer with.
+type Reply = Result<Vec<u8>, c_int>;
+
+/// A bank mounted as a filesystem, which answers the requests of the kernel
+/// with [`Mount::serve`].
+#[derive(Debug)]
+pub struct Mount {
+    device: File,
+    mountpoint: PathBuf,
+    bank: File,
+    tree: BankTree,
+
+    /// When the bank was modified, as the time of every file and directory.
+    modified: (u64, u32),
+
+    uid: u32,
+    gid: u32,
+}
+
+/// Mount the bank read-only on the mount point, which must be an existing
+/// directory. Nothing can be read from the filesystem until [`Mount::serve`]
+/// is called.
+///
+/// # Errors
+///
+/// Will return `Err` if the bank cannot be read or mounting is not
+/// permitted.
+pub fn mount<P: AsRef<Path>, Q: AsRef<Path>>(bank_path: P, mountpoint: Q) -> io::Result<Mount> {
+    let bank_path = bank_path.as_ref();
+    let mountpoint = mountpoint.as_ref();
+    let reader = BankReader::open(bank_path)?;
+    let mut tree = BankTree::new(&reader.items());
+    tree.sort_naturally();
+    drop(reader);
+
+    let bank = File::open(bank_path)?;
+    let modified = bank
+        .metadata()?
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or((0, 0), |duration| {
+            (duration.as_secs(), duration.subsec_nanos())
+        });
+
+    let device = open_device(mountpoint)?;
+    info!(
+        "Mounted {} on {}",
+        bank_path.display(),
+        mountpoint.display()
+    );
+    // SAFETY: Getting the IDs of the process always succeeds.
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    Ok(Mount {
+        device,
+        mountpoint: mountpoint.to_owned(),
+        bank,
+        tree,
+        modified,
+        uid,
+        gid,
+    })
+}
+
+/// Unmount the filesystem at the mount point, such as when the program
+/// mounting it is stopped. [`Mount::serve`] then returns.
+///
+/// # Errors
+///
+/// Will return `Err` if the mount point cannot be unmounted.
+pub fn unmount<P: AsRef<Path>>(mountpoint: P) -> io::Result<()> {
+    let mountpoint = mountpoint.as_ref();
+    let path = c_path(mountpoint)?;
+    // SAFETY: The path is a valid null terminated string.
+    if unsafe { libc::umount2(path.as_ptr(), libc::MNT_DETACH) } == 0 {
+        return Ok(());
+    }
+    let error = Error::last_os_error();
+    if error.raw_os_error() != Some(libc::EPERM) {
+        return Err(error);
+    }
+
+    // Only root may unmount directly, other users unmount what they mounted
+    // with fusermount.
+    for program in ["fusermount3", "fusermount"] {
+        match Command::new(program)
+            .arg("-u")
+            .arg("-z")
+            .arg("--")
+            .arg(mountpoint)
+            .status()
+        {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => {
+                let msg = format!(
+                    "{program} could not unmount {}: {status}",
+                    mountpoint.display()
+                );
+                return Err(Error::other(msg));
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Err(error)
+}
+
+impl Mount {
+    #[must_use]
+    pub fn mountpoint(&self) -> &Path {
+        &self.mountpoint
+    }
+
+    /// Answer the requests of the kernel until the filesystem is unmounted.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the kernel cannot be talked to.
+    pub fn serve(self) -> io::Result<()> {
+        let mut buffer = vec![0_u8; BUFFER_SIZE];
+        loop {
+            let len = match (&self.device).read(&mut buffer) {
+                Ok(len) => len,
+                // The filesystem was unmounted.
+                Err(error) if error.raw_os_error() == Some(libc::ENODEV) => return Ok(()),
+                // The request was interrupted before it was read.
+                Err(error)
+                    if error.kind() == ErrorKind::Interrupted
+                        || error.raw_os_error() == Some(libc::ENOENT) =>
+                {
+                    continue
+                }
+                Err(error) => return Err(error),
+            };
+            let request = &buffer[..len];
+            if len < IN_HEADER_SIZE {
+                let msg = format!("Request of {len} bytes is too short");
+                return Err(Error::new(ErrorKind::InvalidData, msg));
+            }
+            let opcode = u32_at(request, 4);
+            let unique = u64_at(request, 8);
+            let inode = u64_at(request, 16);
+            let body = &request[IN_HEADER_SIZE..];
+            debug!("Request {opcode} for inode {inode}");
+
+            let reply = match opcode {
+                INIT => self.init(body),
+                LOOKUP => self.lookup(inode, body),
+                GETATTR => self.getattr(inode),
+                OPEN => self.open(inode, body),
+                OPENDIR => self.opendir(inode),
+                READ => self.read(inode, body),
+                READDIR => self.readdir(inode, body),
+                STATFS => Ok(self.statfs()),
+                RELEASE | RELEASEDIR | FLUSH => Ok(Vec::new()),
+                DESTROY => {
+                    self.reply(unique, Ok(Vec::new()))?;
+                    return Ok(());
+                }
+                // Nothing is answered to these.
+                FORGET | BATCH_FORGET | INTERRUPT => continue,
+                _ => Err(libc::ENOSYS),
+            };
+            self.reply(unique, reply)?;
+        }
+    }
+
+    fn reply(&self, unique: u64, reply: Reply) -> io::Result<()> {
+        let (error, body) = match reply {
+            Ok(body) => (0, body),
+            Err(errno) => (-errno, Vec::new()),
+        };
+        let mut out = Vec::with_capacity(OUT_HEADER_SIZE + body.len());
+        #[allow(clippy::cast_possible_truncation)]
+        out.extend_from_slice(&((OUT_HEADER_SIZE + body.len()) as u32).to_ne_bytes());
+        out.extend_from_slice(&error.to_ne_bytes());
+        out.extend_from_slice(&unique.to_ne_bytes());
+        out.extend_from_slice(&body);
+        match (&self.device).write_all(&out) {
+            // The request was interrupted and doesn't need an answer.
+            Err(error) if error.raw_os_error() == Some(libc::ENOENT) => Ok(()),
+            result => result,
+        }
+    }
+
+    fn init(&self, body: &[u8]) -> Reply {
+        let major = u32_at(body, 0);
+        let minor = u32_at(body, 4);
+        let max_readahead = u32_at(body, 8);
+        debug!("Kernel speaks FUSE {major}.{minor}");
+        let mut out = Vec::with_capacity(64);
+        out.extend_from_slice(&PROTOCOL_MAJOR.to_ne_bytes());
+        if major > PROTOCOL_MAJOR {
+            // The kernel asks again in this version.
+            return Ok(out);
+        }
+        if major < PROTOCOL_MAJOR {
+            return Err(libc::EPROTO);
+        }
+        out.extend_from_slice(&minor.min(PROTOCOL_MINOR).to_ne_bytes());
+        out.extend_from_slice(&max_readahead.to_ne_bytes());
+        out.extend_from_slice(&0_u32.to_ne_bytes()); // flags
+        out.extend_from_slice(&16_u16.to_ne_bytes()); // max_background
+        out.extend_from_slice(&12_u16.to_ne_bytes()); // congestion_threshold
+        out.extend_from_slice(&MAX_WRITE.to_ne_bytes());
+        out.extend_from_slice(&1_u32.to_ne_bytes()); // time_gran
+        out.resize(64, 0);
+
+        // Kernels before 7.23 only know the first fields.
+        if minor < 23 {
+            out.truncate(24);
+        }
+        Ok(out)
+    }
+
+    fn lookup(&self, parent: Inode, body: &[u8]) -> Reply {
+        let name = &body[..body.iter().position(|b| *b == 0).unwrap_or(body.len())];
+
+        // Names are matched exactly, since the kernel expects each
+        // directory to have only one name.
+        let inode = self
+            .tree
+            .children(parent)
+            .iter()
+            .copied()
+            .find(|inode| self.tree.node(*inode).is_some_and(|node| node.name == name))
+            .ok_or(libc::ENOENT)?;
+        let node = self.node(inode)?;
+        let mut out = Vec::with_capacity(128);
+        out.extend_from_slice(&inode.to_ne_bytes());
+        out.extend_from_slice(&0_u64.to_ne_bytes()); // generation
+        out.extend_from_slice(&TTL.to_ne_bytes()); // entry_valid
+        out.extend_from_slice(&TTL.to_ne_bytes()); // attr_valid
+        out.extend_from_slice(&0_u32.to_ne_bytes());
+        out.extend_from_slice(&0_u32.to_ne_bytes());
+        self.attr(inode, node, &mut out);
+        Ok(out)
+    }
+
+    fn getattr(&self, inode: Inode) -> Reply {
+        let node = self.node(inode)?;
+        let mut out = Vec::with_capacity(104);
+        out.extend_from_slice(&TTL.to_ne_bytes()); // attr_valid
+        out.extend_from_slice(&0_u32.to_ne_bytes());
+        out.extend_from_slice(&0_u32.to_ne_bytes());
+        self.attr(inode, node, &mut out);
+        Ok(out)
+    }
+
+    fn open(&self, inode: Inode, body: &[u8]) -> Reply {
+        let flags = u32_at(body, 0);
+        #[allow(clippy::cast_sign_loss)]
+        if flags & libc::O_ACCMODE as u32 != libc::O_RDONLY as u32 {
+            return Err(libc::EROFS);
+        }
+        if self.node(inode)?.is_directory() {
+            return Err(libc::EISDIR);
+        }
+        Ok(open_out(FOPEN_KEEP_CACHE))
+    }
+
+    fn opendir(&self, inode: Inode) -> Reply {
+        if !self.node(inode)?.is_directory() {
+            return Err(libc::ENOTDIR);
+        }
+        Ok(open_out(0))
+    }
+
+    fn read(&self, inode: Inode, body: &[u8]) -> Reply {
+        let offset = u64_at(body, 8);
+        let size = u64::from(u32_at(body, 16));
+        let item = self.node(inode)?.item().ok_or(libc::EISDIR)?;
+        let end = offset.saturating_add(size).min(item.size());
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let mut contents = vec![0_u8; (end - offset) as usize];
+        let mut filled = 0;
+        while filled < contents.len() {
+            match self.bank.read_at(
+                &mut contents[filled..],
+                item.offset() + offset + filled as u64,
+            ) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error.raw_os_error().unwrap_or(libc::EIO)),
+            }
+        }
+        contents.truncate(filled);
+        Ok(contents)
+    }
+
+    fn readdir(&self, inode: Inode, body: &[u8]) -> Reply {
+        let offset = u64_at(body, 8);
+        #[allow(clippy::cast_possible_truncation)]
+        let size = u32_at(body, 16) as usize;
+        let node = self.node(inode)?;
+        if !node.is_directory() {
+            return Err(libc::ENOTDIR);
+        }
+
+        let entries = [(inode, &b"."[..]), (node.parent, &b".."[..])]
+            .into_iter()
+            .chain(self.tree.children(inode).iter().filter_map(|child| {
+                self.tree
+                    .node(*child)
+                    .map(|node| (*child, node.name.as_slice()))
+            }));
+        let mut out = Vec::new();
+        #[allow(clippy::cast_possible_truncation)]
+        for (position, (entry, name)) in entries.enumerate().skip(offset as usize) {
+            let kind = match self.tree.node(entry) {
+                Some(node) if !node.is_directory() => libc::DT_REG,
+                _ => libc::DT_DIR,
+            };
+            let entry_size = (24 + name.len()).next_multiple_of(8);
+            if out.len() + entry_size > size {
+                break;
+            }
+            out.extend_from_slice(&entry.to_ne_bytes());
+            out.extend_from_slice(&(position as u64 + 1).to_ne_bytes()); // off
+            out.extend_from_slice(&(name.len() as u32).to_ne_bytes());
+            out.extend_from_slice(&u32::from(kind).to_ne_bytes());
+            out.extend_from_slice(name);
+            out.resize(out.len().next_multiple_of(8), 0);
+        }
+        Ok(out)
+    }
+
+    fn statfs(&self) -> Vec<u8> {
+        let size = (1..=self.tree.len() as Inode)
+            .filter_map(|inode| self.tree.node(inode))
+            .map(Node::size)
+            .sum::<u64>();
+        let mut out = Vec::with_capacity(80);
+        out.extend_from_slice(&size.div_ceil(u64::from(BLOCK_SIZE)).to_ne_bytes()); // blocks
+        out.extend_from_slice(&0_u64.to_ne_bytes()); // bfree
+        out.extend_from_slice(&0_u64.to_ne_bytes()); // bavail
+        out.extend_from_slice(&(self.tree.len() as u64).to_ne_bytes()); // files
+        out.extend_from_slice(&0_u64.to_ne_bytes()); // ffree
+        out.extend_from_slice(&BLOCK_SIZE.to_ne_bytes()); // bsize
+        out.extend_from_slice(&255_u32.to_ne_bytes()); // namelen
+        out.extend_from_slice(&BLOCK_SIZE.to_ne_bytes()); // frsize
+        out.resize(80, 0);
+        out
+    }
+
+    /// Add the attributes of the node in the layout of `fuse_attr`.
+    fn attr(&self, inode: Inode, node: &Node, out: &mut Vec<u8>) {
+        let (seconds, nanoseconds) = self.modified;
+        let (mode, links) = if node.is_directory() {
+            (libc::S_IFDIR | 0o555, 2_u32)
+        } else {
+            (libc::S_IFREG | 0o444, 1)
+        };
+        out.extend_from_slice(&inode.to_ne_bytes());
+        out.extend_from_slice(&node.size().to_ne_bytes());
+        out.extend_from_slice(&node.size().div_ceil(512).to_ne_bytes()); // blocks
+        for _ in 0..3 {
+            out.extend_from_slice(&seconds.to_ne_bytes()); // atime, mtime, ctime
+        }
+        for _ in 0..3 {
+            out.extend_from_slice(&nanoseconds.to_ne_bytes());
+        }
+        out.extend_from_slice(&mode.to_ne_bytes());
+        out.extend_from_slice(&links.to_ne_bytes());
+        out.extend_from_slice(&self.uid.to_ne_bytes());
+        out.extend_from_slice(&self.gid.to_ne_bytes());
+        out.extend_from_slice(&0_u32.to_ne_bytes()); // rdev
+        out.extend_from_slice(&BLOCK_SIZE.to_ne_bytes()); // blksize
+        out.extend_from_slice(&0_u32.to_ne_bytes()); // flags
+    }
+
+    fn node(&self, inode: Inode) -> Result<&Node, c_int> {
+        self.tree.node(inode).ok_or(libc::ENOENT)
+    }
+}
+
+/// Open the FUSE device and mount it, directly when running as root or
+/// else with `fusermount`.
+fn open_device(mountpoint: &Path) -> io::Result<File> {
+    let device = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/fuse")?;
+    // SAFETY: Getting the IDs of the process always succeeds.
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    let options = CString::new(format!(
+        "fd={},rootmode=40000,user_id={uid},group_id={gid}",
+        device.as_raw_fd()
+    ))?;
+    let target = c_path(mountpoint)?;
+    // SAFETY: Every string is valid and null terminated.
+    let mounted = unsafe {
+        libc::mount(
+            b"kibank\0".as_ptr().cast(),
+            target.as_ptr(),
+            b"fuse.kibank\0".as_ptr().cast(),
+            libc::MS_NOSUID | libc::MS_NODEV | libc::MS_RDONLY,
+            options.as_ptr().cast(),
+        )
+    };
+    if mounted == 0 {
+        return Ok(device);
+    }
+    let error = Error::last_os_error();
+    if error.raw_os_error() != Some(libc::EPERM) {
+        return Err(error);
+    }
+    debug!("Mounting with fusermount since {error}");
+    drop(device);
+    mount_with_fusermount(mountpoint).map_err(|fusermount_error| {
+        let msg = format!("Mounting needs root or fusermount: {fusermount_error}");
+        Error::new(ErrorKind::PermissionDenied, msg)
+    })
+}
+
+/// Mount with the `fusermount` program, which is allowed to mount for
+/// other users and sends the opened FUSE device back over a socket.
+fn mount_with_fusermount(mountpoint: &Path) -> io::Result<File> {
+    let mut fds = [0; 2];
+    // SAFETY: There is room for both descriptors.
+    if unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) } != 0 {
+        return Err(Error::last_os_error());
+    }
+    // SAFETY: The descriptors were just created and nothing else owns them.
+    let (ours, theirs) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    // SAFETY: The descriptor is open.
+    unsafe { libc::fcntl(ours.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+
+    let mut child = None;
+    for program in ["fusermount3", "fusermount"] {
+        match Command::new(program)
+            .arg("-o")
+            .arg(MOUNT_OPTIONS)
+            .arg("--")
+            .arg(mountpoint)
+            .env("_FUSE_COMMFD", theirs.as_raw_fd().to_string())
+            .spawn()
+        {
+            Ok(spawned) => {
+                child = Some(spawned);
+                break;
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+    }
+    let mut child =
+        child.ok_or_else(|| Error::new(ErrorKind::NotFound, "fusermount was not found"))?;
+    drop(theirs);
+
+    let device = receive_fd(&ours);
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(Error::other(format!("fusermount failed: {status}")));
+    }
+    device
+}
+
+/// Receive a file descriptor sent over the socket.
+fn receive_fd(socket: &OwnedFd) -> io::Result<File> {
+    let mut byte = [0_u8; 1];
+    let mut iov = libc::iovec {
+        iov_base: byte.as_mut_ptr().cast(),
+        iov_len: byte.len(),
+    };
+    // Room for the header and one descriptor, aligned for the header.
+    let mut control = [0_u64; 8];
+    // SAFETY: A message header of zeros is valid.
+    let mut message: libc::msghdr = unsafe { mem::zeroed() };
+    message.msg_iov = &mut iov;
+    message.msg_iovlen = 1;
+    message.msg_control = control.as_mut_ptr().cast();
+    message.msg_controllen = mem::size_of_val(&control) as _;
+
+    // SAFETY: The message points to buffers that live until it returns.
+    if unsafe { libc::recvmsg(socket.as_raw_fd(), &mut message, 0) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    // SAFETY: The control buffer was filled in by recvmsg.
+    unsafe {
+        let header = libc::CMSG_FIRSTHDR(&message);
+        if header.is_null()
+            || (*header).cmsg_level != libc::SOL_SOCKET
+            || (*header).cmsg_type != libc::SCM_RIGHTS
+        {
+            return Err(Error::other("fusermount did not send the FUSE device"));
+        }
+        let fd = ptr::read_unaligned(libc::CMSG_DATA(header).cast::<c_int>());
+        Ok(File::from_raw_fd(fd))
+    }
+}
+
+fn open_out(open_flags: u32) -> Vec<u8> {
+    let mut out = Vec::with_capacity(16);
+    out.extend_from_slice(&0_u64.to_ne_bytes()); // fh
+    out.extend_from_slice(&open_flags.to_ne_bytes());
+    out.extend_from_slice(&0_u32.to_ne_bytes());
+    out
+}
+
+fn c_path(path: &Path) -> io::Result<CString> {
+    CString::new(OsStr::as_bytes(path.as_os_str())).map_err(Into::into)
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    bytes
+        .get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map_or(0, u32::from_ne_bytes)
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    bytes
+        .get(offset..offset + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map_or(0, u64::from_ne_bytes)
+}
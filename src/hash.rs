@@ -0,0 +1,415 @@
+//! Digests of the contents of banks.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, Error, ErrorKind, Read, Seek};
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use sha1_smol::Sha1;
+#[cfg(feature = "checksums")]
+use sha2::{Digest, Sha256};
+
+use crate::cancel::{self, CancellationToken};
+use crate::read::{BankReader, Item};
+
+pub mod known;
+
+/// Number of bytes read at a time when hashing, which is all the memory
+/// needed no matter how large the contents are.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Hash functions available for digests and fingerprints. BLAKE3 is the
+/// fastest and suits finding duplicates locally, SHA-256 is the most widely
+/// supported for published checksums and SHA-1 matches the form of the `hash`
+/// found in the metadata. BLAKE3 and SHA-256 need the `checksums` feature.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Algorithm {
+    #[cfg(feature = "checksums")]
+    Blake3,
+    #[cfg(feature = "checksums")]
+    Sha256,
+    Sha1,
+}
+
+impl Algorithm {
+    /// Names accepted by [`Algorithm::from_name`].
+    #[cfg(feature = "checksums")]
+    pub const NAMES: [&'static str; 3] = ["blake3", "sha256", "sha1"];
+
+    /// Names accepted by [`Algorithm::from_name`].
+    #[cfg(not(feature = "checksums"))]
+    pub const NAMES: [&'static str; 1] = ["sha1"];
+
+    /// Find the algorithm from a name such as `sha256`, ignoring case and
+    /// any hyphen, so `SHA-256` is also accepted.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Algorithm> {
+        match name.replace('-', "").to_ascii_lowercase().as_str() {
+            #[cfg(feature = "checksums")]
+            "blake3" => Some(Algorithm::Blake3),
+            #[cfg(feature = "checksums")]
+            "sha256" => Some(Algorithm::Sha256),
+            "sha1" => Some(Algorithm::Sha1),
+            _ => None,
+        }
+    }
+
+    /// Number of characters in a digest written as hex.
+    #[must_use]
+    pub fn hex_len(self) -> usize {
+        match self {
+            #[cfg(feature = "checksums")]
+            Algorithm::Blake3 | Algorithm::Sha256 => 64,
+            Algorithm::Sha1 => 40,
+        }
+    }
+
+    /// A new hasher for the algorithm.
+    #[must_use]
+    pub fn hasher(self) -> Box<dyn Hasher + Send> {
+        match self {
+            #[cfg(feature = "checksums")]
+            Algorithm::Blake3 => Box::new(blake3::Hasher::new()),
+            #[cfg(feature = "checksums")]
+            Algorithm::Sha256 => Box::new(Sha256::new()),
+            Algorithm::Sha1 => Box::new(Sha1::new()),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            #[cfg(feature = "checksums")]
+            Algorithm::Blake3 => "BLAKE3",
+            #[cfg(feature = "checksums")]
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha1 => "SHA-1",
+        })
+    }
+}
+
+/// A hash function that is given bytes a piece at a time. Implemented for
+/// each [`Algorithm`], and can be implemented for others.
+pub trait Hasher {
+    /// Add bytes to those being hashed.
+    fn update(&mut self, bytes: &[u8]);
+
+    /// The digest of every byte given so far as a lowercase hex string.
+    fn hex_digest(&self) -> String;
+}
+
+impl Hasher for Sha1 {
+    fn update(&mut self, bytes: &[u8]) {
+        Sha1::update(self, bytes);
+    }
+
+    fn hex_digest(&self) -> String {
+        self.digest().to_string()
+    }
+}
+
+#[cfg(feature = "checksums")]
+impl Hasher for Sha256 {
+    fn update(&mut self, bytes: &[u8]) {
+        Digest::update(self, bytes);
+    }
+
+    fn hex_digest(&self) -> String {
+        self.clone()
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
+
+#[cfg(feature = "checksums")]
+impl Hasher for blake3::Hasher {
+    fn update(&mut self, bytes: &[u8]) {
+        blake3::Hasher::update(self, bytes);
+    }
+
+    fn hex_digest(&self) -> String {
+        self.finalize().to_hex().to_string()
+    }
+}
+
+/// Digests of the files in a bank by path, in the same form as the output of
+/// tools like `sha256sum`: one file per line with the digest, two spaces and
+/// the path.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Checksums {
+    pub algorithm: Algorithm,
+    pub digests: BTreeMap<String, String>,
+}
+
+impl Checksums {
+    /// The digest of every file in the bank, including the metadata.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` on read or seek failure, or if the bank is truncated.
+    pub fn of_bank<ReaderType: Read + Seek + BufRead>(
+        reader: &mut BankReader<ReaderType>,
+        algorithm: Algorithm,
+    ) -> io::Result<Checksums> {
+        let mut digests = BTreeMap::new();
+        for item in reader.items().iter().filter(|item| item.is_file()) {
+            let digest = item_digest_using(reader, item, algorithm)?;
+            digests.insert(item.file_name_lossy(), digest);
+        }
+        Ok(Checksums { algorithm, digests })
+    }
+
+    /// Read checksums in the form written by [`Checksums`]'s `Display`.
+    /// Blank lines are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a line is not a digest and a path, or a digest is
+    /// not the length of the algorithm's digests.
+    pub fn parse(text: &str, algorithm: Algorithm) -> io::Result<Checksums> {
+        let mut digests = BTreeMap::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let (digest, path) = line.split_once("  ").ok_or_else(|| {
+                let msg = format!("Checksum line is not a digest and a path: {line}");
+                Error::new(ErrorKind::InvalidData, msg)
+            })?;
+            if digest.len() != algorithm.hex_len()
+                || !digest.bytes().all(|byte| byte.is_ascii_hexdigit())
+            {
+                let msg = format!("Checksum of {path} is not a {algorithm} digest");
+                return Err(Error::new(ErrorKind::InvalidData, msg));
+            }
+            digests.insert(path.to_string(), digest.to_ascii_lowercase());
+        }
+        Ok(Checksums { algorithm, digests })
+    }
+}
+
+impl fmt::Display for Checksums {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, digest) in &self.digests {
+            writeln!(f, "{digest}  {path}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compute a 160-bit hash of the contents of a bank as a lowercase hex
+/// string, the same form as the `hash` found in the metadata.
+///
+/// The hash covers the path and contents of every file in the bank except
+/// the metadata, in the order they appear in the bank. The algorithm used by
+/// Kilohearts for the factory content banks is not known so the result is only
+/// comparable with other hashes computed by this function.
+///
+/// # Errors
+///
+/// Will return `Err` on read or seek failure, or if the bank is truncated.
+pub fn content_hash<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+) -> io::Result<String> {
+    content_hash_with(reader, None)
+}
+
+/// The same as [`content_hash`] but stops before the next item when the
+/// token is cancelled.
+///
+/// # Errors
+///
+/// Will return `Err` on read or seek failure, if the bank is truncated, or
+/// with [`crate::cancel::Cancelled`] if it is cancelled.
+pub fn content_hash_with<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    cancel: Option<&CancellationToken>,
+) -> io::Result<String> {
+    content_hash_using(reader, Algorithm::Sha1, cancel)
+}
+
+/// The same as [`content_hash_with`] but with the algorithm given, so the
+/// hash is the length of the algorithm's digests rather than always 160 bits.
+///
+/// # Errors
+///
+/// Will return `Err` on read or seek failure, if the bank is truncated, or
+/// with [`crate::cancel::Cancelled`] if it is cancelled.
+pub fn content_hash_using<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    algorithm: Algorithm,
+    cancel: Option<&CancellationToken>,
+) -> io::Result<String> {
+    let items = reader
+        .items()
+        .into_iter()
+        .filter(|item| item.is_file() && !item.is_metadata_file())
+        .collect::<Vec<_>>();
+    if let Some(cancel) = cancel {
+        cancel.start(items.len());
+    }
+
+    let mut hasher = algorithm.hasher();
+    for item in items {
+        cancel::check(cancel)?;
+        // The null separates the path from the contents since the path
+        // never contains a null.
+        hasher.update(item.path_bytes());
+        hasher.update(&[0_u8]);
+        update_item(hasher.as_mut(), reader, &item)?;
+        if let Some(cancel) = cancel {
+            cancel.item_done();
+        }
+    }
+    Ok(hasher.hex_digest())
+}
+
+/// SHA-1 of the contents of one item as a lowercase hex string. The contents
+/// are read a piece at a time so memory use doesn't depend on the size of the item.
+///
+/// # Errors
+///
+/// Will return `Err` on read or seek failure, or if the bank is truncated.
+pub fn item_digest<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    item: &Item,
+) -> io::Result<String> {
+    item_digest_using(reader, item, Algorithm::Sha1)
+}
+
+/// The same as [`item_digest`] but with the algorithm given.
+///
+/// # Errors
+///
+/// Will return `Err` on read or seek failure, or if the bank is truncated.
+pub fn item_digest_using<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    item: &Item,
+    algorithm: Algorithm,
+) -> io::Result<String> {
+    let mut hasher = algorithm.hasher();
+    update_item(hasher.as_mut(), reader, item)?;
+    Ok(hasher.hex_digest())
+}
+
+/// SHA-1 of everything that can be read from the source as a lowercase hex string.
+///
+/// # Errors
+///
+/// Will return `Err` on read failure.
+pub fn digest<R: Read>(source: R) -> io::Result<String> {
+    digest_using(source, Algorithm::Sha1)
+}
+
+/// The same as [`digest`] but with the algorithm given.
+///
+/// # Errors
+///
+/// Will return `Err` on read failure.
+pub fn digest_using<R: Read>(source: R, algorithm: Algorithm) -> io::Result<String> {
+    let mut hasher = algorithm.hasher();
+    update(hasher.as_mut(), source)?;
+    Ok(hasher.hex_digest())
+}
+
+/// [`digest`] of each file in the same order as the paths. Hashing large files
+/// is slow so up to `threads` files are hashed at once, or one for each CPU
+/// when `threads` is zero.
+///
+/// # Errors
+///
+/// Will return `Err` if a file cannot be opened or read.
+pub fn digest_files<P: AsRef<Path> + Sync>(paths: &[P], threads: usize) -> io::Result<Vec<String>> {
+    let threads = match threads {
+        0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        threads => threads,
+    }
+    .min(paths.len())
+    .max(1);
+
+    // Each thread takes the next file that hasn't been started.
+    let next = AtomicUsize::new(0);
+    let hash_next = || {
+        let mut digests = Vec::new();
+        loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(path) = paths.get(index) else {
+                return digests;
+            };
+            let path = path.as_ref();
+            let result = File::open(path).and_then(digest).map_err(|error| {
+                let msg = format!("Cannot hash {}: {error}", path.display());
+                Error::new(error.kind(), msg)
+            });
+            digests.push((index, result));
+        }
+    };
+    let mut results = thread::scope(|scope| {
+        let workers = (1..threads)
+            .map(|_| scope.spawn(hash_next))
+            .collect::<Vec<_>>();
+        let mut results = hash_next();
+        for worker in workers {
+            results.extend(worker.join().expect("Hashing threads don't panic"));
+        }
+        results
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Combine the digests of named files into a single hash that doesn't depend on
+/// the order the files were given.
+#[must_use]
+pub fn manifest_hash(mut entries: Vec<(Vec<u8>, String)>) -> String {
+    entries.sort();
+    let mut hasher = Sha1::new();
+    for (name, digest) in entries {
+        hasher.update(&name);
+        hasher.update(&[0_u8]);
+        hasher.update(digest.as_bytes());
+        hasher.update(b"\n");
+    }
+    hasher.digest().to_string()
+}
+
+/// Hash the contents of the item, failing if there are fewer bytes than expected.
+fn update_item<ReaderType: Read + Seek + BufRead>(
+    hasher: &mut dyn Hasher,
+    reader: &mut BankReader<ReaderType>,
+    item: &Item,
+) -> io::Result<()> {
+    let read_count = update(hasher, reader.contents_reader(item)?)?;
+    if read_count == item.size() {
+        Ok(())
+    } else {
+        let msg = format!(
+            "Item {} is truncated, {read_count} of {} bytes available",
+            item.file_name_lossy(),
+            item.size()
+        );
+        Err(Error::new(ErrorKind::UnexpectedEof, msg))
+    }
+}
+
+/// Hash everything that can be read from the source. Returns the number of
+/// bytes read.
+fn update<R: Read>(hasher: &mut dyn Hasher, mut source: R) -> io::Result<u64> {
+    let mut buffer = vec![0_u8; BUFFER_SIZE];
+    let mut total = 0;
+    loop {
+        let read_count = match source.read(&mut buffer) {
+            Ok(0) => return Ok(total),
+            Ok(read_count) => read_count,
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        hasher.update(&buffer[..read_count]);
+        total += read_count as u64;
+    }
+}
@@ -0,0 +1,48 @@
+//! Kilohearts factory banks, recognized by the `hash` in their metadata, so
+//! catalogs can tell factory content from other content.
+
+/// A bank of factory content published by Kilohearts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FactoryBank {
+    /// The `hash` in the metadata of the bank, as lowercase hex.
+    pub hash: &'static str,
+    pub id: &'static str,
+    pub name: &'static str,
+}
+
+/// Factory banks with documented hashes. A bank is only added once its hash
+/// has been checked against the bank downloaded from Kilohearts, so the list
+/// is not complete.
+pub const FACTORY_BANKS: &[FactoryBank] = &[];
+
+/// Where a bank came from according to the hash in its metadata.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Origin {
+    /// One of the [`FACTORY_BANKS`].
+    Factory(&'static FactoryBank),
+
+    /// Not a listed factory bank. This is not proof of third-party content
+    /// because factory banks are only listed once their hashes are checked.
+    Unknown,
+}
+
+impl Origin {
+    /// The factory bank, if the bank is one.
+    #[must_use]
+    pub fn factory(self) -> Option<&'static FactoryBank> {
+        match self {
+            Origin::Factory(bank) => Some(bank),
+            Origin::Unknown => None,
+        }
+    }
+}
+
+/// Whether the hash from the metadata of a bank is that of a factory bank,
+/// ignoring ASCII case.
+#[must_use]
+pub fn identify_factory(hash: &str) -> Origin {
+    FACTORY_BANKS
+        .iter()
+        .find(|bank| bank.hash.eq_ignore_ascii_case(hash))
+        .map_or(Origin::Unknown, Origin::Factory)
+}
@@ -0,0 +1,169 @@
+//! Safety checks of banks from sources that are not trusted, such as uploads
+//! to a marketplace. Nothing is extracted or written. Banks that cannot be
+//! opened are reported rather than failing so every upload gets a report.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::io::{BufRead, Read, Seek};
+use std::path::Path;
+
+use crate::read::{BankReader, BankReaderOptions, Item};
+use crate::verify::Report;
+use crate::PATH_SEPARATOR;
+
+/// Limits on what is read from a bank.
+#[derive(Clone, Debug)]
+pub struct InspectOptions {
+    /// Refuse to read bank files larger than this many bytes.
+    pub max_bank_size: Option<u64>,
+
+    /// See [`BankReaderOptions::max_items`].
+    pub max_items: Option<u64>,
+
+    /// See [`BankReaderOptions::max_item_size`].
+    pub max_item_size: Option<u64>,
+
+    /// Report paths with more directories than this.
+    pub max_depth: usize,
+}
+
+impl InspectOptions {
+    /// Limits for banks from anyone, generous enough for the largest factory
+    /// banks.
+    #[must_use]
+    pub fn sandbox() -> InspectOptions {
+        InspectOptions {
+            max_bank_size: Some(4 * 1024 * 1024 * 1024),
+            max_items: Some(100_000),
+            max_item_size: Some(1024 * 1024 * 1024),
+            ..InspectOptions::default()
+        }
+    }
+}
+
+impl Default for InspectOptions {
+    fn default() -> Self {
+        InspectOptions {
+            max_bank_size: None,
+            max_items: None,
+            max_item_size: None,
+            max_depth: 16,
+        }
+    }
+}
+
+/// Open the bank file within the limits and check its items. Banks that are
+/// too large or cannot be opened, such as those with items that overlap, are
+/// reported as errors.
+///
+/// # Errors
+///
+/// Will return `Err` only if the file cannot be opened.
+pub fn inspect_file<P: AsRef<Path>>(path: P, options: &InspectOptions) -> io::Result<Report> {
+    let path = path.as_ref();
+    let mut report = Report::default();
+    let size = fs::metadata(path)?.len();
+    if let Some(max_bank_size) = options.max_bank_size {
+        if size > max_bank_size {
+            report.error(format!(
+                "Bank is {size} bytes, more than the limit of {max_bank_size}"
+            ));
+            return Ok(report);
+        }
+    }
+
+    let reader_options = BankReaderOptions {
+        normalize_separators: false,
+        max_items: options.max_items,
+        max_item_size: options.max_item_size,
+        max_bank_size: options.max_bank_size,
+        ..BankReaderOptions::default()
+    };
+    match BankReader::open_with_options(path, reader_options) {
+        Ok(reader) => report.issues.extend(inspect_bank(&reader, options).issues),
+        Err(error) => report.error(format!("Cannot be opened: {error}")),
+    }
+    Ok(report)
+}
+
+/// Check the paths of the items of an open bank, and that their contents fit
+/// in it.
+#[must_use]
+pub fn inspect_bank<ReaderType: Read + Seek + BufRead>(
+    reader: &BankReader<ReaderType>,
+    options: &InspectOptions,
+) -> Report {
+    let items = reader.items();
+    let mut report = inspect_items(&items, options);
+    report
+        .issues
+        .extend(inspect_sizes(&items, reader.stream_len()).issues);
+    report
+}
+
+/// Check the contents of every item are within the bank of `bank_len` bytes.
+/// Reading an item that claims to be larger than the whole bank could use
+/// far more memory than the bank itself.
+#[must_use]
+pub fn inspect_sizes(items: &[Item], bank_len: u64) -> Report {
+    let mut report = Report::default();
+    for item in items {
+        let name = item.file_name_lossy();
+        if item.size() > bank_len {
+            report.error(format!(
+                "Item {name:?} claims {} bytes, more than the {bank_len} bytes of the bank",
+                item.size()
+            ));
+        } else if item
+            .offset()
+            .checked_add(item.size())
+            .map_or(true, |end| end > bank_len)
+        {
+            report.error(format!(
+                "Contents of item {name:?} extend past the end of the bank"
+            ));
+        }
+    }
+    report
+}
+
+/// Check the paths of the items for anything that could harm a tool that
+/// extracts them or confuse a person reading them.
+#[must_use]
+pub fn inspect_items(items: &[Item], options: &InspectOptions) -> Report {
+    let mut report = Report::default();
+    let mut paths = HashSet::with_capacity(items.len());
+    for item in items {
+        let path = item.path_bytes();
+        let name = item.file_name_lossy();
+        let is_separator = |byte: &u8| *byte == PATH_SEPARATOR as u8 || *byte == b'\\';
+
+        if path.first().is_some_and(is_separator)
+            || (path.len() >= 2 && path[0].is_ascii_alphabetic() && path[1] == b':')
+        {
+            report.error(format!("Absolute path {name:?}"));
+        }
+        let components = path.split(is_separator).collect::<Vec<_>>();
+        if components.iter().any(|component| *component == b"..") {
+            report.error(format!("Path {name:?} leads outside of the bank"));
+        }
+        if components.len() > options.max_depth + 1 {
+            report.warning(format!(
+                "Path {name:?} has more than {} directories",
+                options.max_depth
+            ));
+        }
+        if std::str::from_utf8(path).is_err() {
+            report.warning(format!("Path {name:?} is not UTF-8"));
+        } else if name.chars().any(char::is_control) {
+            report.error(format!(
+                "Path {name:?} has characters that are not printable"
+            ));
+        }
+        if !paths.insert(path.to_ascii_lowercase()) {
+            report.warning(format!("Path {name:?} is used more than once"));
+        }
+    }
+    report
+}
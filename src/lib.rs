@@ -1,16 +1,113 @@
 //! Support for [Kilohearts](https://kilohearts.com) banks.
 
-use std::collections::HashMap;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use core::mem::size_of;
+#[cfg(feature = "std")]
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "std")]
 use std::ffi::OsStr;
+#[cfg(feature = "std")]
+use std::fmt;
+#[cfg(feature = "std")]
 use std::fmt::Debug;
-use std::mem::size_of;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
 use std::path::Path;
+#[cfg(feature = "std")]
+use std::str::FromStr;
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
 
+#[cfg(feature = "std")]
+use log::warn;
+#[cfg(feature = "std")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
 use serde_json::Value;
 
+#[cfg(feature = "std")]
+pub mod annotations;
+#[cfg(feature = "std")]
+pub mod attributes;
+#[cfg(feature = "std")]
+pub mod background;
+#[cfg(feature = "std")]
+pub mod bank;
+#[cfg(feature = "std")]
+pub mod cancel;
+#[cfg(feature = "std")]
+pub mod catalog;
+#[cfg(feature = "std")]
+pub mod collect;
+#[cfg(feature = "std")]
+pub mod compat;
+#[cfg(feature = "compression")]
+pub mod compress;
+#[cfg(feature = "std")]
+pub mod concat;
+#[cfg(feature = "std")]
+pub mod create;
+#[cfg(feature = "std")]
+pub mod diff;
+#[cfg(feature = "encryption")]
+pub mod encrypt;
+#[cfg(feature = "std")]
+pub mod extract;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(all(feature = "fuse", target_os = "linux"))]
+pub mod fuse;
+#[cfg(feature = "std")]
+pub mod hash;
+#[cfg(feature = "std")]
+pub mod inspect;
+#[cfg(feature = "std")]
+pub mod lint;
+#[cfg(feature = "std")]
+pub mod merge;
+#[cfg(feature = "std")]
+pub mod names;
+pub mod parse;
+#[cfg(feature = "std")]
+pub mod patch;
+#[cfg(feature = "std")]
+pub mod previews;
+#[cfg(feature = "std")]
+pub mod project;
+#[cfg(feature = "std")]
 pub mod read;
+#[cfg(feature = "std")]
+pub mod repair;
+#[cfg(feature = "std")]
+pub mod roundtrip;
+#[cfg(feature = "std")]
+pub mod size;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod template;
+#[cfg(feature = "testdata")]
+pub mod testdata;
+#[cfg(feature = "std")]
+pub mod tree;
+#[cfg(feature = "std")]
+pub mod verify;
+#[cfg(feature = "std")]
+pub mod vfs;
+#[cfg(feature = "std")]
+pub mod volume;
+#[cfg(feature = "std")]
 pub mod write;
+#[cfg(feature = "zip")]
+pub mod zip;
 
 /// First bytes that identify the kind of the file.
 const FILE_ID: &[u8] = &[137_u8, b'k', b'H', b's'];
@@ -22,6 +119,9 @@ const FORMAT_VERSION: &[u8] = "Bank0001".as_bytes();
 /// First part the background image file name without the trailing dot.
 pub const BACKGROUND_FILE_STEM: &str = "background";
 
+/// First part of the changelog file name without the trailing dot.
+pub const CHANGELOG_FILE_STEM: &str = "changelog";
+
 /// These bytes are written as part of the header to check to detect incorrect
 /// end of line format conversion. The same sequence of bytes is used by the
 /// [PNG format](https://en.wikipedia.org/wiki/Portable_Network_Graphics#File_header).
@@ -33,11 +133,26 @@ const CORRUPTION_CHECK_BYTES: &[u8] = &[0x0d, 0x0a, 0x1a, 0x0a];
 pub const PATH_SEPARATOR: char = '/';
 
 /// Types of files supported in banks, in the order they appear in the bank.
+#[cfg(feature = "std")]
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum ItemKind {
     Background,
     Metadata,
+
+    /// Notes about the changes in each version of the bank.
+    Changelog,
+
+    /// Images other than the background, such as the logo of the author,
+    /// found in some factory banks.
+    Image,
+
     Sample,
+
+    /// Recordings of presets played, named after the preset, so stores and
+    /// browsers can play a preset without loading it. Kilohearts products
+    /// ignore them.
+    Preview,
+
     MultipassPreset,
     PhasePlantPreset,
     SnapHeapPreset,
@@ -78,17 +193,71 @@ pub enum ItemKind {
     TransientShaper,
 }
 
+#[cfg(feature = "std")]
 impl ItemKind {
     /// Name of the directory that contains files of this type inside the bank.
     #[must_use]
     pub fn directory(&self) -> Option<&'static str> {
         match self {
-            ItemKind::Background | ItemKind::Metadata => None,
+            ItemKind::Background | ItemKind::Metadata | ItemKind::Changelog => None,
+            ItemKind::Image => Some("images"),
             ItemKind::Sample => Some("samples"),
+            ItemKind::Preview => Some("previews"),
             kind => kind.extensions().first().copied(),
         }
     }
 
+    /// Name of the type of files for people, such as `Phase Plant preset`.
+    #[must_use]
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Background => "Background",
+            Self::Metadata => "Metadata",
+            Self::Changelog => "Changelog",
+            Self::Image => "Image",
+            Self::Sample => "Sample",
+            Self::Preview => "Preview",
+            Self::MultipassPreset => "Multipass preset",
+            Self::PhasePlantPreset => "Phase Plant preset",
+            Self::SnapHeapPreset => "Snap Heap preset",
+            Self::ThreeBandEq => "3-Band EQ preset",
+            Self::Bitcrush => "Bitcrush preset",
+            Self::CarveEq => "Carve EQ preset",
+            Self::Chorus => "Chorus preset",
+            Self::CombFilter => "Comb Filter preset",
+            Self::Compressor => "Compressor preset",
+            Self::Convolver => "Convolver preset",
+            Self::Delay => "Delay preset",
+            Self::Disperser => "Disperser preset",
+            Self::Distortion => "Distortion preset",
+            Self::Dynamics => "Dynamics preset",
+            Self::Ensemble => "Ensemble preset",
+            Self::Faturator => "Faturator preset",
+            Self::Filter => "Filter preset",
+            Self::Flanger => "Flanger preset",
+            Self::FormatFilter => "Formant Filter preset",
+            Self::FrequencyShifter => "Frequency Shifter preset",
+            Self::Gain => "Gain preset",
+            Self::Gate => "Gate preset",
+            Self::Haas => "Haas preset",
+            Self::LadderFilter => "Ladder Filter preset",
+            Self::Limiter => "Limiter preset",
+            Self::NonlinearFilter => "Nonlinear Filter preset",
+            Self::PhaseDistortion => "Phase Distortion preset",
+            Self::Phaser => "Phaser preset",
+            Self::PitchShifter => "Pitch Shifter preset",
+            Self::Resonator => "Resonator preset",
+            Self::Reverb => "Reverb preset",
+            Self::Reverser => "Reverser preset",
+            Self::RingMod => "Ring Mod preset",
+            Self::SliceEq => "Slice EQ preset",
+            Self::Stereo => "Stereo preset",
+            Self::TapeStop => "Tape Stop preset",
+            Self::TranceGate => "Trance Gate preset",
+            Self::TransientShaper => "Transient Shaper preset",
+        }
+    }
+
     /// File name extensions that are used for the type of files, without the
     /// leading dot.
     #[must_use]
@@ -96,7 +265,10 @@ impl ItemKind {
         match self {
             Self::Background => vec!["jpg", "png"],
             Self::Metadata => vec!["json"],
+            Self::Changelog => vec!["md", "txt"],
+            Self::Image => vec!["jpg", "png"],
             Self::Sample => vec!["flac", "mp3", "wav"],
+            Self::Preview => vec!["flac", "mp3"],
             Self::MultipassPreset => vec!["multipass"],
             Self::PhasePlantPreset => vec!["phaseplant"],
             Self::SnapHeapPreset => vec!["snapheap"],
@@ -147,16 +319,48 @@ impl ItemKind {
             .any(|ext| ext.eq_ignore_ascii_case(&extension))
     }
 
+    /// Returns `true` for presets of Kilohearts products, which is everything
+    /// except the background, metadata, changelog, images, samples and
+    /// previews.
+    #[must_use]
+    pub fn is_preset(&self) -> bool {
+        !matches!(
+            self,
+            Self::Background
+                | Self::Metadata
+                | Self::Changelog
+                | Self::Image
+                | Self::Sample
+                | Self::Preview
+        )
+    }
+
+    /// The product that opens presets of this kind, or `None` for items that
+    /// are not presets.
+    #[must_use]
+    pub fn product(&self) -> Option<Product> {
+        match self {
+            Self::PhasePlantPreset => Some(Product::PhasePlant),
+            Self::MultipassPreset => Some(Product::Multipass),
+            Self::SnapHeapPreset => Some(Product::SnapHeap),
+            kind if kind.is_preset() => Some(Product::Snapins),
+            _ => None,
+        }
+    }
+
     /// Every supported item kind
     #[must_use]
-    pub const fn all() -> [ItemKind; 41] {
+    pub const fn all() -> [ItemKind; 44] {
         [
             Self::Background,
             Self::Metadata,
+            Self::Changelog,
+            Self::Image,
             Self::MultipassPreset,
             Self::PhasePlantPreset,
             Self::SnapHeapPreset,
             Self::Sample,
+            Self::Preview,
             Self::ThreeBandEq,
             Self::Bitcrush,
             Self::CarveEq,
@@ -196,7 +400,10 @@ impl ItemKind {
     }
 
     /// Find the kind of a file from the file name extension. The background
-    /// and metadata also require a specific file name.
+    /// metadata and changelog also require a specific file name. Images in a directory
+    /// named like the directory of [`ItemKind::Image`] are images rather than
+    /// the background, and audio in the directory of [`ItemKind::Preview`] are
+    /// previews rather than samples.
     #[must_use]
     pub fn from<P: AsRef<Path>>(path: P) -> Option<ItemKind> {
         // Assumes the well-known file names and file extensions are ASCII.
@@ -211,16 +418,168 @@ impl ItemKind {
             .eq_ignore_ascii_case(BACKGROUND_FILE_STEM)
         {
             return Some(ItemKind::Background);
+        } else if path
+            .as_ref()
+            .file_stem()
+            .is_some_and(|stem| stem.eq_ignore_ascii_case(CHANGELOG_FILE_STEM))
+            && path
+                .as_ref()
+                .extension()
+                .is_some_and(|extension| ItemKind::Changelog.has_extension(extension))
+        {
+            return Some(ItemKind::Changelog);
+        }
+
+        for kind in [ItemKind::Image, ItemKind::Preview] {
+            let in_directory = path
+                .as_ref()
+                .parent()
+                .and_then(Path::file_name)
+                .zip(kind.directory())
+                .is_some_and(|(parent, directory)| parent.eq_ignore_ascii_case(directory));
+            if in_directory
+                && path
+                    .as_ref()
+                    .extension()
+                    .is_some_and(|extension| kind.has_extension(extension))
+            {
+                return Some(kind);
+            }
         }
 
         // Match file name extension to see if it should be included in the bank.
-        path.as_ref().extension().and_then(|extension| {
-            ItemKind::all().into_iter().find(|kind| {
-                kind.extensions()
-                    .iter()
-                    .any(|ext| ext.eq_ignore_ascii_case(&extension.to_string_lossy()))
+        path.as_ref()
+            .extension()
+            .and_then(|extension| ItemKind::for_extension(&extension.to_string_lossy()))
+    }
+
+    /// Find the kind of item that uses the file name extension, ignoring case
+    /// and any leading dot. Unlike [`ItemKind::from`] the file name of the
+    /// background and metadata is not checked, images are always the
+    /// background and audio is always a sample. The changelog is never found since it needs its file name.
+    #[must_use]
+    pub fn for_extension(extension: &str) -> Option<ItemKind> {
+        let extension = extension.strip_prefix('.').unwrap_or(extension);
+        ItemKind::all()
+            .into_iter()
+            .filter(|kind| *kind != ItemKind::Changelog)
+            .find(|kind| kind.has_extension(extension.as_ref()))
+    }
+
+    /// Every known file name extension with the kind of item that uses it.
+    /// The directory the items are stored in is [`ItemKind::directory`]. The
+    /// extensions of images are mapped to the background and those of
+    /// previews to samples, like [`ItemKind::for_extension`], and the
+    /// changelog is left out.
+    #[must_use]
+    pub fn extension_map() -> BTreeMap<&'static str, ItemKind> {
+        let mut map = BTreeMap::new();
+        for kind in ItemKind::all()
+            .into_iter()
+            .filter(|kind| *kind != ItemKind::Changelog)
+        {
+            for extension in kind.extensions() {
+                map.entry(extension).or_insert(kind);
+            }
+        }
+        map
+    }
+}
+
+/// Kilohearts products that presets are made for, with every snapin effect
+/// counted together the way they are sold.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Product {
+    PhasePlant,
+    Multipass,
+    SnapHeap,
+    Snapins,
+}
+
+#[cfg(feature = "std")]
+impl Product {
+    /// Number of presets for each product among the kinds of items. Products
+    /// without any presets are left out.
+    pub fn count_presets<I: IntoIterator<Item = ItemKind>>(kinds: I) -> BTreeMap<Product, usize> {
+        let mut counts = BTreeMap::new();
+        for product in kinds.into_iter().filter_map(|kind| kind.product()) {
+            *counts.entry(product).or_default() += 1;
+        }
+        counts
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for Product {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Product::PhasePlant => write!(f, "Phase Plant"),
+            Product::Multipass => write!(f, "Multipass"),
+            Product::SnapHeap => write!(f, "Snap Heap"),
+            Product::Snapins => write!(f, "snapins"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for ItemKind {
+    type Err = io::Error;
+
+    /// Parse the name of the kind such as `PhasePlantPreset`, ignoring case and
+    /// any dashes or underscores so `phase-plant-preset` is accepted too.
+    fn from_str(name: &str) -> io::Result<ItemKind> {
+        let normalize = |name: &str| {
+            name.chars()
+                .filter(|c| *c != '-' && *c != '_')
+                .collect::<String>()
+                .to_lowercase()
+        };
+        let wanted = normalize(name);
+        ItemKind::all()
+            .into_iter()
+            .find(|kind| normalize(&format!("{kind:?}")) == wanted)
+            .ok_or_else(|| {
+                let msg = format!("{name} is not a kind of item");
+                io::Error::new(io::ErrorKind::InvalidInput, msg)
             })
-        })
+    }
+}
+
+/// A field of the metadata with a different value, see
+/// [`Metadata::changes_from`]. A field that is not in the JSON is null.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FieldChange {
+    pub from: Value,
+    pub to: Value,
+}
+
+/// The differences between two versions of the metadata, see
+/// [`Metadata::changes_from`].
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MetadataChanges {
+    /// Fields with a different value, including extra fields, by their name
+    /// in the JSON.
+    pub changed: BTreeMap<String, FieldChange>,
+
+    /// Extra fields, which are not part of [`Metadata`], kept as they were.
+    pub extra_kept: Vec<String>,
+
+    /// Extra fields that were removed.
+    pub extra_dropped: Vec<String>,
+}
+
+#[cfg(feature = "std")]
+impl MetadataChanges {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.extra_kept.is_empty() && self.extra_dropped.is_empty()
     }
 }
 
@@ -229,7 +588,9 @@ impl ItemKind {
 ///
 /// Some fields have only been found in Kilohearts factory content banks and not
 /// in those created with Kilohearts Bank Maker.
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Metadata {
     /// Only found in Kilohearts factory content banks.
     pub version: Option<u32>,
@@ -241,6 +602,15 @@ pub struct Metadata {
     pub author: String,
     pub description: String,
 
+    /// Translations of the name by language tag, such as `de` or `ja`. Not
+    /// read by Kilohearts products, which always show the name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub name_localized: BTreeMap<String, String>,
+
+    /// Translations of the description by language tag.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub description_localized: BTreeMap<String, String>,
+
     /// A 160-bit hash as a hex string. Only found in Kilohearts factory content banks.
     /// The hash of a bank appears to be the same no matter who downloaded it or with
     /// which version of the application.
@@ -251,10 +621,52 @@ pub struct Metadata {
     pub extra: HashMap<String, Value>,
 }
 
+#[cfg(feature = "std")]
 impl Metadata {
     /// Name of the file inside and outside of the bank that contains the metadata.
     pub const FILE_NAME: &'static str = "index.json";
 
+    /// Longest name, in characters, observed in the Kilohearts factory content banks.
+    pub const MAX_NAME_LENGTH: usize = 50;
+
+    /// Longest description, in characters, observed in the Kilohearts factory content banks.
+    pub const MAX_DESCRIPTION_LENGTH: usize = 500;
+
+    /// Check the metadata against the rules followed by the Kilohearts factory
+    /// content banks. Returns every rule that is broken.
+    #[must_use]
+    pub fn validate(&self) -> Vec<MetadataIssue> {
+        let mut issues = Vec::new();
+        if self.id.is_empty() {
+            issues.push(MetadataIssue::EmptyId);
+        } else if Metadata::sanitize_id(&self.id) != self.id {
+            issues.push(MetadataIssue::UnsanitizedId {
+                id: self.id.clone(),
+            });
+        }
+        if let Some(hash) = self.hash.as_ref().filter(|hash| !hash.is_empty()) {
+            if hash.len() != 40 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                issues.push(MetadataIssue::MalformedHash { hash: hash.clone() });
+            }
+        }
+        if self.version == Some(0) {
+            issues.push(MetadataIssue::ZeroVersion);
+        }
+        let name_length = self.name.chars().count();
+        if name_length > Metadata::MAX_NAME_LENGTH {
+            issues.push(MetadataIssue::NameTooLong {
+                length: name_length,
+            });
+        }
+        let description_length = self.description.chars().count();
+        if description_length > Metadata::MAX_DESCRIPTION_LENGTH {
+            issues.push(MetadataIssue::DescriptionTooLong {
+                length: description_length,
+            });
+        }
+        issues
+    }
+
     /// Bank IDs are lowercase and alphanumeric, plus a dot used as a separator.
     #[must_use]
     pub fn sanitize_id(str: &str) -> String {
@@ -268,24 +680,363 @@ impl Metadata {
             })
             .collect::<String>()
     }
+
+    /// The ID of the bank. When there isn't one the ID is made from the
+    /// author and name, which is how banks are identified when they are written.
+    #[must_use]
+    pub fn effective_id(&self) -> String {
+        if !self.id.is_empty() {
+            return self.id.clone();
+        }
+
+        let mut id_parts = Vec::with_capacity(2);
+        let author_part = Metadata::sanitize_id(&self.author);
+        let name_part = Metadata::sanitize_id(&self.name);
+        if !author_part.is_empty() {
+            id_parts.push(author_part);
+        }
+        if !name_part.is_empty() {
+            id_parts.push(name_part);
+        }
+        id_parts.join(".")
+    }
+
+    /// How this metadata differs from the metadata it was made from, such as
+    /// the metadata file of a bank with values given on the command line.
+    /// Fields are compared as they are written to the JSON.
+    #[must_use]
+    pub fn changes_from(&self, original: &Metadata) -> MetadataChanges {
+        let to_object = |metadata: &Metadata| match serde_json::to_value(metadata) {
+            Ok(Value::Object(object)) => object,
+            _ => serde_json::Map::new(),
+        };
+        let before = to_object(original);
+        let after = to_object(self);
+
+        let mut changes = MetadataChanges::default();
+        for (field, from) in &before {
+            let is_extra = original.extra.contains_key(field);
+            match after.get(field) {
+                Some(to) if to == from => {
+                    if is_extra {
+                        changes.extra_kept.push(field.clone());
+                    }
+                }
+                Some(to) => {
+                    changes.changed.insert(
+                        field.clone(),
+                        FieldChange {
+                            from: from.clone(),
+                            to: to.clone(),
+                        },
+                    );
+                }
+                None if is_extra => changes.extra_dropped.push(field.clone()),
+                None => {
+                    changes.changed.insert(
+                        field.clone(),
+                        FieldChange {
+                            from: from.clone(),
+                            to: Value::Null,
+                        },
+                    );
+                }
+            }
+        }
+        for (field, to) in after
+            .iter()
+            .filter(|(field, _)| !before.contains_key(*field))
+        {
+            changes.changed.insert(
+                field.clone(),
+                FieldChange {
+                    from: Value::Null,
+                    to: to.clone(),
+                },
+            );
+        }
+        changes
+    }
+
+    /// Increment the version, starting at 1 if there is no version yet.
+    /// Returns the new version.
+    pub fn bump_version(&mut self) -> u32 {
+        let version = self.version.map_or(1, |version| version.saturating_add(1));
+        self.version = Some(version);
+        version
+    }
+
+    /// How the bank was made, if it was recorded.
+    #[must_use]
+    pub fn provenance(&self) -> Option<Provenance> {
+        self.extra_get(Provenance::KEY).ok().flatten()
+    }
+
+    /// Record how the bank was made, replacing any previous provenance.
+    pub fn set_provenance(&mut self, provenance: &Provenance) {
+        if let Err(error) = self.extra_set(Provenance::KEY, provenance) {
+            warn!("Cannot record the provenance: {error}");
+        }
+    }
+
+    /// Returns `true` if the key has a namespace followed by a dot and a name,
+    /// such as `"vendor.key"`. Extra metadata is only set with namespaced keys
+    /// so tools that store data in the same bank don't replace each other's
+    /// values, or the fields used by Kilohearts which never contain a dot.
+    #[must_use]
+    pub fn is_namespaced_key(key: &str) -> bool {
+        key.split_once('.')
+            .is_some_and(|(namespace, name)| !namespace.is_empty() && !name.is_empty())
+    }
+
+    /// The value stored in the extra metadata under the namespaced key, or
+    /// `None` if there is no value.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the value is not of the expected type.
+    pub fn extra_get<T: DeserializeOwned>(&self, key: &str) -> io::Result<Option<T>> {
+        self.extra
+            .get(key)
+            .map(|value| T::deserialize(value).map_err(io::Error::from))
+            .transpose()
+    }
+
+    /// Store the value in the extra metadata under the namespaced key,
+    /// replacing any previous value.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the key is not namespaced or the value cannot be
+    /// converted to JSON.
+    pub fn extra_set<T: Serialize>(&mut self, key: &str, value: &T) -> io::Result<()> {
+        if !Metadata::is_namespaced_key(key) {
+            let msg = format!("Extra metadata key {key} must have a namespace, such as vendor.key");
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+        self.extra
+            .insert(key.to_string(), serde_json::to_value(value)?);
+        Ok(())
+    }
+
+    /// Remove the value stored under the key, returning it if there was one.
+    pub fn extra_remove(&mut self, key: &str) -> Option<Value> {
+        self.extra.remove(key)
+    }
+
+    /// The name in the language with the tag, such as `de` or `de-AT`. Falls
+    /// back to the language without its region, then to the name.
+    #[must_use]
+    pub fn localized_name(&self, lang: &str) -> &str {
+        localized(&self.name_localized, lang).unwrap_or(&self.name)
+    }
+
+    /// The description in the language with the tag. Falls back like
+    /// [`Metadata::localized_name`].
+    #[must_use]
+    pub fn localized_description(&self, lang: &str) -> &str {
+        localized(&self.description_localized, lang).unwrap_or(&self.description)
+    }
+
+    /// Blank a field so it isn't shared, such as the hash in a bank for
+    /// customers. The fields are `id`, `name`, `author`, `description`,
+    /// `name_localized`, `description_localized`, `version`, `hash`, `extra`
+    /// for all of the extra metadata, `provenance`, or any namespaced key of
+    /// the extra metadata.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the field is not known.
+    pub fn strip(&mut self, field: &str) -> io::Result<()> {
+        match field {
+            "id" => self.id.clear(),
+            "name" => self.name.clear(),
+            "author" => self.author.clear(),
+            "description" => self.description.clear(),
+            "name_localized" => self.name_localized.clear(),
+            "description_localized" => self.description_localized.clear(),
+            "version" => self.version = None,
+            "hash" => self.hash = None,
+            "extra" => self.extra.clear(),
+            "provenance" => {
+                self.extra_remove(Provenance::KEY);
+            }
+            key if Metadata::is_namespaced_key(key) => {
+                self.extra_remove(key);
+            }
+            _ => {
+                let msg = format!("{field} is not a field of the metadata");
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The translation for the language tag, ignoring case, or for the language
+/// without its region.
+#[cfg(feature = "std")]
+fn localized<'a>(translations: &'a BTreeMap<String, String>, lang: &str) -> Option<&'a str> {
+    let find = |tag: &str| {
+        translations
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(tag))
+            .map(|(_, text)| text.as_str())
+    };
+    find(lang).or_else(|| find(lang.split(['-', '_']).next().unwrap_or_default()))
+}
+
+/// A rule broken by the metadata. See [`Metadata::validate`].
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MetadataIssue {
+    EmptyId,
+
+    /// The ID changes when it is sanitized with [`Metadata::sanitize_id`].
+    UnsanitizedId {
+        id: String,
+    },
+
+    /// The hash is not 40 hexadecimal digits.
+    MalformedHash {
+        hash: String,
+    },
+
+    ZeroVersion,
+    NameTooLong {
+        length: usize,
+    },
+    DescriptionTooLong {
+        length: usize,
+    },
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for MetadataIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataIssue::EmptyId => write!(f, "ID is empty"),
+            MetadataIssue::UnsanitizedId { id } => write!(
+                f,
+                "ID {id} is not lowercase letters and numbers separated by dots"
+            ),
+            MetadataIssue::MalformedHash { hash } => {
+                write!(f, "Hash {hash} is not 40 hexadecimal digits")
+            }
+            MetadataIssue::ZeroVersion => write!(f, "Version is zero"),
+            MetadataIssue::NameTooLong { length } => write!(
+                f,
+                "Name is {length} characters, more than {}",
+                Metadata::MAX_NAME_LENGTH
+            ),
+            MetadataIssue::DescriptionTooLong { length } => write!(
+                f,
+                "Description is {length} characters, more than {}",
+                Metadata::MAX_DESCRIPTION_LENGTH
+            ),
+        }
+    }
+}
+
+/// Details about how and when a bank was made. Stored in the extra metadata
+/// under the [`Provenance::KEY`] so other tools can ignore it.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Provenance {
+    /// Name of the application that created the bank.
+    pub tool: String,
+
+    /// Version of the application that created the bank.
+    pub tool_version: String,
+
+    /// When the bank was created, in seconds since the Unix epoch.
+    pub created: u64,
+
+    /// Hash of the names and contents of the files the bank was created from.
+    pub source_hash: String,
+}
+
+#[cfg(feature = "std")]
+impl Provenance {
+    /// Key in the extra metadata that holds the provenance.
+    pub const KEY: &'static str = "kibank.provenance";
+
+    /// Provenance for a bank created now by this crate.
+    #[must_use]
+    pub fn new(source_hash: String) -> Provenance {
+        Provenance {
+            tool: env!("CARGO_PKG_NAME").to_string(),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            created: unix_now(),
+            source_hash,
+        }
+    }
+
+    /// The creation time in the UTC ISO 8601 format, such as `2024-02-26T17:05:00Z`.
+    #[must_use]
+    pub fn created_utc(&self) -> String {
+        format_utc(self.created)
+    }
+}
+
+/// Seconds since the Unix epoch.
+#[cfg(feature = "std")]
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Format seconds since the Unix epoch in the UTC ISO 8601 format.
+#[cfg(feature = "std")]
+fn format_utc(unix_seconds: u64) -> String {
+    // Convert days since the epoch to a civil date using the algorithm
+    // from https://howardhinnant.github.io/date_algorithms.html
+    #![allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+    let days = (unix_seconds / 86_400) as i64 + 719_468;
+    let seconds = unix_seconds % 86_400;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
 }
 
+/// Where the name and contents of an item are, as stored in the header.
+/// Public only for the parsing stages in [`parse`].
+#[doc(hidden)]
 #[derive(Clone, Debug)]
-struct Location {
+pub struct Location {
     /// From start of file name block
-    file_name_offset: u64,
+    pub file_name_offset: u64,
 
     /// From the start of the file
-    data_offset: u64,
+    pub data_offset: u64,
 
-    data_size: u64,
+    pub data_size: u64,
 }
 
 impl Location {
     /// Number of bytes used to store the structure on disk.
-    const BLOCK_SIZE: usize = size_of::<u64>() * 3;
+    pub const BLOCK_SIZE: usize = size_of::<u64>() * 3;
 
+    /// End of the contents, which is never less than the start even when the
+    /// header of a malformed bank claims an impossible size.
     pub fn data_end(&self) -> u64 {
-        self.data_offset + self.data_size
+        self.data_offset.saturating_add(self.data_size)
     }
 }
@@ -0,0 +1,245 @@
+//! Policies for the contents of banks, such as requiring a description or
+//! limiting the size of samples.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::io::{BufRead, Read, Seek};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::background;
+use crate::read::BankReader;
+use crate::{ItemKind, Metadata};
+
+/// How seriously a rule is treated.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// The rule is not checked.
+    Allow,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Allow => write!(f, "allow"),
+            Level::Warning => write!(f, "warning"),
+            Level::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Every check that can be made of a bank.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    MissingDescription,
+    EmptyAuthor,
+    SmallBackground,
+    UppercaseExtension,
+    NonAsciiName,
+    OversizedSample,
+}
+
+impl Rule {
+    /// Every rule.
+    #[must_use]
+    pub const fn all() -> [Rule; 6] {
+        [
+            Rule::MissingDescription,
+            Rule::EmptyAuthor,
+            Rule::SmallBackground,
+            Rule::UppercaseExtension,
+            Rule::NonAsciiName,
+            Rule::OversizedSample,
+        ]
+    }
+
+    /// Short stable identifier of the rule for use in reports.
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        match self {
+            Rule::MissingDescription => "K001",
+            Rule::EmptyAuthor => "K002",
+            Rule::SmallBackground => "K003",
+            Rule::UppercaseExtension => "K004",
+            Rule::NonAsciiName => "K005",
+            Rule::OversizedSample => "K006",
+        }
+    }
+
+    /// Level of the rule when it isn't configured.
+    #[must_use]
+    pub fn default_level(&self) -> Level {
+        match self {
+            Rule::EmptyAuthor => Level::Error,
+            Rule::MissingDescription
+            | Rule::SmallBackground
+            | Rule::UppercaseExtension
+            | Rule::NonAsciiName
+            | Rule::OversizedSample => Level::Warning,
+        }
+    }
+}
+
+/// Rule levels and limits, typically loaded from a TOML file such as:
+///
+/// ```toml
+/// min-background-width = 1024
+/// max-sample-size = 50_000_000
+///
+/// [rules]
+/// missing-description = "error"
+/// non-ascii-name = "allow"
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LintConfig {
+    /// Levels that replace the default level of each rule.
+    pub rules: BTreeMap<Rule, Level>,
+
+    /// Backgrounds narrower than this many pixels are reported.
+    pub min_background_width: u32,
+
+    /// Backgrounds shorter than this many pixels are reported.
+    pub min_background_height: u32,
+
+    /// Samples larger than this many bytes are reported.
+    pub max_sample_size: u64,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            rules: BTreeMap::new(),
+            min_background_width: 400,
+            min_background_height: 200,
+            max_sample_size: 100_000_000,
+        }
+    }
+}
+
+impl LintConfig {
+    /// The configured level of the rule.
+    #[must_use]
+    pub fn level(&self, rule: Rule) -> Level {
+        self.rules
+            .get(&rule)
+            .copied()
+            .unwrap_or_else(|| rule.default_level())
+    }
+}
+
+/// A rule that was broken.
+#[derive(Clone, Debug)]
+pub struct Finding {
+    pub rule: Rule,
+    pub level: Level,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.level, self.rule.code(), self.message)
+    }
+}
+
+/// Check the bank against every rule that isn't allowed.
+///
+/// # Errors
+///
+/// Will return `Err` on read or seek failure, or if the metadata is malformed.
+pub fn lint<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    config: &LintConfig,
+) -> io::Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    let mut report = |rule: Rule, message: String| {
+        let level = config.level(rule);
+        if level != Level::Allow {
+            findings.push(Finding {
+                rule,
+                level,
+                message,
+            });
+        }
+    };
+
+    let items = reader.items();
+    let metadata = match items.iter().find(|item| item.is_metadata_file()) {
+        Some(item) => reader.read_metadata(item)?,
+        None => Metadata::default(),
+    };
+    if metadata.description.trim().is_empty() {
+        report(Rule::MissingDescription, "No description".to_string());
+    }
+    if metadata.author.trim().is_empty() {
+        report(Rule::EmptyAuthor, "No author".to_string());
+    }
+
+    for item in &items {
+        let name = item.file_name_lossy();
+        if !item.path_bytes().is_ascii() {
+            report(
+                Rule::NonAsciiName,
+                format!("Name of {name} contains characters that are not ASCII"),
+            );
+        }
+        if !item.is_file() {
+            continue;
+        }
+
+        let extension = Path::new(&name)
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy();
+        if extension.chars().any(|c| c.is_ascii_uppercase()) {
+            report(
+                Rule::UppercaseExtension,
+                format!("Extension of {name} is not lowercase"),
+            );
+        }
+
+        if ItemKind::from(Path::new(&name)) == Some(ItemKind::Sample)
+            && item.size() > config.max_sample_size
+        {
+            report(
+                Rule::OversizedSample,
+                format!(
+                    "Sample {name} is {} bytes, more than {} bytes",
+                    item.size(),
+                    config.max_sample_size
+                ),
+            );
+        }
+
+        if item.is_background_file() {
+            let contents = reader.read_contents(item)?;
+            match background::dimensions(&contents) {
+                Some((width, height))
+                    if width < config.min_background_width
+                        || height < config.min_background_height =>
+                {
+                    report(
+                        Rule::SmallBackground,
+                        format!(
+                            "Background {name} is {width}x{height}, smaller than {}x{}",
+                            config.min_background_width, config.min_background_height
+                        ),
+                    );
+                }
+                Some(_) => {}
+                None => report(
+                    Rule::SmallBackground,
+                    format!("Cannot find the size of background {name}"),
+                ),
+            }
+        }
+    }
+
+    Ok(findings)
+}
@@ -0,0 +1,216 @@
+//! Combining several banks into one, such as when consolidating personal
+//! preset collections. Files with the same path but different contents, and
+//! metadata fields with different values, are conflicts. The first bank wins
+//! each conflict unless another bank is chosen.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{BufRead, Error, ErrorKind, Read, Seek};
+
+use serde_json::{Map, Value};
+
+use crate::read::{BankReader, Item, OwnedItem};
+use crate::{hash, Metadata};
+
+/// The contents of a file in one of the banks being merged.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Candidate {
+    /// Position of the bank in the banks being merged.
+    pub bank: usize,
+
+    /// Size of the contents in bytes.
+    pub size: u64,
+
+    /// SHA-1 of the contents as a lowercase hex string.
+    pub digest: String,
+}
+
+/// A path with different contents in more than one bank.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ItemConflict {
+    pub path: String,
+
+    /// Each version of the contents, from the first bank that has it.
+    pub candidates: Vec<Candidate>,
+}
+
+/// A metadata field set to different values in more than one bank. Fields
+/// that are null or empty don't conflict with those that are set.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FieldConflict {
+    pub field: String,
+
+    /// Each value with the first bank that has it.
+    pub values: Vec<(usize, Value)>,
+}
+
+/// What goes into the merged bank.
+#[derive(Clone, Debug, Default)]
+pub struct MergePlan {
+    /// Each file of the merged bank by path, with the bank it is copied from.
+    /// The metadata file and directories are not included.
+    pub files: BTreeMap<String, (usize, OwnedItem)>,
+
+    pub item_conflicts: Vec<ItemConflict>,
+    pub field_conflicts: Vec<FieldConflict>,
+
+    /// Fields of the merged metadata as they are written to the JSON.
+    fields: Map<String, Value>,
+
+    /// Every file by path in each bank, for choosing another version.
+    items: Vec<BTreeMap<String, OwnedItem>>,
+}
+
+impl MergePlan {
+    /// Find the files and metadata of the banks and where they conflict. The
+    /// contents are only read for files with the same path.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` on read or seek failure, if a bank is truncated, or
+    /// if the metadata of a bank cannot be read.
+    pub fn new<ReaderType: Read + Seek + BufRead>(
+        readers: &mut [BankReader<ReaderType>],
+    ) -> io::Result<MergePlan> {
+        let mut plan = MergePlan::default();
+        let mut candidates = BTreeMap::<String, Vec<Candidate>>::new();
+        let mut metadata = Vec::new();
+        for bank in 0..readers.len() {
+            let mut files = BTreeMap::new();
+            for item in readers[bank].items().into_iter().filter(Item::is_file) {
+                if item.is_metadata_file() {
+                    metadata.push((bank, readers[bank].read_metadata(&item)?));
+                } else {
+                    files.insert(item.file_name_lossy(), item.into_owned());
+                }
+            }
+            for (path, item) in &files {
+                if !plan.files.contains_key(path) {
+                    plan.files.insert(path.clone(), (bank, item.clone()));
+                    continue;
+                }
+                let versions = match candidates.get_mut(path) {
+                    Some(versions) => versions,
+                    None => {
+                        let (first_bank, first_item) = &plan.files[path];
+                        let first = candidate(&mut readers[*first_bank], *first_bank, first_item)?;
+                        candidates.entry(path.clone()).or_insert(vec![first])
+                    }
+                };
+                let version = candidate(&mut readers[bank], bank, item)?;
+                if versions.iter().all(|other| other.digest != version.digest) {
+                    versions.push(version);
+                }
+            }
+            plan.items.push(files);
+        }
+
+        plan.item_conflicts = candidates
+            .into_iter()
+            .filter(|(_, candidates)| candidates.len() > 1)
+            .map(|(path, candidates)| ItemConflict { path, candidates })
+            .collect();
+        plan.merge_metadata(&metadata)?;
+        Ok(plan)
+    }
+
+    /// Copy the file at the path from another bank instead.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` with [`ErrorKind::NotFound`] if the bank doesn't
+    /// have the file.
+    pub fn choose_item(&mut self, path: &str, bank: usize) -> io::Result<()> {
+        let item = self
+            .items
+            .get(bank)
+            .and_then(|files| files.get(path))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("Bank {} has no file {path}", bank + 1),
+                )
+            })?;
+        self.files.insert(path.to_string(), (bank, item.clone()));
+        Ok(())
+    }
+
+    /// Use the value for the metadata field instead.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` with [`ErrorKind::InvalidData`] if the value is not
+    /// valid for the field.
+    pub fn choose_field(&mut self, field: &str, value: Value) -> io::Result<()> {
+        let previous = self.fields.insert(field.to_string(), value);
+        if let Err(err) = self.metadata() {
+            match previous {
+                Some(previous) => self.fields.insert(field.to_string(), previous),
+                None => self.fields.remove(field),
+            };
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// The metadata of the merged bank, which is the default metadata if no
+    /// bank has any.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` with [`ErrorKind::InvalidData`] if the merged fields
+    /// are not valid metadata.
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        if self.fields.is_empty() {
+            return Ok(Metadata::default());
+        }
+        serde_json::from_value(Value::Object(self.fields.clone()))
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    /// Each field takes the first value that is set, and the banks that set it
+    /// to something else are conflicts.
+    fn merge_metadata(&mut self, metadata: &[(usize, Metadata)]) -> io::Result<()> {
+        let is_set = |value: &Value| !value.is_null() && value.as_str() != Some("");
+        let mut values = BTreeMap::<String, Vec<(usize, Value)>>::new();
+        for (bank, metadata) in metadata {
+            let Value::Object(fields) = serde_json::to_value(metadata)
+                .map_err(|err| Error::new(ErrorKind::InvalidData, err))?
+            else {
+                continue;
+            };
+            for (field, value) in fields {
+                self.fields.entry(field.clone()).or_insert(value.clone());
+                let versions = values.entry(field).or_default();
+                if is_set(&value) && versions.iter().all(|(_, other)| *other != value) {
+                    versions.push((*bank, value));
+                }
+            }
+        }
+
+        for (field, versions) in values {
+            if let Some((_, first)) = versions.first() {
+                self.fields.insert(field.clone(), first.clone());
+            }
+            if versions.len() > 1 {
+                self.field_conflicts.push(FieldConflict {
+                    field,
+                    values: versions,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn candidate<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    bank: usize,
+    item: &Item,
+) -> io::Result<Candidate> {
+    Ok(Candidate {
+        bank,
+        size: item.size(),
+        digest: hash::item_digest(reader, item)?,
+    })
+}
@@ -0,0 +1,163 @@
+//! Rules for the consistent naming of presets.
+
+use std::cmp::Ordering;
+
+use crate::PATH_SEPARATOR;
+
+/// Rules applied to the name of a preset, without the extension. Rules that are
+/// not enabled leave the name untouched.
+#[derive(Clone, Debug, Default)]
+pub struct NameRules {
+    /// Capitalize the first letter of every word. The rest of each word is
+    /// left alone so abbreviations like "LFO" are preserved.
+    pub title_case: bool,
+
+    /// Replace runs of whitespace with a single space and remove leading and
+    /// trailing whitespace.
+    pub collapse_spaces: bool,
+
+    /// Start every name with a two digit number in the form `NN - Name`. An
+    /// existing leading number is kept, otherwise the position is used.
+    pub numbering: bool,
+
+    /// Maximum number of characters in a name.
+    pub max_length: Option<usize>,
+}
+
+impl NameRules {
+    /// Returns `true` if no rules are enabled.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        !self.title_case && !self.collapse_spaces && !self.numbering && self.max_length.is_none()
+    }
+
+    /// Apply the rules to a name.
+    ///
+    /// * `name` - name of the preset without the extension
+    /// * `position` - one-based position of the preset, used when numbering
+    #[must_use]
+    pub fn apply(&self, name: &str, position: usize) -> String {
+        let mut name = name.to_string();
+
+        if self.collapse_spaces {
+            name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+
+        if self.numbering {
+            let (number, rest) = split_number(&name);
+            name = format!("{:02} - {rest}", number.unwrap_or(position));
+        }
+
+        if self.title_case {
+            let mut capitalize = true;
+            name = name
+                .chars()
+                .map(|c| {
+                    let c = if capitalize {
+                        c.to_ascii_uppercase()
+                    } else {
+                        c
+                    };
+                    capitalize = c.is_whitespace() || c == '-' || c == '_';
+                    c
+                })
+                .collect();
+        }
+
+        if let Some(max_length) = self.max_length {
+            name = name.chars().take(max_length).collect::<String>();
+            if self.collapse_spaces {
+                name.truncate(name.trim_end().len());
+            }
+        }
+
+        name
+    }
+}
+
+/// Separate a leading number, with any following separator, from the rest of
+/// the name. For example `3- Bass` becomes `(Some(3), "Bass")`.
+fn split_number(name: &str) -> (Option<usize>, &str) {
+    let digits = name.chars().take_while(char::is_ascii_digit).count();
+    match name[..digits].parse() {
+        Ok(number) => {
+            let rest = name[digits..].trim_start_matches(|c: char| {
+                c.is_whitespace() || c == '-' || c == '_' || c == '.'
+            });
+            (Some(number), rest)
+        }
+        Err(_) => (None, name),
+    }
+}
+
+/// Compare names the way people expect a list of presets to be ordered, with
+/// runs of digits compared by their value so `Preset 2` comes before
+/// `Preset 10`. Everything else is compared ignoring ASCII case. Names that
+/// are only different in case or leading zeros are ordered by their bytes so
+/// the order is always the same.
+#[must_use]
+pub fn natural_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+            let a_digits = digits(&a[i..]);
+            let b_digits = digits(&b[j..]);
+            let ordering = compare_numbers(a_digits, b_digits);
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            i += a_digits.len();
+            j += b_digits.len();
+        } else {
+            let ordering = a[i].to_ascii_lowercase().cmp(&b[j].to_ascii_lowercase());
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            i += 1;
+            j += 1;
+        }
+    }
+    (a.len() - i).cmp(&(b.len() - j)).then_with(|| a.cmp(b))
+}
+
+/// The path with the extension of the file name in lowercase, or `None` if
+/// it is already lowercase. Some hosts only recognize lowercase extensions.
+/// The rest of the path, including the name before the extension, is kept.
+#[must_use]
+pub fn lowercase_extension(path: &[u8]) -> Option<Vec<u8>> {
+    let name_start = path
+        .iter()
+        .rposition(|byte| *byte == PATH_SEPARATOR as u8)
+        .map_or(0, |index| index + 1);
+    let dot = path[name_start..]
+        .iter()
+        .rposition(|byte| *byte == b'.')
+        .filter(|dot| *dot > 0)?
+        + name_start;
+    if !path[dot..].iter().any(u8::is_ascii_uppercase) {
+        return None;
+    }
+    let mut lowercase = path.to_vec();
+    lowercase[dot..].make_ascii_lowercase();
+    Some(lowercase)
+}
+
+/// The run of ASCII digits at the start of the bytes.
+fn digits(bytes: &[u8]) -> &[u8] {
+    let len = bytes
+        .iter()
+        .take_while(|byte| byte.is_ascii_digit())
+        .count();
+    &bytes[..len]
+}
+
+/// Compare two runs of digits by value without parsing them, so numbers of
+/// any length can be compared.
+fn compare_numbers(a: &[u8], b: &[u8]) -> Ordering {
+    let trim = |digits: &[u8]| {
+        let zeros = digits.iter().take_while(|digit| **digit == b'0').count();
+        digits[zeros..].to_vec()
+    };
+    let (a, b) = (trim(a), trim(b));
+    a.len().cmp(&b.len()).then_with(|| a.cmp(&b))
+}
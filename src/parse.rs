@@ -0,0 +1,228 @@
+//! Parsing of bank headers from bytes in memory. Only `core` and `alloc` are
+//! used, not `std::io`, so the parts of a bank can be found where there is no
+//! filesystem, such as a WebAssembly runtime or the sandbox of a plugin host.
+//! [`crate::read::BankReader`] reads each part from a stream and parses it
+//! with the same functions.
+//!
+//! Each stage of parsing is also public but hidden from the documentation,
+//! so fuzz targets and property tests can give malformed input to one stage
+//! at a time. The stages may change in any release.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use log::trace;
+
+use crate::{Location, CORRUPTION_CHECK_BYTES, FILE_ID, FORMAT_VERSION};
+
+/// Length of the start of the header, up to and including the number of
+/// locations.
+pub const PREFIX_LEN: usize =
+    FILE_ID.len() + CORRUPTION_CHECK_BYTES.len() + FORMAT_VERSION.len() + U64_LEN;
+
+const U64_LEN: usize = core::mem::size_of::<u64>();
+
+/// Why the bytes are not a bank.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    message: String,
+}
+
+impl ParseError {
+    fn new(message: String) -> ParseError {
+        ParseError { message }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Where the name and the contents of an item are.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+    /// Position of the name in the file name block, without the final null.
+    pub name: Range<usize>,
+
+    /// From the start of the bank.
+    pub data_offset: u64,
+
+    pub data_size: u64,
+}
+
+/// The items described by the header of a bank.
+#[derive(Clone, Debug)]
+pub struct Header<'a> {
+    pub file_name_block: &'a [u8],
+    pub entries: Vec<Entry>,
+
+    /// Number of bytes in the header, which is where the contents start.
+    pub len: u64,
+}
+
+impl Header<'_> {
+    /// The path of the item in the bank.
+    #[must_use]
+    pub fn name(&self, entry: &Entry) -> &[u8] {
+        &self.file_name_block[entry.name.clone()]
+    }
+}
+
+/// Parse the header at the start of the bytes, which don't need to include
+/// the contents of the items.
+///
+/// # Errors
+///
+/// Will return `Err` if the bytes are not the start of a bank or the header is
+/// longer than the bytes.
+pub fn parse(bytes: &[u8]) -> Result<Header<'_>, ParseError> {
+    let prefix = bytes
+        .get(..PREFIX_LEN)
+        .ok_or_else(|| ParseError::new(String::from("Not a Kilohearts bank")))?;
+    let location_count = parse_header(prefix)?;
+
+    let rest = &bytes[PREFIX_LEN..];
+    let location_block_len = location_block_len(location_count, rest.len() as u64)?;
+    let locations = parse_locations(&rest[..location_block_len]);
+
+    let rest = &rest[location_block_len..];
+    let file_name_block_length = rest
+        .get(..U64_LEN)
+        .map(read_u64)
+        .ok_or_else(|| ParseError::new(String::from("File name block length is missing")))?;
+    let rest = &rest[U64_LEN..];
+    let file_name_block = usize::try_from(file_name_block_length)
+        .ok()
+        .and_then(|len| rest.get(..len))
+        .ok_or_else(|| {
+            ParseError::new(format!(
+                "File name block length {file_name_block_length} is larger than the file"
+            ))
+        })?;
+
+    let names = parse_name_block(file_name_block, &locations)?;
+    let entries = names
+        .into_iter()
+        .zip(locations)
+        .map(|(name, location)| Entry {
+            name,
+            data_offset: location.data_offset,
+            data_size: location.data_size,
+        })
+        .collect();
+    Ok(Header {
+        file_name_block,
+        entries,
+        len: (PREFIX_LEN + location_block_len + U64_LEN + file_name_block.len()) as u64,
+    })
+}
+
+/// Check the identifying bytes at the start of a bank and return the number
+/// of locations that follow. Bytes after [`PREFIX_LEN`] are ignored.
+#[doc(hidden)]
+pub fn parse_header(prefix: &[u8]) -> Result<u64, ParseError> {
+    if prefix.len() < PREFIX_LEN {
+        return Err(ParseError::new(String::from("Not a Kilohearts bank")));
+    }
+    let (file_id, rest) = prefix.split_at(FILE_ID.len());
+    if file_id != FILE_ID {
+        return Err(ParseError::new(String::from("Not a Kilohearts bank")));
+    }
+
+    let (check_bytes, rest) = rest.split_at(CORRUPTION_CHECK_BYTES.len());
+    if check_bytes != CORRUPTION_CHECK_BYTES {
+        return Err(ParseError::new(format!(
+            "Unexpected check bytes {}",
+            check_bytes.escape_ascii()
+        )));
+    }
+
+    let (format_version, rest) = rest.split_at(FORMAT_VERSION.len());
+    if format_version != FORMAT_VERSION {
+        return Err(ParseError::new(format!(
+            "Unexpected format version {}",
+            format_version.escape_ascii()
+        )));
+    }
+
+    let location_count = read_u64(rest);
+    trace!("Number of locations is {location_count}");
+    Ok(location_count)
+}
+
+/// Length of the locations, which must fit in what remains of the bank. This
+/// also makes the count safe to use for allocation.
+#[doc(hidden)]
+pub fn location_block_len(location_count: u64, remaining: u64) -> Result<usize, ParseError> {
+    location_count
+        .checked_mul(Location::BLOCK_SIZE as u64)
+        .filter(|len| *len <= remaining)
+        .and_then(|len| usize::try_from(len).ok())
+        .ok_or_else(|| {
+            ParseError::new(format!(
+                "Location count {location_count} is larger than the file"
+            ))
+        })
+}
+
+/// Parse every location in the block. Any bytes after the last whole
+/// location are ignored.
+#[doc(hidden)]
+pub fn parse_locations(block: &[u8]) -> Vec<Location> {
+    block
+        .chunks_exact(Location::BLOCK_SIZE)
+        .map(|chunk| {
+            let location = Location {
+                file_name_offset: read_u64(&chunk[..U64_LEN]),
+                data_offset: read_u64(&chunk[U64_LEN..U64_LEN * 2]),
+                data_size: read_u64(&chunk[U64_LEN * 2..]),
+            };
+            trace!(
+                "File name offset is {}, data offset is {}, data size is {}",
+                location.file_name_offset,
+                location.data_offset,
+                location.data_size
+            );
+            location
+        })
+        .collect()
+}
+
+/// Find the name of each location in the file name block. Names end with a
+/// null, or the end of the block if the final null is missing, so a name
+/// never contains a null.
+#[doc(hidden)]
+pub fn parse_name_block(
+    block: &[u8],
+    locations: &[Location],
+) -> Result<Vec<Range<usize>>, ParseError> {
+    locations
+        .iter()
+        .map(|location| {
+            let start = usize::try_from(location.file_name_offset)
+                .ok()
+                .filter(|start| *start < block.len())
+                .ok_or_else(|| {
+                    ParseError::new(format!(
+                        "File name offset {} is past the end of the file name block",
+                        location.file_name_offset
+                    ))
+                })?;
+            let names = &block[start..];
+            let end = start + memchr::memchr(0_u8, names).unwrap_or(names.len());
+            Ok(start..end)
+        })
+        .collect()
+}
+
+/// Little-endian number from the first eight bytes, which must be present.
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut number = [0_u8; U64_LEN];
+    number.copy_from_slice(&bytes[..U64_LEN]);
+    u64::from_le_bytes(number)
+}
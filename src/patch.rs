@@ -0,0 +1,206 @@
+//! Updates between two versions of a bank that only contain the items that
+//! changed, so a large bank can be updated with a small download.
+//!
+//! A patch starts with [`MAGIC`], followed by the length of a JSON header as a
+//! little-endian `u64`, the header, then the contents of every item that is
+//! not in the old bank. The header lists every item of the new bank in order
+//! and where its contents come from.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io;
+use std::io::{BufRead, Error, ErrorKind, Read, Seek, Write};
+use std::path::Path;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::hash;
+use crate::read::BankReader;
+use crate::write::{BankWriter, BankWriterOptions};
+use crate::ItemKind;
+
+/// First bytes of a patch.
+pub const MAGIC: &[u8] = b"kbpatch1";
+
+/// File name extension of patches, without the leading dot.
+pub const EXTENSION: &str = "kbpatch";
+
+/// Where the contents of an item of the new bank come from.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "source")]
+pub enum Source {
+    /// The item is a directory.
+    Directory,
+
+    /// The item is unchanged from the old bank.
+    Old { digest: String },
+
+    /// The contents are in the patch.
+    Patch { digest: String, size: u64 },
+}
+
+/// An item of the new bank.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PatchItem {
+    pub path: String,
+
+    #[serde(flatten)]
+    pub source: Source,
+}
+
+/// Describes the new bank in terms of the old bank.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PatchHeader {
+    pub items: Vec<PatchItem>,
+
+    /// Paths of the files in the old bank that are not in the new bank.
+    pub removed: Vec<String>,
+}
+
+impl PatchHeader {
+    /// Number of items whose contents are in the patch.
+    #[must_use]
+    pub fn changed(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|item| matches!(item.source, Source::Patch { .. }))
+            .count()
+    }
+}
+
+/// Write a patch that turns the old bank into the new bank. Returns the
+/// header of the patch. Files of unknown kinds cannot be written and are
+/// skipped.
+///
+/// # Errors
+///
+/// Will return `Err` on read or write failure.
+pub fn diff<OldType, NewType, WriterType>(
+    old: &mut BankReader<OldType>,
+    new: &mut BankReader<NewType>,
+    mut out: WriterType,
+) -> io::Result<PatchHeader>
+where
+    OldType: Read + Seek + BufRead,
+    NewType: Read + Seek + BufRead,
+    WriterType: Write,
+{
+    let mut old_digests = HashMap::new();
+    for item in old.items().iter().filter(|item| item.is_file()) {
+        old_digests.insert(item.file_name_lossy(), hash::item_digest(old, item)?);
+    }
+
+    let mut header = PatchHeader::default();
+    let mut changed = Vec::new();
+    for item in new.items() {
+        let path = item.file_name_lossy();
+        let source = if item.is_directory() {
+            Source::Directory
+        } else if ItemKind::from(Path::new(&path)).is_none() {
+            warn!("Skipping {path} because it is an unknown type of file");
+            continue;
+        } else {
+            let digest = hash::item_digest(new, &item)?;
+            if old_digests.get(&path) == Some(&digest) {
+                Source::Old { digest }
+            } else {
+                debug!("{path} has changed");
+                changed.push(item.clone());
+                Source::Patch {
+                    digest,
+                    size: item.size(),
+                }
+            }
+        };
+        header.items.push(PatchItem { path, source });
+    }
+    header.removed = old_digests
+        .into_keys()
+        .filter(|path| {
+            !header
+                .items
+                .iter()
+                .any(|item| item.path == *path && item.source != Source::Directory)
+        })
+        .collect();
+    header.removed.sort();
+
+    let json = serde_json::to_vec(&header)?;
+    out.write_all(MAGIC)?;
+    out.write_u64::<LittleEndian>(json.len() as u64)?;
+    out.write_all(&json)?;
+    for item in &changed {
+        io::copy(&mut new.contents_reader(item)?, &mut out)?;
+    }
+    out.flush()?;
+    Ok(header)
+}
+
+/// Write the new bank made by applying the patch to the old bank. The
+/// contents of every item are checked against the digests in the patch.
+///
+/// # Errors
+///
+/// Will return `Err` if the patch is malformed, if it was made from a
+/// different old bank, or on read or write failure.
+pub fn apply<OldType, PatchType, WriterType>(
+    old: &mut BankReader<OldType>,
+    mut patch: PatchType,
+    out: WriterType,
+) -> io::Result<PatchHeader>
+where
+    OldType: Read + Seek + BufRead,
+    PatchType: Read,
+    WriterType: Write,
+{
+    let mut magic = [0_u8; MAGIC.len()];
+    patch.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(Error::new(ErrorKind::InvalidData, "Not a kibank patch"));
+    }
+    let header_len = patch.read_u64::<LittleEndian>()?;
+    let mut json = Vec::new();
+    patch.by_ref().take(header_len).read_to_end(&mut json)?;
+    let header: PatchHeader = serde_json::from_slice(&json)?;
+
+    let options = BankWriterOptions {
+        preserve_order: true,
+        ..BankWriterOptions::default()
+    };
+    let mut writer = BankWriter::with_options(out, options);
+    for item in &header.items {
+        let path = OsStr::new(&item.path);
+        let (digest, contents) = match &item.source {
+            Source::Directory => {
+                writer.add_directory(path)?;
+                continue;
+            }
+            Source::Old { digest } => {
+                let old_item = old.get(&item.path).cloned().ok_or_else(|| {
+                    let msg = format!("{} is not in the old bank", item.path);
+                    Error::new(ErrorKind::InvalidData, msg)
+                })?;
+                (digest, old.read_contents(&old_item)?)
+            }
+            Source::Patch { digest, size } => {
+                let mut contents = Vec::new();
+                patch.by_ref().take(*size).read_to_end(&mut contents)?;
+                (digest, contents)
+            }
+        };
+
+        if hash::digest(contents.as_slice())? != *digest {
+            let msg = format!("Contents of {} are not the expected contents", item.path);
+            return Err(Error::new(ErrorKind::InvalidData, msg));
+        }
+        let kind = ItemKind::from(Path::new(path)).ok_or_else(|| {
+            let msg = format!("{} is an unknown type of file", item.path);
+            Error::new(ErrorKind::InvalidData, msg)
+        })?;
+        writer.add_with_path(kind, path, contents)?;
+    }
+    writer.write()?;
+    Ok(header)
+}
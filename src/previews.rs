@@ -0,0 +1,66 @@
+//! Audio previews of presets shipped inside the bank, so store pages and
+//! browsers can play a preset without loading it. Previews are MP3 or FLAC
+//! files in the `previews` directory named after the preset they belong to,
+//! such as `previews/Big Bass.mp3` for `phaseplant/Big Bass.phaseplant`.
+//! Kilohearts products ignore the directory.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::read::Item;
+use crate::ItemKind;
+
+/// The presets of a bank with their previews.
+#[derive(Clone, Debug, Default)]
+pub struct Previews<'a> {
+    /// Every preset with the preview named after it, if there is one.
+    pub presets: Vec<(Item<'a>, Option<Item<'a>>)>,
+
+    /// Previews not named after any preset.
+    pub unmatched: Vec<Item<'a>>,
+}
+
+impl<'a> Previews<'a> {
+    /// Pair each preset with the preview that has the same file name, without
+    /// the extension and ignoring case. Presets for different products with
+    /// the same name share the preview.
+    #[must_use]
+    pub fn pair(items: &[Item<'a>]) -> Previews<'a> {
+        let kind = |item: &Item| ItemKind::from(Path::new(&item.file_name_lossy()));
+        let mut previews = items
+            .iter()
+            .filter(|item| item.is_file() && kind(item) == Some(ItemKind::Preview))
+            .map(|item| (stem(item), (item, false)))
+            .collect::<HashMap<_, _>>();
+
+        let presets = items
+            .iter()
+            .filter(|item| item.is_file() && kind(item).is_some_and(|kind| kind.is_preset()))
+            .map(|preset| {
+                let preview = previews.get_mut(&stem(preset)).map(|(preview, used)| {
+                    *used = true;
+                    (*preview).clone()
+                });
+                (preset.clone(), preview)
+            })
+            .collect();
+
+        let mut unmatched = previews
+            .into_values()
+            .filter(|(_, used)| !used)
+            .map(|(preview, _)| preview.clone())
+            .collect::<Vec<_>>();
+        unmatched.sort_by(|a, b| a.path_bytes().cmp(b.path_bytes()));
+
+        Previews { presets, unmatched }
+    }
+}
+
+/// File name of the item without the directory or extension, in lowercase.
+fn stem(item: &Item) -> String {
+    Path::new(&item.file_name_lossy())
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase()
+}
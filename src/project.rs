@@ -0,0 +1,305 @@
+//! Projects that build several banks from one source tree, such as "Lite" and
+//! "Full" editions of a pack that share the same samples but have different
+//! presets. A project is typically loaded from a TOML file named
+//! [`PROJECT_FILE_NAME`] such as:
+//!
+//! ```toml
+//! author = "Your Name"
+//!
+//! [[bank]]
+//! name = "lite"
+//! output = "dist/My Pack Lite.bank"
+//! inputs = ["samples", "presets/lite"]
+//! title = "My Pack Lite"
+//!
+//! [[bank]]
+//! name = "full"
+//! output = "dist/My Pack.bank"
+//! inputs = ["samples", "presets/lite", "presets/full"]
+//! title = "My Pack"
+//! ```
+//!
+//! Paths are relative to the directory of the project file.
+//!
+//! Hooks listed in `post-build`, for the project or for one bank, run in order
+//! after each bank is built:
+//!
+//! ```toml
+//! post-build = [
+//!     { checksum = "blake3" },
+//!     { copy-to = "release" },
+//!     { run = ["signer", "--sign", "{bank}"] },
+//! ]
+//! ```
+
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::io::{Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use log::info;
+use serde::Deserialize;
+
+use crate::create::Collected;
+use crate::hash::{Algorithm, Checksums};
+use crate::read::BankReader;
+use crate::write::BankWriter;
+
+/// Name of the project file looked for in the current directory.
+pub const PROJECT_FILE_NAME: &str = "kibank.toml";
+
+/// Banks built from the same source tree.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Project {
+    /// Author of every bank that doesn't have its own.
+    pub author: Option<String>,
+
+    /// Description of every bank that doesn't have its own.
+    pub description: Option<String>,
+
+    #[serde(rename = "bank")]
+    pub banks: Vec<ProjectBank>,
+
+    /// Hooks run after each bank is built, after those of the bank.
+    pub post_build: Vec<Hook>,
+}
+
+/// A bank built by a project.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ProjectBank {
+    /// Name used to choose the bank to build, which is not shown to users.
+    pub name: String,
+
+    /// File name of the bank.
+    pub output: PathBuf,
+
+    /// Files and directories to include in the bank.
+    pub inputs: Vec<PathBuf>,
+
+    /// Name of the bank shown in Kilohearts products, replacing the name in
+    /// any metadata file in the inputs.
+    #[serde(default)]
+    pub title: Option<String>,
+
+    #[serde(default)]
+    pub author: Option<String>,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub id: Option<String>,
+
+    /// Hooks run after the bank is built.
+    #[serde(default)]
+    pub post_build: Vec<Hook>,
+}
+
+/// A step run after a bank is built.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub enum Hook {
+    /// Write the checksums of the files in the bank with the hash function
+    /// named, such as `blake3`, to a file named after the bank and the hash
+    /// function, such as `My Pack.bank.blake3`.
+    Checksum(String),
+
+    /// Copy the bank and the files written by the hooks before this one to
+    /// the directory.
+    CopyTo(PathBuf),
+
+    /// Run a program with arguments from the directory of the project. `{bank}`
+    /// in an argument is replaced with the path of the bank and `{name}` with
+    /// the name of the bank in the project.
+    Run(Vec<String>),
+}
+
+impl Project {
+    /// The bank with the name.
+    #[must_use]
+    pub fn bank(&self, name: &str) -> Option<&ProjectBank> {
+        self.banks.iter().find(|bank| bank.name == name)
+    }
+
+    /// Check that there is at least one bank, the names are unique and every
+    /// bank has inputs.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` describing the first problem found.
+    pub fn validate(&self) -> io::Result<()> {
+        if self.banks.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "The project has no banks",
+            ));
+        }
+        let mut names = HashSet::new();
+        for bank in &self.banks {
+            if !names.insert(bank.name.as_str()) {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("More than one bank is named {}", bank.name),
+                ));
+            }
+            if bank.inputs.is_empty() {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Bank {} has no inputs", bank.name),
+                ));
+            }
+        }
+        for hook in self.banks.iter().flat_map(|bank| &bank.post_build) {
+            hook.validate()?;
+        }
+        for hook in &self.post_build {
+            hook.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Run the hooks of the bank and then those of the project, stopping at
+    /// the first one that fails. The bank was written to the path and other
+    /// paths are relative to the directory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a hook fails.
+    pub fn post_build(&self, bank: &ProjectBank, bank_path: &Path, dir: &Path) -> io::Result<()> {
+        // The bank and every file written by a hook, for copying.
+        let mut outputs = vec![bank_path.to_owned()];
+        for hook in bank.post_build.iter().chain(&self.post_build) {
+            hook.run(bank, dir, &mut outputs)?;
+        }
+        Ok(())
+    }
+
+    /// Add the files and metadata of the bank to the writer without writing
+    /// the bank. Inputs are relative to the directory. Returns the number of
+    /// files added.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a file cannot be read or added.
+    pub fn add_to<W: Write>(
+        &self,
+        bank: &ProjectBank,
+        dir: &Path,
+        writer: &mut BankWriter<W>,
+    ) -> io::Result<usize> {
+        let classified = Collected::new(bank.inputs.iter().map(|input| dir.join(input))).classify();
+
+        let author = bank.author.as_ref().or(self.author.as_ref());
+        let description = bank.description.as_ref().or(self.description.as_ref());
+        let merged = if bank.title.is_some()
+            || author.is_some()
+            || description.is_some()
+            || bank.id.is_some()
+        {
+            let mut metadata = classified.metadata()?;
+            if let Some(title) = &bank.title {
+                metadata.name.clone_from(title);
+            }
+            if let Some(author) = author {
+                metadata.author.clone_from(author);
+            }
+            if let Some(description) = description {
+                metadata.description.clone_from(description);
+            }
+            if let Some(id) = &bank.id {
+                metadata.id.clone_from(id);
+            }
+            classified.with_metadata(metadata)
+        } else {
+            classified.keep_metadata()
+        };
+
+        Ok(merged.add_to(writer)?.len())
+    }
+}
+
+impl Hook {
+    /// Check the hook can run, such as the hash function being known.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the hook cannot run.
+    pub fn validate(&self) -> io::Result<()> {
+        match self {
+            Hook::Checksum(name) if Algorithm::from_name(name).is_none() => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Unknown hash function {name}, expected one of {}",
+                    Algorithm::NAMES.join(", ")
+                ),
+            )),
+            Hook::Run(command) if command.is_empty() => Err(Error::new(
+                ErrorKind::InvalidData,
+                "A run hook has no program",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Run the hook for the bank, which is the first of the outputs. Files
+    /// written by the hook are added to the outputs.
+    fn run(&self, bank: &ProjectBank, dir: &Path, outputs: &mut Vec<PathBuf>) -> io::Result<()> {
+        self.validate()?;
+        match self {
+            Hook::Checksum(name) => {
+                let bank_path = outputs[0].clone();
+                // The name was checked when the hook was validated.
+                let algorithm = Algorithm::from_name(name).unwrap_or(Algorithm::Sha1);
+                let mut reader = BankReader::open(&bank_path)?;
+                let checksums = Checksums::of_bank(&mut reader, algorithm)?;
+                let mut checksums_path = OsString::from(&bank_path);
+                checksums_path.push(".");
+                checksums_path.push(name.to_ascii_lowercase());
+                let checksums_path = PathBuf::from(checksums_path);
+                info!("Writing checksums to {}", checksums_path.display());
+                fs::write(&checksums_path, checksums.to_string())?;
+                outputs.push(checksums_path);
+            }
+            Hook::CopyTo(to_dir) => {
+                let to_dir = dir.join(to_dir);
+                fs::create_dir_all(&to_dir)?;
+                for output in outputs.iter() {
+                    if let Some(file_name) = output.file_name() {
+                        info!("Copying {} to {}", output.display(), to_dir.display());
+                        fs::copy(output, to_dir.join(file_name))?;
+                    }
+                }
+            }
+            Hook::Run(command) => {
+                // The program runs in the directory of the project, where the
+                // output path of the bank is relative to.
+                let bank_arg = bank.output.to_string_lossy();
+                let args = command
+                    .iter()
+                    .map(|arg| {
+                        arg.replace("{bank}", &bank_arg)
+                            .replace("{name}", &bank.name)
+                    })
+                    .collect::<Vec<_>>();
+                info!("Running {}", args.join(" "));
+                let status = Command::new(&args[0])
+                    .args(&args[1..])
+                    .current_dir(if dir.as_os_str().is_empty() {
+                        Path::new(".")
+                    } else {
+                        dir
+                    })
+                    .status()?;
+                if !status.success() {
+                    return Err(Error::other(format!("{} failed with {status}", args[0])));
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,29 +1,74 @@
-use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::fmt::Debug;
 use std::fs::File;
 use std::io;
-use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Seek, SeekFrom};
-use std::path::Path;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Seek, SeekFrom, Take, Write};
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use byteorder::{LittleEndian, ReadBytesExt};
 use log::{debug, trace};
 
+use crate::parse::{self, ParseError};
+use crate::size::SizeBreakdown;
+use crate::write::ContentSource;
 use crate::{
-    Location, Metadata, BACKGROUND_FILE_STEM, CORRUPTION_CHECK_BYTES, FILE_ID, FORMAT_VERSION,
+    ItemKind, Location, Metadata, BACKGROUND_FILE_STEM, CORRUPTION_CHECK_BYTES, FILE_ID,
+    FORMAT_VERSION, PATH_SEPARATOR,
 };
 
+/// Number of bytes copied at a time by [`BankReader::copy_to`], which is all
+/// the memory needed no matter how large the contents are.
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+impl std::error::Error for ParseError {}
+
+impl From<ParseError> for Error {
+    fn from(error: ParseError) -> Self {
+        Error::new(ErrorKind::InvalidData, error)
+    }
+}
+
+/// An item that can be kept after the reader is gone and sent between threads.
+pub type OwnedItem = Item<'static>;
+
 #[derive(Clone, Debug)]
 pub struct Item<'a> {
-    /// The name of the file as it appears in the bank. The name is not a string because paths
-    /// are not guaranteed to be valid UTF-8 on all platforms. Names are case-insensitive.
-    /// Directories are separated by `BANK_PATH_SEPARATOR` regardless of platform.
-    pub path_bytes: Cow<'a, [u8]>,
+    /// The file name block of the bank, shared by every item to avoid an
+    /// allocation for each name.
+    names: Arc<[u8]>,
+
+    /// Location of the name of this item in the file name block.
+    path_range: Range<usize>,
 
     location: Location,
+
+    /// The path was stored with `\` separating directories.
+    windows_separators: bool,
+
+    marker: PhantomData<&'a [u8]>,
 }
 
 /// Read a Kilohearts bank file.
 impl Item<'_> {
+    /// The name of the file as it appears in the bank. The name is not a string because paths
+    /// are not guaranteed to be valid UTF-8 on all platforms. Names are case-insensitive.
+    /// Directories are separated by `PATH_SEPARATOR` regardless of platform.
+    #[must_use]
+    pub fn path_bytes(&self) -> &[u8] {
+        &self.names[self.path_range.clone()]
+    }
+
+    /// Returns `true` if the path was stored with `\` separating directories,
+    /// as some third-party tools do, whether or not it was normalized.
+    #[must_use]
+    pub fn has_windows_separators(&self) -> bool {
+        self.windows_separators
+    }
+
     #[must_use]
     pub fn is_directory(&self) -> bool {
         self.location.data_size == 0
@@ -34,6 +79,27 @@ impl Item<'_> {
         self.location.data_size != 0
     }
 
+    /// Returns `true` for directories that don't belong to any kind of item,
+    /// such as custom directories created by other tools.
+    #[must_use]
+    pub fn is_unknown_directory(&self) -> bool {
+        self.is_directory() && self.directory_kind().is_none()
+    }
+
+    /// The kind of the items kept in the directory, such as
+    /// [`ItemKind::Sample`] for `samples`, ignoring ASCII case. `None` for
+    /// files and unknown directories.
+    #[must_use]
+    pub fn directory_kind(&self) -> Option<ItemKind> {
+        if !self.is_directory() {
+            return None;
+        }
+        ItemKind::all().into_iter().find(|kind| {
+            kind.directory()
+                .is_some_and(|dir| self.path_bytes().eq_ignore_ascii_case(dir.as_bytes()))
+        })
+    }
+
     #[must_use]
     pub fn is_background_file(&self) -> bool {
         self.is_file()
@@ -43,111 +109,259 @@ impl Item<'_> {
                 .eq_ignore_ascii_case(BACKGROUND_FILE_STEM)
     }
 
+    /// Returns `true` for the changelog at the top of the bank, such as
+    /// `changelog.md`.
+    #[must_use]
+    pub fn is_changelog_file(&self) -> bool {
+        self.is_file()
+            && !self.path_bytes().contains(&(PATH_SEPARATOR as u8))
+            && ItemKind::from(Path::new(&self.file_name_lossy())) == Some(ItemKind::Changelog)
+    }
+
     #[must_use]
     pub fn is_metadata_file(&self) -> bool {
         self.is_file()
             && self
-                .path_bytes
+                .path_bytes()
                 .eq_ignore_ascii_case(Metadata::FILE_NAME.as_ref())
     }
 
+    /// The same item without the borrow of the reader. Items don't copy their
+    /// name, so this doesn't allocate.
+    #[must_use]
+    pub fn into_owned(self) -> OwnedItem {
+        Item {
+            names: self.names,
+            path_range: self.path_range,
+            location: self.location,
+            windows_separators: self.windows_separators,
+            marker: PhantomData,
+        }
+    }
+
+    /// Position of the contents from the start of the bank.
+    #[must_use]
+    pub fn offset(&self) -> u64 {
+        self.location.data_offset
+    }
+
+    /// Number of bytes of contents, which is zero for directories.
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        self.location.data_size
+    }
+
     /// The file name converted to text. File names are not guaranteed to be valid UTF-8.
     #[must_use]
     pub fn file_name_lossy(&self) -> String {
-        String::from_utf8_lossy(&self.path_bytes).to_string()
+        String::from_utf8_lossy(self.path_bytes()).to_string()
     }
 }
 
+/// Items of a bank arranged for finding them quickly.
+#[derive(Debug)]
+struct Index<'a> {
+    items: Vec<Item<'a>>,
+
+    /// Position in `items` of each lowercase path. The first of any items
+    /// with the same path is used.
+    by_path: HashMap<Vec<u8>, usize>,
+
+    by_kind: BTreeMap<ItemKind, Vec<Item<'a>>>,
+}
+
+impl<'a> Index<'a> {
+    fn new(items: Vec<Item<'a>>) -> Self {
+        let mut by_path = HashMap::with_capacity(items.len());
+        let mut by_kind = BTreeMap::<ItemKind, Vec<Item<'a>>>::new();
+        for (position, item) in items.iter().enumerate() {
+            by_path
+                .entry(item.path_bytes().to_ascii_lowercase())
+                .or_insert(position);
+            if item.is_file() {
+                if let Some(kind) = ItemKind::from(Path::new(&item.file_name_lossy())) {
+                    by_kind.entry(kind).or_default().push(item.clone());
+                }
+            }
+        }
+        Index {
+            items,
+            by_path,
+            by_kind,
+        }
+    }
+}
+
+/// Settings that change how a bank is read.
+#[derive(Clone, Debug)]
+pub struct BankReaderOptions {
+    /// When reading the contents of an item also read at least this many
+    /// bytes of the items that follow it. Reading the items in data order then
+    /// needs fewer, larger reads, which is much faster on network storage.
+    /// Zero disables reading ahead.
+    pub readahead_bytes: usize,
+
+    /// Replace any `\` in the paths of items with `PATH_SEPARATOR` so banks
+    /// written by tools using Windows separators have the intended
+    /// directories. On by default.
+    pub normalize_separators: bool,
+
+    /// Refuse to open banks with more items than this, including directories.
+    pub max_items: Option<u64>,
+
+    /// Refuse to open banks with an item larger than this many bytes, to
+    /// limit how much memory reading the contents of an item can use.
+    pub max_item_size: Option<u64>,
+
+    /// Refuse to open banks larger than this many bytes. Also limits how much
+    /// a compressed bank is decompressed, see
+    /// [`crate::compress::decompress_to_temp`].
+    pub max_bank_size: Option<u64>,
+}
+
+impl Default for BankReaderOptions {
+    fn default() -> Self {
+        BankReaderOptions {
+            readahead_bytes: 0,
+            normalize_separators: true,
+            max_items: None,
+            max_item_size: None,
+            max_bank_size: None,
+        }
+    }
+}
+
+/// A problem with the header of a bank found by
+/// [`BankReader::new_collecting`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReadIssue {
+    /// Where the problem is from the start of the bank.
+    pub offset: u64,
+
+    pub message: String,
+}
+
+impl fmt::Display for ReadIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+/// Reads the items of a bank. A bank without any items, not even the
+/// metadata, is valid but empty, see [`BankReader::is_empty`].
 pub struct BankReader<'a, ReaderType: Read + Seek + BufRead> {
     inner: ReaderType,
-    items: Vec<Item<'a>>,
+
+    /// Shared with clones of the reader so the header is only parsed once.
+    index: Arc<Index<'a>>,
+    options: BankReaderOptions,
+
+    /// Location of the bank when it was opened from a file, for reopening it.
+    path: Option<PathBuf>,
+
+    /// Length of the underlying stream.
+    stream_len: u64,
+
+    /// Length of everything before the contents of the items.
+    header_len: u64,
+
+    /// Contents read ahead of when they were needed, starting at `readahead_offset`.
+    readahead: Vec<u8>,
+    readahead_offset: u64,
 }
 
 impl<'a, ReaderType: Read + Seek + BufRead> BankReader<'a, ReaderType> {
     /// # Errors
     ///
     /// Will return `Err` if the file is not a Kilohearts bank or if it is malformed.
-    pub fn new(mut inner: ReaderType) -> io::Result<Self> {
-        let mut file_id = [0_u8; FILE_ID.len()];
-        inner.read_exact(&mut file_id)?;
-        if file_id != FILE_ID {
-            return Err(Error::new(ErrorKind::InvalidData, "Not a Kilohearts bank"));
-        }
+    pub fn new(inner: ReaderType) -> io::Result<Self> {
+        Self::with_options(inner, BankReaderOptions::default())
+    }
 
-        let mut check_bytes = [0_u8; CORRUPTION_CHECK_BYTES.len()];
-        inner.read_exact(&mut check_bytes)?;
-        if check_bytes != CORRUPTION_CHECK_BYTES {
-            let msg = format!("Unexpected check bytes {}", check_bytes.escape_ascii());
+    /// # Errors
+    ///
+    /// Will return `Err` if the file is not a Kilohearts bank or if it is malformed.
+    pub fn with_options(mut inner: ReaderType, options: BankReaderOptions) -> io::Result<Self> {
+        let mut prefix = [0_u8; parse::PREFIX_LEN];
+        inner.read_exact(&mut prefix)?;
+        let location_count = parse::parse_header(&prefix)?;
+        if let Some(max_items) = options.max_items.filter(|max| location_count > *max) {
+            let msg =
+                format!("Bank has {location_count} items, more than the limit of {max_items}");
             return Err(Error::new(ErrorKind::InvalidData, msg));
         }
 
-        let mut format_version = [0_u8; FORMAT_VERSION.len()];
-        inner.read_exact(&mut format_version)?;
-        if format_version != FORMAT_VERSION {
-            let msg = format!(
-                "Unexpected format version {}",
-                format_version.escape_ascii()
-            );
+        let location_block_start = inner.stream_position()?;
+        let stream_len = inner.seek(SeekFrom::End(0))?;
+        if let Some(max_bank_size) = options.max_bank_size.filter(|max| stream_len > *max) {
+            let msg = format!("Bank is {stream_len} bytes, more than the limit of {max_bank_size}");
             return Err(Error::new(ErrorKind::InvalidData, msg));
         }
+        inner.seek(SeekFrom::Start(location_block_start))?;
+        let location_block_len = parse::location_block_len(
+            location_count,
+            stream_len.saturating_sub(location_block_start),
+        )?;
+        trace!("Location block start is {location_block_start}");
 
-        let location_count = inner.read_u64::<LittleEndian>()?;
-        trace!("Number of locations is {location_count}");
-
-        let mut locations = Vec::with_capacity(location_count.min(1000) as u32 as usize);
-        trace!("Location block start is {}", inner.stream_position()?);
-        for _ in 0..location_count {
-            let file_name_offset = inner.read_u64::<LittleEndian>()?;
-            let data_offset = inner.read_u64::<LittleEndian>()?;
-            let data_size = inner.read_u64::<LittleEndian>()?;
-            trace!("File name offset is {file_name_offset}, data offset is {data_offset}, daa size is {data_size}");
-            locations.push(Location {
-                file_name_offset,
-                data_offset,
-                data_size,
-            });
-        }
+        // Read all locations at once rather than a field at a time.
+        let mut location_block = vec![0_u8; location_block_len];
+        inner.read_exact(&mut location_block)?;
+        let locations = parse::parse_locations(&location_block);
 
         // File names
         let file_name_block_length = inner.read_u64::<LittleEndian>()?;
         let file_name_block_start = inner.stream_position()?;
         debug!("File name block length is {file_name_block_length} starting at {file_name_block_start}");
+        if file_name_block_length > stream_len.saturating_sub(file_name_block_start) {
+            let msg =
+                format!("File name block length {file_name_block_length} is larger than the file");
+            return Err(Error::new(ErrorKind::InvalidData, msg));
+        }
 
-        let mut items = Vec::with_capacity(locations.len());
-        for location in locations {
-            let mut file_name_bytes = Vec::with_capacity(32);
-
-            let file_name_pos = location.file_name_offset + file_name_block_start;
-            inner.seek(SeekFrom::Start(file_name_pos))?;
-
-            // This guarantees the file name will never contain a null.
-            let read_count = inner.read_until(0_u8, &mut file_name_bytes)?;
-            if read_count == 0 {
-                return Err(Error::new(
-                    ErrorKind::Other,
-                    "Zero length read of file name at position {file_name_pos}",
-                ));
-            }
+        // Accept the 32-bit limit on the size of the tables.
+        #[allow(clippy::cast_possible_truncation)]
+        let mut file_name_block = vec![0_u8; file_name_block_length as usize];
+        inner.read_exact(&mut file_name_block)?;
 
-            // Ensure the file name is within bounds.
-            if file_name_pos + read_count as u64 > file_name_block_start + file_name_block_length {
-                return Err(Error::new(
-                    ErrorKind::Other,
-                    "Read past the end of the file name block",
-                ));
+        let header_len = file_name_block_start + file_name_block_length;
+        let names = parse::parse_name_block(&file_name_block, &locations)?;
+        let mut ranges = Vec::with_capacity(locations.len());
+        for (name, location) in names.into_iter().zip(locations) {
+            debug!("File name {}", file_name_block[name.clone()].escape_ascii());
+            if let Some(max_size) = options
+                .max_item_size
+                .filter(|max| location.data_size > *max)
+            {
+                let msg = format!(
+                    "Item {} is {} bytes, more than the limit of {max_size} bytes",
+                    file_name_block[name].escape_ascii(),
+                    location.data_size
+                );
+                return Err(Error::new(ErrorKind::InvalidData, msg));
             }
+            let windows_separators = file_name_block[name.clone()].contains(&b'\\');
+            ranges.push((name, location, windows_separators));
+        }
 
-            // Remove the trailing null, which won't be there if we hit the end fo the file.
-            if let Some(0_u8) = file_name_bytes.last() {
-                file_name_bytes.pop();
+        if options.normalize_separators {
+            for byte in &mut file_name_block {
+                if *byte == b'\\' {
+                    *byte = PATH_SEPARATOR as u8;
+                }
             }
-
-            debug!("File name {}", file_name_bytes.escape_ascii());
-            items.push(Item {
-                location,
-                path_bytes: Cow::from(file_name_bytes),
-            });
         }
+        let file_name_block = Arc::<[u8]>::from(file_name_block);
+        let items = ranges
+            .into_iter()
+            .map(|(path_range, location, windows_separators)| Item {
+                names: Arc::clone(&file_name_block),
+                path_range,
+                location,
+                windows_separators,
+                marker: PhantomData,
+            })
+            .collect::<Vec<_>>();
 
         // Verify no ranges overlap. Besides being an indicator of a corrupt file, overlapping
         // data ranges can also be an amplification attack where many files can use the same
@@ -161,38 +375,247 @@ impl<'a, ReaderType: Read + Seek + BufRead> BankReader<'a, ReaderType> {
             if window[0].location.data_end() > window[1].location.data_offset {
                 let msg = format!(
                     "Bank item {} overlaps item {}",
-                    String::from_utf8_lossy(&window[0].path_bytes),
-                    String::from_utf8_lossy(&window[1].path_bytes)
+                    window[0].file_name_lossy(),
+                    window[1].file_name_lossy()
                 );
-                return Err(Error::new(ErrorKind::Other, msg));
+                return Err(Error::other(msg));
             }
         }
 
-        Ok(BankReader { inner, items })
+        Ok(BankReader {
+            inner,
+            index: Arc::new(Index::new(items)),
+            options,
+            path: None,
+            stream_len,
+            header_len,
+            readahead: Vec::new(),
+            readahead_offset: 0,
+        })
+    }
+
+    /// Like [`BankReader::new`] but instead of stopping at the first problem,
+    /// every problem with the header that can be found is returned, such as
+    /// for showing everything wrong with a damaged bank at once. The reader is
+    /// only returned when the bank can be opened, though there may still be
+    /// problems, such as contents past the end of a truncated bank.
+    pub fn new_collecting(inner: ReaderType) -> (Option<Self>, Vec<ReadIssue>) {
+        Self::with_options_collecting(inner, BankReaderOptions::default())
+    }
+
+    /// Like [`BankReader::new_collecting`] with the options.
+    pub fn with_options_collecting(
+        mut inner: ReaderType,
+        options: BankReaderOptions,
+    ) -> (Option<Self>, Vec<ReadIssue>) {
+        let mut issues = match collect_issues(&mut inner, &options) {
+            Ok(issues) => issues,
+            Err(error) => vec![ReadIssue {
+                offset: 0,
+                message: error.to_string(),
+            }],
+        };
+        let reader = inner
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| Self::with_options(inner, options));
+        match reader {
+            Ok(reader) => (Some(reader), issues),
+            Err(error) => {
+                // Such as a failure to read, which is not a problem with the
+                // header itself.
+                if issues.is_empty() {
+                    issues.push(ReadIssue {
+                        offset: 0,
+                        message: error.to_string(),
+                    });
+                }
+                (None, issues)
+            }
+        }
+    }
+
+    /// The file the bank was opened from with [`BankReader::open`], or given
+    /// with [`BankReader::with_path`].
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Length in bytes of the stream the bank is read from, which the contents
+    /// of every item should fit in.
+    #[must_use]
+    pub fn stream_len(&self) -> u64 {
+        self.stream_len
+    }
+
+    /// Size of the bank in bytes, from the start of the header to the end of
+    /// the contents of the last item. Less than the length of the stream when
+    /// there is something after the bank, such as another bank.
+    pub fn size(&self) -> u64 {
+        self.index
+            .items
+            .iter()
+            .filter(|item| item.is_file())
+            .map(|item| item.location.data_end())
+            .fold(self.header_len, u64::max)
+    }
+
+    /// Where the bytes of the bank go, adding up to [`BankReader::size`].
+    #[must_use]
+    pub fn size_breakdown(&self) -> SizeBreakdown {
+        let header = parse::PREFIX_LEN as u64;
+        let locations = (self.index.items.len() * Location::BLOCK_SIZE) as u64;
+        let mut breakdown = SizeBreakdown {
+            header,
+            locations,
+            names: self.header_len.saturating_sub(header + locations),
+            ..SizeBreakdown::default()
+        };
+        for item in self.index.items.iter().filter(|item| item.is_file()) {
+            breakdown.add_data(item.path_bytes(), item.size());
+        }
+        breakdown.padding = self.size().saturating_sub(breakdown.total());
+        breakdown
     }
 
     /// All of the items in the bank.
     pub fn items(&self) -> Vec<Item<'a>> {
-        self.items.clone()
+        self.index.items.clone()
+    }
+
+    /// Returns `true` if the bank has no items, not even the metadata.
+    /// Kilohearts products always write the metadata so an empty bank was
+    /// most likely written by another tool.
+    pub fn is_empty(&self) -> bool {
+        self.index.items.is_empty()
+    }
+
+    /// The item with the path, ignoring ASCII case. Directories are separated
+    /// by `PATH_SEPARATOR`.
+    pub fn get<P: AsRef<[u8]>>(&self, path: P) -> Option<&Item<'a>> {
+        self.index
+            .by_path
+            .get(&path.as_ref().to_ascii_lowercase())
+            .map(|position| &self.index.items[*position])
+    }
+
+    /// The files in the bank grouped by their kind. Directories and files of
+    /// unknown kinds are not included.
+    pub fn items_by_kind(&self) -> &BTreeMap<ItemKind, Vec<Item<'a>>> {
+        &self.index.by_kind
+    }
+
+    /// The directories in the bank with the kind of the items kept in each,
+    /// or `None` for unknown directories, in the order they are in the bank.
+    pub fn directories(&self) -> Vec<(Item<'a>, Option<ItemKind>)> {
+        self.index
+            .items
+            .iter()
+            .filter(|item| item.is_directory())
+            .map(|item| (item.clone(), item.directory_kind()))
+            .collect()
+    }
+
+    /// All of the items in the order their contents appear in the bank, so
+    /// reading each item in turn moves strictly forward through the underlying
+    /// stream. Directories, which have no contents, are first.
+    pub fn items_in_data_order(&self) -> Vec<Item<'a>> {
+        let mut items = self.index.items.clone();
+        items.sort_by_key(|item| (item.is_file(), item.location.data_offset));
+        items
     }
 
     /// # Errors
     ///
     /// Will return `Err` on read or seek failure.
     pub fn read_contents(&mut self, item: &Item) -> io::Result<Vec<u8>> {
+        let mut result = Vec::new();
+        self.read_contents_into(item, &mut result)?;
+        Ok(result)
+    }
+
+    /// Read the contents of the item into a buffer, replacing what was in the
+    /// buffer. Reusing the same buffer for many items avoids an allocation
+    /// for each item.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` on read or seek failure.
+    pub fn read_contents_into(&mut self, item: &Item, buffer: &mut Vec<u8>) -> io::Result<()> {
         // Accept the 32-bit limit on item contents for files on platforms with 32-bit pointers.
         #![allow(clippy::cast_possible_truncation)]
-        let mut result = vec![0_u8; item.location.data_size as usize];
+        let start = item.location.data_offset;
+        let size = item.location.data_size as usize;
+        buffer.clear();
+
+        // Banks that are truncated or claim impossible sizes are noticed
+        // before allocating room for the contents.
+        if item.location.data_end() > self.stream_len {
+            let msg = format!(
+                "Item {} extends past the end of the bank",
+                item.file_name_lossy()
+            );
+            return Err(Error::new(ErrorKind::UnexpectedEof, msg));
+        }
+
+        if self.options.readahead_bytes > 0 {
+            // Refill the read ahead buffer when it doesn't have the contents.
+            let buffered = start >= self.readahead_offset
+                && item.location.data_end() <= self.readahead_offset + self.readahead.len() as u64;
+            if !buffered {
+                let len = size
+                    .max(self.options.readahead_bytes)
+                    .min(self.stream_len.saturating_sub(start) as usize)
+                    .max(size);
+                trace!("Reading ahead {len} bytes at {start}");
+                self.readahead.resize(len, 0_u8);
+                self.inner.seek(SeekFrom::Start(start))?;
+                if let Err(error) = self.inner.read_exact(&mut self.readahead) {
+                    self.readahead.clear();
+                    return Err(error);
+                }
+                self.readahead_offset = start;
+            }
+
+            let buffer_start = (start - self.readahead_offset) as usize;
+            buffer.extend_from_slice(&self.readahead[buffer_start..buffer_start + size]);
+            return Ok(());
+        }
+
+        buffer.resize(size, 0_u8);
+        self.inner.seek(SeekFrom::Start(start))?;
+        self.inner.read_exact(buffer)
+    }
+
+    /// A reader of the contents of the item, for when the contents are too
+    /// large to read into memory all at once. Fewer bytes than the size of the
+    /// item are available if the bank is truncated.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` on seek failure.
+    pub fn contents_reader(&mut self, item: &Item) -> io::Result<Take<&mut ReaderType>> {
         self.inner
             .seek(SeekFrom::Start(item.location.data_offset))?;
-        self.inner.read_exact(&mut result)?;
-        Ok(result)
+        Ok(self.inner.by_ref().take(item.location.data_size))
     }
 
     /// # Errors
     ///
     /// Will return `Err` if the item does not refer to metadata and on read or seek failure.
     pub fn read_metadata(&mut self, item: &Item) -> io::Result<Metadata> {
+        let data = self.read_metadata_raw(item)?;
+        let metadata: Metadata = BankReader::parse_metadata(&data)?;
+        Ok(metadata)
+    }
+
+    /// The metadata exactly as it is stored, without parsing it, so fields
+    /// that [`Metadata`] doesn't know and the formatting are kept.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the item does not refer to metadata and on read or seek failure.
+    pub fn read_metadata_raw(&mut self, item: &Item) -> io::Result<Vec<u8>> {
         if !item.is_metadata_file() {
             return Err(Error::new(
                 ErrorKind::InvalidData,
@@ -200,22 +623,109 @@ impl<'a, ReaderType: Read + Seek + BufRead> BankReader<'a, ReaderType> {
             ));
         }
 
-        let data = self.read_contents(item)?;
-        let metadata: Metadata = BankReader::parse_metadata(&data)?;
-        Ok(metadata)
+        self.read_contents(item)
     }
 
-    /// Write the contents of the item to a new file.
+    /// Write the contents of the item to a new file. The contents are
+    /// streamed with [`BankReader::copy_to`].
     ///
     /// # Errors
     ///
-    /// Will return `Err` if the contents of the item cannot be read from the underlying stream.
+    /// Will return `Err` if the contents of the item cannot be read from the
+    /// underlying stream or the file cannot be written.
     pub fn copy<P: AsRef<Path>>(&mut self, item: &Item, path: P) -> io::Result<()> {
-        // Accept the 32-bit limit on item contents for files on platforms with 32-bit pointers.
-        #![allow(clippy::cast_possible_truncation)]
-        let mut result = vec![0_u8; item.location.data_size as usize];
-        self.inner.read_exact(&mut result)?;
-        std::fs::write(path, result)
+        let mut file = File::create(path)?;
+        self.copy_to(item, &mut file)?;
+        file.flush()
+    }
+
+    /// Write the contents of the item to the writer a piece at a time, so
+    /// large samples don't have to fit in memory. Contents that fit in the
+    /// read ahead buffer are read with it. Returns the number of bytes
+    /// written.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` on read, seek or write failure, or if the bank is
+    /// truncated.
+    pub fn copy_to<W: Write + ?Sized>(&mut self, item: &Item, writer: &mut W) -> io::Result<u64> {
+        let size = item.location.data_size;
+        if item.location.data_end() > self.stream_len {
+            let msg = format!(
+                "Item {} extends past the end of the bank",
+                item.file_name_lossy()
+            );
+            return Err(Error::new(ErrorKind::UnexpectedEof, msg));
+        }
+        if size <= self.options.readahead_bytes as u64 {
+            writer.write_all(&self.read_contents(item)?)?;
+            return Ok(size);
+        }
+
+        self.inner
+            .seek(SeekFrom::Start(item.location.data_offset))?;
+        let mut chunk = vec![0_u8; COPY_CHUNK_SIZE];
+        let mut remaining = size;
+        while remaining > 0 {
+            // The chunk is never larger than a usize.
+            #[allow(clippy::cast_possible_truncation)]
+            let len = remaining.min(COPY_CHUNK_SIZE as u64) as usize;
+            self.inner.read_exact(&mut chunk[..len])?;
+            writer.write_all(&chunk[..len])?;
+            remaining -= len as u64;
+        }
+        Ok(size)
+    }
+
+    /// The contents of the item as a source for a
+    /// [`BankWriter`](crate::write::BankWriter). When the bank was opened from
+    /// a file the contents are only read from it as the new bank is written,
+    /// so a bank can be copied without holding its items in memory. Otherwise
+    /// the contents are read now.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the bank is truncated, or on read or seek failure
+    /// when the contents are read now.
+    pub fn content_source(&mut self, item: &Item) -> io::Result<ContentSource> {
+        if item.location.data_end() > self.stream_len {
+            let msg = format!(
+                "Item {} extends past the end of the bank",
+                item.file_name_lossy()
+            );
+            return Err(Error::new(ErrorKind::UnexpectedEof, msg));
+        }
+        let Some(path) = self.path.clone() else {
+            return self.read_contents(item).map(ContentSource::Bytes);
+        };
+
+        let offset = item.location.data_offset;
+        let size = item.location.data_size;
+        let name = item.file_name_lossy();
+        let generate = move |writer: &mut dyn Write| {
+            let mut file = File::open(&path)?;
+            file.seek(SeekFrom::Start(offset))?;
+            let copied = io::copy(&mut BufReader::new(file).take(size), writer)?;
+            if copied < size {
+                let msg = format!("Item {name} extends past the end of the bank");
+                return Err(Error::new(ErrorKind::UnexpectedEof, msg));
+            }
+            Ok(())
+        };
+        Ok(ContentSource::Generated {
+            size,
+            generate: Box::new(generate),
+        })
+    }
+
+    /// Record the file the bank is read from, for a reader that wasn't
+    /// created with [`BankReader::open`], so [`BankReader::content_source`]
+    /// can read contents from it later. The bank must start at the beginning
+    /// of the file.
+    #[must_use]
+    pub fn with_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.path = Some(path.as_ref().to_owned());
+        self
     }
 }
 
@@ -224,11 +734,52 @@ impl BankReader<'_, BufReader<File>> {
     ///
     /// Will return `Err` if the path cannot be opened as a file.
     pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::open_with_options(path, BankReaderOptions::default())
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the path cannot be opened as a file.
+    pub fn open_with_options<P: AsRef<Path>>(
+        path: P,
+        options: BankReaderOptions,
+    ) -> io::Result<Self> {
         let path_ref = path.as_ref();
         let file = File::open(path_ref)?;
         debug!("File {} opened", path_ref.display());
         let reader: BufReader<File> = BufReader::new(file);
-        Self::new(reader)
+        let mut bank_reader = Self::with_options(reader, options)?;
+        bank_reader.path = Some(path_ref.to_owned());
+        Ok(bank_reader)
+    }
+
+    /// Another reader of the same bank, independent of this one so each can
+    /// be used by a different thread. The file is opened again but the items
+    /// are shared rather than read again.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be opened again, or if the reader
+    /// was not created with [`BankReader::open`].
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let path = self.path.as_ref().ok_or_else(|| {
+            Error::new(
+                ErrorKind::Unsupported,
+                "Only readers of opened files can be cloned",
+            )
+        })?;
+        let file = File::open(path)?;
+        debug!("File {} opened again", path.display());
+        Ok(BankReader {
+            inner: BufReader::new(file),
+            index: Arc::clone(&self.index),
+            options: self.options.clone(),
+            path: Some(path.clone()),
+            stream_len: self.stream_len,
+            header_len: self.header_len,
+            readahead: Vec::new(),
+            readahead_offset: 0,
+        })
     }
 
     /// # Errors
@@ -238,3 +789,121 @@ impl BankReader<'_, BufReader<File>> {
         serde_json::from_slice(json).map_err(Into::into)
     }
 }
+
+/// Every problem with the header of the bank, continuing past each one with
+/// whatever of the header can still be read.
+fn collect_issues<ReaderType: Read + Seek>(
+    inner: &mut ReaderType,
+    options: &BankReaderOptions,
+) -> io::Result<Vec<ReadIssue>> {
+    let mut issues = Vec::new();
+    let mut issue = |offset: u64, message: String| issues.push(ReadIssue { offset, message });
+
+    let stream_len = inner.seek(SeekFrom::End(0))?;
+    inner.seek(SeekFrom::Start(0))?;
+    let mut prefix = Vec::with_capacity(parse::PREFIX_LEN);
+    inner
+        .take(parse::PREFIX_LEN as u64)
+        .read_to_end(&mut prefix)?;
+    if prefix.len() < parse::PREFIX_LEN || !prefix.starts_with(FILE_ID) {
+        issue(0, "Not a Kilohearts bank".to_string());
+        return Ok(issues);
+    }
+
+    let mut offset = FILE_ID.len();
+    let check_bytes = &prefix[offset..offset + CORRUPTION_CHECK_BYTES.len()];
+    if check_bytes != CORRUPTION_CHECK_BYTES {
+        let msg = format!("Unexpected check bytes {}", check_bytes.escape_ascii());
+        issue(offset as u64, msg);
+    }
+    offset += CORRUPTION_CHECK_BYTES.len();
+    let format_version = &prefix[offset..offset + FORMAT_VERSION.len()];
+    if format_version != FORMAT_VERSION {
+        let msg = format!(
+            "Unexpected format version {}",
+            format_version.escape_ascii()
+        );
+        issue(offset as u64, msg);
+    }
+    offset += FORMAT_VERSION.len();
+
+    let mut location_count = (&prefix[offset..]).read_u64::<LittleEndian>()?;
+    if let Some(max_items) = options.max_items.filter(|max| location_count > *max) {
+        let msg = format!("Bank has {location_count} items, more than the limit of {max_items}");
+        issue(offset as u64, msg);
+    }
+    let location_block_start = parse::PREFIX_LEN as u64;
+    let fitting = (stream_len - location_block_start) / Location::BLOCK_SIZE as u64;
+    if location_count > fitting {
+        let msg = format!("Location count {location_count} is larger than the file");
+        issue(offset as u64, msg);
+        location_count = fitting;
+    }
+
+    // The count fits in the file so it is safe to allocate.
+    #[allow(clippy::cast_possible_truncation)]
+    let mut location_block = vec![0_u8; location_count as usize * Location::BLOCK_SIZE];
+    inner.read_exact(&mut location_block)?;
+    let locations = parse::parse_locations(&location_block);
+
+    let name_block_len_offset = inner.stream_position()?;
+    let Ok(mut file_name_block_length) = inner.read_u64::<LittleEndian>() else {
+        issue(
+            name_block_len_offset,
+            "File name block length is missing".to_string(),
+        );
+        return Ok(issues);
+    };
+    let file_name_block_start = name_block_len_offset + std::mem::size_of::<u64>() as u64;
+    let remaining = stream_len - file_name_block_start;
+    if file_name_block_length > remaining {
+        let msg =
+            format!("File name block length {file_name_block_length} is larger than the file");
+        issue(name_block_len_offset, msg);
+        file_name_block_length = remaining;
+    }
+    // The length fits in the file so it is safe to allocate.
+    #[allow(clippy::cast_possible_truncation)]
+    let mut file_name_block = vec![0_u8; file_name_block_length as usize];
+    inner.read_exact(&mut file_name_block)?;
+
+    let mut files = Vec::new();
+    for (position, location) in locations.iter().enumerate() {
+        let location_offset = location_block_start + (position * Location::BLOCK_SIZE) as u64;
+        let name = match parse::parse_name_block(&file_name_block, std::slice::from_ref(location)) {
+            Ok(names) => file_name_block[names[0].clone()].escape_ascii().to_string(),
+            Err(error) => {
+                issue(location_offset, error.to_string());
+                continue;
+            }
+        };
+        if let Some(max_size) = options
+            .max_item_size
+            .filter(|max| location.data_size > *max)
+        {
+            let msg = format!(
+                "Item {name} is {} bytes, more than the limit of {max_size} bytes",
+                location.data_size
+            );
+            issue(location_offset, msg);
+        }
+        // Directories are the items without contents.
+        if location.data_size > 0 {
+            if location.data_end() > stream_len {
+                let msg = format!("Contents of item {name} are past the end of the file");
+                issue(location.data_offset, msg);
+            }
+            files.push((name, location));
+        }
+    }
+
+    files.sort_by_key(|(_, location)| location.data_offset);
+    for window in files.windows(2) {
+        let ((name, location), (next_name, next_location)) = (&window[0], &window[1]);
+        if location.data_end() > next_location.data_offset {
+            let msg = format!("Bank item {name} overlaps item {next_name}");
+            issue(next_location.data_offset, msg);
+        }
+    }
+    Ok(issues)
+}
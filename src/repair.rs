@@ -0,0 +1,201 @@
+//! Fix banks damaged in ways seen from older export tools, which
+//! [`crate::read::BankReader`] rejects or reads incorrectly, by reading them
+//! leniently and writing a corrected bank.
+
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io;
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use log::{debug, warn};
+use memchr::memchr;
+
+use crate::write::{BankWriter, BankWriterOptions};
+use crate::{ItemKind, Location, CORRUPTION_CHECK_BYTES, FILE_ID, FORMAT_VERSION, PATH_SEPARATOR};
+
+/// A problem that was fixed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Fix {
+    /// The last file name was not followed by a null.
+    MissingFinalNull,
+
+    /// The length of the file name block was not the distance from the
+    /// start of the block to the contents of the first item.
+    FileNameBlockLength { declared: u64, actual: u64 },
+
+    /// A file was in a directory that had no entry of its own.
+    MissingDirectory { path: String },
+
+    /// A file whose contents would extend past the end of the bank was left
+    /// out, since the size of its contents cannot be trusted.
+    ItemPastEnd { path: String },
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fix::MissingFinalNull => write!(f, "Added the null after the last file name"),
+            Fix::FileNameBlockLength { declared, actual } => write!(
+                f,
+                "Corrected the length of the file names from {declared} to {actual} bytes"
+            ),
+            Fix::MissingDirectory { path } => write!(f, "Added the directory {path}"),
+            Fix::ItemPastEnd { path } => {
+                write!(f, "Left out {path}, which extends past the end of the bank")
+            }
+        }
+    }
+}
+
+/// Read the damaged bank and write a corrected bank. Returns what was fixed,
+/// which is empty when the bank did not need repairing. Files of unknown
+/// kinds cannot be written and are left out, as are files whose contents
+/// would extend past the end of the bank.
+///
+/// # Errors
+///
+/// Will return `Err` if the bank is too damaged to repair, or on read or
+/// write failure.
+pub fn repair<ReaderType, WriterType>(
+    mut inner: ReaderType,
+    out: WriterType,
+) -> io::Result<Vec<Fix>>
+where
+    ReaderType: Read + Seek,
+    WriterType: Write,
+{
+    let mut header = [0_u8; 16];
+    inner.read_exact(&mut header)?;
+    if header[..4] != *FILE_ID
+        || header[4..8] != *CORRUPTION_CHECK_BYTES
+        || header[8..] != *FORMAT_VERSION
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Not a Kilohearts bank"));
+    }
+
+    let location_count = inner.read_u64::<LittleEndian>()?;
+    let location_block_start = inner.stream_position()?;
+    let stream_len = inner.seek(SeekFrom::End(0))?;
+    inner.seek(SeekFrom::Start(location_block_start))?;
+    if location_count.saturating_mul(Location::BLOCK_SIZE as u64)
+        > stream_len.saturating_sub(location_block_start)
+    {
+        let msg = format!("Location count {location_count} is larger than the file");
+        return Err(Error::new(ErrorKind::InvalidData, msg));
+    }
+    let mut locations = Vec::new();
+    for _ in 0..location_count {
+        locations.push(Location {
+            file_name_offset: inner.read_u64::<LittleEndian>()?,
+            data_offset: inner.read_u64::<LittleEndian>()?,
+            data_size: inner.read_u64::<LittleEndian>()?,
+        });
+    }
+
+    // The contents of the items follow the file names, so the start of the
+    // first contents is the most reliable end of the file name block.
+    let mut fixes = Vec::new();
+    let declared = inner.read_u64::<LittleEndian>()?;
+    let file_name_block_start = inner.stream_position()?;
+    let actual = locations
+        .iter()
+        .filter(|location| location.data_size > 0)
+        .map(|location| location.data_offset)
+        .min()
+        .filter(|data_start| *data_start >= file_name_block_start)
+        .map_or(declared, |data_start| data_start - file_name_block_start)
+        .min(stream_len.saturating_sub(file_name_block_start));
+    if actual != declared {
+        debug!("File name block is {actual} bytes instead of {declared} bytes");
+        fixes.push(Fix::FileNameBlockLength { declared, actual });
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    let mut file_name_block = vec![0_u8; actual as usize];
+    inner.read_exact(&mut file_name_block)?;
+    if file_name_block.last().is_some_and(|last| *last != 0) {
+        fixes.push(Fix::MissingFinalNull);
+    }
+
+    let mut entries = Vec::with_capacity(locations.len());
+    for location in locations {
+        let start = usize::try_from(location.file_name_offset)
+            .ok()
+            .filter(|start| *start < file_name_block.len())
+            .ok_or_else(|| {
+                let msg = format!(
+                    "File name offset {} is past the end of the file name block",
+                    location.file_name_offset
+                );
+                Error::new(ErrorKind::InvalidData, msg)
+            })?;
+        let names = &file_name_block[start..];
+        let name = names[..memchr(0_u8, names).unwrap_or(names.len())].to_vec();
+        entries.push((name, location));
+    }
+
+    let mut directories = entries
+        .iter()
+        .filter(|(_, location)| location.data_size == 0)
+        .map(|(name, _)| name.to_ascii_lowercase())
+        .collect::<BTreeSet<_>>();
+    let options = BankWriterOptions {
+        preserve_order: true,
+        ..BankWriterOptions::default()
+    };
+    let mut writer = BankWriter::with_options(out, options);
+    for (name, location) in &entries {
+        // Names are written as UTF-8, like the writer does for every bank.
+        let path = String::from_utf8_lossy(name).into_owned();
+        if location.data_size == 0 {
+            writer.add_directory(OsStr::new(&path))?;
+            continue;
+        }
+
+        // Every directory containing the file must have an entry.
+        for (position, _) in name
+            .iter()
+            .enumerate()
+            .filter(|(_, byte)| **byte == PATH_SEPARATOR as u8)
+        {
+            let directory = &name[..position];
+            if directories.insert(directory.to_ascii_lowercase()) {
+                let path = String::from_utf8_lossy(directory).into_owned();
+                debug!("Adding missing directory {path}");
+                writer.add_directory(OsStr::new(&path))?;
+                fixes.push(Fix::MissingDirectory { path });
+            }
+        }
+
+        let Some(kind) = ItemKind::from(Path::new(&path)) else {
+            warn!(
+                "Skipping {} because it is an unknown type of file",
+                name.escape_ascii()
+            );
+            continue;
+        };
+        // Checked before allocating, since the size may be anything.
+        let in_bank = location
+            .data_offset
+            .checked_add(location.data_size)
+            .is_some_and(|data_end| data_end <= stream_len);
+        if !in_bank {
+            warn!(
+                "Skipping {} because it extends past the end of the bank",
+                name.escape_ascii()
+            );
+            fixes.push(Fix::ItemPastEnd { path });
+            continue;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let mut contents = vec![0_u8; location.data_size as usize];
+        inner.seek(SeekFrom::Start(location.data_offset))?;
+        inner.read_exact(&mut contents)?;
+        writer.add_with_path(kind, OsStr::new(&path), contents)?;
+    }
+    writer.write()?;
+    Ok(fixes)
+}
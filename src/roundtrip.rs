@@ -0,0 +1,136 @@
+//! Checks that a bank can be rebuilt by the writer without losing anything.
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io;
+use std::io::{BufRead, Cursor, Read, Seek, Write};
+use std::path::Path;
+
+use log::{debug, warn};
+
+use crate::read::{BankReader, Item};
+use crate::write::{BankWriter, BankWriterOptions};
+use crate::ItemKind;
+
+/// A way the rebuilt bank is not the same as the original.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Difference {
+    /// The item is in the original but not the rebuilt bank.
+    Missing { path: String },
+
+    /// The item is in the rebuilt bank but not the original.
+    Added { path: String },
+
+    /// The item is a file in one bank and a directory in the other.
+    Type { path: String },
+
+    /// The contents of the file are different.
+    Contents { path: String },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::Missing { path } => write!(f, "{path} is missing from the rebuilt bank"),
+            Difference::Added { path } => write!(f, "{path} was added to the rebuilt bank"),
+            Difference::Type { path } => {
+                write!(f, "{path} is a different type in the rebuilt bank")
+            }
+            Difference::Contents { path } => {
+                write!(f, "{path} has different contents in the rebuilt bank")
+            }
+        }
+    }
+}
+
+/// Add every item of the bank being read to the writer in their original
+/// order, including the metadata exactly as it was written. Files of unknown
+/// kinds cannot be written and are skipped.
+///
+/// # Errors
+///
+/// Will return `Err` on read failure or if the bank has already been written.
+pub fn rebuild<ReaderType: Read + Seek + BufRead, WriterType: Write>(
+    reader: &mut BankReader<ReaderType>,
+    writer: &mut BankWriter<WriterType>,
+) -> io::Result<()> {
+    for item in reader.items() {
+        let path = OsStr::new(&*item.file_name_lossy()).to_owned();
+        if item.is_directory() {
+            writer.add_directory(&path)?;
+        } else if let Some(kind) = ItemKind::from(Path::new(&path)) {
+            let contents = reader.read_contents(&item)?;
+            writer.add_with_path(kind, &path, contents)?;
+        } else {
+            warn!(
+                "Skipping {} because it is an unknown type of file",
+                item.file_name_lossy()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Rebuild the bank at the path in memory and compare the result with the
+/// original.
+///
+/// # Errors
+///
+/// Will return `Err` if the file is not a readable bank.
+pub fn check<P: AsRef<Path>>(path: P) -> io::Result<Vec<Difference>> {
+    let mut reader = BankReader::open(path)?;
+    check_reader(&mut reader)
+}
+
+/// Rebuild the bank being read in memory and compare the names and contents
+/// of the items with the original. The order of the items is not compared.
+///
+/// # Errors
+///
+/// Will return `Err` on read failure.
+pub fn check_reader<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+) -> io::Result<Vec<Difference>> {
+    let mut rebuilt = Vec::new();
+    let options = BankWriterOptions {
+        preserve_order: true,
+        ..BankWriterOptions::default()
+    };
+    let mut writer = BankWriter::with_options(Cursor::new(&mut rebuilt), options);
+    rebuild(reader, &mut writer)?;
+    writer.write()?;
+    debug!("Rebuilt bank is {} bytes", rebuilt.len());
+
+    let mut rebuilt_reader = BankReader::new(Cursor::new(rebuilt))?;
+    let by_path = |items: Vec<Item<'static>>| {
+        items
+            .into_iter()
+            .map(|item| (item.path_bytes().to_owned(), item))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let original_items = by_path(reader.items().into_iter().map(Item::into_owned).collect());
+    let mut rebuilt_items = by_path(rebuilt_reader.items());
+
+    let mut differences = Vec::new();
+    for (path_bytes, original) in original_items {
+        let path = original.file_name_lossy();
+        match rebuilt_items.remove(&path_bytes) {
+            None => differences.push(Difference::Missing { path }),
+            Some(rebuilt) if rebuilt.is_file() != original.is_file() => {
+                differences.push(Difference::Type { path });
+            }
+            Some(rebuilt) => {
+                if original.is_file()
+                    && reader.read_contents(&original)? != rebuilt_reader.read_contents(&rebuilt)?
+                {
+                    differences.push(Difference::Contents { path });
+                }
+            }
+        }
+    }
+    differences.extend(rebuilt_items.values().map(|item| Difference::Added {
+        path: item.file_name_lossy(),
+    }));
+    Ok(differences)
+}
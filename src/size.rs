@@ -0,0 +1,110 @@
+//! Exact accounting of the bytes in a bank, so upload tools can enforce a
+//! hosting quota on a bank that has been written, or on the items planned for
+//! one before it is built.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsStr;
+use std::path::Path;
+
+use crate::{parse, ItemKind, Location, PATH_SEPARATOR};
+
+/// Where the bytes of a bank go. The parts add up to [`SizeBreakdown::total`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SizeBreakdown {
+    /// The file identifier, the format version and the number of items.
+    pub header: u64,
+
+    /// Where the name and contents of each item are, including directories.
+    pub locations: u64,
+
+    /// The length of the names followed by the path of every item, each
+    /// ending with a null.
+    pub names: u64,
+
+    /// Contents of the files of each kind.
+    pub data: BTreeMap<ItemKind, u64>,
+
+    /// Contents of files that are not any kind of item.
+    pub other_data: u64,
+
+    /// Bytes between the contents of the items that belong to no item. Banks
+    /// written by this crate have none.
+    pub padding: u64,
+}
+
+impl SizeBreakdown {
+    /// Size of the bank in bytes.
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.header
+            + self.locations
+            + self.names
+            + self.data.values().sum::<u64>()
+            + self.other_data
+            + self.padding
+    }
+
+    /// The size of a bank with the items, given like [`BankWriter::add`] with
+    /// the kind, the file name without its directory and the size of the
+    /// contents. The directory of each kind is counted too. Unlike
+    /// [`BankWriter::write`], no metadata is added, so include the metadata
+    /// in the items for an exact size.
+    ///
+    /// [`BankWriter::add`]: crate::write::BankWriter::add
+    /// [`BankWriter::write`]: crate::write::BankWriter::write
+    #[must_use]
+    pub fn projected<'p, I>(items: I) -> SizeBreakdown
+    where
+        I: IntoIterator<Item = (ItemKind, &'p OsStr, u64)>,
+    {
+        let mut directories = BTreeSet::new();
+        let mut entries = Vec::new();
+        for (kind, file_name, size) in items {
+            let file_name = file_name.to_string_lossy();
+            let path = match kind.directory() {
+                Some(directory) => {
+                    directories.insert(directory);
+                    format!("{directory}{PATH_SEPARATOR}{file_name}")
+                }
+                None => file_name.into_owned(),
+            };
+            entries.push((path.into_bytes(), size));
+        }
+        entries.extend(
+            directories
+                .into_iter()
+                .map(|directory| (directory.as_bytes().to_owned(), 0)),
+        );
+        SizeBreakdown::of_entries(entries.iter().map(|(path, size)| (path.as_slice(), *size)))
+    }
+
+    /// The size of a bank with the items given by their path and the size of
+    /// their contents, which is zero for directories, without any padding.
+    pub(crate) fn of_entries<'e, I>(entries: I) -> SizeBreakdown
+    where
+        I: IntoIterator<Item = (&'e [u8], u64)>,
+    {
+        let mut breakdown = SizeBreakdown {
+            header: parse::PREFIX_LEN as u64,
+            names: std::mem::size_of::<u64>() as u64,
+            ..SizeBreakdown::default()
+        };
+        for (path, size) in entries {
+            breakdown.locations += Location::BLOCK_SIZE as u64;
+            breakdown.names += path.len() as u64 + 1;
+            breakdown.add_data(path, size);
+        }
+        breakdown
+    }
+
+    /// Count the contents of a file by its kind. Directories have none.
+    pub(crate) fn add_data(&mut self, path: &[u8], size: u64) {
+        if size == 0 {
+            return;
+        }
+        match ItemKind::from(Path::new(&*String::from_utf8_lossy(path))) {
+            Some(kind) => *self.data.entry(kind).or_default() += size,
+            None => self.other_data += size,
+        }
+    }
+}
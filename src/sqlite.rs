@@ -0,0 +1,120 @@
+//! Write a [`Catalog`] to a SQLite database so a large library of content
+//! can be searched with SQL. Every file in each bank is included, which would
+//! make a JSON catalog too large to work with.
+//!
+//! The schema is:
+//!
+//! ```sql
+//! CREATE TABLE banks (
+//!     id INTEGER PRIMARY KEY,
+//!     path TEXT NOT NULL,
+//!     bank_id TEXT NOT NULL,
+//!     name TEXT NOT NULL,
+//!     author TEXT NOT NULL,
+//!     description TEXT NOT NULL,
+//!     version INTEGER,
+//!     hash TEXT,
+//!     item_count INTEGER NOT NULL,
+//!     size INTEGER NOT NULL,
+//!     fingerprint TEXT
+//! );
+//! CREATE TABLE items (
+//!     bank INTEGER NOT NULL REFERENCES banks (id),
+//!     path TEXT NOT NULL,
+//!     kind TEXT,
+//!     size INTEGER NOT NULL
+//! );
+//! ```
+
+use std::io;
+use std::path::Path;
+
+use log::debug;
+use rusqlite::{params, Connection};
+
+use crate::catalog::Catalog;
+use crate::read::BankReader;
+use crate::ItemKind;
+
+const SCHEMA: &str = "
+    DROP TABLE IF EXISTS items;
+    DROP TABLE IF EXISTS banks;
+    CREATE TABLE banks (
+        id INTEGER PRIMARY KEY,
+        path TEXT NOT NULL,
+        bank_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        author TEXT NOT NULL,
+        description TEXT NOT NULL,
+        version INTEGER,
+        hash TEXT,
+        item_count INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        fingerprint TEXT
+    );
+    CREATE TABLE items (
+        bank INTEGER NOT NULL REFERENCES banks (id),
+        path TEXT NOT NULL,
+        kind TEXT,
+        size INTEGER NOT NULL
+    );
+    CREATE INDEX items_bank ON items (bank);
+";
+
+/// Write the banks of the catalog and the files in each bank to the database,
+/// replacing any catalog already in it. Directories are not included. The
+/// kind of an item is the name of its [`ItemKind`], or null when the kind is
+/// not known.
+///
+/// # Errors
+///
+/// Will return `Err` if a bank cannot be read again or the database cannot be
+/// written.
+pub fn export<P: AsRef<Path>>(catalog: &Catalog, path: P) -> io::Result<()> {
+    let mut connection = Connection::open(path).map_err(io::Error::other)?;
+    let transaction = connection.transaction().map_err(io::Error::other)?;
+    transaction
+        .execute_batch(SCHEMA)
+        .map_err(io::Error::other)?;
+    {
+        let mut insert_bank = transaction
+            .prepare(
+                "INSERT INTO banks (path, bank_id, name, author, description, version, hash,
+                    item_count, size, fingerprint)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )
+            .map_err(io::Error::other)?;
+        let mut insert_item = transaction
+            .prepare("INSERT INTO items (bank, path, kind, size) VALUES (?1, ?2, ?3, ?4)")
+            .map_err(io::Error::other)?;
+        for bank in &catalog.banks {
+            debug!("Adding {} to the database", bank.path.display());
+            #[allow(clippy::cast_possible_wrap)]
+            let row_id = insert_bank
+                .insert(params![
+                    bank.path.to_string_lossy(),
+                    bank.id,
+                    bank.name,
+                    bank.author,
+                    bank.description,
+                    bank.version,
+                    bank.hash,
+                    bank.item_count as i64,
+                    bank.size as i64,
+                    bank.fingerprint,
+                ])
+                .map_err(io::Error::other)?;
+
+            let reader = BankReader::open(&bank.path)?;
+            for item in reader.items().iter().filter(|item| item.is_file()) {
+                let item_path = item.file_name_lossy();
+                let kind = ItemKind::from(&item_path).map(|kind| format!("{kind:?}"));
+                #[allow(clippy::cast_possible_wrap)]
+                insert_item
+                    .execute(params![row_id, item_path, kind, item.size() as i64])
+                    .map_err(io::Error::other)?;
+            }
+        }
+    }
+    transaction.commit().map_err(io::Error::other)
+}
@@ -0,0 +1,91 @@
+//! Timing, size and outcome of each bank processed by a batch command, such as
+//! scanning a library of thousands of banks, to find slow or problematic
+//! files afterwards. Statistics are only written to a local file when asked
+//! for and are never sent anywhere.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// What happened to a bank.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Ok,
+
+    /// The bank could be read but has problems, such as failing verification.
+    Invalid,
+
+    /// The bank could not be read.
+    Unreadable,
+}
+
+/// Statistics for one bank.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BankStats {
+    pub path: PathBuf,
+
+    /// Size of the bank file in bytes, if it could be found.
+    pub size: Option<u64>,
+
+    /// Time taken to process the bank, in seconds.
+    pub seconds: f64,
+
+    pub outcome: Outcome,
+
+    /// Why the bank is invalid or unreadable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Statistics for every bank processed, in the order they were processed.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Stats {
+    pub banks: Vec<BankStats>,
+}
+
+impl Stats {
+    /// Add the statistics for a bank. The size is read from the file.
+    pub fn record<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        elapsed: Duration,
+        outcome: Outcome,
+        error: Option<String>,
+    ) {
+        let path = path.as_ref();
+        self.banks.push(BankStats {
+            path: path.to_owned(),
+            size: fs::metadata(path).ok().map(|metadata| metadata.len()),
+            seconds: elapsed.as_secs_f64(),
+            outcome,
+            error,
+        });
+    }
+
+    /// Write the statistics to a file as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Read statistics previously saved with [`Stats::save`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be read or does not contain
+    /// statistics.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Stats> {
+        let json = fs::read(path)?;
+        serde_json::from_slice(&json).map_err(Into::into)
+    }
+}
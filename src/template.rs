@@ -0,0 +1,109 @@
+//! Placeholder templates such as `{author}.{name}` for generating text from
+//! values like the metadata of a bank or the path of an item.
+
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use crate::{format_utc, unix_now, ItemKind, Metadata, PATH_SEPARATOR};
+
+/// Replace each `{placeholder}` in the template with its value. A literal
+/// brace is written as `{{` or `}}`.
+///
+/// # Errors
+///
+/// Will return `Err` if a placeholder has no value or a brace is not matched.
+pub fn render<F>(template: &str, value: F) -> io::Result<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let end = rest.find('}').ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("Placeholder is not closed in template {template}"),
+                    )
+                })?;
+                let name = &rest[..end];
+                let replacement = value(name).ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("Unknown placeholder {{{name}}} in template {template}"),
+                    )
+                })?;
+                result.push_str(&replacement);
+                chars = rest[end + 1..].chars();
+            }
+            '}' => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Unexpected closing brace in template {template}"),
+                ))
+            }
+            c => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+/// Values of the placeholders available for metadata templates: `author`,
+/// `name`, `description`, `id`, `version`, `year` and `date`. The year and
+/// date are the current UTC date.
+#[must_use]
+pub fn metadata_value(metadata: &Metadata, placeholder: &str) -> Option<String> {
+    match placeholder {
+        "author" => Some(metadata.author.clone()),
+        "name" => Some(metadata.name.clone()),
+        "description" => Some(metadata.description.clone()),
+        "id" => Some(metadata.id.clone()),
+        "version" => Some(metadata.version.unwrap_or_default().to_string()),
+        "year" => Some(format_utc(unix_now())[..4].to_string()),
+        "date" => Some(format_utc(unix_now())[..10].to_string()),
+        _ => None,
+    }
+}
+
+/// Values of the placeholders available for naming an item of a bank, given
+/// its path in the bank: `path`, `dir` for the directory in the bank, `name`
+/// for the file name, `stem` and `ext` for the file name without and with
+/// only the extension, and `kind` for the directory of its kind of file, such
+/// as `phaseplant`. The `bankid`, `bankname` and `bankauthor` placeholders
+/// are from the metadata of the bank.
+#[must_use]
+pub fn item_value(path: &str, metadata: &Metadata, placeholder: &str) -> Option<String> {
+    let (dir, name) = path.rsplit_once(PATH_SEPARATOR).unwrap_or(("", path));
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, ext),
+        _ => (name, ""),
+    };
+    match placeholder {
+        "path" => Some(path.to_string()),
+        "dir" => Some(dir.to_string()),
+        "name" => Some(name.to_string()),
+        "stem" => Some(stem.to_string()),
+        "ext" => Some(ext.to_string()),
+        "kind" => Some(
+            ItemKind::from(Path::new(path))
+                .and_then(|kind| kind.directory())
+                .unwrap_or_default()
+                .to_string(),
+        ),
+        "bankid" => Some(metadata.id.clone()),
+        "bankname" => Some(metadata.name.clone()),
+        "bankauthor" => Some(metadata.author.clone()),
+        _ => None,
+    }
+}
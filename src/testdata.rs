@@ -0,0 +1,142 @@
+//! Banks built in code with the structure of those written by Kilohearts Bank
+//! Maker, for testing integrations against realistic banks without
+//! redistributing Kilohearts content.
+//!
+//! The metadata is formatted the way Bank Maker formats it, so [`blank`] and
+//! [`with_metadata`] are byte for byte the same as the banks Bank Maker
+//! writes. The contents of the presets are placeholders since the preset
+//! formats are not public.
+
+use std::ffi::OsStr;
+use std::io;
+use std::io::{Cursor, Write};
+
+use serde::Serialize;
+
+use crate::write::BankWriter;
+use crate::{ItemKind, Metadata, BACKGROUND_FILE_STEM};
+
+/// A PNG image of a single transparent pixel.
+pub const BACKGROUND_PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+    0x89, 0x00, 0x00, 0x00, 0x0a, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x00, 0x01, 0x00, 0x00,
+    0x05, 0x00, 0x01, 0x0d, 0x0a, 0x2d, 0xb4, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae,
+    0x42, 0x60, 0x82,
+];
+
+/// A WAV file of one silent 16-bit sample at 44.1 kHz in mono.
+pub const SAMPLE_WAV: &[u8] = &[
+    b'R', b'I', b'F', b'F', 38, 0, 0, 0, b'W', b'A', b'V', b'E', b'f', b'm', b't', b' ', 16, 0, 0,
+    0, 1, 0, 1, 0, 0x44, 0xac, 0, 0, 0x88, 0x58, 0x01, 0, 2, 0, 16, 0, b'd', b'a', b't', b'a', 2,
+    0, 0, 0, 0, 0,
+];
+
+/// Stem of the file name of each item in [`one_of_each_kind`].
+pub const FILE_STEM: &str = "Test";
+
+/// The metadata fields in the order Bank Maker writes them.
+#[derive(Serialize)]
+struct BankMakerMetadata<'a> {
+    id: &'a str,
+    author: &'a str,
+    name: &'a str,
+    description: &'a str,
+}
+
+/// The contents of the metadata file as Bank Maker writes it, indented with
+/// four spaces and ending with a newline. Fields Bank Maker does not write,
+/// such as the version and hash, are left out.
+///
+/// # Errors
+///
+/// Will return `Err` if the metadata could not be formatted.
+pub fn metadata_contents(metadata: &Metadata) -> io::Result<Vec<u8>> {
+    let fields = BankMakerMetadata {
+        id: &metadata.id,
+        author: &metadata.author,
+        name: &metadata.name,
+        description: &metadata.description,
+    };
+    let mut contents = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut contents, formatter);
+    fields.serialize(&mut serializer)?;
+    contents.push(b'\n');
+    Ok(contents)
+}
+
+/// A bank with only empty metadata, like a new bank saved by Bank Maker
+/// without adding anything.
+///
+/// # Errors
+///
+/// Will return `Err` if the bank could not be written.
+pub fn blank() -> io::Result<Vec<u8>> {
+    with_metadata(&Metadata::default())
+}
+
+/// A bank with only the metadata. The ID is written as given, so leave it
+/// empty to match a bank saved by Bank Maker without an ID.
+///
+/// # Errors
+///
+/// Will return `Err` if the bank could not be written.
+pub fn with_metadata(metadata: &Metadata) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    add_metadata(&mut writer, metadata)?;
+    writer.write()?;
+    Ok(out)
+}
+
+/// A bank with a background, a sample and a preset of every kind Bank Maker
+/// accepts, each named [`FILE_STEM`] in the directory Bank Maker puts it in.
+/// The changelog, images other than the background and previews are left out
+/// since Bank Maker does not write them.
+///
+/// # Errors
+///
+/// Will return `Err` if the bank could not be written.
+pub fn one_of_each_kind() -> io::Result<Vec<u8>> {
+    let metadata = Metadata {
+        id: "kibank.test".to_string(),
+        author: "kibank".to_string(),
+        name: "Test".to_string(),
+        description: "One item of each kind".to_string(),
+        ..Metadata::default()
+    };
+
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    add_metadata(&mut writer, &metadata)?;
+    for kind in ItemKind::all() {
+        let (stem, contents) = match kind {
+            ItemKind::Metadata | ItemKind::Changelog | ItemKind::Image | ItemKind::Preview => {
+                continue
+            }
+            ItemKind::Background => (BACKGROUND_FILE_STEM, BACKGROUND_PNG.to_vec()),
+            ItemKind::Sample => (FILE_STEM, SAMPLE_WAV.to_vec()),
+            kind => (FILE_STEM, preset_contents(kind)),
+        };
+        // The last extension is PNG for the background and WAV for samples.
+        let extensions = kind.extensions();
+        let file_name = format!("{stem}.{}", extensions[extensions.len() - 1]);
+        writer.add(kind, OsStr::new(&file_name), contents)?;
+    }
+    writer.write()?;
+    Ok(out)
+}
+
+/// Placeholder contents of a preset, which are not a working preset.
+fn preset_contents(kind: ItemKind) -> Vec<u8> {
+    format!("kibank test preset for {kind:?}\n").into_bytes()
+}
+
+fn add_metadata<W: Write>(writer: &mut BankWriter<W>, metadata: &Metadata) -> io::Result<()> {
+    writer.add(
+        ItemKind::Metadata,
+        OsStr::new(Metadata::FILE_NAME),
+        metadata_contents(metadata)?,
+    )
+}
@@ -0,0 +1,199 @@
+//! The items of a bank arranged as a tree of directories with a number for
+//! each node, the form needed to present a bank as a filesystem. Directories
+//! that are only implied by the paths of files are included.
+
+use crate::names::natural_cmp;
+use crate::read::{Item, OwnedItem};
+use crate::PATH_SEPARATOR;
+
+/// Number of a node in the tree, which stays the same for as long as the tree
+/// exists. Zero is never used.
+pub type Inode = u64;
+
+/// The directory containing everything in the bank.
+pub const ROOT: Inode = 1;
+
+/// A file or directory in the tree.
+#[derive(Clone, Debug)]
+pub struct Node {
+    /// Name of the file or directory without the names of its parents. Empty
+    /// for the root.
+    pub name: Vec<u8>,
+
+    /// The directory containing the node. The root is its own parent.
+    pub parent: Inode,
+
+    pub kind: NodeKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum NodeKind {
+    Directory { children: Vec<Inode> },
+    File { item: OwnedItem },
+}
+
+impl Node {
+    #[must_use]
+    pub fn is_directory(&self) -> bool {
+        matches!(self.kind, NodeKind::Directory { .. })
+    }
+
+    /// The item with the contents of a file.
+    #[must_use]
+    pub fn item(&self) -> Option<&OwnedItem> {
+        match &self.kind {
+            NodeKind::File { item } => Some(item),
+            NodeKind::Directory { .. } => None,
+        }
+    }
+
+    /// Size of the contents in bytes, which is zero for directories.
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        self.item().map_or(0, Item::size)
+    }
+}
+
+/// Every item of a bank by its place in the tree of directories.
+#[derive(Clone, Debug)]
+pub struct BankTree {
+    /// The node for each inode, offset by one.
+    nodes: Vec<Node>,
+}
+
+impl BankTree {
+    /// Arrange the items, such as from [`crate::read::BankReader::items`].
+    /// Names are compared ignoring ASCII case, like the paths in a bank, and
+    /// the first of any items with the same path is used.
+    #[must_use]
+    pub fn new(items: &[Item]) -> BankTree {
+        let mut tree = BankTree {
+            nodes: vec![Node {
+                name: Vec::new(),
+                parent: ROOT,
+                kind: NodeKind::Directory {
+                    children: Vec::new(),
+                },
+            }],
+        };
+        for item in items {
+            let mut parent = ROOT;
+            let mut names = item
+                .path_bytes()
+                .split(|byte| *byte == PATH_SEPARATOR as u8)
+                .filter(|name| !name.is_empty())
+                .peekable();
+            while let Some(name) = names.next() {
+                let is_last = names.peek().is_none();
+                parent = match tree.lookup(parent, name) {
+                    Some(inode) => inode,
+                    None if is_last && item.is_file() => tree.insert(
+                        parent,
+                        name,
+                        NodeKind::File {
+                            item: item.clone().into_owned(),
+                        },
+                    ),
+                    None => tree.insert(
+                        parent,
+                        name,
+                        NodeKind::Directory {
+                            children: Vec::new(),
+                        },
+                    ),
+                };
+
+                // Nothing can be inside a file.
+                if !tree.nodes[Self::index(parent)].is_directory() {
+                    break;
+                }
+            }
+        }
+        tree
+    }
+
+    /// Number of files and directories, including the root.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Always `false` since there is always a root.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    #[must_use]
+    pub fn node(&self, inode: Inode) -> Option<&Node> {
+        inode
+            .checked_sub(1)
+            .and_then(|index| usize::try_from(index).ok())
+            .and_then(|index| self.nodes.get(index))
+    }
+
+    /// The contents of a directory, which is empty for files.
+    #[must_use]
+    pub fn children(&self, inode: Inode) -> &[Inode] {
+        match self.node(inode).map(|node| &node.kind) {
+            Some(NodeKind::Directory { children }) => children,
+            _ => &[],
+        }
+    }
+
+    /// Find the file or directory with the name in the directory, ignoring
+    /// ASCII case.
+    #[must_use]
+    pub fn lookup(&self, parent: Inode, name: &[u8]) -> Option<Inode> {
+        self.children(parent).iter().copied().find(|inode| {
+            self.node(*inode)
+                .is_some_and(|node| node.name.eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// Find the file or directory with the path in the bank, ignoring ASCII
+    /// case. An empty path is the root.
+    #[must_use]
+    pub fn find(&self, path: &[u8]) -> Option<Inode> {
+        path.split(|byte| *byte == PATH_SEPARATOR as u8)
+            .filter(|name| !name.is_empty())
+            .try_fold(ROOT, |parent, name| self.lookup(parent, name))
+    }
+
+    /// Order the contents of every directory by name with [`natural_cmp`],
+    /// so `Preset 2` is before `Preset 10`, instead of the order of the items.
+    pub fn sort_naturally(&mut self) {
+        for index in 0..self.nodes.len() {
+            let NodeKind::Directory { children } = &self.nodes[index].kind else {
+                continue;
+            };
+            let mut children = children.clone();
+            children.sort_by(|a, b| {
+                natural_cmp(
+                    &self.nodes[Self::index(*a)].name,
+                    &self.nodes[Self::index(*b)].name,
+                )
+            });
+            self.nodes[index].kind = NodeKind::Directory { children };
+        }
+    }
+
+    fn insert(&mut self, parent: Inode, name: &[u8], kind: NodeKind) -> Inode {
+        self.nodes.push(Node {
+            name: name.to_vec(),
+            parent,
+            kind,
+        });
+        let inode = self.nodes.len() as Inode;
+        if let NodeKind::Directory { children } = &mut self.nodes[Self::index(parent)].kind {
+            children.push(inode);
+        }
+        inode
+    }
+
+    /// Position in `nodes` of an inode known to be in the tree.
+    #[allow(clippy::cast_possible_truncation)]
+    fn index(inode: Inode) -> usize {
+        (inode - 1) as usize
+    }
+}
@@ -0,0 +1,214 @@
+//! Checks that a bank is well formed and its contents can be read.
+
+use std::fmt;
+use std::io;
+use std::io::{BufRead, Read, Seek};
+
+use log::debug;
+
+use crate::cancel::{CancellationToken, Cancelled};
+use crate::hash;
+use crate::hash::Checksums;
+use crate::inspect;
+use crate::lint::Level;
+use crate::names::lowercase_extension;
+use crate::read::BankReader;
+use crate::roundtrip;
+use crate::PATH_SEPARATOR;
+
+/// Which checks are made in addition to those made when the bank is opened.
+#[derive(Clone, Debug, Default)]
+pub struct VerifyOptions {
+    /// Read the contents of every item. Contents are read in small pieces so
+    /// memory use stays the same no matter how large the bank is.
+    pub deep: bool,
+
+    /// Rebuild the bank in memory and report anything that is lost. See
+    /// [`roundtrip::check_reader`].
+    pub roundtrip: bool,
+
+    /// Compare the digest of each file with these checksums. Files that are
+    /// missing or different are errors, and files without a checksum are
+    /// warnings.
+    pub checksums: Option<Checksums>,
+
+    /// Report metadata that breaks the rules followed by Kilohearts as errors.
+    /// See [`crate::Metadata::validate`].
+    pub strict_metadata: bool,
+
+    /// Stops a deep verification before the next item when cancelled.
+    pub cancel: Option<CancellationToken>,
+}
+
+/// A problem found with a bank.
+#[derive(Clone, Debug)]
+pub struct Issue {
+    pub level: Level,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.level, self.message)
+    }
+}
+
+/// The results of verifying a bank.
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub issues: Vec<Issue>,
+
+    /// Hash of the contents when the contents were read. See [`hash::content_hash`].
+    pub fingerprint: Option<String>,
+}
+
+impl Report {
+    /// Returns `true` if there are no issues at the error level.
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        !self.issues.iter().any(|issue| issue.level == Level::Error)
+    }
+
+    pub(crate) fn warning(&mut self, message: String) {
+        self.issues.push(Issue {
+            level: Level::Warning,
+            message,
+        });
+    }
+
+    pub(crate) fn error(&mut self, message: String) {
+        self.issues.push(Issue {
+            level: Level::Error,
+            message,
+        });
+    }
+}
+
+/// Verify a bank that has been opened. Problems with the bank are reported as
+/// issues rather than errors.
+///
+/// # Errors
+///
+/// Will return `Err` only for failures unrelated to the contents of the bank,
+/// or if it is cancelled.
+pub fn verify<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    options: &VerifyOptions,
+) -> io::Result<Report> {
+    let mut report = Report::default();
+    let items = reader.items();
+
+    // Only the header is needed to find contents missing from a truncated
+    // bank, so this is checked even without reading the contents.
+    report
+        .issues
+        .extend(inspect::inspect_sizes(&items, reader.stream_len()).issues);
+
+    let metadata_items = items
+        .iter()
+        .filter(|item| item.is_metadata_file())
+        .collect::<Vec<_>>();
+    match metadata_items.first() {
+        None if reader.is_empty() => report.warning("The bank has no items".to_string()),
+        None => report.warning("No metadata".to_string()),
+        Some(item) => match reader.read_metadata(item) {
+            Ok(metadata) if options.strict_metadata => {
+                for issue in metadata.validate() {
+                    report.error(format!("Metadata: {issue}"));
+                }
+            }
+            Ok(_) => {}
+            Err(error) => report.error(format!("Cannot read the metadata: {error}")),
+        },
+    }
+    if metadata_items.len() > 1 {
+        report.warning("More than one metadata file".to_string());
+    }
+
+    let windows_paths = items
+        .iter()
+        .filter(|item| item.has_windows_separators())
+        .map(|item| item.file_name_lossy())
+        .collect::<Vec<_>>();
+    if !windows_paths.is_empty() {
+        report.warning(format!(
+            "Paths separated with \\ instead of {PATH_SEPARATOR}: {}",
+            windows_paths.join(", ")
+        ));
+    }
+
+    let uppercase_extensions = items
+        .iter()
+        .filter(|item| item.is_file() && lowercase_extension(item.path_bytes()).is_some())
+        .map(|item| item.file_name_lossy())
+        .collect::<Vec<_>>();
+    if !uppercase_extensions.is_empty() {
+        report.warning(format!(
+            "Extensions that are not lowercase: {}",
+            uppercase_extensions.join(", ")
+        ));
+    }
+
+    if options.deep {
+        match hash::content_hash_with(reader, options.cancel.as_ref()) {
+            Ok(fingerprint) => {
+                debug!("Fingerprint is {fingerprint}");
+                report.fingerprint = Some(fingerprint);
+            }
+            Err(error) if Cancelled::from_error(&error).is_some() => return Err(error),
+            Err(error) => report.error(format!("Cannot read the contents: {error}")),
+        }
+    }
+
+    if let Some(checksums) = &options.checksums {
+        check_checksums(reader, checksums, &mut report);
+    }
+
+    if options.roundtrip {
+        match roundtrip::check_reader(reader) {
+            Ok(differences) => {
+                for difference in differences {
+                    report.error(difference.to_string());
+                }
+            }
+            Err(error) => report.error(format!("Cannot rebuild the bank: {error}")),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Compare the files in the bank with the checksums.
+fn check_checksums<ReaderType: Read + Seek + BufRead>(
+    reader: &mut BankReader<ReaderType>,
+    checksums: &Checksums,
+    report: &mut Report,
+) {
+    let items = reader.items();
+    for (path, expected) in &checksums.digests {
+        let Some(item) = items
+            .iter()
+            .find(|item| item.is_file() && item.file_name_lossy() == *path)
+        else {
+            report.error(format!("Missing {path}"));
+            continue;
+        };
+        match hash::item_digest_using(reader, item, checksums.algorithm) {
+            Ok(digest) if digest == *expected => {}
+            Ok(_) => report.error(format!(
+                "{} checksum of {path} does not match",
+                checksums.algorithm
+            )),
+            Err(error) => report.error(format!("Cannot read {path}: {error}")),
+        }
+    }
+
+    let unlisted = items
+        .iter()
+        .filter(|item| item.is_file() && !checksums.digests.contains_key(&item.file_name_lossy()))
+        .map(|item| item.file_name_lossy())
+        .collect::<Vec<_>>();
+    if !unlisted.is_empty() {
+        report.warning(format!("No checksum for {}", unlisted.join(", ")));
+    }
+}
@@ -0,0 +1,133 @@
+//! Access to the files in a bank by path, for applications that already read
+//! their content through their own abstraction of a filesystem, such as
+//! samplers and preset browsers.
+
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, Cursor, Error, ErrorKind, Read, Seek};
+use std::sync::Mutex;
+
+use crate::concat::Section;
+use crate::read::BankReader;
+use crate::tree::{BankTree, Inode};
+
+/// An open file of a [`Vfs`].
+pub trait VfsFile: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> VfsFile for T {}
+
+/// What [`Vfs::stat`] knows about a file or directory.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Stat {
+    pub is_directory: bool,
+
+    /// Size of the contents in bytes, which is zero for directories.
+    pub size: u64,
+}
+
+/// A read-only filesystem. Paths are separated by `/` and are relative to
+/// the root, which is the empty path.
+pub trait Vfs {
+    /// # Errors
+    ///
+    /// Will return `Err` if the path is not found.
+    fn stat(&self, path: &str) -> io::Result<Stat>;
+
+    /// Names of the files and directories in the directory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the path is not found or is not a directory.
+    fn list(&self, path: &str) -> io::Result<Vec<String>>;
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the path is not found, is a directory, or cannot
+    /// be read.
+    fn open(&self, path: &str) -> io::Result<Box<dyn VfsFile>>;
+
+    /// The whole contents of the file.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be opened or read.
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let mut contents = Vec::new();
+        self.open(path)?.read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+}
+
+/// The files of a bank as a [`Vfs`]. Names are compared ignoring ASCII case,
+/// like the paths in a bank.
+pub struct BankVfs<'a, ReaderType: Read + Seek + BufRead> {
+    reader: Mutex<BankReader<'a, ReaderType>>,
+    tree: BankTree,
+}
+
+impl<'a, ReaderType: Read + Seek + BufRead> BankVfs<'a, ReaderType> {
+    #[must_use]
+    pub fn new(reader: BankReader<'a, ReaderType>) -> Self {
+        let tree = BankTree::new(&reader.items());
+        BankVfs {
+            reader: Mutex::new(reader),
+            tree,
+        }
+    }
+
+    fn find(&self, path: &str) -> io::Result<Inode> {
+        self.tree.find(path.as_bytes()).ok_or_else(|| {
+            let msg = format!("{path} is not in the bank");
+            Error::new(ErrorKind::NotFound, msg)
+        })
+    }
+}
+
+impl<ReaderType: Read + Seek + BufRead> Vfs for BankVfs<'_, ReaderType> {
+    fn stat(&self, path: &str) -> io::Result<Stat> {
+        let node = self.tree.node(self.find(path)?).expect("Found nodes exist");
+        Ok(Stat {
+            is_directory: node.is_directory(),
+            size: node.size(),
+        })
+    }
+
+    fn list(&self, path: &str) -> io::Result<Vec<String>> {
+        let inode = self.find(path)?;
+        if !self
+            .tree
+            .node(inode)
+            .is_some_and(|node| node.is_directory())
+        {
+            let msg = format!("{path} is not a directory");
+            return Err(Error::new(ErrorKind::InvalidInput, msg));
+        }
+        Ok(self
+            .tree
+            .children(inode)
+            .iter()
+            .filter_map(|child| self.tree.node(*child))
+            .map(|node| String::from_utf8_lossy(&node.name).into_owned())
+            .collect())
+    }
+
+    fn open(&self, path: &str) -> io::Result<Box<dyn VfsFile>> {
+        let node = self.tree.node(self.find(path)?).expect("Found nodes exist");
+        let item = node.item().ok_or_else(|| {
+            let msg = format!("{path} is a directory");
+            Error::new(ErrorKind::InvalidInput, msg)
+        })?;
+
+        // Banks opened from a file are read through a file of their own so
+        // large items aren't read into memory.
+        let mut reader = self
+            .reader
+            .lock()
+            .map_err(|_| Error::other("Reader was poisoned"))?;
+        if let Some(bank_path) = reader.path() {
+            let range = item.offset()..item.offset() + item.size();
+            return Ok(Box::new(Section::new(File::open(bank_path)?, range)));
+        }
+        Ok(Box::new(Cursor::new(reader.read_contents(item)?)))
+    }
+}
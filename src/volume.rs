@@ -0,0 +1,276 @@
+//! Banks split into volumes of a limited size, such as `pack.bank.001` and
+//! `pack.bank.002`, for distribution channels that limit the size of files. A
+//! small JSON index such as `pack.bank.volumes.json` lists the volumes.
+
+use std::ffi::OsString;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::write::AtomicFile;
+
+/// Added to the file name of the bank to make the file name of the index.
+pub const INDEX_SUFFIX: &str = ".volumes.json";
+
+/// One part of a split bank.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Volume {
+    /// File name of the volume, in the same directory as the index.
+    pub file_name: String,
+
+    /// Size of the volume in bytes.
+    pub size: u64,
+}
+
+/// The volumes of a split bank, in order.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct VolumeIndex {
+    pub volumes: Vec<Volume>,
+}
+
+impl VolumeIndex {
+    /// Total size of the bank in bytes.
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        self.volumes.iter().map(|volume| volume.size).sum()
+    }
+}
+
+/// Path of the volume of the bank with the number, starting at one.
+#[must_use]
+pub fn volume_path(bank_path: &Path, number: usize) -> PathBuf {
+    let mut path = OsString::from(bank_path);
+    path.push(format!(".{number:03}"));
+    PathBuf::from(path)
+}
+
+/// Path of the index of the volumes of the bank.
+#[must_use]
+pub fn index_path(bank_path: &Path) -> PathBuf {
+    let mut path = OsString::from(bank_path);
+    path.push(INDEX_SUFFIX);
+    PathBuf::from(path)
+}
+
+/// Find the index of a split bank given the path of the index itself, the
+/// first volume or the bank without any volume number.
+#[must_use]
+pub fn find_index(path: &Path) -> Option<PathBuf> {
+    let file_name = path.file_name()?.to_string_lossy();
+    if file_name.ends_with(INDEX_SUFFIX) {
+        return Some(path.to_owned());
+    }
+    let bank_path = match file_name.strip_suffix(".001") {
+        Some(bank_file_name) => path.with_file_name(bank_file_name),
+        None => path.to_owned(),
+    };
+    Some(index_path(&bank_path)).filter(|index_path| index_path.is_file())
+}
+
+/// Writes a bank across volumes of at most `volume_size` bytes each. The
+/// volumes are written to temporary files, like [`AtomicFile`], which only
+/// replace any volumes and index at their paths once the writer is committed
+/// with [`VolumeWriter::commit`]. Dropping the writer without committing it
+/// removes the temporary files.
+pub struct VolumeWriter {
+    bank_path: PathBuf,
+    volume_size: u64,
+    index: VolumeIndex,
+    files: Vec<AtomicFile>,
+}
+
+impl VolumeWriter {
+    /// Volumes are created as they are needed.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the volume size is zero.
+    pub fn new<P: AsRef<Path>>(bank_path: P, volume_size: u64) -> io::Result<VolumeWriter> {
+        if volume_size == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Volume size must be more than zero",
+            ));
+        }
+        Ok(VolumeWriter {
+            bank_path: bank_path.as_ref().to_owned(),
+            volume_size,
+            index: VolumeIndex::default(),
+            files: Vec::new(),
+        })
+    }
+
+    /// The volumes written so far.
+    #[must_use]
+    pub fn index(&self) -> &VolumeIndex {
+        &self.index
+    }
+
+    /// Replace any volumes and index at their paths with everything
+    /// written. The old index is removed first and the new one written last,
+    /// so the index never lists a mix of old and new volumes, and any more
+    /// volumes of the old bank are removed. Returns the index.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a volume or the index cannot be saved, in which
+    /// case the temporary files that are left are removed.
+    pub fn commit(self) -> io::Result<VolumeIndex> {
+        let index_path = index_path(&self.bank_path);
+        let mut index_file = AtomicFile::create(&index_path)?;
+        index_file.write_all(&serde_json::to_vec_pretty(&self.index)?)?;
+        if let Err(error) = fs::remove_file(&index_path) {
+            if error.kind() != ErrorKind::NotFound {
+                return Err(error);
+            }
+        }
+        for file in self.files {
+            file.commit()?;
+        }
+
+        // Volumes left over from an old bank that needed more of them.
+        let mut number = self.index.volumes.len() + 1;
+        while volume_path(&self.bank_path, number).is_file() {
+            fs::remove_file(volume_path(&self.bank_path, number))?;
+            number += 1;
+        }
+        debug!("Writing volume index {}", index_path.display());
+        index_file.commit()?;
+        Ok(self.index)
+    }
+}
+
+impl Write for VolumeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let full = self
+            .index
+            .volumes
+            .last()
+            .map_or(true, |volume| volume.size >= self.volume_size);
+        if self.files.is_empty() || full {
+            let path = volume_path(&self.bank_path, self.index.volumes.len() + 1);
+            debug!("Creating volume {}", path.display());
+            self.files.push(AtomicFile::create(&path)?);
+            self.index.volumes.push(Volume {
+                file_name: path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+                size: 0,
+            });
+        }
+
+        let (Some(file), Some(volume)) = (self.files.last_mut(), self.index.volumes.last_mut())
+        else {
+            return Err(Error::other("No volume to write to"));
+        };
+        let remaining = usize::try_from(self.volume_size - volume.size).unwrap_or(usize::MAX);
+        let len = file.write(&buf[..buf.len().min(remaining)])?;
+        volume.size += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.files.last_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Reads the volumes of a split bank as if they were one file.
+pub struct VolumeReader {
+    files: Vec<File>,
+
+    /// Position of the start of each volume in the bank.
+    starts: Vec<u64>,
+
+    size: u64,
+    position: u64,
+}
+
+impl VolumeReader {
+    /// Open every volume listed in the index.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the index cannot be read, or if a volume is
+    /// missing or a different size than the index says.
+    pub fn open<P: AsRef<Path>>(index_path: P) -> io::Result<VolumeReader> {
+        let index_path = index_path.as_ref();
+        let index: VolumeIndex = serde_json::from_slice(&fs::read(index_path)?)?;
+        let dir = index_path.parent().unwrap_or_else(|| Path::new(""));
+
+        let mut files = Vec::with_capacity(index.volumes.len());
+        let mut starts = Vec::with_capacity(index.volumes.len());
+        let mut size = 0;
+        for volume in &index.volumes {
+            // Volumes are always next to the index.
+            let path = dir.join(Path::new(&volume.file_name).file_name().unwrap_or_default());
+            let file = File::open(&path)?;
+            let len = file.metadata()?.len();
+            if len != volume.size {
+                let msg = format!(
+                    "Volume {} is {len} bytes instead of {} bytes",
+                    path.display(),
+                    volume.size
+                );
+                return Err(Error::new(ErrorKind::InvalidData, msg));
+            }
+            files.push(file);
+            starts.push(size);
+            size += len;
+        }
+
+        Ok(VolumeReader {
+            files,
+            starts,
+            size,
+            position: 0,
+        })
+    }
+}
+
+impl Read for VolumeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+
+        // The last volume that starts at or before the position.
+        let number = self.starts.partition_point(|start| *start <= self.position) - 1;
+        let end = self.starts.get(number + 1).copied().unwrap_or(self.size);
+        let file = &mut self.files[number];
+        file.seek(SeekFrom::Start(self.position - self.starts[number]))?;
+        let len = usize::try_from(end - self.position)
+            .unwrap_or(usize::MAX)
+            .min(buf.len());
+        let read = file.read(&mut buf[..len])?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for VolumeReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        }
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "Cannot seek before the start of the bank",
+            )
+        })?;
+        self.position = position;
+        Ok(position)
+    }
+}
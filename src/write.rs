@@ -1,21 +1,195 @@
 use std::collections::BTreeSet;
 use std::ffi::{OsStr, OsString};
+use std::fmt;
 use std::fs;
+use std::fs::File;
 use std::io;
-use std::io::{Error, ErrorKind, Write};
+use std::io::{Error, ErrorKind, Read, Write};
 use std::mem::size_of;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
 
 use byteorder::{LittleEndian, WriteBytesExt};
 use log::debug;
+use sha1_smol::Sha1;
 
+use crate::cancel::{self, CancellationToken};
+use crate::hash;
+use crate::names::natural_cmp;
 use crate::{
-    ItemKind, Location, Metadata, CORRUPTION_CHECK_BYTES, FILE_ID, FORMAT_VERSION, PATH_SEPARATOR,
+    ItemKind, Location, Metadata, Provenance, CORRUPTION_CHECK_BYTES, FILE_ID, FORMAT_VERSION,
+    PATH_SEPARATOR,
 };
 
+/// Writes the contents of an item added with [`BankWriter::add_lazy`].
+pub type Generate = dyn FnOnce(&mut dyn Write) -> io::Result<()> + Send;
+
+/// Where the contents of an item come from. Only contents in memory are held
+/// until the bank is written, the others are read during the final pass of
+/// [`BankWriter::write`] so banks larger than memory can be written.
+pub enum ContentSource {
+    /// Contents already in memory.
+    Bytes(Vec<u8>),
+
+    /// Contents of a file, which must not change size until the bank is
+    /// written.
+    File(PathBuf),
+
+    /// Contents read from a stream. The size is needed up front because the
+    /// sizes of all items are written before any contents.
+    Reader {
+        size: u64,
+        reader: Box<dyn Read + Send>,
+    },
+
+    /// Contents written by a function when they are needed, such as audio
+    /// that is transcoded on the fly. The function must write exactly `size`
+    /// bytes.
+    Generated { size: u64, generate: Box<Generate> },
+}
+
+impl ContentSource {
+    /// Size of the contents in bytes. Files are only looked at, not read.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the size of the file cannot be found.
+    pub fn size(&self) -> io::Result<u64> {
+        match self {
+            ContentSource::Bytes(contents) => Ok(contents.len() as u64),
+            ContentSource::File(path) => Ok(fs::metadata(path)?.len()),
+            ContentSource::Reader { size, .. } | ContentSource::Generated { size, .. } => Ok(*size),
+        }
+    }
+
+    /// Read the contents into memory if they aren't already, to use them
+    /// before the bank is written.
+    fn load(&mut self) -> io::Result<&[u8]> {
+        if !matches!(self, ContentSource::Bytes(_)) {
+            *self = ContentSource::Bytes(std::mem::take(self).into_bytes()?);
+        }
+        match self {
+            ContentSource::Bytes(contents) => Ok(contents),
+            _ => unreachable!("The contents were just loaded"),
+        }
+    }
+
+    /// Add the contents to the hash. Files are read a piece at a time and
+    /// read again when the bank is written, while streams and generated
+    /// contents are kept in memory since they can only be read once.
+    fn hash_into(&mut self, hasher: &mut Sha1) -> io::Result<()> {
+        if let ContentSource::File(path) = self {
+            let mut file = File::open(path)?;
+            let mut buffer = vec![0_u8; 64 * 1024];
+            loop {
+                match file.read(&mut buffer)? {
+                    0 => return Ok(()),
+                    read_count => hasher.update(&buffer[..read_count]),
+                }
+            }
+        }
+        hasher.update(self.load()?);
+        Ok(())
+    }
+
+    /// Read all of the contents into memory.
+    fn into_bytes(self) -> io::Result<Vec<u8>> {
+        match self {
+            ContentSource::Bytes(contents) => Ok(contents),
+            ContentSource::File(path) => fs::read(path),
+            ContentSource::Reader { size, reader } => {
+                let mut contents = Vec::new();
+                reader.take(size).read_to_end(&mut contents)?;
+                Ok(contents)
+            }
+            source @ ContentSource::Generated { .. } => {
+                let mut contents = Vec::new();
+                let size = source.size()?;
+                source.copy_to(size, &mut contents)?;
+                Ok(contents)
+            }
+        }
+    }
+
+    /// Copy exactly `size` bytes of the contents to the writer.
+    fn copy_to<W: Write>(self, size: u64, writer: &mut W) -> io::Result<()> {
+        let copied = match self {
+            ContentSource::Bytes(contents) => {
+                writer.write_all(&contents)?;
+                contents.len() as u64
+            }
+            ContentSource::File(path) => io::copy(&mut File::open(path)?.take(size), writer)?,
+            ContentSource::Reader { reader, .. } => io::copy(&mut reader.take(size), writer)?,
+            ContentSource::Generated { generate, .. } => {
+                let mut sized = SizedWriter {
+                    inner: writer,
+                    remaining: size,
+                };
+                generate(&mut sized)?;
+                size - sized.remaining
+            }
+        };
+        if copied == size {
+            Ok(())
+        } else {
+            let msg = format!("Expected {size} bytes of contents but there were only {copied}");
+            Err(Error::new(ErrorKind::UnexpectedEof, msg))
+        }
+    }
+}
+
+impl Default for ContentSource {
+    fn default() -> Self {
+        ContentSource::Bytes(Vec::new())
+    }
+}
+
+impl fmt::Debug for ContentSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentSource::Bytes(contents) => write!(f, "Bytes({} bytes)", contents.len()),
+            ContentSource::File(path) => f.debug_tuple("File").field(path).finish(),
+            ContentSource::Reader { size, .. } => write!(f, "Reader({size} bytes)"),
+            ContentSource::Generated { size, .. } => write!(f, "Generated({size} bytes)"),
+        }
+    }
+}
+
+/// Fails rather than write more than the size declared for the contents,
+/// which would move the contents of every item after it.
+struct SizedWriter<'w, W: Write> {
+    inner: &'w mut W,
+    remaining: u64,
+}
+
+impl<W: Write> Write for SizedWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() as u64 > self.remaining {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "More contents were generated than their declared size",
+            ));
+        }
+        let written = self.inner.write(buf)?;
+        self.remaining -= written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl From<Vec<u8>> for ContentSource {
+    fn from(contents: Vec<u8>) -> Self {
+        ContentSource::Bytes(contents)
+    }
+}
+
 pub struct Item {
-    kind: ItemKind,
-    contents: Vec<u8>,
+    /// Directories that don't belong to any kind of item have no kind.
+    kind: Option<ItemKind>,
+    contents: ContentSource,
 
     /// Path of the file within the bank, including any leading directory.
     path_os: OsString,
@@ -28,9 +202,67 @@ impl Item {
     }
 }
 
+/// Ways to give a bank an ID when its metadata has none and there is no author
+/// or name to make one from, see [`Metadata::effective_id`]. The generated IDs
+/// depend only on the paths and contents of the other items, so writing the
+/// same items again gives the same ID while banks with different items get
+/// different IDs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IdOnEmpty {
+    /// Fail to write the bank.
+    Error,
+
+    /// A name-based UUID written as 32 hex digits without dashes, since IDs
+    /// are only letters, digits and dots.
+    Uuid,
+
+    /// The SHA-1 digest of the items in hex.
+    Hash,
+}
+
+/// Settings that change how a bank is written.
+#[derive(Clone, Debug, Default)]
+pub struct BankWriterOptions {
+    /// Write the items in the order they were added rather than grouped by
+    /// kind. Rewriting an existing bank with its items added in their original
+    /// order then changes as little of the bank as possible.
+    pub preserve_order: bool,
+
+    /// Write the items of each kind in natural order of their paths, so
+    /// `Preset 2` is before `Preset 10`, rather than in the order they were
+    /// added. Ignored when the order is preserved.
+    pub natural_order: bool,
+
+    /// Stops writing before the contents of the next item when cancelled,
+    /// leaving the bank incomplete.
+    pub cancel: Option<CancellationToken>,
+
+    /// What to do when the metadata has no ID and no author or name to make
+    /// one from. The ID is left empty when `None`.
+    pub id_on_empty: Option<IdOnEmpty>,
+
+    /// Compress the contents of every item other than the metadata, and mark
+    /// the metadata, to make a smaller bank. This is experimental and not
+    /// standard: Kilohearts products cannot read these banks. See
+    /// `compress::X_ITEM_COMPRESSION_KEY`. Writing fails without the
+    /// `compression` feature.
+    pub x_compress_items: bool,
+
+    /// Record a [`Provenance`] in the metadata with a hash of the names and
+    /// contents of the other items, see [`hash::manifest_hash`]. Files are
+    /// named by their file name and hashed while the other items are written,
+    /// so the contents of the metadata are written after every other item.
+    pub stamp: bool,
+
+    /// Number of files to hash at once for `stamp`, or one for each CPU when
+    /// zero.
+    pub hash_threads: usize,
+}
+
 pub struct BankWriter<WriterType: Write> {
     inner: WriterType,
     items: Vec<Item>,
+    options: BankWriterOptions,
 
     /// If the data has already been committed with a call to `write()`.
     written: bool,
@@ -38,9 +270,14 @@ pub struct BankWriter<WriterType: Write> {
 
 impl<WriterType: Write> BankWriter<WriterType> {
     pub fn new(inner: WriterType) -> BankWriter<WriterType> {
+        Self::with_options(inner, BankWriterOptions::default())
+    }
+
+    pub fn with_options(inner: WriterType, options: BankWriterOptions) -> BankWriter<WriterType> {
         BankWriter {
             inner,
             items: Vec::new(),
+            options,
             written: false,
         }
     }
@@ -55,17 +292,47 @@ impl<WriterType: Write> BankWriter<WriterType> {
     ///
     /// # Errors
     ///
-    /// Will return `Err` if the bank has already been written
+    /// Will return `Err` if the bank has already been written or the file
+    /// name is not valid in a bank. See [`normalize_path`].
     pub fn add(&mut self, kind: ItemKind, file_name: &OsStr, contents: Vec<u8>) -> io::Result<()> {
-        if self.written {
-            return Err(Error::new(
-                ErrorKind::Other,
-                "Cannot add to a bank that has already been written",
-            ));
-        }
+        self.add_source(kind, file_name, ContentSource::Bytes(contents))
+    }
 
+    /// Like `add()` except the path already includes any leading directory,
+    /// such as the path of an item read from an existing bank.
+    ///
+    /// * `kind` - type of the file
+    /// * `path` - path of the file within the bank
+    /// * `contents` - the data to include in the bank
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the bank has already been written or the path is
+    /// not valid in a bank. See [`normalize_path`].
+    pub fn add_with_path(
+        &mut self,
+        kind: ItemKind,
+        path: &OsStr,
+        contents: Vec<u8>,
+    ) -> io::Result<()> {
+        self.add_source_with_path(kind, path, ContentSource::Bytes(contents))
+    }
+
+    /// Like `add()` except the contents are only read when the bank is
+    /// written, unless they are already in memory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the bank has already been written or the file
+    /// name is not valid in a bank. See [`normalize_path`].
+    pub fn add_source(
+        &mut self,
+        kind: ItemKind,
+        file_name: &OsStr,
+        source: ContentSource,
+    ) -> io::Result<()> {
         // Add the leading directory so the item is ready to use.
-        let file_name = if let Some(dir_name) = kind.directory() {
+        let path = if let Some(dir_name) = kind.directory() {
             let mut path_str = OsString::from(dir_name);
             path_str.push(PATH_SEPARATOR.to_string());
             path_str.push(file_name);
@@ -73,33 +340,117 @@ impl<WriterType: Write> BankWriter<WriterType> {
         } else {
             file_name.to_owned()
         };
+        self.add_source_with_path(kind, &path, source)
+    }
+
+    /// Like `add_source()` except the path already includes any leading
+    /// directory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the bank has already been written or the path is
+    /// not valid in a bank. See [`normalize_path`].
+    pub fn add_source_with_path(
+        &mut self,
+        kind: ItemKind,
+        path: &OsStr,
+        source: ContentSource,
+    ) -> io::Result<()> {
+        if self.written {
+            return Err(Error::other(
+                "Cannot add to a bank that has already been written",
+            ));
+        }
 
         self.items.push(Item {
-            kind,
-            contents,
-            path_os: file_name,
+            kind: Some(kind),
+            contents: source,
+            path_os: normalize_path(path)?,
         });
         Ok(())
     }
 
+    /// Like `add()` except the contents are written by `generate` while the
+    /// bank is written, so they never have to be held in memory. The size is
+    /// needed up front because the sizes of all items are written before any
+    /// contents, and `generate` must write exactly that many bytes.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the bank has already been written or the file
+    /// name is not valid in a bank. See [`normalize_path`].
+    pub fn add_lazy<F>(
+        &mut self,
+        kind: ItemKind,
+        file_name: &OsStr,
+        size: u64,
+        generate: F,
+    ) -> io::Result<()>
+    where
+        F: FnOnce(&mut dyn Write) -> io::Result<()> + Send + 'static,
+    {
+        let generate = Box::new(generate);
+        self.add_source(kind, file_name, ContentSource::Generated { size, generate })
+    }
+
+    /// Add a directory that doesn't belong to any kind of item. Directories
+    /// for the kinds of items that are added are created automatically.
+    ///
+    /// * `path` - path of the directory within the bank
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the bank has already been written or the path is
+    /// not valid in a bank. See [`normalize_path`].
+    pub fn add_directory(&mut self, path: &OsStr) -> io::Result<()> {
+        if self.written {
+            return Err(Error::other(
+                "Cannot add to a bank that has already been written",
+            ));
+        }
+
+        let path = normalize_path(path)?;
+        if !self
+            .items
+            .iter()
+            .any(|item| item.kind.is_none() && item.path_os.eq_ignore_ascii_case(&path))
+        {
+            self.items.push(Item {
+                kind: None,
+                contents: ContentSource::default(),
+                path_os: path,
+            });
+        }
+        Ok(())
+    }
+
+    /// The file is only read when the bank is written, so it is not held in
+    /// memory until then.
+    ///
     /// * `kind` - type of the file
     /// * `file_name` - name of the file within the bank
     /// * `data_path` - location of the file that contains the data to include
     ///
     /// # Errors
     ///
-    /// Will return `Err` if the bank has already been written
+    /// Will return `Err` if the bank has already been written or the file
+    /// cannot be found.
     pub fn add_file<P: AsRef<Path>>(
         &mut self,
         kind: ItemKind,
         file_name: &OsStr,
         data_path: P,
     ) -> io::Result<()> {
-        let contents = fs::read(data_path)?;
-        self.add(kind, file_name, contents)
+        let data_path = data_path.as_ref();
+        // Fail now rather than part way through writing the bank.
+        fs::metadata(data_path)?;
+        self.add_source(kind, file_name, ContentSource::File(data_path.to_owned()))
     }
 
-    /// A default ID will be created if one is not provided.
+    /// A default ID will be created from the author and name if one is not
+    /// provided. When there is no author or name either, the ID is left empty
+    /// or generated when the bank is written, following
+    /// [`BankWriterOptions::id_on_empty`].
     ///
     /// # Errors
     ///
@@ -107,23 +458,9 @@ impl<WriterType: Write> BankWriter<WriterType> {
     pub fn add_metadata(&mut self, metadata: &Metadata) -> io::Result<()> {
         // Create the ID from the author and name if there isn't one.
         let contents = if metadata.id.is_empty() {
-            let mut id_parts = Vec::with_capacity(2);
-            let author_part = Metadata::sanitize_id(&metadata.author);
-            let name_part = Metadata::sanitize_id(&metadata.name);
-            if !author_part.is_empty() {
-                id_parts.push(author_part);
-            }
-            if !name_part.is_empty() {
-                id_parts.push(name_part);
-            }
             let metadata = Metadata {
-                version: metadata.version,
-                id: id_parts.join("."),
-                name: metadata.name.clone(),
-                author: metadata.author.clone(),
-                description: metadata.description.clone(),
-                hash: metadata.hash.clone(),
-                extra: metadata.extra.clone(),
+                id: metadata.effective_id(),
+                ..metadata.clone()
             };
 
             // Pretty-print the JSON to match what Bank Maker does. Bank
@@ -154,28 +491,64 @@ impl<WriterType: Write> BankWriter<WriterType> {
         // The file is written in one pass, without seeking backwards, to allow
         // the possibility of streaming the output.
         if self.written {
-            return Err(Error::new(
-                ErrorKind::Other,
-                "The bank has already been written",
-            ));
+            return Err(Error::other("The bank has already been written"));
         }
 
         // Include metadata if it hasn't been provided.
         if !self
             .items
             .iter()
-            .any(|item| item.kind == ItemKind::Metadata)
+            .any(|item| item.path_os.eq_ignore_ascii_case(Metadata::FILE_NAME))
         {
             debug!("Adding default metadata");
             self.add_metadata(&Metadata::default())?;
         }
 
-        let kinds = self
-            .items
+        if let Some(id_on_empty) = self.options.id_on_empty {
+            self.fill_empty_id(id_on_empty)?;
+        }
+
+        if self.options.x_compress_items {
+            #[cfg(feature = "compression")]
+            self.x_compress_items()?;
+            #[cfg(not(feature = "compression"))]
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "Compressing items needs the compression feature",
+            ));
+        }
+
+        let mut stamp = if self.options.stamp {
+            Some(self.start_stamp()?)
+        } else {
+            None
+        };
+
+        let entries = if self.options.preserve_order {
+            entries_in_added_order(&self.items)
+        } else {
+            entries_by_kind(&self.items, self.options.natural_order)
+        };
+
+        // The contents are written in the order of the entries, except that
+        // stamped metadata is last so it can include the hash of the others.
+        let mut order = (0..entries.len()).collect::<Vec<_>>();
+        if let Some(stamp) = &stamp {
+            order.sort_by_key(|index| entries[*index].1 == Some(stamp.position));
+        }
+
+        // The sizes are needed before any contents are written.
+        let sizes = entries
             .iter()
-            .map(|item| item.kind)
-            .collect::<BTreeSet<ItemKind>>();
-        debug!("Kinds of items in this bank are {:?}", kinds);
+            .map(|(_, item)| match item {
+                Some(position) => self.items[*position].contents.size(),
+                None => Ok(0),
+            })
+            .collect::<io::Result<Vec<u64>>>()?;
+
+        // Contents that aren't in memory are used up as they are written, so
+        // the bank cannot be written again even if writing fails.
+        self.written = true;
 
         // Header
         self.inner.write_all(FILE_ID)?;
@@ -183,9 +556,7 @@ impl<WriterType: Write> BankWriter<WriterType> {
         self.inner.write_all(FORMAT_VERSION)?;
 
         // Number of files and directories added to the bank.
-        let file_count = self.items.len();
-        let directory_count = kinds.iter().filter_map(ItemKind::directory).count();
-        let location_count = file_count + directory_count;
+        let location_count = entries.len();
         self.inner
             .write_u64::<LittleEndian>(location_count as u64)?;
         debug!("Number of location is {location_count}");
@@ -194,59 +565,37 @@ impl<WriterType: Write> BankWriter<WriterType> {
         let location_block_start =
             FILE_ID.len() + CORRUPTION_CHECK_BYTES.len() + FORMAT_VERSION.len() + size_of::<u64>();
 
-        let file_name_block_length: usize = kinds
-            .iter()
-            .map(|kind| {
-                // All the filenames and directory names for the kind.
-                let dir_name_len = kind.directory().map_or(0, |dir| dir.as_bytes().len() + 1);
-
-                let file_names_len = self
-                    .items
-                    .iter()
-                    .map(|item| {
-                        if item.kind == *kind {
-                            item.file_name_bytes().len() + 1
-                        } else {
-                            0
-                        }
-                    })
-                    .sum::<usize>();
-
-                file_names_len + dir_name_len
-            })
-            .sum();
+        // All the file names and directory names, each followed by a null.
+        let file_name_block_length: usize = entries.iter().map(|(name, _)| name.len() + 1).sum();
 
         let mut data_offset = (location_block_start
             + (location_count * Location::BLOCK_SIZE)
             + size_of::<u64>()
             + file_name_block_length) as u64;
+        let mut data_offsets = vec![0; entries.len()];
+        for index in &order {
+            data_offsets[*index] = data_offset;
+            data_offset += sizes[*index];
+        }
 
         // Locations
-        let mut file_name_block = Vec::new();
-        for kind in &kinds {
-            // Some kinds of items require a directory entry.
-            if let Some(directory) = kind.directory() {
-                debug!("Writing directory {directory}");
-                self.inner
-                    .write_u64::<LittleEndian>(file_name_block.len() as u64)?;
-                file_name_block.extend_from_slice(directory.as_bytes());
-                file_name_block.push(0_u8);
-
-                self.inner.write_u64::<LittleEndian>(0)?; // Data offset
-                self.inner.write_u64::<LittleEndian>(0)?; // Data size
-            }
-
-            for item in self.items.iter().filter(|item| item.kind == *kind) {
-                self.inner
-                    .write_u64::<LittleEndian>(file_name_block.len() as u64)?;
-                file_name_block.extend(item.file_name_bytes());
-                file_name_block.push(0_u8);
-
-                let contents_len = item.contents.len() as u64;
-                self.inner.write_u64::<LittleEndian>(data_offset)?;
-                self.inner.write_u64::<LittleEndian>(contents_len)?;
-                data_offset += contents_len;
+        let mut file_name_block = Vec::with_capacity(file_name_block_length);
+        for (((name, _), contents_len), data_offset) in
+            entries.iter().zip(&sizes).zip(&data_offsets)
+        {
+            self.inner
+                .write_u64::<LittleEndian>(file_name_block.len() as u64)?;
+            file_name_block.extend_from_slice(name);
+            file_name_block.push(0_u8);
+
+            // Directories have no contents so their data offset is zero.
+            if *contents_len == 0 {
+                debug!("Writing directory {}", name.escape_ascii());
+                self.inner.write_u64::<LittleEndian>(0)?;
+            } else {
+                self.inner.write_u64::<LittleEndian>(*data_offset)?;
             }
+            self.inner.write_u64::<LittleEndian>(*contents_len)?;
         }
 
         debug!("File name block length is {file_name_block_length}");
@@ -254,20 +603,398 @@ impl<WriterType: Write> BankWriter<WriterType> {
             .write_u64::<LittleEndian>(file_name_block_length as u64)?;
         self.inner.write_all(&file_name_block)?;
 
-        // Write the contents of each item.
-        for kind in kinds {
-            for item in self.items.iter().filter(|item| kind == item.kind) {
+        // Write the contents of each item, reading any that aren't in memory.
+        let cancel = self.options.cancel.as_ref();
+        if let Some(cancel) = cancel {
+            cancel.start(sizes.iter().filter(|size| **size > 0).count());
+        }
+        for index in order {
+            let ((name, item), contents_len) = (&entries[index], &sizes[index]);
+            if let (Some(position), 1..) = (item, contents_len) {
+                cancel::check(cancel)?;
+                if stamp
+                    .as_ref()
+                    .is_some_and(|stamp| stamp.position == *position)
+                {
+                    if let Some(stamp) = stamp.take() {
+                        let contents = stamp.finish()?;
+                        if contents.len() as u64 != *contents_len {
+                            let msg = "The provenance changed the size of the metadata";
+                            return Err(Error::new(ErrorKind::InvalidData, msg));
+                        }
+                        self.items[*position].contents = ContentSource::Bytes(contents);
+                    }
+                }
                 debug!(
-                    "Writing item {} ({} bytes)",
-                    item.path_os.to_string_lossy(),
-                    item.contents.len()
+                    "Writing item {} ({contents_len} bytes)",
+                    name.escape_ascii()
                 );
-                self.inner.write_all(&item.contents)?;
+                let contents = std::mem::take(&mut self.items[*position].contents);
+                contents
+                    .copy_to(*contents_len, &mut self.inner)
+                    .map_err(|error| {
+                        let msg = format!("Cannot write {}: {error}", name.escape_ascii());
+                        Error::new(error.kind(), msg)
+                    })?;
+                if let Some(cancel) = cancel {
+                    cancel.item_done();
+                }
             }
         }
 
         self.inner.flush()?;
-        self.written = true;
         Ok(())
     }
 }
+
+/// The metadata being stamped with [`BankWriterOptions::stamp`] while the
+/// files are hashed.
+struct Stamp {
+    /// Position of the metadata in the items.
+    position: usize,
+    metadata: Metadata,
+    provenance: Provenance,
+
+    /// Names and digests of the items, other than the files being hashed.
+    digests: Vec<(Vec<u8>, String)>,
+
+    /// Names of the files in the order they are hashed.
+    file_names: Vec<Vec<u8>>,
+    hashing: JoinHandle<io::Result<Vec<String>>>,
+}
+
+impl Stamp {
+    /// The contents of the metadata with the provenance of every item, once
+    /// the files are hashed.
+    fn finish(mut self) -> io::Result<Vec<u8>> {
+        let file_digests = self
+            .hashing
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))?;
+        self.digests
+            .extend(self.file_names.into_iter().zip(file_digests));
+        self.provenance.source_hash = hash::manifest_hash(self.digests);
+        debug!("Provenance is {:?}", self.provenance);
+        self.metadata.set_provenance(&self.provenance);
+        Ok(serde_json::to_vec_pretty(&self.metadata)?)
+    }
+}
+
+impl<WriterType: Write> BankWriter<WriterType> {
+    /// Start hashing the files for the provenance and give the metadata a
+    /// provenance of the same size to write the locations with. Contents that
+    /// aren't files are hashed now, keeping streams in memory.
+    fn start_stamp(&mut self) -> io::Result<Stamp> {
+        let Some(position) = self.items.iter().position(|item| {
+            item.kind == Some(ItemKind::Metadata)
+                && item.path_os.eq_ignore_ascii_case(Metadata::FILE_NAME)
+        }) else {
+            let msg = "Stamping the bank needs metadata to record the provenance";
+            return Err(Error::new(ErrorKind::InvalidInput, msg));
+        };
+        let mut metadata: Metadata = serde_json::from_slice(self.items[position].contents.load()?)?;
+
+        let mut digests = Vec::new();
+        let mut file_names = Vec::new();
+        let mut paths = Vec::new();
+        for item in &mut self.items {
+            if item.kind == Some(ItemKind::Metadata) || item.contents.size()? == 0 {
+                continue;
+            }
+            if let ContentSource::File(path) = &item.contents {
+                let name = path.file_name().unwrap_or_default();
+                file_names.push(name.to_string_lossy().as_bytes().to_owned());
+                paths.push(path.clone());
+            } else {
+                let name = Path::new(&item.path_os).file_name().unwrap_or_default();
+                let name = name.to_string_lossy().as_bytes().to_owned();
+                digests.push((name, hash::digest(item.contents.load()?)?));
+            }
+        }
+        let threads = self.options.hash_threads;
+        let hashing = thread::spawn(move || hash::digest_files(&paths, threads));
+
+        // Every source hash is the same length so the size of the metadata is
+        // known before the files are hashed.
+        let provenance = Provenance::new(hash::manifest_hash(Vec::new()));
+        metadata.set_provenance(&provenance);
+        self.items[position].contents = ContentSource::Bytes(serde_json::to_vec_pretty(&metadata)?);
+        Ok(Stamp {
+            position,
+            metadata,
+            provenance,
+            digests,
+            file_names,
+            hashing,
+        })
+    }
+
+    /// Give the metadata an ID following the policy if it doesn't have one.
+    fn fill_empty_id(&mut self, id_on_empty: IdOnEmpty) -> io::Result<()> {
+        let Some(position) = self.items.iter().position(|item| {
+            item.kind == Some(ItemKind::Metadata)
+                && item.path_os.eq_ignore_ascii_case(Metadata::FILE_NAME)
+        }) else {
+            return Ok(());
+        };
+        let mut metadata: Metadata = serde_json::from_slice(self.items[position].contents.load()?)?;
+        if !metadata.effective_id().is_empty() {
+            return Ok(());
+        }
+
+        // The items are sorted so the order they were added doesn't matter.
+        let mut items = Vec::new();
+        for (other, item) in self.items.iter().enumerate() {
+            let size = item.contents.size()?;
+            if other != position && size > 0 {
+                items.push((item.file_name_bytes(), size, other));
+            }
+        }
+        items.sort();
+        let mut hasher = Sha1::new();
+        for (path, size, other) in items {
+            hasher.update(&path);
+            hasher.update(&[0]);
+            hasher.update(&size.to_le_bytes());
+            self.items[other].contents.hash_into(&mut hasher)?;
+        }
+        let digest = hasher.digest();
+
+        metadata.id = match id_on_empty {
+            IdOnEmpty::Error => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "The bank has no ID and no author or name to make one from",
+                ))
+            }
+            IdOnEmpty::Uuid => {
+                // Version 5 and the RFC 4122 variant, like a UUID made from a
+                // name with SHA-1.
+                let mut bytes = [0_u8; 16];
+                bytes.copy_from_slice(&digest.bytes()[..16]);
+                bytes[6] = (bytes[6] & 0x0f) | 0x50;
+                bytes[8] = (bytes[8] & 0x3f) | 0x80;
+                bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+            }
+            IdOnEmpty::Hash => digest.to_string(),
+        };
+        debug!("Generated the ID {}", metadata.id);
+        self.items[position].contents = ContentSource::Bytes(serde_json::to_vec_pretty(&metadata)?);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "compression")]
+impl<WriterType: Write> BankWriter<WriterType> {
+    /// Compress the contents of the items and mark the metadata. The
+    /// contents of every item are read into memory to compress them.
+    ///
+    /// Without the mark the compressed items would be read as they are, so
+    /// it is an error if there is no metadata to mark.
+    fn x_compress_items(&mut self) -> io::Result<()> {
+        let mut marked = false;
+        for item in &mut self.items {
+            if item.contents.size()? == 0 {
+                continue;
+            }
+            let contents = if item.path_os.eq_ignore_ascii_case(Metadata::FILE_NAME) {
+                let mut metadata: Metadata = serde_json::from_slice(item.contents.load()?)?;
+                crate::compress::x_mark_compressed_items(&mut metadata)?;
+                marked = true;
+                serde_json::to_vec_pretty(&metadata)?
+            } else {
+                crate::compress::x_compress_item(item.contents.load()?)?
+            };
+            item.contents = ContentSource::Bytes(contents);
+        }
+        if !marked {
+            let msg = "Compressed items need metadata to mark the bank";
+            return Err(Error::new(ErrorKind::InvalidInput, msg));
+        }
+        Ok(())
+    }
+}
+
+/// Names and positions of the items grouped by kind, each kind preceded by
+/// its directory, the way Bank Maker writes them. Directories that don't
+/// belong to a kind are first. The items of each kind are in the order they
+/// were added unless `natural` sorts them with [`natural_cmp`].
+fn entries_by_kind(items: &[Item], natural: bool) -> Vec<(Vec<u8>, Option<usize>)> {
+    let kinds = items
+        .iter()
+        .filter_map(|item| item.kind)
+        .collect::<BTreeSet<ItemKind>>();
+    debug!("Kinds of items in this bank are {:?}", kinds);
+
+    // Directories that would otherwise be written twice are skipped.
+    let mut entries = items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| {
+            item.kind.is_none()
+                && !kinds.iter().any(|kind| {
+                    kind.directory()
+                        .is_some_and(|dir| item.path_os.eq_ignore_ascii_case(dir))
+                })
+        })
+        .map(|(position, item)| (item.file_name_bytes(), Some(position)))
+        .collect::<Vec<_>>();
+
+    for kind in kinds {
+        // Some kinds of items require a directory entry.
+        if let Some(directory) = kind.directory() {
+            entries.push((directory.as_bytes().to_owned(), None));
+        }
+        let start = entries.len();
+        entries.extend(
+            items
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| item.kind == Some(kind))
+                .map(|(position, item)| (item.file_name_bytes(), Some(position))),
+        );
+        if natural {
+            entries[start..].sort_by(|(a, _), (b, _)| natural_cmp(a, b));
+        }
+    }
+    entries
+}
+
+/// Names and positions of the items in the order they were added. The
+/// directory of a kind is added before the first item of that kind
+/// unless it was added explicitly.
+fn entries_in_added_order(items: &[Item]) -> Vec<(Vec<u8>, Option<usize>)> {
+    let mut directories = items
+        .iter()
+        .filter(|item| item.kind.is_none())
+        .map(|item| item.path_os.to_ascii_lowercase())
+        .collect::<BTreeSet<OsString>>();
+
+    let mut entries = Vec::with_capacity(items.len());
+    for (position, item) in items.iter().enumerate() {
+        if let Some(directory) = item.kind.and_then(|kind| kind.directory()) {
+            if directories.insert(OsString::from(directory)) {
+                entries.push((directory.as_bytes().to_owned(), None));
+            }
+        }
+        entries.push((item.file_name_bytes(), Some(position)));
+    }
+    entries
+}
+
+/// The path of an item as it is written to a bank, with any `\` replaced by
+/// `PATH_SEPARATOR` so every tool finds the intended directories.
+///
+/// # Errors
+///
+/// Will return `Err` if the path is empty, contains a null, starts with a
+/// separator or has a `..` component, any of which could make other tools
+/// fail to read the bank or extract outside of their destination.
+pub fn normalize_path(path: &OsStr) -> io::Result<OsString> {
+    let invalid = |reason: &str| {
+        let msg = format!("Path {} {reason}", path.to_string_lossy());
+        Err(Error::new(ErrorKind::InvalidInput, msg))
+    };
+    let normalized = path
+        .to_string_lossy()
+        .replace('\\', &PATH_SEPARATOR.to_string());
+    if normalized.is_empty() {
+        return invalid("is empty");
+    }
+    if normalized.contains('\0') {
+        return invalid("contains a null");
+    }
+    if normalized.starts_with(PATH_SEPARATOR) {
+        return invalid("starts with a separator");
+    }
+    if normalized
+        .split(PATH_SEPARATOR)
+        .any(|component| component == "..")
+    {
+        return invalid("refers to a parent directory");
+    }
+    Ok(OsString::from(normalized))
+}
+
+/// A file that only replaces the file at its path once it has been completely
+/// written, so a bank that fails or is cancelled part way through, such as
+/// with [`BankWriterOptions::cancel`], never leaves a partial bank behind.
+/// The contents are written to a temporary file next to the destination,
+/// which is removed if the file is dropped without being committed.
+pub struct AtomicFile {
+    /// `None` once committed.
+    file: Option<File>,
+    temp_path: PathBuf,
+    path: PathBuf,
+}
+
+impl AtomicFile {
+    /// Suffix added to the path of the destination for the temporary file.
+    pub const TEMP_SUFFIX: &'static str = ".partial";
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the temporary file cannot be created.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<AtomicFile> {
+        let path = path.as_ref().to_owned();
+        let mut temp_path = OsString::from(&path);
+        temp_path.push(Self::TEMP_SUFFIX);
+        let temp_path = PathBuf::from(temp_path);
+        Ok(AtomicFile {
+            file: Some(File::create(&temp_path)?),
+            temp_path,
+            path,
+        })
+    }
+
+    /// Where the contents are written until they are committed.
+    #[must_use]
+    pub fn temp_path(&self) -> &Path {
+        &self.temp_path
+    }
+
+    /// Replace the destination with everything written.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the contents cannot be saved or the destination
+    /// cannot be replaced, in which case the temporary file is removed.
+    pub fn commit(mut self) -> io::Result<()> {
+        // Until the file is taken, dropping it on error removes it.
+        if let Some(file) = self.file.as_mut() {
+            file.flush()?;
+            file.sync_all()?;
+        }
+        drop(self.file.take());
+        if let Err(error) = fs::rename(&self.temp_path, &self.path) {
+            let _ = fs::remove_file(&self.temp_path);
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    fn file(&mut self) -> io::Result<&mut File> {
+        self.file
+            .as_mut()
+            .ok_or_else(|| Error::other("The file has already been committed"))
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file()?.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            debug!("Removing partial file {}", self.temp_path.display());
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
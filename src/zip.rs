@@ -0,0 +1,366 @@
+//! Banks inside zip archives, which is how many vendors ship them, so a bank
+//! can be inspected without unzipping it first. Only what is needed to find
+//! and read an entry is supported: entries that are stored or compressed
+//! with Deflate, in archives that aren't split, encrypted or Zip64.
+
+use std::fs::File;
+use std::io;
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use flate2::read::DeflateDecoder;
+use flate2::CrcReader;
+use log::warn;
+
+use crate::catalog::BANK_EXTENSION;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_SIGNATURE: u32 = 0x0605_4b50;
+
+/// Length of the end of central directory record without the comment.
+const END_LEN: u64 = 22;
+
+/// Longest comment at the end of an archive.
+const MAX_COMMENT_LEN: u64 = u16::MAX as u64;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+/// A file in a zip archive.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+    /// Path of the file in the archive, with directories separated by `/`.
+    pub name: String,
+
+    /// Size of the file once it is decompressed.
+    pub size: u64,
+
+    compressed_size: u64,
+    method: u16,
+    flags: u16,
+    crc: u32,
+    local_header_offset: u64,
+}
+
+impl Entry {
+    /// The name ends with the extension of banks, ignoring ASCII case.
+    #[must_use]
+    pub fn is_bank(&self) -> bool {
+        self.name
+            .rsplit_once('.')
+            .is_some_and(|(_, extension)| extension.eq_ignore_ascii_case(BANK_EXTENSION))
+    }
+}
+
+/// The bytes start like a zip archive with at least one file.
+#[must_use]
+pub fn is_zip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&LOCAL_HEADER_SIGNATURE.to_le_bytes())
+}
+
+/// Every file in the archive, in the order of the central directory.
+///
+/// # Errors
+///
+/// Will return `Err` with [`ErrorKind::Unsupported`] for Zip64 archives, or
+/// with [`ErrorKind::InvalidData`] if the archive is not well formed.
+pub fn entries<R: Read + Seek>(archive: &mut R) -> io::Result<Vec<Entry>> {
+    let (count, directory_offset) = find_central_directory(archive)?;
+    archive.seek(SeekFrom::Start(directory_offset))?;
+    let mut entries = Vec::with_capacity(usize::from(count));
+    for _ in 0..count {
+        if archive.read_u32::<LittleEndian>()? != CENTRAL_HEADER_SIGNATURE {
+            return Err(invalid("Zip central directory is damaged"));
+        }
+        // Versions made by and needed to extract.
+        archive.seek(SeekFrom::Current(4))?;
+        let flags = archive.read_u16::<LittleEndian>()?;
+        let method = archive.read_u16::<LittleEndian>()?;
+        // Modification time and date.
+        archive.seek(SeekFrom::Current(4))?;
+        let crc = archive.read_u32::<LittleEndian>()?;
+        let compressed_size = archive.read_u32::<LittleEndian>()?;
+        let size = archive.read_u32::<LittleEndian>()?;
+        let name_len = archive.read_u16::<LittleEndian>()?;
+        let extra_len = archive.read_u16::<LittleEndian>()?;
+        let comment_len = archive.read_u16::<LittleEndian>()?;
+        // Disk number, internal and external attributes.
+        archive.seek(SeekFrom::Current(8))?;
+        let local_header_offset = archive.read_u32::<LittleEndian>()?;
+        let mut name = vec![0; usize::from(name_len)];
+        archive.read_exact(&mut name)?;
+        archive.seek(SeekFrom::Current(
+            i64::from(extra_len) + i64::from(comment_len),
+        ))?;
+
+        if [compressed_size, size, local_header_offset].contains(&u32::MAX) {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "Zip64 archives are not supported",
+            ));
+        }
+        entries.push(Entry {
+            name: String::from_utf8_lossy(&name).into_owned(),
+            size: u64::from(size),
+            compressed_size: u64::from(compressed_size),
+            method,
+            flags,
+            crc,
+            local_header_offset: u64::from(local_header_offset),
+        });
+    }
+    Ok(entries)
+}
+
+/// The decompressed contents of the file in the archive, in memory. Use
+/// [`open_entry`] for files that may be large, such as banks.
+///
+/// # Errors
+///
+/// Will return `Err` with [`ErrorKind::Unsupported`] if the file is
+/// encrypted or compressed with anything other than Deflate, or with
+/// [`ErrorKind::InvalidData`] if the contents are damaged.
+pub fn read_entry<R: Read + Seek>(archive: &mut R, entry: &Entry) -> io::Result<Vec<u8>> {
+    // The size is not trusted to allocate memory up front.
+    let mut contents = Vec::new();
+    let mut crc = CrcReader::new(decoder(archive, entry)?);
+    crc.read_to_end(&mut contents)
+        .map_err(|error| decode_error(entry, error))?;
+    check(entry, contents.len() as u64, crc.crc().sum())?;
+    Ok(contents)
+}
+
+/// Open the file in the archive for reading and seeking without holding it
+/// in memory. Stored files are read from the archive when needed, and are
+/// not checked against their CRC since that would read them in full. Files
+/// compressed with Deflate are decompressed into a temporary file first, up
+/// to the size given in the archive.
+///
+/// # Errors
+///
+/// Will return `Err` like [`read_entry`], or if the temporary file cannot be
+/// written.
+pub fn open_entry<R: Read + Seek>(mut archive: R, entry: &Entry) -> io::Result<EntryReader<R>> {
+    if entry.method == STORED {
+        let start = contents_offset(&mut archive, entry)?;
+        if entry.compressed_size != entry.size {
+            let msg = format!("{} is damaged in the zip archive", entry.name);
+            return Err(invalid(&msg));
+        }
+        return Ok(EntryReader(Source::Stored {
+            archive,
+            start,
+            len: entry.size,
+            position: 0,
+        }));
+    }
+
+    let mut temp = tempfile::tempfile()?;
+    let mut crc = CrcReader::new(decoder(&mut archive, entry)?);
+    let len = io::copy(&mut crc, &mut temp).map_err(|error| decode_error(entry, error))?;
+    check(entry, len, crc.crc().sum())?;
+    temp.seek(SeekFrom::Start(0))?;
+    Ok(EntryReader(Source::Decompressed(temp)))
+}
+
+/// The name and contents of the first bank in the archive. Vendors usually
+/// ship one bank in each archive. See [`open_entry`].
+///
+/// # Errors
+///
+/// Will return `Err` with [`ErrorKind::NotFound`] if there is no bank in the
+/// archive, or if the archive or the bank cannot be read.
+pub fn open_bank<R: Read + Seek>(mut archive: R) -> io::Result<(String, EntryReader<R>)> {
+    let banks = entries(&mut archive)?
+        .into_iter()
+        .filter(Entry::is_bank)
+        .collect::<Vec<_>>();
+    let Some(bank) = banks.first() else {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            "No bank was found in the zip archive",
+        ));
+    };
+    if banks.len() > 1 {
+        warn!(
+            "The zip archive has {} banks, reading {}",
+            banks.len(),
+            bank.name
+        );
+    }
+    Ok((bank.name.clone(), open_entry(archive, bank)?))
+}
+
+/// The contents of a file in a zip archive, from [`open_entry`].
+#[derive(Debug)]
+pub struct EntryReader<R>(Source<R>);
+
+#[derive(Debug)]
+enum Source<R> {
+    /// Read from the archive between `start` and `start + len`.
+    Stored {
+        archive: R,
+        start: u64,
+        len: u64,
+        position: u64,
+    },
+    Decompressed(File),
+}
+
+impl<R: Read + Seek> Read for EntryReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.0 {
+            Source::Stored {
+                archive,
+                start,
+                len,
+                position,
+            } => {
+                let remaining = len.saturating_sub(*position);
+                let wanted = usize::try_from(remaining).map_or(buf.len(), |r| r.min(buf.len()));
+                if wanted == 0 {
+                    return Ok(0);
+                }
+                archive.seek(SeekFrom::Start(*start + *position))?;
+                let read = archive.read(&mut buf[..wanted])?;
+                *position += read as u64;
+                Ok(read)
+            }
+            Source::Decompressed(file) => file.read(buf),
+        }
+    }
+}
+
+impl<R: Read + Seek> Seek for EntryReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match &mut self.0 {
+            Source::Stored { len, position, .. } => {
+                let target = match pos {
+                    SeekFrom::Start(offset) => Some(offset),
+                    SeekFrom::End(offset) => len.checked_add_signed(offset),
+                    SeekFrom::Current(offset) => position.checked_add_signed(offset),
+                };
+                *position = target.ok_or_else(|| {
+                    Error::new(ErrorKind::InvalidInput, "Cannot seek before the start")
+                })?;
+                Ok(*position)
+            }
+            Source::Decompressed(file) => file.seek(pos),
+        }
+    }
+}
+
+/// Where the contents of the file start, after its local header.
+fn contents_offset<R: Read + Seek>(archive: &mut R, entry: &Entry) -> io::Result<u64> {
+    if entry.flags & 1 != 0 {
+        let msg = format!("{} is encrypted in the zip archive", entry.name);
+        return Err(Error::new(ErrorKind::Unsupported, msg));
+    }
+
+    archive.seek(SeekFrom::Start(entry.local_header_offset))?;
+    if archive.read_u32::<LittleEndian>()? != LOCAL_HEADER_SIGNATURE {
+        let msg = format!("Zip archive is damaged at {}", entry.name);
+        return Err(invalid(&msg));
+    }
+    // Everything up to the lengths is repeated in the central directory.
+    archive.seek(SeekFrom::Current(22))?;
+    let name_len = archive.read_u16::<LittleEndian>()?;
+    let extra_len = archive.read_u16::<LittleEndian>()?;
+    archive.seek(SeekFrom::Current(
+        i64::from(name_len) + i64::from(extra_len),
+    ))
+}
+
+/// A reader of the decompressed contents, which stops one byte past the size
+/// given in the archive so larger contents are noticed without reading them
+/// all.
+fn decoder<'a, R: Read + Seek + 'a>(
+    archive: &'a mut R,
+    entry: &Entry,
+) -> io::Result<Box<dyn Read + 'a>> {
+    contents_offset(archive, entry)?;
+    let compressed = archive.take(entry.compressed_size);
+    let limit = entry.size.saturating_add(1);
+    Ok(match entry.method {
+        STORED => Box::new(compressed.take(limit)),
+        DEFLATED => Box::new(DeflateDecoder::new(compressed).take(limit)),
+        method => {
+            let msg = format!(
+                "{} uses compression method {method} which is not supported",
+                entry.name
+            );
+            return Err(Error::new(ErrorKind::Unsupported, msg));
+        }
+    })
+}
+
+/// Check the contents that were read match the size and CRC in the archive.
+fn check(entry: &Entry, len: u64, crc: u32) -> io::Result<()> {
+    if len != entry.size || crc != entry.crc {
+        let msg = format!("{} is damaged in the zip archive", entry.name);
+        return Err(invalid(&msg));
+    }
+    Ok(())
+}
+
+/// Errors reported by the decoder for data that isn't Deflate are damage to
+/// the file.
+fn decode_error(entry: &Entry, error: Error) -> Error {
+    match error.kind() {
+        ErrorKind::InvalidInput => invalid(&format!(
+            "{} is damaged in the zip archive: {error}",
+            entry.name
+        )),
+        _ => error,
+    }
+}
+
+/// The number of entries and where the central directory starts, from the
+/// record at the end of the archive.
+fn find_central_directory<R: Read + Seek>(archive: &mut R) -> io::Result<(u16, u64)> {
+    let len = archive.seek(SeekFrom::End(0))?;
+    if len < END_LEN {
+        return Err(invalid("Zip archive is too short"));
+    }
+    let search_len = len.min(END_LEN + MAX_COMMENT_LEN);
+    archive.seek(SeekFrom::Start(len - search_len))?;
+    let mut tail = Vec::new();
+    archive.take(search_len).read_to_end(&mut tail)?;
+
+    // The comment may contain the signature so search from the end.
+    let signature = END_SIGNATURE.to_le_bytes();
+    let last = usize::try_from(END_LEN)
+        .ok()
+        .and_then(|end_len| tail.len().checked_sub(end_len))
+        .ok_or_else(|| invalid("Zip archive is too short"))?;
+    let end = (0..=last)
+        .rev()
+        .find(|&position| tail[position..].starts_with(&signature))
+        .ok_or_else(|| invalid("Zip archive has no central directory"))?;
+
+    let mut record = &tail[end + 4..];
+    let disk = record.read_u16::<LittleEndian>()?;
+    let directory_disk = record.read_u16::<LittleEndian>()?;
+    let _disk_count = record.read_u16::<LittleEndian>()?;
+    let count = record.read_u16::<LittleEndian>()?;
+    let _directory_size = record.read_u32::<LittleEndian>()?;
+    let directory_offset = record.read_u32::<LittleEndian>()?;
+    if disk != 0 || directory_disk != 0 {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "Split zip archives are not supported",
+        ));
+    }
+    if count == u16::MAX || directory_offset == u32::MAX {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "Zip64 archives are not supported",
+        ));
+    }
+    Ok((count, u64::from(directory_offset)))
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
@@ -0,0 +1,88 @@
+//! Test curation notes about the items of a bank.
+#![cfg(feature = "std")]
+
+use std::io::Cursor;
+use std::path::Path;
+
+use assert_fs::prelude::*;
+
+use kibank::annotations::{Annotations, FILE_NAME};
+use kibank::create::Collected;
+use kibank::read::BankReader;
+use kibank::write::BankWriter;
+
+const JSON: &str = r#"{
+    "phaseplant/Bass.phaseplant": {"tags": ["bass", "dark"], "category": "Bass"},
+    "samples/Kick.wav": {"demos": ["Track 3"]}
+}"#;
+
+#[test]
+fn accessors() {
+    let annotations = Annotations::parse(JSON.as_bytes()).unwrap();
+    assert_eq!(
+        annotations.tags("phaseplant/Bass.phaseplant"),
+        vec!["bass", "dark"]
+    );
+    assert_eq!(
+        annotations.category("phaseplant/Bass.phaseplant"),
+        Some("Bass")
+    );
+    assert!(annotations.tags("samples/Kick.wav").is_empty());
+    assert_eq!(annotations.category("samples/Kick.wav"), None);
+    assert!(annotations.get("samples/Snare.wav").is_none());
+
+    let json = annotations.to_json().unwrap();
+    assert_eq!(Annotations::parse(&json).unwrap(), annotations);
+
+    assert!(Annotations::parse(b"[]").is_err());
+}
+
+#[test]
+fn sidecar_path() {
+    assert_eq!(
+        Annotations::sidecar_path("dir/My Bank.bank"),
+        Path::new("dir/My Bank.bank.annotations.json")
+    );
+}
+
+#[test]
+fn created_bank() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("Bass.phaseplant").write_str("bass").unwrap();
+    dir.child("index.json")
+        .write_str(r#"{"id": "", "name": "Annotated", "author": "", "description": ""}"#)
+        .unwrap();
+    dir.child(FILE_NAME).write_str(JSON).unwrap();
+
+    let classified = Collected::new([dir.path()]).classify();
+    assert!(classified.annotations.is_some());
+    assert!(classified.metadata_file.is_some());
+    assert!(classified.ignored.is_empty());
+
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    classified.keep_metadata().add_to(&mut writer).unwrap();
+    writer.write().unwrap();
+
+    let mut reader = BankReader::new(Cursor::new(out)).unwrap();
+    let annotations = Annotations::read(&mut reader).unwrap().unwrap();
+    assert_eq!(annotations, Annotations::parse(JSON.as_bytes()).unwrap());
+}
+
+#[test]
+fn invalid_annotations() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("Bass.phaseplant").write_str("bass").unwrap();
+    dir.child(FILE_NAME).write_str("not json").unwrap();
+
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    let merged = Collected::new([dir.path()]).classify().keep_metadata();
+    assert!(merged.add_to(&mut writer).is_err());
+}
+
+#[test]
+fn no_annotations() {
+    let mut reader = BankReader::open("tests/blank.bank").unwrap();
+    assert!(Annotations::read(&mut reader).unwrap().is_none());
+}
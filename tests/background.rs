@@ -1,6 +1,9 @@
+#![cfg(feature = "std")]
+
 use std::ffi::OsStr;
 use std::io::Cursor;
 
+use kibank::background::{self, BackgroundDetails, ImageFormat};
 use kibank::read::BankReader;
 use kibank::write::BankWriter;
 use kibank::{ItemKind, BACKGROUND_FILE_STEM};
@@ -25,5 +28,134 @@ fn png() {
     let items = reader.items();
     let item = items.first().unwrap();
     assert!(item.is_background_file());
-    assert_eq!(item.path_bytes, file_name.as_bytes());
+    assert_eq!(item.path_bytes(), file_name.as_bytes());
+}
+
+#[test]
+fn dimensions() {
+    let png = std::fs::read("tests/images/background.png").unwrap();
+    assert_eq!(ImageFormat::detect(&png), Some(ImageFormat::Png));
+    assert_eq!(background::dimensions(&png), Some((173, 76)));
+
+    let jpeg = std::fs::read("tests/images/background.jpg").unwrap();
+    assert_eq!(ImageFormat::detect(&jpeg), Some(ImageFormat::Jpeg));
+    assert_eq!(background::dimensions(&jpeg), Some((151, 65)));
+}
+
+#[test]
+fn details() {
+    let mut out = Vec::with_capacity(512);
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add_file(
+            ItemKind::Background,
+            OsStr::new("background.png"),
+            "tests/images/background.png",
+        )
+        .unwrap();
+    writer.write().unwrap();
+
+    let mut reader = BankReader::new(Cursor::new(out)).unwrap();
+    let details = BackgroundDetails::of_bank(&mut reader).unwrap().unwrap();
+    assert_eq!(details.path, "background.png");
+    assert_eq!(
+        details.size,
+        std::fs::metadata("tests/images/background.png")
+            .unwrap()
+            .len()
+    );
+    assert_eq!(details.format, Some(ImageFormat::Png));
+    assert_eq!(details.dimensions, Some((173, 76)));
+
+    let mut out = Vec::with_capacity(512);
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add(ItemKind::Sample, OsStr::new("kick.wav"), vec![1, 2, 3])
+        .unwrap();
+    writer.write().unwrap();
+    let mut reader = BankReader::new(Cursor::new(out)).unwrap();
+    assert_eq!(BackgroundDetails::of_bank(&mut reader).unwrap(), None);
+}
+
+#[test]
+fn read_contents_into() {
+    let mut out = Vec::with_capacity(512);
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add_file(
+            ItemKind::Background,
+            OsStr::new("background.png"),
+            "tests/images/background.png",
+        )
+        .unwrap();
+    writer.write().unwrap();
+
+    let mut reader = BankReader::new(Cursor::new(out)).unwrap();
+    let mut buffer = vec![1_u8; 10_000];
+    for item in reader.items() {
+        reader.read_contents_into(&item, &mut buffer).unwrap();
+        assert_eq!(buffer, reader.read_contents(&item).unwrap());
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn placeholder() {
+    use std::io::Read;
+
+    use kibank::background::placeholder;
+
+    let image = placeholder::render("Preset 2 & More", 400, 200).unwrap();
+    assert_eq!(ImageFormat::detect(&image), Some(ImageFormat::Png));
+    assert_eq!(background::dimensions(&image), Some((400, 200)));
+
+    // Checksum of an IEND chunk is always the same.
+    assert!(image.ends_with(&[0xae, 0x42, 0x60, 0x82]));
+
+    // The pixels decompress to a filter byte and three bytes per pixel for
+    // each row, with some of the text drawn in white.
+    let data = &image[41..image.len() - 16];
+    let mut pixels = Vec::new();
+    flate2::read::ZlibDecoder::new(data)
+        .read_to_end(&mut pixels)
+        .unwrap();
+    assert_eq!(pixels.len(), 200 * (1 + 400 * 3));
+    assert!(pixels.windows(3).any(|pixel| pixel == [0xff; 3]));
+
+    // Text too long for the image is cut short rather than failing.
+    let image = placeholder::render(&"x".repeat(1000), 10, 10).unwrap();
+    assert_eq!(background::dimensions(&image), Some((10, 10)));
+
+    // Sizes without pixels or too large to address fail instead of panicking.
+    assert!(placeholder::render("Empty", 0, 10).is_err());
+    assert!(placeholder::render("Empty", 10, 0).is_err());
+    assert!(placeholder::render("Huge", u32::MAX, 2).is_err());
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn thumbnail() {
+    use std::io::ErrorKind;
+
+    use kibank::background::{placeholder, thumbnail};
+
+    let image = placeholder::render("Thumbnail", 400, 200).unwrap();
+    let small = thumbnail::thumbnail(&image, thumbnail::SIZE).unwrap();
+    assert_eq!(background::dimensions(&small), Some((256, 128)));
+
+    // Images already small enough keep their size.
+    let image = include_bytes!("images/background.png");
+    let small = thumbnail::thumbnail(image, thumbnail::SIZE).unwrap();
+    assert_eq!(
+        background::dimensions(&small),
+        background::dimensions(image)
+    );
+    let small = thumbnail::thumbnail(image, 16).unwrap();
+    assert_eq!(
+        background::dimensions(&small).map(|(width, _)| width),
+        Some(16)
+    );
+
+    let error = thumbnail::thumbnail(include_bytes!("images/background.jpg"), 16).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
 }
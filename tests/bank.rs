@@ -0,0 +1,312 @@
+//! Test editing banks in memory.
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::{Cursor, ErrorKind};
+
+use assert_fs::prelude::*;
+
+use kibank::bank::{self, Bank, ReadOnlyBank};
+use kibank::read::BankReader;
+use kibank::write::{AtomicFile, BankWriter, BankWriterOptions};
+use kibank::{ItemKind, Metadata};
+
+fn written(bank: &Bank) -> BankReader<'static, Cursor<Vec<u8>>> {
+    let mut out = Vec::new();
+    bank.clone()
+        .write(Cursor::new(&mut out), BankWriterOptions::default())
+        .unwrap();
+    BankReader::new(Cursor::new(out)).unwrap()
+}
+
+#[test]
+fn read_modify_write() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add_metadata(&Metadata {
+            id: "me.drums".to_string(),
+            name: "Drums".to_string(),
+            author: "Me".to_string(),
+            ..Metadata::default()
+        })
+        .unwrap();
+    writer
+        .add(ItemKind::Sample, OsStr::new("kick.wav"), b"kick".to_vec())
+        .unwrap();
+    writer
+        .add(ItemKind::Sample, OsStr::new("snare.wav"), b"snare".to_vec())
+        .unwrap();
+    writer.add_directory(OsStr::new("extras")).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let mut bank = Bank::from_reader(&mut BankReader::new(Cursor::new(out)).unwrap()).unwrap();
+    assert_eq!(bank.metadata.name, "Drums");
+    assert_eq!(bank.files().len(), 2);
+    assert_eq!(bank.directories(), ["extras"]);
+    assert_eq!(bank.get("SAMPLES/kick.wav").unwrap().contents, b"kick");
+
+    bank.metadata.name = "More Drums".to_string();
+    bank.add(ItemKind::Sample, "hat.wav", b"hat".to_vec())
+        .unwrap();
+    assert_eq!(bank.remove("samples/snare.wav").unwrap().contents, b"snare");
+    assert!(bank.remove("samples/snare.wav").is_none());
+    bank.rename("samples/kick.wav", "samples/big kick.wav")
+        .unwrap();
+    bank.get_mut("samples/hat.wav").unwrap().contents = b"open hat".to_vec();
+
+    let mut reader = written(&bank);
+    let paths = reader
+        .items()
+        .iter()
+        .map(|item| item.file_name_lossy())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        [
+            "extras",
+            "index.json",
+            "samples",
+            "samples/big kick.wav",
+            "samples/hat.wav"
+        ]
+    );
+    let metadata_item = reader.get("index.json").unwrap().clone();
+    assert_eq!(
+        reader.read_metadata(&metadata_item).unwrap().name,
+        "More Drums"
+    );
+    let hat = reader.get("samples/hat.wav").unwrap().clone();
+    assert_eq!(reader.read_contents(&hat).unwrap(), b"open hat");
+}
+
+#[test]
+fn conflicts() {
+    let mut bank = Bank::default();
+    bank.add(ItemKind::Sample, "kick.wav", b"kick".to_vec())
+        .unwrap();
+    bank.add(ItemKind::Sample, "snare.wav", b"snare".to_vec())
+        .unwrap();
+
+    let err = bank
+        .add(ItemKind::Sample, "KICK.wav", b"other".to_vec())
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    let err = bank
+        .rename("samples/kick.wav", "samples/snare.wav")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    let err = bank.rename("samples/hat.wav", "samples/x.wav").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert!(bank
+        .add_with_path(ItemKind::Metadata, "index.json", b"{}".to_vec())
+        .is_err());
+    assert!(bank
+        .add_with_path(ItemKind::Sample, "../kick.wav", b"kick".to_vec())
+        .is_err());
+
+    // Changing only the case is not a conflict with itself.
+    bank.rename("samples/kick.wav", "samples/Kick.wav").unwrap();
+    assert_eq!(bank.files()[0].path, "samples/Kick.wav");
+}
+
+#[test]
+fn save() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let path = temp.path().join("new.bank");
+    let mut bank = Bank::new(Metadata {
+        name: "New".to_string(),
+        author: "Me".to_string(),
+        ..Metadata::default()
+    });
+    bank.add(
+        ItemKind::PhasePlantPreset,
+        "Lead.phaseplant",
+        b"lead".to_vec(),
+    )
+    .unwrap();
+    bank.save(&path, BankWriterOptions::default()).unwrap();
+
+    let mut reopened = Bank::open(&path).unwrap();
+    assert_eq!(reopened.metadata.id, "me.new");
+    assert_eq!(reopened.files()[0].path, "phaseplant/Lead.phaseplant");
+
+    // Saving over the bank it was read from.
+    reopened.remove("phaseplant/Lead.phaseplant");
+    reopened.save(&path, BankWriterOptions::default()).unwrap();
+    assert!(Bank::open(&path).unwrap().files().is_empty());
+}
+
+/// Banks marked read-only are not replaced, and read-only banks cannot be
+/// changed except by making a copy.
+#[test]
+fn read_only() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let path = temp.path().join("master.bank");
+    let mut bank = Bank::default();
+    bank.add(ItemKind::Sample, "kick.wav", b"kick".to_vec())
+        .unwrap();
+    bank.clone()
+        .save(&path, BankWriterOptions::default())
+        .unwrap();
+
+    std::fs::write(bank::read_only_marker(&path), "").unwrap();
+    assert_eq!(
+        bank::read_only_marker(&path),
+        temp.path().join("master.bank.read-only")
+    );
+    let error = bank.save(&path, BankWriterOptions::default()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+
+    let master = ReadOnlyBank::open(&path).unwrap();
+    assert_eq!(master.get("samples/kick.wav").unwrap().contents, b"kick");
+    let mut copy = master.to_bank();
+    copy.remove("samples/kick.wav");
+    assert!(copy.files().is_empty());
+    assert_eq!(master.files().len(), 1);
+}
+
+/// Files are added with the kind found from their path.
+#[test]
+fn add_file() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let lead = temp.child("Lead.phaseplant");
+    lead.write_str("lead").unwrap();
+    let cover = temp.child("cover.png");
+    cover
+        .write_binary(&std::fs::read("tests/images/background.png").unwrap())
+        .unwrap();
+    let notes = temp.child("notes.xyz");
+    notes.write_str("notes").unwrap();
+    let empty = temp.child("Empty.phaseplant");
+    empty.touch().unwrap();
+
+    let mut bank = Bank::default();
+    assert_eq!(
+        bank.add_file(lead.path()).unwrap(),
+        "phaseplant/Lead.phaseplant"
+    );
+    assert_eq!(bank.add_file(cover.path()).unwrap(), "background.png");
+    assert_eq!(
+        bank.add_file(cover.path()).unwrap_err().kind(),
+        ErrorKind::AlreadyExists
+    );
+    assert_eq!(
+        bank.add_file(lead.path()).unwrap_err().kind(),
+        ErrorKind::AlreadyExists
+    );
+    assert_eq!(
+        bank.add_file(notes.path()).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+    assert_eq!(
+        bank.add_file(empty.path()).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+    assert_eq!(bank.files().len(), 2);
+}
+
+#[test]
+fn path_matches() {
+    assert!(bank::path_matches("samples/*.wav", "samples/kick.wav"));
+    assert!(bank::path_matches("SAMPLES/*.WAV", "samples/kick.wav"));
+    assert!(bank::path_matches("samples/*", "samples/kick.wav"));
+    assert!(bank::path_matches("samples/k?ck.wav", "samples/kick.wav"));
+    assert!(bank::path_matches("*/kick*", "samples/kick.wav"));
+    assert!(bank::path_matches("samples/kick.wav", "samples/kick.wav"));
+    assert!(!bank::path_matches(
+        "samples/*.wav",
+        "samples/drums/kick.wav"
+    ));
+    assert!(!bank::path_matches("samples/*.wav", "samples/kick.aif"));
+    assert!(!bank::path_matches("samples?kick.wav", "samples/kick.wav"));
+    assert!(!bank::path_matches("samples", "samples/kick.wav"));
+}
+
+#[test]
+fn remove_matching() {
+    let mut bank = Bank::default();
+    bank.add(ItemKind::Sample, "kick.wav", b"kick".to_vec())
+        .unwrap();
+    bank.add(ItemKind::Sample, "snare.aif", b"snare".to_vec())
+        .unwrap();
+    bank.add_with_path(ItemKind::Sample, "samples/drums/hat.wav", b"hat".to_vec())
+        .unwrap();
+    bank.add(
+        ItemKind::PhasePlantPreset,
+        "Lead.phaseplant",
+        b"lead".to_vec(),
+    )
+    .unwrap();
+    bank.add_directory("extras/docs").unwrap();
+    bank.add_directory("extras").unwrap();
+    bank.add_with_path(ItemKind::Sample, "extras/docs/kick.wav", b"kick".to_vec())
+        .unwrap();
+
+    assert_eq!(bank.remove_matching("samples/*.wav"), ["samples/kick.wav"]);
+    assert!(bank.remove_matching("samples/*.wav").is_empty());
+    assert_eq!(
+        bank.remove_matching("EXTRAS"),
+        ["extras/docs", "extras", "extras/docs/kick.wav"]
+    );
+    assert!(bank.directories().is_empty());
+
+    let paths: Vec<&str> = bank.files().iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "samples/snare.aif",
+            "samples/drums/hat.wav",
+            "phaseplant/Lead.phaseplant"
+        ]
+    );
+}
+
+/// Only the paths of a bank are read for adding and removing files, and the
+/// remaining files are copied from the bank as it is replaced.
+#[test]
+fn copy_to() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let path = temp.path().join("drums.bank");
+    let mut bank = Bank::default();
+    bank.add(ItemKind::Sample, "kick.wav", b"kick".to_vec())
+        .unwrap();
+    bank.add(ItemKind::Sample, "snare.wav", b"snare".to_vec())
+        .unwrap();
+    bank.save(&path, BankWriterOptions::default()).unwrap();
+
+    let mut reader = BankReader::open(&path).unwrap();
+    let mut bank = Bank::paths_from_reader(&mut reader).unwrap();
+    assert!(bank.get("samples/kick.wav").unwrap().contents.is_empty());
+    bank.remove("samples/snare.wav").unwrap();
+    bank.add(ItemKind::Sample, "hat.wav", b"hat".to_vec())
+        .unwrap();
+    let mut file = AtomicFile::create(&path).unwrap();
+    let mut writer = BankWriter::new(&mut file);
+    bank.copy_to(&mut reader, &mut writer).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    file.commit().unwrap();
+
+    let bank = Bank::open(&path).unwrap();
+    let files: Vec<(&str, &[u8])> = bank
+        .files()
+        .iter()
+        .map(|file| (file.path.as_str(), file.contents.as_slice()))
+        .collect();
+    assert_eq!(
+        files,
+        [
+            ("samples/kick.wav", b"kick".as_slice()),
+            ("samples/hat.wav", b"hat".as_slice())
+        ]
+    );
+
+    // Files without contents must be in the bank that is read.
+    let mut bank = Bank::default();
+    bank.add(ItemKind::Sample, "tom.wav", Vec::new()).unwrap();
+    let mut writer = BankWriter::new(Vec::new());
+    let error = bank.copy_to(&mut reader, &mut writer).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::NotFound);
+}
@@ -1,7 +1,14 @@
-use std::io::Cursor;
+#![cfg(feature = "std")]
 
-use kibank::write::BankWriter;
-use kibank::Metadata;
+use std::ffi::OsStr;
+use std::io::{Cursor, ErrorKind};
+
+use assert_fs::prelude::*;
+
+use kibank::hash;
+use kibank::read::BankReader;
+use kibank::write::{BankWriter, BankWriterOptions, ContentSource, IdOnEmpty};
+use kibank::{ItemKind, Metadata};
 
 /// Must not be able to add to a bank once it has been written.
 #[test]
@@ -12,3 +19,198 @@ fn add_after_write() {
     let result = writer.add_metadata(&Metadata::default());
     assert!(result.is_err());
 }
+
+/// Files and streams are only read when the bank is written.
+#[test]
+fn content_sources() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let kick = dir.child("kick.wav");
+    kick.write_str("before").unwrap();
+
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add_file(ItemKind::Sample, OsStr::new("kick.wav"), kick.path())
+        .unwrap();
+    writer
+        .add_source(
+            ItemKind::Sample,
+            OsStr::new("snare.wav"),
+            ContentSource::Reader {
+                size: 5,
+                reader: Box::new(Cursor::new(b"snare and more".to_vec())),
+            },
+        )
+        .unwrap();
+    kick.write_str("after").unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let mut reader = BankReader::new(Cursor::new(out)).unwrap();
+    let item = reader.get("samples/kick.wav").unwrap().clone();
+    assert_eq!(reader.read_contents(&item).unwrap(), b"after");
+    let item = reader.get("samples/snare.wav").unwrap().clone();
+    assert_eq!(reader.read_contents(&item).unwrap(), b"snare");
+
+    // Missing files are noticed when they are added.
+    let mut writer = BankWriter::new(Vec::new());
+    let error = writer
+        .add_file(
+            ItemKind::Sample,
+            OsStr::new("hat.wav"),
+            dir.path().join("hat.wav"),
+        )
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::NotFound);
+}
+
+/// A stream shorter than its size fails the write instead of writing a
+/// damaged bank.
+#[test]
+fn short_reader() {
+    let mut writer = BankWriter::new(Vec::new());
+    writer
+        .add_source(
+            ItemKind::Sample,
+            OsStr::new("kick.wav"),
+            ContentSource::Reader {
+                size: 100,
+                reader: Box::new(Cursor::new(b"kick".to_vec())),
+            },
+        )
+        .unwrap();
+    let error = writer.write().unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    assert!(error.to_string().contains("samples/kick.wav"));
+}
+
+/// Generated IDs don't depend on where the contents come from.
+#[test]
+fn id_from_sources() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let kick = dir.child("kick.wav");
+    kick.write_str("kick").unwrap();
+
+    let id = |source: ContentSource| {
+        let mut out = Vec::new();
+        let options = BankWriterOptions {
+            id_on_empty: Some(IdOnEmpty::Hash),
+            ..BankWriterOptions::default()
+        };
+        let mut writer = BankWriter::with_options(Cursor::new(&mut out), options);
+        writer
+            .add_source(ItemKind::Sample, OsStr::new("kick.wav"), source)
+            .unwrap();
+        writer.write().unwrap();
+        drop(writer);
+        let mut reader = BankReader::new(Cursor::new(out)).unwrap();
+        let item = reader.get(Metadata::FILE_NAME).unwrap().clone();
+        reader.read_metadata(&item).unwrap().id
+    };
+    let from_bytes = id(ContentSource::Bytes(b"kick".to_vec()));
+    assert!(!from_bytes.is_empty());
+    assert_eq!(id(ContentSource::File(kick.path().to_owned())), from_bytes);
+    assert_eq!(
+        id(ContentSource::Reader {
+            size: 4,
+            reader: Box::new(Cursor::new(b"kick".to_vec())),
+        }),
+        from_bytes
+    );
+}
+
+/// The provenance hashes the files while the other items are written, so the
+/// metadata is written last, and the hash doesn't depend on the threads.
+#[test]
+fn stamp() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let kick = dir.child("kick.wav");
+    kick.write_str("kick").unwrap();
+    let snare = dir.child("snare.wav");
+    snare.write_str("snare").unwrap();
+    let expected = hash::manifest_hash(vec![
+        (b"kick.wav".to_vec(), hash::digest(&b"kick"[..]).unwrap()),
+        (b"snare.wav".to_vec(), hash::digest(&b"snare"[..]).unwrap()),
+        (b"hat.wav".to_vec(), hash::digest(&b"hat"[..]).unwrap()),
+    ]);
+
+    for hash_threads in [0, 1, 3] {
+        let mut out = Vec::new();
+        let options = BankWriterOptions {
+            stamp: true,
+            hash_threads,
+            ..BankWriterOptions::default()
+        };
+        let mut writer = BankWriter::with_options(Cursor::new(&mut out), options);
+        writer
+            .add_metadata(&Metadata {
+                name: "Drums".to_string(),
+                ..Metadata::default()
+            })
+            .unwrap();
+        for file in [&kick, &snare] {
+            let name = file.path().file_name().unwrap();
+            writer
+                .add_file(ItemKind::Sample, name, file.path())
+                .unwrap();
+        }
+        writer
+            .add(ItemKind::Sample, OsStr::new("hat.wav"), b"hat".to_vec())
+            .unwrap();
+        writer.write().unwrap();
+        drop(writer);
+
+        let mut reader = BankReader::new(Cursor::new(out)).unwrap();
+        let last = reader.items_in_data_order().pop().unwrap();
+        assert_eq!(last.path_bytes(), Metadata::FILE_NAME.as_bytes());
+        let metadata = reader.read_metadata(&last).unwrap();
+        assert_eq!(metadata.name, "Drums");
+        assert_eq!(metadata.provenance().unwrap().source_hash, expected);
+        let item = reader.get("samples/snare.wav").unwrap().clone();
+        assert_eq!(reader.read_contents(&item).unwrap(), b"snare");
+    }
+}
+
+/// Generated contents are written during the final pass and must match the
+/// declared size.
+#[test]
+fn add_lazy() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add_lazy(ItemKind::Sample, OsStr::new("tone.wav"), 6, |out| {
+            out.write_all(b"to")?;
+            out.write_all(b"ne!!")
+        })
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let mut reader = BankReader::new(Cursor::new(out)).unwrap();
+    let item = reader.get("samples/tone.wav").unwrap().clone();
+    assert_eq!(reader.read_contents(&item).unwrap(), b"tone!!");
+
+    for generated in [&b"tone"[..], b"tone and more"] {
+        let mut writer = BankWriter::new(Vec::new());
+        writer
+            .add_lazy(ItemKind::Sample, OsStr::new("tone.wav"), 6, move |out| {
+                out.write_all(generated)
+            })
+            .unwrap();
+        assert!(writer.write().is_err());
+    }
+}
+
+/// Compressing items is always an option but fails without the feature.
+#[cfg(not(feature = "compression"))]
+#[test]
+fn compress_items_unsupported() {
+    let options = BankWriterOptions {
+        x_compress_items: true,
+        ..BankWriterOptions::default()
+    };
+    let mut writer = BankWriter::with_options(Vec::new(), options);
+    writer
+        .add(ItemKind::Sample, OsStr::new("kick.wav"), b"kick".to_vec())
+        .unwrap();
+    assert_eq!(writer.write().unwrap_err().kind(), ErrorKind::Unsupported);
+}
@@ -1,5 +1,6 @@
 //! Test banks that are legal but do not have any contents. See `empty` for testing
 //! various forms of invalid incomplete banks.
+#![cfg(feature = "std")]
 
 use std::io;
 use std::io::{BufRead, Cursor, Read, Seek};
@@ -0,0 +1,121 @@
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+use std::path::Path;
+
+use kibank::cancel::{CancellationToken, Cancelled, Progress};
+use kibank::extract::{self, ExtractOptions};
+use kibank::read::BankReader;
+use kibank::verify::{verify, VerifyOptions};
+use kibank::write::{AtomicFile, BankWriter, BankWriterOptions};
+use kibank::ItemKind;
+
+fn bank(options: BankWriterOptions) -> std::io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut writer = BankWriter::with_options(&mut bytes, options);
+    writer.add(ItemKind::Sample, OsStr::new("one.wav"), vec![1; 100])?;
+    writer.add(ItemKind::Sample, OsStr::new("two.wav"), vec![2; 100])?;
+    writer.write()?;
+    Ok(bytes)
+}
+
+#[test]
+fn write() {
+    let cancel = CancellationToken::new();
+    let options = BankWriterOptions {
+        cancel: Some(cancel.clone()),
+        ..BankWriterOptions::default()
+    };
+    bank(options.clone()).unwrap();
+    assert_eq!(cancel.progress(), Progress { done: 3, total: 3 });
+
+    cancel.cancel();
+    let error = bank(options).unwrap_err();
+    let cancelled = Cancelled::from_error(&error).unwrap();
+    assert_eq!(cancelled.progress, Progress { done: 0, total: 3 });
+}
+
+#[test]
+fn verify_deep() {
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let mut reader =
+        BankReader::new(Cursor::new(bank(BankWriterOptions::default()).unwrap())).unwrap();
+    let options = VerifyOptions {
+        deep: true,
+        cancel: Some(cancel),
+        ..VerifyOptions::default()
+    };
+    let error = verify(&mut reader, &options).unwrap_err();
+    assert!(Cancelled::from_error(&error).is_some());
+}
+
+#[test]
+fn extract_all() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let mut reader =
+        BankReader::new(Cursor::new(bank(BankWriterOptions::default()).unwrap())).unwrap();
+    let cancel = CancellationToken::new();
+    let mut options = ExtractOptions {
+        cancel: Some(cancel.clone()),
+        ..ExtractOptions::default()
+    };
+    options.skip.push("samples/TWO.wav".to_string());
+
+    let report = extract::extract_all(&mut reader, dir.path(), &options).unwrap();
+    assert_eq!(report.extracted.len(), 2);
+    assert_eq!(cancel.progress(), Progress { done: 3, total: 3 });
+    assert!(dir.path().join("samples/one.wav").is_file());
+    assert!(!dir.path().join("samples/two.wav").exists());
+
+    cancel.cancel();
+    let error = extract::extract_all(&mut reader, dir.path(), &options).unwrap_err();
+    assert!(Cancelled::from_error(&error).is_some());
+}
+
+#[test]
+fn item_path() {
+    let dest = Path::new("dest");
+    assert_eq!(
+        extract::item_path(dest, b"samples/one.wav").unwrap(),
+        dest.join("samples").join("one.wav")
+    );
+    assert!(extract::item_path(dest, b"/etc/passwd").is_err());
+    assert!(extract::item_path(dest, b"samples/../../up.wav").is_err());
+}
+
+#[test]
+fn write_atomic_file() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let bank_path = dir.path().join("cancelled.bank");
+    std::fs::write(&bank_path, "previous").unwrap();
+
+    // A cancelled bank leaves the previous file untouched.
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let file = AtomicFile::create(&bank_path).unwrap();
+    assert!(file.temp_path().exists());
+    let mut writer = BankWriter::with_options(
+        file,
+        BankWriterOptions {
+            cancel: Some(cancel),
+            ..BankWriterOptions::default()
+        },
+    );
+    writer
+        .add(ItemKind::Sample, OsStr::new("one.wav"), vec![1; 100])
+        .unwrap();
+    let error = writer.write().unwrap_err();
+    assert!(Cancelled::from_error(&error).is_some());
+    drop(writer);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    assert_eq!(std::fs::read(&bank_path).unwrap(), b"previous");
+
+    // A committed bank replaces it.
+    let mut file = AtomicFile::create(&bank_path).unwrap();
+    std::io::Write::write_all(&mut file, b"bank").unwrap();
+    file.commit().unwrap();
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    assert_eq!(std::fs::read(&bank_path).unwrap(), b"bank");
+}
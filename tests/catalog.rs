@@ -0,0 +1,51 @@
+#![cfg(feature = "std")]
+
+use kibank::catalog::{Catalog, Duplicate};
+use kibank::stats::{Outcome, Stats};
+
+/// Scan the test banks.
+#[test]
+fn scan() {
+    let catalog = Catalog::scan("tests").unwrap();
+    assert!(catalog.unreadable.is_empty());
+    assert_eq!(catalog.banks.len(), 2);
+
+    let bank = catalog
+        .banks
+        .iter()
+        .find(|bank| bank.path.ends_with("metadata.bank"))
+        .unwrap();
+    assert_eq!(bank.name, "Title");
+    assert_eq!(bank.author, "Author");
+    assert_eq!(bank.id, "author.title");
+    assert_eq!(bank.item_count, 1);
+    assert!(bank.hash.is_none());
+    assert!(bank.presets.is_empty());
+}
+
+/// Every bank scanned is recorded in the statistics.
+#[test]
+fn scan_with_stats() {
+    let mut stats = Stats::default();
+    let catalog = Catalog::scan_with("tests", Some(&mut stats)).unwrap();
+    assert_eq!(stats.banks.len(), catalog.banks.len());
+    assert!(stats
+        .banks
+        .iter()
+        .all(|bank| bank.outcome == Outcome::Ok && bank.size.is_some_and(|size| size > 0)));
+}
+
+/// Both test banks contain nothing but metadata so their contents are the same.
+#[test]
+fn duplicates() {
+    let mut catalog = Catalog::scan("tests").unwrap();
+    assert!(catalog.duplicates().is_empty());
+
+    catalog.fingerprint().unwrap();
+    let duplicates = catalog.duplicates();
+    assert_eq!(duplicates.len(), 1);
+    match &duplicates[0] {
+        Duplicate::SameContents { paths, .. } => assert_eq!(paths.len(), 2),
+        duplicate => panic!("Unexpected {duplicate:?}"),
+    }
+}
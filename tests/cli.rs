@@ -1,9 +1,16 @@
 //! Test the command line interface.
+#![cfg(feature = "application")]
 
+use std::fs;
+use std::fs::File;
 use std::process::Command;
+use std::time::{Duration, UNIX_EPOCH};
 
 use assert_cmd::crate_name;
 use assert_cmd::prelude::*;
+use assert_fs::prelude::*;
+use kibank::stats::{Outcome, Stats};
+use kibank::ItemKind;
 use predicates::prelude::*;
 
 #[test]
@@ -37,3 +44,2228 @@ fn create_and_list() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn release_bump_and_tag() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let bank = dir.path().join("release.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg(&bank)
+        .arg("tests/images/background.jpg");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("release").arg("--bump").arg("--tag").arg(&bank);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg(dir.path().join("release-v1.bank"));
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Version: 1"))
+        .stdout(predicate::str::is_match("Hash: [0-9a-f]{40}")?);
+
+    Ok(())
+}
+
+#[test]
+fn create_with_stamp() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("create_with_stamp.bank")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--stamp")
+        .arg(file.path())
+        .arg("tests/images/background.jpg");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Created: "))
+        .stdout(predicate::str::contains(" by kibank "))
+        .stdout(predicate::str::is_match("Source hash: [0-9a-f]{40}")?);
+
+    Ok(())
+}
+
+/// The source hash is the same however many files are hashed at once.
+#[test]
+fn create_with_stamp_threads() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    for name in ["bass.phaseplant", "lead.phaseplant", "hall.ksrv"] {
+        dir.child("presets").child(name).write_str(name)?;
+    }
+
+    let mut source_hashes = Vec::new();
+    for threads in ["1", "3"] {
+        let bank = dir.child(format!("threads-{threads}.bank"));
+        let mut cmd = Command::cargo_bin(crate_name!())?;
+        cmd.arg("create")
+            .arg("--stamp")
+            .arg("--threads")
+            .arg(threads)
+            .arg(bank.path())
+            .arg(dir.child("presets").path());
+        cmd.assert().success();
+
+        let mut cmd = Command::cargo_bin(crate_name!())?;
+        let output = cmd.arg("info").arg(bank.path()).output()?;
+        let stdout = String::from_utf8(output.stdout)?;
+        let source_hash = stdout
+            .lines()
+            .find(|line| line.contains("Source hash: "))
+            .map(str::to_owned);
+        assert!(source_hash.is_some(), "No source hash in {stdout}");
+        source_hashes.push(source_hash);
+    }
+    assert_eq!(source_hashes[0], source_hashes[1]);
+
+    Ok(())
+}
+
+#[test]
+fn compat() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("new.phaseplant").write_str("saved with 2.2.0")?;
+    dir.child("old.phaseplant").write_str("saved with 1.8.5")?;
+    dir.child("unknown.ksrv").write_str("preset")?;
+    let bank = dir.child("compat.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg(bank.path())
+        .arg(dir.path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("compat")
+        .arg("--against")
+        .arg("2.1")
+        .arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("new.phaseplant 2.2.0"))
+        .stdout(predicate::str::contains("old.phaseplant").not())
+        .stdout(predicate::str::contains(
+            "1 preset(s) saved with a version newer than 2.1.0, 1 without a version",
+        ));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("compat").arg("--against").arg("2").arg(bank.path());
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn info_presets() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    for name in [
+        "bass.phaseplant",
+        "lead.phaseplant",
+        "bus.multipass",
+        "hall.ksrv",
+    ] {
+        dir.child(name).write_str("preset")?;
+    }
+    let bank = dir.child("presets.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg(bank.path())
+        .arg(dir.path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg(bank.path());
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Presets: Phase Plant: 2, Multipass: 1, snapins: 1",
+    ));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("scan").arg(dir.path());
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let catalog: serde_json::Value = serde_json::from_slice(&output)?;
+    assert_eq!(catalog["banks"][0]["presets"]["phase_plant"], 2);
+    assert_eq!(catalog["banks"][0]["presets"]["snapins"], 1);
+
+    Ok(())
+}
+
+#[test]
+fn info_background() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("in/bass.phaseplant").write_str("preset")?;
+    dir.child("in/background.png")
+        .write_binary(&fs::read("tests/images/background.png")?)?;
+    let bank = dir.child("background.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg(bank.path())
+        .arg(dir.child("in").path());
+    cmd.assert().success();
+
+    let size = fs::metadata("tests/images/background.png")?.len();
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Background: background.png"))
+        .stdout(predicate::str::contains("Background format: PNG"))
+        .stdout(predicate::str::contains("Background dimensions: 173x76"))
+        .stdout(predicate::str::contains(format!(
+            "Background size: {size} bytes"
+        )));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg("tests/metadata.bank");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Background: no"));
+
+    Ok(())
+}
+
+#[test]
+fn info_raw() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let json = "{\"name\":\"Raw\",  \"vendor\": {\"nonstandard\": [1, 2]}}";
+    dir.child("in/index.json").write_str(json)?;
+    dir.child("in/bass.phaseplant").write_str("preset")?;
+    let bank = dir.child("raw.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg(bank.path())
+        .arg(dir.child("in").path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg("--raw").arg(bank.path());
+    cmd.assert().success().stdout(json);
+
+    Ok(())
+}
+
+#[test]
+fn changelog() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("bass.phaseplant").write_str("preset")?;
+    let notes = dir.child("notes.txt");
+    notes.write_str("Version 2 adds a bass.\n")?;
+    let bank = dir.child("changelog.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info")
+        .arg("--changelog")
+        .arg("tests/metadata.bank");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("has no changelog"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg("--changelog")
+        .arg(notes.path())
+        .arg(bank.path())
+        .arg(dir.child("bass.phaseplant").path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg("--changelog").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Version 2 adds a bass."));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("changelog.txt"));
+
+    Ok(())
+}
+
+#[test]
+fn inspect_sandbox() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("inspect")
+        .arg("--sandbox")
+        .arg("tests/metadata.bank");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("is OK"));
+
+    let file = assert_fs::NamedTempFile::new("not-a-bank.bank")?;
+    file.write_str("not a bank")?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("inspect").arg("--sandbox").arg(file.path());
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("error: Cannot be opened"));
+
+    Ok(())
+}
+
+#[test]
+fn lang() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("--lang")
+        .arg("de")
+        .arg("info")
+        .arg("tests/metadata.bank");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Autor: "))
+        .stdout(predicate::str::contains("Beschreibung: "));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("verify")
+        .arg("--lang")
+        .arg("de")
+        .arg("tests/metadata.bank");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("ist in Ordnung"));
+
+    // Translated metadata is shown in the language of the output.
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("index.json").write_str(
+        r#"{"id": "a.b", "name": "Title", "author": "A", "description": "",
+            "name_localized": {"de": "Titel", "ja": "タイトル"}}"#,
+    )?;
+    dir.child("one.wav").write_str("sample")?;
+    let bank = assert_fs::NamedTempFile::new("localized.bank")?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg(bank.path())
+        .arg(dir.path());
+    cmd.assert().success();
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("--lang").arg("de").arg("info").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Name: Titel"));
+
+    // Translations in languages the output isn't available in are shown when
+    // asked for, falling back to the language of the output.
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info")
+        .arg("--bank-lang")
+        .arg("ja")
+        .arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Name: タイトル"));
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("--lang")
+        .arg("de")
+        .arg("info")
+        .arg("--bank-lang")
+        .arg("fr")
+        .arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Name: Titel"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("--lang")
+        .arg("xx")
+        .arg("info")
+        .arg("tests/metadata.bank");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn normalize_names() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let preset = dir.child("deep  bass.phaseplant");
+    preset.write_str("preset")?;
+    let bank = dir.child("normalize.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create").arg(bank.path()).arg(preset.path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("normalize-names")
+        .arg("--title-case")
+        .arg("--collapse-spaces")
+        .arg("--numbering")
+        .arg(bank.path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg(bank.path());
+    cmd.assert().success().stdout(predicate::str::contains(
+        "phaseplant/01 - Deep Bass.phaseplant",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn repack_fix_case() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let preset = dir.child("Big Pad.PHASEPLANT");
+    preset.write_str("preset")?;
+    let bank = dir.child("upper.bank");
+    let fixed = dir.child("fixed.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create").arg(bank.path()).arg(preset.path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("verify").arg(bank.path());
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Extensions that are not lowercase: phaseplant/Big Pad.PHASEPLANT",
+    ));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("repack")
+        .arg("--fix-case")
+        .arg("--output")
+        .arg(fixed.path())
+        .arg(bank.path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg(fixed.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("phaseplant/Big Pad.phaseplant"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("verify").arg(fixed.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("not lowercase").not());
+
+    Ok(())
+}
+
+#[test]
+fn create_show_diff() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let kept = dir.child("Kept.phaseplant");
+    kept.write_str("kept")?;
+    let edited = dir.child("Edited.phaseplant");
+    edited.write_str("before")?;
+    let bank = dir.child("diff.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg(bank.path())
+        .arg(kept.path())
+        .arg(edited.path());
+    cmd.assert().success();
+    let before = std::fs::read(bank.path())?;
+
+    edited.write_str("after")?;
+    let added = dir.child("Added.phaseplant");
+    added.write_str("added")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--show-diff")
+        .arg(bank.path())
+        .arg(kept.path())
+        .arg(edited.path())
+        .arg(added.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("+ phaseplant/Added.phaseplant"))
+        .stdout(predicate::str::contains("~ phaseplant/Edited.phaseplant"))
+        .stdout(predicate::str::contains("Kept.phaseplant").not())
+        .stdout(predicate::str::contains(
+            "1 added, 0 removed, 1 changed, 2 unchanged",
+        ));
+
+    // The existing bank is left untouched.
+    assert_eq!(std::fs::read(bank.path())?, before);
+
+    Ok(())
+}
+
+#[test]
+fn build_project() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("samples/Kick.wav").write_str("kick")?;
+    dir.child("presets/lite/Bass.phaseplant")
+        .write_str("bass")?;
+    dir.child("presets/full/Lead.phaseplant")
+        .write_str("lead")?;
+    let project = dir.child("kibank.toml");
+    project.write_str(
+        r#"author = "Some Author"
+
+[[bank]]
+name = "lite"
+output = "dist/Lite.bank"
+inputs = ["samples", "presets/lite"]
+title = "Pack Lite"
+
+[[bank]]
+name = "full"
+output = "dist/Full.bank"
+inputs = ["samples", "presets"]
+title = "Pack"
+"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("build")
+        .arg("--project")
+        .arg(project.path())
+        .arg("--bank")
+        .arg("lite");
+    cmd.assert().success();
+    dir.child("dist/Lite.bank")
+        .assert(predicate::path::exists());
+    dir.child("dist/Full.bank")
+        .assert(predicate::path::missing());
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("build").current_dir(dir.path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg(dir.child("dist/Full.bank").path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Bass.phaseplant"))
+        .stdout(predicate::str::contains("Lead.phaseplant"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("build")
+        .arg("--project")
+        .arg(project.path())
+        .arg("--bank")
+        .arg("missing");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No bank named missing"));
+
+    Ok(())
+}
+
+#[test]
+fn build_post_build() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("Bass.phaseplant").write_str("bass")?;
+    let kibank = assert_cmd::cargo::cargo_bin(crate_name!());
+    let project = dir.child("kibank.toml");
+    project.write_str(&format!(
+        r#"post-build = [
+    {{ checksum = "blake3" }},
+    {{ run = [{kibank:?}, "verify", "--checksums", "{{bank}}.blake3", "{{bank}}"] }},
+    {{ copy-to = "release" }},
+]
+
+[[bank]]
+name = "pack"
+output = "Pack.bank"
+inputs = ["Bass.phaseplant"]
+"#
+    ))?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("build").current_dir(dir.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Pack.bank is OK"));
+    dir.child("Pack.bank.blake3")
+        .assert(predicate::str::contains("phaseplant/Bass.phaseplant"));
+    dir.child("release/Pack.bank")
+        .assert(predicate::path::exists());
+    dir.child("release/Pack.bank.blake3")
+        .assert(predicate::path::exists());
+
+    // A hook that fails stops the build.
+    project.write_str(&format!(
+        r#"[[bank]]
+name = "pack"
+output = "Pack.bank"
+inputs = ["Bass.phaseplant"]
+post-build = [{{ run = [{kibank:?}, "verify", "Missing.bank"] }}]
+"#
+    ))?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("build").current_dir(dir.path());
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "A post-build hook of bank pack failed",
+    ));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("build").arg("--no-hooks").current_dir(dir.path());
+    cmd.assert().success();
+
+    Ok(())
+}
+
+#[test]
+fn list_annotations() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let preset = dir.child("Bass.phaseplant");
+    preset.write_str("bass")?;
+    let bank = dir.child("annotated.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create").arg(bank.path()).arg(preset.path());
+    cmd.assert().success();
+
+    // Annotations kept next to the bank are used when the bank has none.
+    dir.child("annotated.bank.annotations.json")
+        .write_str(r#"{"phaseplant/Bass.phaseplant": {"tags": ["bass"]}}"#)?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg("--annotations").arg(bank.path());
+    cmd.assert().success().stdout(predicate::str::contains(
+        r#"phaseplant/Bass.phaseplant  {"tags":["bass"]}"#,
+    ));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("tags").not());
+
+    Ok(())
+}
+
+#[test]
+fn list_previews() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("presets/Bass.phaseplant").write_str("bass")?;
+    dir.child("presets/Pad.phaseplant").write_str("pad")?;
+    dir.child("audio/bass.mp3").write_str("bass preview")?;
+    dir.child("audio/Lead.flac").write_str("lead preview")?;
+    dir.child("audio/notes.txt").write_str("not a preview")?;
+    let bank = dir.child("previewed.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg("--previews")
+        .arg(dir.child("audio").path())
+        .arg(bank.path())
+        .arg(dir.child("presets").path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg("--previews").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "phaseplant/Bass.phaseplant  previews/bass.mp3\n",
+        ))
+        .stdout(predicate::str::contains("phaseplant/Pad.phaseplant\n"))
+        .stdout(predicate::str::contains(
+            "previews/Lead.flac is not named after any preset",
+        ))
+        .stdout(predicate::str::contains("notes.txt").not());
+
+    Ok(())
+}
+
+#[test]
+fn diff_with_directory() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let kept = dir.child("src/Kept.phaseplant");
+    kept.write_str("kept")?;
+    let edited = dir.child("src/Edited.phaseplant");
+    edited.write_str("before")?;
+    let bank = dir.child("work.bank");
+    let work = dir.child("work");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg(bank.path())
+        .arg(kept.path())
+        .arg(edited.path());
+    cmd.assert().success();
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("-d")
+        .arg(work.path())
+        .arg(bank.path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("diff").arg(bank.path()).arg(work.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("0 added, 0 removed, 0 changed"));
+
+    let changed = work.child("phaseplant/Edited.phaseplant");
+    changed.write_str("after")?;
+    File::options()
+        .write(true)
+        .open(changed.path())?
+        .set_modified(std::time::SystemTime::now() + Duration::from_secs(60))?;
+    work.child("phaseplant/Added.phaseplant")
+        .write_str("added")?;
+    fs::remove_file(work.child("phaseplant/Kept.phaseplant").path())?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("diff").arg(bank.path()).arg(work.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("+ phaseplant/Added.phaseplant"))
+        .stdout(predicate::str::contains(
+            "~ phaseplant/Edited.phaseplant (newer in the directory)",
+        ))
+        .stdout(predicate::str::contains("- phaseplant/Kept.phaseplant"))
+        .stdout(predicate::str::contains(
+            "1 added, 1 removed, 1 changed, 1 unchanged",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn diff_banks() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("v1/Kept.phaseplant").write_str("kept")?;
+    dir.child("v1/Edited.phaseplant").write_str("before")?;
+    dir.child("v1/Gone.phaseplant").write_str("gone")?;
+    dir.child("v2/Kept.phaseplant").write_str("kept")?;
+    dir.child("v2/Edited.phaseplant").write_str("after")?;
+    dir.child("v2/Added.phaseplant").write_str("added")?;
+    for (version, name) in [("v1", "Drums"), ("v2", "More Drums")] {
+        let mut cmd = Command::cargo_bin(crate_name!())?;
+        cmd.arg("create")
+            .arg("--summary")
+            .arg("none")
+            .arg("--id")
+            .arg("me.drums")
+            .arg("--name")
+            .arg(name)
+            .arg(dir.child(format!("{version}.bank")).path())
+            .arg(dir.child(version).path());
+        cmd.assert().success();
+    }
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("diff")
+        .arg(dir.child("v1.bank").path())
+        .arg(dir.child("v2.bank").path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("+ phaseplant/Added.phaseplant"))
+        .stdout(predicate::str::contains("~ phaseplant/Edited.phaseplant\n"))
+        .stdout(predicate::str::contains("- phaseplant/Gone.phaseplant"))
+        .stdout(predicate::str::contains(
+            "Metadata name changed from \"Drums\" to \"More Drums\"",
+        ))
+        .stdout(predicate::str::contains("1 unchanged"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("diff")
+        .arg(dir.child("v1.bank").path())
+        .arg(dir.child("v1.bank").path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Metadata").not())
+        .stdout(predicate::str::contains("0 added, 0 removed, 0 changed"));
+
+    Ok(())
+}
+
+#[test]
+fn empty_bank() -> Result<(), Box<dyn std::error::Error>> {
+    let bank = assert_fs::NamedTempFile::new("empty.bank")?;
+    let mut contents = vec![137_u8, b'k', b'H', b's', 0x0d, 0x0a, 0x1a, 0x0a];
+    contents.extend_from_slice(b"Bank0001");
+    contents.extend_from_slice(&[0; 16]);
+    bank.write_binary(&contents)?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("No metadata item"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("verify").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("The bank has no items"));
+
+    Ok(())
+}
+
+#[test]
+fn create_with_id_template() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("create_with_id_template.bank")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--author")
+        .arg("Some Author")
+        .arg("--name")
+        .arg("Bank Name")
+        .arg("--id-template")
+        .arg("{author}.{name}.v{version}")
+        .arg(file.path())
+        .arg("tests/images/background.jpg");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("ID: someauthor.bankname.v0"));
+
+    Ok(())
+}
+
+#[test]
+fn scan_table() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("scan")
+        .arg("--table")
+        .arg("--filter")
+        .arg("author=auth")
+        .arg("tests");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            "Name +Author +ID +Items +Size +Hash +Path",
+        )?)
+        .stdout(predicate::str::contains("author.title"))
+        .stdout(predicate::str::contains("blank.bank").not());
+    Ok(())
+}
+
+#[test]
+fn stats_file() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let stats_file = dir.child("stats.json");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("scan")
+        .arg("--stats-file")
+        .arg(stats_file.path())
+        .arg("tests");
+    cmd.assert().success();
+    let stats = Stats::load(stats_file.path())?;
+    assert_eq!(stats.banks.len(), 2);
+
+    // A bank that cannot be read doesn't stop the others being verified.
+    let not_a_bank = dir.child("not.bank");
+    not_a_bank.write_str("not a bank")?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("verify")
+        .arg("--stats-file")
+        .arg(stats_file.path())
+        .arg("tests/blank.bank")
+        .arg(not_a_bank.path())
+        .arg("tests/metadata.bank");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("metadata.bank"));
+    let stats = Stats::load(stats_file.path())?;
+    let outcomes = stats
+        .banks
+        .iter()
+        .map(|bank| bank.outcome)
+        .collect::<Vec<_>>();
+    assert_eq!(outcomes, [Outcome::Ok, Outcome::Unreadable, Outcome::Ok]);
+    assert_eq!(stats.banks[1].size, Some(10));
+    assert!(stats.banks[1].error.is_some());
+
+    Ok(())
+}
+
+#[test]
+fn verify_every_header_problem() -> Result<(), Box<dyn std::error::Error>> {
+    // Wrong format version and a location with a name past the names.
+    let mut bank = vec![137_u8, b'k', b'H', b's', 0x0d, 0x0a, 0x1a, 0x0a];
+    bank.extend_from_slice(b"Bank0002");
+    bank.extend_from_slice(&1_u64.to_le_bytes());
+    for field in [10_u64, 0, 0] {
+        bank.extend_from_slice(&field.to_le_bytes());
+    }
+    bank.extend_from_slice(&2_u64.to_le_bytes());
+    bank.extend_from_slice(b"a\0");
+    let file = assert_fs::NamedTempFile::new("damaged.bank")?;
+    file.write_binary(&bank)?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("verify").arg(file.path());
+    cmd.assert().failure().stdout(
+        predicate::str::contains("error: Unexpected format version Bank0002").and(
+            predicate::str::contains("error: File name offset 10 is past the end"),
+        ),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn verify_recursive() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("verify").arg("--recursive").arg("tests");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2 passed, 0 failed"));
+
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("a.bank").write_str("not a bank")?;
+    dir.child("b/b.bank").write_str("not a bank either")?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("verify").arg("--recursive").arg(dir.path());
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("0 passed, 2 failed"))
+        .stdout(predicate::str::contains("b.bank"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("verify")
+        .arg("--recursive")
+        .arg("--fail-fast")
+        .arg(dir.path());
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("0 passed, 1 failed"));
+
+    Ok(())
+}
+
+#[test]
+fn checksum_and_verify() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("checksum")
+        .arg("--algo")
+        .arg("sha256")
+        .arg("tests/metadata.bank");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    assert_eq!(
+        String::from_utf8(output.clone())?,
+        "759d37769bafe62b88aaeabb16ff8f530d659c71e16bf19cdd17461b22bc8827  index.json\n"
+    );
+
+    let checksums = assert_fs::NamedTempFile::new("metadata.sha256")?;
+    checksums.write_binary(&output)?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("verify")
+        .arg("--checksums")
+        .arg(checksums.path())
+        .arg("--algo")
+        .arg("sha256")
+        .arg("tests/metadata.bank");
+    cmd.assert().success();
+
+    // The metadata of the blank bank is different.
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("verify")
+        .arg("--checksums")
+        .arg(checksums.path())
+        .arg("--algo")
+        .arg("sha256")
+        .arg("tests/blank.bank");
+    cmd.assert().failure().stdout(predicate::str::contains(
+        "SHA-256 checksum of index.json does not match",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn x_compress() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let preset = dir.child("pad.phaseplant");
+    preset.write_str(&"{\"preset\": true}".repeat(100))?;
+    let bank = dir.child("compressed.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--x-compress")
+        .arg(bank.path())
+        .arg(preset.path());
+    cmd.assert().success();
+
+    let dest = dir.child("extracted");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("--dest")
+        .arg(dest.path())
+        .arg(bank.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("compressed"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("--x-decompress")
+        .arg("--dest")
+        .arg(dest.path())
+        .arg(bank.path());
+    cmd.assert().success();
+    dest.child("phaseplant/pad.phaseplant")
+        .assert(predicate::path::eq_file(preset.path()));
+
+    // Converted back to a bank that Kilohearts products can read.
+    let standard = dir.child("standard.bank");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("repack")
+        .arg("--x-decompress")
+        .arg("--output")
+        .arg(standard.path())
+        .arg(bank.path());
+    cmd.assert().success();
+    let standard_dest = dir.child("standard");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("--dest")
+        .arg(standard_dest.path())
+        .arg(standard.path());
+    cmd.assert().success();
+    standard_dest
+        .child("phaseplant/pad.phaseplant")
+        .assert(predicate::path::eq_file(preset.path()));
+
+    Ok(())
+}
+
+#[test]
+fn create_with_colliding_id() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("create_with_colliding_id.bank")?;
+
+    // The bank tests/metadata.bank has the same ID.
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--author")
+        .arg("Author")
+        .arg("--name")
+        .arg("Title")
+        .arg("--check-id")
+        .arg("tests")
+        .arg(file.path())
+        .arg("tests/images/background.jpg");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("author.title is already used"));
+
+    Ok(())
+}
+
+#[test]
+fn preserve_times() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let sample = dir.child("kick.wav");
+    sample.write_binary(&[1; 100])?;
+    let modified = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    File::options()
+        .write(true)
+        .open(sample.path())?
+        .set_modified(modified)?;
+    let bank = dir.child("times.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--preserve-times")
+        .arg(bank.path())
+        .arg(sample.path());
+    cmd.assert().success();
+
+    let dest = dir.child("out");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("--preserve-times")
+        .arg("--dest")
+        .arg(dest.path())
+        .arg(bank.path());
+    cmd.assert().success();
+
+    let extracted = dest.child("samples/kick.wav");
+    assert_eq!(extracted.path().metadata()?.modified()?, modified);
+    dest.child(".kibank-attrs.json")
+        .assert(predicate::path::missing());
+
+    Ok(())
+}
+
+/// Files left out by the filters have no attributes to restore.
+#[test]
+fn preserve_times_filtered() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let modified = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    for (name, size) in [("a.wav", 100), ("b.wav", 5000)] {
+        let sample = dir.child(format!("in/{name}"));
+        sample.write_binary(&vec![1; size])?;
+        File::options()
+            .write(true)
+            .open(sample.path())?
+            .set_modified(modified)?;
+    }
+    let bank = dir.child("times.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--preserve-times")
+        .arg(bank.path())
+        .arg(dir.child("in").path());
+    cmd.assert().success();
+
+    let dest = dir.child("out");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("--preserve-times")
+        .arg("--max-size")
+        .arg("1000")
+        .arg("--dest")
+        .arg(dest.path())
+        .arg(bank.path());
+    cmd.assert().success();
+
+    let extracted = dest.child("samples/a.wav");
+    assert_eq!(extracted.path().metadata()?.modified()?, modified);
+    dest.child("samples/b.wav")
+        .assert(predicate::path::missing());
+
+    Ok(())
+}
+
+#[test]
+fn encrypt() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let encrypted = dir.child("metadata.bank.enc");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("encrypt")
+        .arg("--password")
+        .arg("secret")
+        .arg("--output")
+        .arg(encrypted.path())
+        .arg("tests/metadata.bank");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg(encrypted.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("is encrypted"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info")
+        .arg("--password")
+        .arg("secret")
+        .arg(encrypted.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("author.title"));
+
+    Ok(())
+}
+
+#[test]
+fn compress() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let bank = dir.child("metadata.bank");
+    bank.write_file(std::path::Path::new("tests/metadata.bank"))?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("compress").arg(bank.path());
+    cmd.assert().success();
+    let compressed = dir.child("metadata.bank.zst");
+    compressed.assert(predicate::path::exists());
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg(compressed.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("author.title"));
+
+    std::fs::remove_file(bank.path())?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("decompress").arg(compressed.path());
+    cmd.assert().success();
+    bank.assert(predicate::path::eq_file("tests/metadata.bank"));
+
+    Ok(())
+}
+
+#[test]
+fn create_volumes() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let bank = dir.child("pack.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--volume-size")
+        .arg("1K")
+        .arg(bank.path())
+        .arg("tests/images/background.jpg");
+    cmd.assert().success();
+    dir.child("pack.bank.001").assert(predicate::path::exists());
+    dir.child("pack.bank.002").assert(predicate::path::exists());
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("background.jpg"));
+
+    Ok(())
+}
+
+#[test]
+fn create_with_strict_metadata() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("strict.bank")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--strict-metadata")
+        .arg("--id")
+        .arg("Not Sanitized")
+        .arg(file.path())
+        .arg("tests/images/background.jpg");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("is not lowercase"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--strict-metadata")
+        .arg("--author")
+        .arg("Author")
+        .arg("--name")
+        .arg("Title")
+        .arg(file.path())
+        .arg("tests/images/background.jpg");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("verify").arg("--strict-metadata").arg(file.path());
+    cmd.assert().success();
+
+    Ok(())
+}
+
+#[test]
+fn create_summary() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let preset = dir.child("bass.phaseplant");
+    preset.write_str("preset")?;
+    let notes = dir.child("notes.txt");
+    notes.write_str("notes")?;
+    let bank = assert_fs::NamedTempFile::new("summary.bank")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("json")
+        .arg(bank.path())
+        .arg(dir.path());
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let summary: serde_json::Value = serde_json::from_slice(&output)?;
+    assert_eq!(summary["items"]["PhasePlantPreset"], 1);
+    assert_eq!(summary["items"]["Metadata"], 1);
+    assert!(summary["fingerprint"]
+        .as_str()
+        .is_some_and(|f| f.len() == 40));
+    assert_eq!(summary["skipped"].as_array().map(Vec::len), Some(1));
+    assert_eq!(summary["skipped"][0]["reason"], "unknown_type");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create").arg(bank.path()).arg(dir.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Created "))
+        .stdout(predicate::str::contains("PhasePlantPreset: 1"))
+        .stdout(predicate::str::contains("notes.txt: unknown type of file"));
+
+    Ok(())
+}
+
+#[test]
+fn create_with_deny_skips() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("bass.phaseplant").write_str("preset")?;
+    dir.child("notes.txt").write_str("notes")?;
+    let bank = assert_fs::NamedTempFile::new("deny_skips.bank")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--deny-skips")
+        .arg(bank.path())
+        .arg(dir.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("notes.txt (unknown type of file)"));
+    bank.assert(predicate::path::missing());
+
+    Ok(())
+}
+
+#[test]
+fn create_only_and_skip() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("bass.phaseplant").write_str("preset")?;
+    dir.child("kick.wav").write_str("sample")?;
+    let samples = assert_fs::NamedTempFile::new("samples.bank")?;
+    let presets = assert_fs::NamedTempFile::new("presets.bank")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--only")
+        .arg("sample")
+        .arg(samples.path())
+        .arg(dir.path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg(samples.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("kick.wav"))
+        .stdout(predicate::str::contains("bass.phaseplant").not());
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--skip")
+        .arg("Sample")
+        .arg(presets.path())
+        .arg(dir.path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg(presets.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("bass.phaseplant"))
+        .stdout(predicate::str::contains("kick.wav").not());
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--only")
+        .arg("presets")
+        .arg(presets.path())
+        .arg(dir.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("presets is not a kind of item"));
+
+    Ok(())
+}
+
+#[test]
+fn split() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let banks = [
+        fs::read("tests/blank.bank")?,
+        fs::read("tests/metadata.bank")?,
+    ]
+    .concat();
+    dir.child("banks.bin").write_binary(&banks)?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("split").arg(dir.child("banks.bin").path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("banks-2.bank"));
+    dir.child("banks-1.bank")
+        .assert(predicate::path::eq_file("tests/blank.bank"));
+    dir.child("banks-2.bank")
+        .assert(predicate::path::eq_file("tests/metadata.bank"));
+
+    Ok(())
+}
+
+#[test]
+fn repair() -> Result<(), Box<dyn std::error::Error>> {
+    let bank = assert_fs::NamedTempFile::new("repair.bank")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("repair")
+        .arg("--output")
+        .arg(bank.path())
+        .arg("tests/metadata.bank");
+    cmd.assert().success().stdout(predicate::str::is_empty());
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("verify").arg(bank.path());
+    cmd.assert().success();
+
+    Ok(())
+}
+
+#[test]
+fn export() -> Result<(), Box<dyn std::error::Error>> {
+    let bank = assert_fs::NamedTempFile::new("export.bank")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--stamp")
+        .arg("--hash")
+        .arg("0123456789abcdef0123456789abcdef01234567")
+        .arg(bank.path())
+        .arg("tests/images/background.jpg");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("export")
+        .arg("--strip")
+        .arg("hash,provenance")
+        .arg(bank.path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Hash: \n"))
+        .stdout(predicate::str::contains("Source hash").not());
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("export")
+        .arg("--strip")
+        .arg("colour")
+        .arg(bank.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("colour is not a field"));
+
+    Ok(())
+}
+
+#[test]
+fn rebrand() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let bank = dir.child("pack.bank");
+    bank.write_binary(&fs::read("tests/metadata.bank")?)?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("rebrand")
+        .arg("--dry-run")
+        .arg("--author")
+        .arg("New Vendor")
+        .arg("--id-prefix")
+        .arg("newvendor")
+        .arg(dir.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "ID author.title -> newvendor.title",
+        ))
+        .stdout(predicate::str::contains("Author Author -> New Vendor"));
+    bank.assert(predicate::path::eq_file("tests/metadata.bank"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("rebrand")
+        .arg("--id-prefix")
+        .arg("newvendor")
+        .arg(dir.path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("ID: newvendor.title"))
+        .stdout(predicate::str::contains("Author: Author"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("rebrand").arg(dir.path());
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn extract_preflight() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let blocker = dir.child("file");
+    blocker.write_str("not a directory")?;
+
+    // Nothing is extracted when the destination cannot be written to.
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("-d")
+        .arg(blocker.child("dest").path())
+        .arg("tests/metadata.bank");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot create directory"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("-d")
+        .arg(dir.child("dest").path())
+        .arg("tests/metadata.bank");
+    cmd.assert().success();
+    // Only the contents of the bank are left behind.
+    let extracted = fs::read_dir(dir.child("dest").path())?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(extracted.len(), 1);
+    assert_eq!(extracted[0].file_name(), "index.json");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("--no-preflight")
+        .arg("-d")
+        .arg(dir.child("unchecked").path())
+        .arg("tests/metadata.bank");
+    cmd.assert().success();
+
+    Ok(())
+}
+
+#[test]
+fn extract_keep_going() -> Result<(), Box<dyn std::error::Error>> {
+    let source = assert_fs::TempDir::new()?;
+    source.child("kick.wav").write_str("sample")?;
+    source.child("bass.phaseplant").write_str("preset")?;
+    let bank = assert_fs::NamedTempFile::new("keep-going.bank")?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg(bank.path())
+        .arg(source.path());
+    cmd.assert().success();
+
+    // A file where the directory of samples should be.
+    let dest = assert_fs::TempDir::new()?;
+    dest.child("samples").write_str("in the way")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("--no-preflight")
+        .arg("--keep-going")
+        .arg("-d")
+        .arg(dest.path())
+        .arg(bank.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot extract samples/kick.wav"))
+        .stderr(predicate::str::contains("could not be extracted"));
+    dest.child("phaseplant/bass.phaseplant").assert("preset");
+
+    Ok(())
+}
+
+#[test]
+fn create_id_on_empty() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("preset.phaseplant").write_str("preset")?;
+    let bank = dir.child("unnamed.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--id-on-empty")
+        .arg("error")
+        .arg(bank.path())
+        .arg(dir.child("preset.phaseplant").path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("no ID and no author or name"));
+    bank.assert(predicate::path::missing());
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg("--id-on-empty")
+        .arg("hash")
+        .arg(bank.path())
+        .arg(dir.child("preset.phaseplant").path());
+    cmd.assert().success();
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match("ID: [0-9a-f]{40}")?);
+
+    Ok(())
+}
+
+#[test]
+fn create_metadata_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let input = dir.child("input");
+    input.child("index.json").write_str(
+        r#"{"id":"a.old","name":"Old","author":"A","description":"","vendor.tag":"x"}"#,
+    )?;
+    input.child("preset.phaseplant").write_str("preset")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--name")
+        .arg("New")
+        .arg("--summary")
+        .arg("json")
+        .arg(dir.child("merged.bank").path())
+        .arg(input.path());
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output)?;
+    let summary: serde_json::Value = serde_json::from_str(&output[output.find('{').unwrap()..])?;
+    let changes = &summary["metadata_changes"];
+    assert_eq!(changes["changed"]["name"]["from"], "Old");
+    assert_eq!(changes["changed"]["name"]["to"], "New");
+    assert_eq!(changes["extra_kept"][0], "vendor.tag");
+
+    Ok(())
+}
+
+#[test]
+fn merge_interactive() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    for (name, contents) in [("first", "one"), ("second", "two")] {
+        let input = dir.child(name);
+        input.child("preset.phaseplant").write_str(contents)?;
+        let mut cmd = Command::cargo_bin(crate_name!())?;
+        cmd.arg("create")
+            .arg("--summary")
+            .arg("none")
+            .arg("--name")
+            .arg(name)
+            .arg(dir.child(format!("{name}.bank")).path())
+            .arg(input.path());
+        cmd.assert().success();
+    }
+    let merged = dir.child("merged.bank");
+
+    // The second preset and the first name.
+    let mut cmd = assert_cmd::Command::cargo_bin(crate_name!())?;
+    cmd.arg("merge")
+        .arg("--interactive")
+        .arg("--output")
+        .arg(merged.path())
+        .arg(dir.child("first.bank").path())
+        .arg(dir.child("second.bank").path())
+        .write_stdin("2\n\n\n\n\n\n");
+    cmd.assert().success().stdout(
+        predicate::str::contains("phaseplant/preset.phaseplant is different")
+            .and(predicate::str::contains("3 bytes")),
+    );
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg(merged.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Name: first"));
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("--dest")
+        .arg(dir.child("out").path())
+        .arg(merged.path());
+    cmd.assert().success();
+    dir.child("out/phaseplant/preset.phaseplant").assert("two");
+
+    Ok(())
+}
+
+#[test]
+fn extract_rename_template() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("--dest")
+        .arg(dir.path())
+        .arg("--rename-template")
+        .arg("{stem}_{bankid}.{ext}")
+        .arg("tests/metadata.bank");
+    cmd.assert().success();
+    dir.child("index_author.title.json")
+        .assert(predicate::path::is_file());
+
+    Ok(())
+}
+
+#[test]
+fn info_zip() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg("tests/metadata.zip");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("ID: author.title"));
+
+    Ok(())
+}
+
+#[test]
+fn extract_tag_origin() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("input/bass.phaseplant").write_str("preset")?;
+    let bank = dir.child("tagged.bank");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg("--author")
+        .arg("Me")
+        .arg("--name")
+        .arg("Tagged")
+        .arg(bank.path())
+        .arg(dir.child("input").path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("--dest")
+        .arg(dir.child("out").path())
+        .arg("--tag-origin")
+        .arg(bank.path());
+    cmd.assert().success();
+    dir.child("out/phaseplant/bass.phaseplant.origin.json")
+        .assert(predicate::str::contains(r#""bank_name": "Tagged""#));
+
+    Ok(())
+}
+
+#[test]
+fn extract_filters() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("input/kick.wav").write_str("kick")?;
+    dir.child("input/snare.wav").write_str("snare")?;
+    let bank = dir.child("filtered.bank");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg(bank.path())
+        .arg(dir.child("input").path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("--dest")
+        .arg(dir.child("out").path())
+        .arg("--hash")
+        .arg("0db10f2c2f332cd27cf1407fa16c686337b2b23f46125d6e17740dbfc6df427e")
+        .arg(bank.path());
+    cmd.assert().success();
+    dir.child("out/samples/kick.wav").assert("kick");
+    dir.child("out/samples/snare.wav")
+        .assert(predicate::path::missing());
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("--hash")
+        .arg("kick")
+        .arg(bank.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("not a SHA-256 digest"));
+
+    Ok(())
+}
+
+#[test]
+fn add() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("input/kick.wav").write_str("kick")?;
+    dir.child("input/background.png")
+        .write_binary(&fs::read("tests/images/background.png")?)?;
+    let bank = dir.child("shipped.bank");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg("--name")
+        .arg("Shipped")
+        .arg(bank.path())
+        .arg(dir.child("input").path());
+    cmd.assert().success();
+
+    dir.child("Lead.phaseplant").write_str("lead")?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("add")
+        .arg(bank.path())
+        .arg(dir.child("Lead.phaseplant").path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Added phaseplant/Lead.phaseplant"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("phaseplant/Lead.phaseplant"))
+        .stdout(predicate::str::contains("samples/kick.wav"))
+        .stdout(predicate::str::contains("background.png"));
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Shipped"));
+
+    // Adding the same preset again fails without changing the bank.
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("add")
+        .arg(bank.path())
+        .arg(dir.child("Lead.phaseplant").path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("already has a file"));
+
+    Ok(())
+}
+
+#[test]
+fn add_show_diff() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("input/kick.wav").write_str("kick")?;
+    let bank = dir.child("shipped.bank");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg(bank.path())
+        .arg(dir.child("input").path());
+    cmd.assert().success();
+    let before = fs::read(bank.path())?;
+
+    dir.child("Lead.phaseplant").write_str("lead")?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("add")
+        .arg("--show-diff")
+        .arg(bank.path())
+        .arg(dir.child("Lead.phaseplant").path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("+ phaseplant/Lead.phaseplant"))
+        .stdout(predicate::str::contains("kick.wav").not())
+        .stdout(predicate::str::contains("Added").not())
+        .stdout(predicate::str::contains("1 added, 0 removed, 0 changed"));
+
+    // The bank is left untouched.
+    assert_eq!(fs::read(bank.path())?, before);
+
+    Ok(())
+}
+
+#[test]
+fn remove() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("input/kick.wav").write_str("kick")?;
+    dir.child("input/snare.wav").write_str("snare")?;
+    dir.child("input/Lead.phaseplant").write_str("lead")?;
+    let bank = dir.child("shipped.bank");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg("--name")
+        .arg("Shipped")
+        .arg(bank.path())
+        .arg(dir.child("input").path());
+    cmd.assert().success();
+
+    let smaller = dir.child("smaller.bank");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("remove")
+        .arg("--output")
+        .arg(smaller.path())
+        .arg(bank.path())
+        .arg("samples/*.wav");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Removed samples/kick.wav"))
+        .stdout(predicate::str::contains("Removed samples/snare.wav"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg(smaller.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("phaseplant/Lead.phaseplant"))
+        .stdout(predicate::str::contains("samples/kick.wav").not());
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("info").arg(smaller.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Shipped"));
+
+    // A pattern that matches nothing fails without changing the bank.
+    let before = fs::read(bank.path())?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("remove")
+        .arg(bank.path())
+        .arg("samples/kick.wav")
+        .arg("samples/*.aif");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("matches samples/*.aif"));
+    assert_eq!(fs::read(bank.path())?, before);
+
+    Ok(())
+}
+
+#[test]
+fn remove_show_diff() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("input/kick.wav").write_str("kick")?;
+    dir.child("input/Lead.phaseplant").write_str("lead")?;
+    let bank = dir.child("shipped.bank");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg(bank.path())
+        .arg(dir.child("input").path());
+    cmd.assert().success();
+    let before = fs::read(bank.path())?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("remove")
+        .arg("--show-diff")
+        .arg(bank.path())
+        .arg("samples/*.wav");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("- samples/kick.wav"))
+        .stdout(predicate::str::contains("Lead.phaseplant").not())
+        .stdout(predicate::str::contains("Removed").not())
+        .stdout(predicate::str::contains("0 added, 1 removed, 0 changed"));
+
+    // The bank is left untouched.
+    assert_eq!(fs::read(bank.path())?, before);
+
+    Ok(())
+}
+
+#[test]
+fn extract_all() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("banks/old").create_dir_all()?;
+    for (bank, sample) in [
+        ("banks/Drums.bank", "kick"),
+        ("banks/old/Drums.bank", "snare"),
+    ] {
+        dir.child(format!("{sample}/{sample}.wav"))
+            .write_str(sample)?;
+        let mut cmd = Command::cargo_bin(crate_name!())?;
+        cmd.arg("create")
+            .arg("--summary")
+            .arg("none")
+            .arg("--name")
+            .arg("Drums")
+            .arg("--author")
+            .arg("Me")
+            .arg(dir.child(bank).path())
+            .arg(dir.child(sample).path());
+        cmd.assert().success();
+    }
+
+    let out = dir.child("out");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract-all")
+        .arg("--dest")
+        .arg(out.path())
+        .arg(dir.child("banks").path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Extracted"));
+    out.child("me.drums/samples/kick.wav").assert("kick");
+    out.child("me.drums-2/samples/snare.wav").assert("snare");
+
+    dir.child("banks/broken.bank").write_str("not a bank")?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract-all")
+        .arg("--dest")
+        .arg(dir.child("again").path())
+        .arg(dir.child("banks").path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("broken.bank"))
+        .stderr(predicate::str::contains(
+            "1 of 3 banks could not be extracted",
+        ));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn extract_mode() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("input/kick.wav").write_str("kick")?;
+    let bank = dir.child("drums.bank");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg(bank.path())
+        .arg(dir.child("input").path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("--mode")
+        .arg("0604")
+        .arg("--fsync")
+        .arg("-d")
+        .arg(dir.child("out").path())
+        .arg(bank.path());
+    cmd.assert().success();
+    let mode = fs::metadata(dir.child("out/samples/kick.wav").path())?
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o7777, 0o604);
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("extract")
+        .arg("--mode")
+        .arg("rw-r--r--")
+        .arg(bank.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("permissions in octal"));
+
+    Ok(())
+}
+
+#[test]
+fn read_only_marker() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("input/kick.wav").write_str("kick")?;
+    let bank = dir.child("master.bank");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg(bank.path())
+        .arg(dir.child("input").path());
+    cmd.assert().success();
+    let before = fs::read(bank.path())?;
+
+    dir.child("master.bank.read-only").touch()?;
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("repack").arg(bank.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("read-only"));
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg(bank.path())
+        .arg(dir.child("input").path());
+    cmd.assert().failure();
+    assert_eq!(fs::read(bank.path())?, before);
+
+    // Copies can still be written elsewhere.
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("repack")
+        .arg("--output")
+        .arg(dir.child("copy.bank").path())
+        .arg(bank.path());
+    cmd.assert().success();
+
+    Ok(())
+}
+
+#[test]
+fn create_require_background() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("preset.phaseplant").write_str("preset")?;
+    let bank = dir.child("plain.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--require-background")
+        .arg(bank.path())
+        .arg(dir.child("preset.phaseplant").path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No background image"));
+
+    Ok(())
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn create_generate_background() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("preset.phaseplant").write_str("preset")?;
+    let bank = dir.child("generated.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg("--require-background")
+        .arg("--generate-background")
+        .arg("--name")
+        .arg("Generated")
+        .arg(bank.path())
+        .arg(dir.child("preset.phaseplant").path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg(bank.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("background.png"));
+
+    Ok(())
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn scan_thumbnails() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("banks/background.png")
+        .write_binary(include_bytes!("images/background.png"))?;
+    dir.child("banks/preset.phaseplant").write_str("preset")?;
+    let bank = dir.child("banks/pictured.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg(bank.path())
+        .arg(dir.child("banks/background.png").path())
+        .arg(dir.child("banks/preset.phaseplant").path());
+    cmd.assert().success();
+
+    let thumbnails = dir.child("thumbnails");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("scan")
+        .arg("--thumbnails")
+        .arg(thumbnails.path())
+        .arg(dir.child("banks").path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"thumbnail\""));
+    assert_eq!(fs::read_dir(thumbnails.path())?.count(), 1);
+
+    Ok(())
+}
+
+#[cfg(feature = "schema")]
+#[test]
+fn schema() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("schema").arg("catalog");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#""title": "Catalog""#))
+        .stdout(predicate::str::contains("BankSummary"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("schema").arg("create-summary");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("fingerprint"));
+
+    Ok(())
+}
+
+#[test]
+fn kinds() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("kinds");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("PhasePlantPreset"))
+        .stdout(predicate::str::contains("Phase Plant preset"))
+        .stdout(predicate::str::contains("phaseplant"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("kinds").arg("--json");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let kinds: serde_json::Value = serde_json::from_slice(&output)?;
+    let kinds = kinds.as_array().ok_or("Not an array")?;
+    assert_eq!(kinds.len(), ItemKind::all().len());
+    assert!(kinds
+        .iter()
+        .any(|kind| kind["kind"] == "PhasePlantPreset" && kind["directory"] == "phaseplant"));
+
+    Ok(())
+}
+
+#[test]
+fn create_preserve_input_order() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("z.phaseplant").write_str("preset")?;
+    dir.child("a.wav").write_str("sample")?;
+    let bank = dir.child("ordered.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg("--preserve-input-order")
+        .arg(bank.path())
+        .arg(dir.child("z.phaseplant").path())
+        .arg(dir.child("a.wav").path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg(bank.path());
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let listing = String::from_utf8(output)?;
+    let preset = listing.find("z.phaseplant").unwrap();
+    let sample = listing.find("a.wav").unwrap();
+    assert!(preset < sample);
+
+    Ok(())
+}
+
+#[test]
+fn natural_order() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("Preset 10.phaseplant").write_str("ten")?;
+    dir.child("Preset 2.phaseplant").write_str("two")?;
+    let bank = dir.child("natural.bank");
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg("--natural-order")
+        .arg(bank.path())
+        .arg(dir.child("Preset 10.phaseplant").path())
+        .arg(dir.child("Preset 2.phaseplant").path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg(bank.path());
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let listing = String::from_utf8(output)?;
+    assert!(listing.find("Preset 2.").unwrap() < listing.find("Preset 10.").unwrap());
+
+    // Listing sorts the items however they were written.
+    let added = dir.child("added.bank");
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("create")
+        .arg("--summary")
+        .arg("none")
+        .arg("--preserve-input-order")
+        .arg(added.path())
+        .arg(dir.child("Preset 10.phaseplant").path())
+        .arg(dir.child("Preset 2.phaseplant").path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("list").arg("--natural-order").arg(added.path());
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let listing = String::from_utf8(output)?;
+    assert!(listing.find("Preset 2.").unwrap() < listing.find("Preset 10.").unwrap());
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+#![cfg(feature = "std")]
+
+use assert_fs::prelude::*;
+
+use kibank::collect::{self, SkipReason};
+use kibank::ItemKind;
+
+/// Files that cannot be added are reported along with why.
+#[test]
+fn skipped() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("bass.phaseplant").write_str("preset").unwrap();
+    dir.child("empty.phaseplant").touch().unwrap();
+    dir.child("notes.txt").write_str("notes").unwrap();
+    dir.child("samples/kick.wav").write_str("sample").unwrap();
+
+    // Listing a file again does not add it twice.
+    let collection = collect::collect([dir.path(), &dir.path().join("bass.phaseplant")]);
+    let kinds = collection
+        .files
+        .iter()
+        .map(|file| file.kind)
+        .collect::<Vec<_>>();
+    assert_eq!(kinds, [ItemKind::PhasePlantPreset, ItemKind::Sample]);
+
+    let reasons = collection
+        .skipped
+        .iter()
+        .map(|skipped| (skipped.path.file_name().unwrap(), &skipped.reason))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reasons,
+        [
+            ("empty.phaseplant".as_ref(), &SkipReason::Empty),
+            ("notes.txt".as_ref(), &SkipReason::UnknownType)
+        ]
+    );
+}
+
+#[test]
+fn unreadable() {
+    let collection = collect::collect(["tests/does/not/exist"]);
+    assert!(collection.files.is_empty());
+    assert!(matches!(
+        collection.skipped[0].reason,
+        SkipReason::Unreadable { .. }
+    ));
+}
+
+/// A link back up the tree is not followed, while links to files and the
+/// directories given are.
+#[cfg(unix)]
+#[test]
+fn symlink_loop() {
+    use std::os::unix::fs::symlink;
+
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("in/background.png").write_str("image").unwrap();
+    dir.child("in/samples/kick.wav")
+        .write_str("sample")
+        .unwrap();
+    symlink(dir.child("in").path(), dir.child("in/samples/loop").path()).unwrap();
+    dir.child("other/snare.wav").write_str("sample").unwrap();
+    symlink(
+        dir.child("other/snare.wav").path(),
+        dir.child("in/snare.wav").path(),
+    )
+    .unwrap();
+    symlink(dir.child("in").path(), dir.child("link").path()).unwrap();
+
+    let collection = collect::collect([dir.child("link").path()]);
+    assert!(collection.skipped.is_empty());
+    let names = collection
+        .files
+        .iter()
+        .map(|file| file.path.file_name().unwrap().to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["background.png", "kick.wav", "snare.wav"]);
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+
+use kibank::compat::{find_version, preset_versions, Version};
+use kibank::read::BankReader;
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+#[test]
+fn parse_version() {
+    let version = "2.1".parse::<Version>().unwrap();
+    assert_eq!(
+        version,
+        Version {
+            major: 2,
+            minor: 1,
+            patch: 0
+        }
+    );
+    assert_eq!(version.to_string(), "2.1.0");
+    assert!("2.1.3".parse::<Version>().unwrap() > version);
+    for invalid in ["", "2", "2.", "2.x", "2.1.3.4", "-2.1"] {
+        assert!(invalid.parse::<Version>().is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn find_in_header() {
+    assert_eq!(
+        find_version(b"\x00\x01PhasePlant 2.1.3\x00"),
+        "2.1.3".parse().ok()
+    );
+    assert_eq!(find_version(b"v1.4\x00\x00"), "1.4".parse().ok());
+
+    // Lone numbers, long runs and binary data are not versions.
+    assert_eq!(find_version(b"\x02\x00 42 "), None);
+    assert_eq!(find_version(b"192.168.100.200.1"), None);
+    assert_eq!(find_version(b"123456.1"), None);
+}
+
+#[test]
+fn presets() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    let presets: [(ItemKind, &str, &[u8]); 3] = [
+        (
+            ItemKind::PhasePlantPreset,
+            "new.phaseplant",
+            b"\x00saved 2.2.0\x00",
+        ),
+        (
+            ItemKind::PhasePlantPreset,
+            "old.phaseplant",
+            b"\x00saved 1.8.5\x00",
+        ),
+        (
+            ItemKind::PhasePlantPreset,
+            "unknown.phaseplant",
+            b"\x00\x01\x02",
+        ),
+    ];
+    for (kind, name, contents) in presets {
+        writer
+            .add(kind, OsStr::new(name), contents.to_vec())
+            .unwrap();
+    }
+    writer
+        .add(ItemKind::Sample, OsStr::new("v3.0.wav"), b"3.0.0".to_vec())
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let mut reader = BankReader::new(Cursor::new(out)).unwrap();
+    let versions = preset_versions(&mut reader).unwrap();
+    assert_eq!(versions.len(), 3);
+    let target = "2.1".parse().unwrap();
+    let newer = versions
+        .iter()
+        .filter(|preset| preset.is_newer_than(target))
+        .map(|preset| preset.path.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(newer, ["phaseplant/new.phaseplant"]);
+    assert!(versions
+        .iter()
+        .any(|preset| preset.path.ends_with("unknown.phaseplant") && preset.version.is_none()));
+}
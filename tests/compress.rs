@@ -0,0 +1,124 @@
+//! Test compressing and decompressing banks.
+#![cfg(feature = "compression")]
+
+use std::ffi::OsStr;
+use std::io::{Cursor, ErrorKind, Read};
+
+use kibank::compress::{
+    compress, decompress, decompress_to_temp, x_bank_has_compressed_items, x_has_compressed_items,
+    x_read_contents, Compression,
+};
+use kibank::extract::{extract_all, ExtractOptions};
+use kibank::read::BankReader;
+use kibank::write::{BankWriter, BankWriterOptions};
+use kibank::{ItemKind, Metadata};
+
+#[test]
+fn round_trip() {
+    let bank = std::fs::read("tests/metadata.bank").unwrap();
+    for compression in [Compression::Gzip, Compression::Zstd] {
+        let mut compressed = Vec::new();
+        compress(bank.as_slice(), &mut compressed, compression).unwrap();
+        assert_eq!(Compression::detect(&compressed), Some(compression));
+
+        let mut decompressed = Vec::new();
+        decompress(compressed.as_slice(), &mut decompressed, compression).unwrap();
+        assert_eq!(decompressed, bank);
+    }
+    assert_eq!(Compression::detect(&bank), None);
+}
+
+/// Banks are decompressed to a file, up to the limit.
+#[test]
+fn decompress_limit() {
+    let bank = std::fs::read("tests/metadata.bank").unwrap();
+    let len = bank.len() as u64;
+    let mut compressed = Vec::new();
+    compress(bank.as_slice(), &mut compressed, Compression::Zstd).unwrap();
+
+    let mut file = decompress_to_temp(compressed.as_slice(), Compression::Zstd, Some(len)).unwrap();
+    let mut decompressed = Vec::new();
+    file.read_to_end(&mut decompressed).unwrap();
+    assert_eq!(decompressed, bank);
+
+    let error =
+        decompress_to_temp(compressed.as_slice(), Compression::Zstd, Some(len - 1)).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn from_path() {
+    assert_eq!(
+        Compression::from_path("pack.bank.zst"),
+        Some(Compression::Zstd)
+    );
+    assert_eq!(
+        Compression::from_path("pack.bank.GZ"),
+        Some(Compression::Gzip)
+    );
+    assert_eq!(Compression::from_path("pack.bank"), None);
+}
+
+/// Items compressed one at a time are only extracted when asked to
+/// decompress them.
+#[test]
+fn compressed_items() {
+    let preset = "{\"preset\": true}".repeat(100).into_bytes();
+    let options = BankWriterOptions {
+        x_compress_items: true,
+        ..BankWriterOptions::default()
+    };
+    let mut bytes = Vec::new();
+    let mut writer = BankWriter::with_options(Cursor::new(&mut bytes), options);
+    writer
+        .add(
+            ItemKind::PhasePlantPreset,
+            OsStr::new("pad.phaseplant"),
+            preset.clone(),
+        )
+        .unwrap();
+    writer.write().unwrap();
+
+    let mut reader = BankReader::new(Cursor::new(bytes)).unwrap();
+    assert!(x_bank_has_compressed_items(&mut reader));
+    let item = reader.get("phaseplant/pad.phaseplant").cloned().unwrap();
+    assert!(item.size() < preset.len() as u64);
+    assert_eq!(x_read_contents(&mut reader, &item).unwrap(), preset);
+    let metadata_item = reader.get("index.json").cloned().unwrap();
+    let metadata: Metadata =
+        serde_json::from_slice(&x_read_contents(&mut reader, &metadata_item).unwrap()).unwrap();
+    assert!(!x_has_compressed_items(&metadata));
+
+    let dest = assert_fs::TempDir::new().unwrap();
+    assert!(extract_all(&mut reader, dest.path(), &ExtractOptions::default()).is_err());
+    let options = ExtractOptions {
+        x_decompress: true,
+        ..ExtractOptions::default()
+    };
+    extract_all(&mut reader, dest.path(), &options).unwrap();
+    assert_eq!(
+        std::fs::read(dest.path().join("phaseplant").join("pad.phaseplant")).unwrap(),
+        preset
+    );
+
+    let mut reader = BankReader::open("tests/metadata.bank").unwrap();
+    assert!(!x_bank_has_compressed_items(&mut reader));
+    assert!(extract_all(&mut reader, dest.path(), &options).is_err());
+}
+
+/// Compressed items cannot be written without metadata to mark the bank.
+#[test]
+fn compressed_items_empty_metadata() {
+    let options = BankWriterOptions {
+        x_compress_items: true,
+        ..BankWriterOptions::default()
+    };
+    let mut writer = BankWriter::with_options(Cursor::new(Vec::new()), options);
+    writer
+        .add(ItemKind::Metadata, OsStr::new("index.json"), Vec::new())
+        .unwrap();
+    writer
+        .add(ItemKind::Sample, OsStr::new("kick.wav"), vec![1; 100])
+        .unwrap();
+    assert_eq!(writer.write().unwrap_err().kind(), ErrorKind::InvalidInput);
+}
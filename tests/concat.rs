@@ -0,0 +1,47 @@
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::{BufReader, Cursor};
+
+use kibank::concat::{self, Section};
+use kibank::read::BankReader;
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+fn bank(file_name: &str, contents: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut writer = BankWriter::new(&mut bytes);
+    writer
+        .add(
+            ItemKind::PhasePlantPreset,
+            OsStr::new(file_name),
+            contents.as_bytes().to_vec(),
+        )
+        .unwrap();
+    writer.write().unwrap();
+    bytes
+}
+
+/// Banks written one after another are each found, ignoring what follows.
+#[test]
+fn bank_ranges() {
+    let first = bank("first.phaseplant", "first");
+    let second = bank("second.phaseplant", "second preset");
+    let banks = [first.as_slice(), second.as_slice(), b"trailing"].concat();
+
+    let ranges = concat::bank_ranges(&mut Cursor::new(&banks)).unwrap();
+    let first_len = first.len() as u64;
+    let second_len = second.len() as u64;
+    assert_eq!(ranges, [0..first_len, first_len..first_len + second_len]);
+
+    let section = Section::new(Cursor::new(&banks), ranges[1].clone());
+    let mut reader = BankReader::new(BufReader::new(section)).unwrap();
+    assert_eq!(reader.size(), second_len);
+    let item = reader.get("phaseplant/second.phaseplant").unwrap().clone();
+    assert_eq!(reader.read_contents(&item).unwrap(), b"second preset");
+}
+
+#[test]
+fn not_a_bank() {
+    assert!(concat::bank_ranges(&mut Cursor::new(b"not a bank")).is_err());
+}
@@ -0,0 +1,120 @@
+#![cfg(feature = "std")]
+
+use std::io::Cursor;
+
+use assert_fs::prelude::*;
+
+use kibank::create::{self, Collected, Event};
+use kibank::read::BankReader;
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+fn source_dir() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("bass.phaseplant").write_str("bass").unwrap();
+    dir.child("lead.phaseplant").write_str("lead").unwrap();
+    dir.child("samples/kick.wav").write_str("kick").unwrap();
+    dir.child("background.png")
+        .write_binary(include_bytes!("images/background.png"))
+        .unwrap();
+    dir.child("index.json")
+        .write_str(
+            r#"{"id": "author.file", "name": "From file", "author": "Author", "description": ""}"#,
+        )
+        .unwrap();
+    dir
+}
+
+/// Each stage can be inspected before going on to the next.
+#[test]
+fn stages() {
+    let dir = source_dir();
+    let classified = Collected::new([dir.path()]).classify();
+    let counts = classified.counts();
+    assert_eq!(counts[&ItemKind::PhasePlantPreset], 2);
+    assert_eq!(counts[&ItemKind::Sample], 1);
+    assert_eq!(counts[&ItemKind::Background], 1);
+    assert!(classified.metadata_file.is_some());
+
+    let mut metadata = classified.metadata().unwrap();
+    assert_eq!(metadata.name, "From file");
+    metadata.name = "Changed".to_string();
+    let merged = classified.with_metadata(metadata);
+
+    let mut bank = Vec::new();
+    let mut writer = BankWriter::new(&mut bank);
+    let added = merged.add_to(&mut writer).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    assert_eq!(added.len(), 4);
+
+    let mut reader = BankReader::new(Cursor::new(bank)).unwrap();
+    let item = reader
+        .items()
+        .into_iter()
+        .find(|item| item.is_metadata_file())
+        .unwrap();
+    let metadata = reader.read_metadata(&item).unwrap();
+    assert_eq!(metadata.name, "Changed");
+    assert_eq!(metadata.author, "Author");
+}
+
+/// Every step is observed and the bank is only written if every step is
+/// accepted.
+#[test]
+fn run() {
+    let dir = source_dir();
+    let mut steps = Vec::new();
+    let mut bank = Vec::new();
+    let written = create::run([dir.path()], &mut BankWriter::new(&mut bank), |event| {
+        steps.push(match event {
+            Event::Collected(collection) => format!("collected {}", collection.files.len()),
+            Event::Classified(classified) => format!("classified {}", classified.items.len()),
+            Event::MetadataMerged(merged) => format!("merged {}", merged.metadata.is_some()),
+            Event::Written { files } => format!("written {files}"),
+        });
+        true
+    })
+    .unwrap();
+    assert!(written);
+    assert_eq!(
+        steps,
+        ["collected 5", "classified 3", "merged false", "written 5"]
+    );
+    assert!(!bank.is_empty());
+
+    // Stopping after the files are classified leaves the bank unwritten.
+    let mut bank = Vec::new();
+    let written = create::run([dir.path()], &mut BankWriter::new(&mut bank), |event| {
+        !matches!(event, Event::Classified(_))
+    })
+    .unwrap();
+    assert!(!written);
+    assert!(bank.is_empty());
+}
+
+/// A changelog found with the other files is written to the top of the bank
+/// with a lowercase name.
+#[test]
+fn changelog() {
+    let dir = source_dir();
+    dir.child("docs/ChangeLog.MD").write_str("# 1.0\n").unwrap();
+    let classified = Collected::new([dir.path()]).classify();
+    assert!(classified.changelog.is_some());
+    assert_eq!(classified.counts()[&ItemKind::Changelog], 1);
+
+    let mut bank = Vec::new();
+    let mut writer = BankWriter::new(&mut bank);
+    classified.keep_metadata().add_to(&mut writer).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let mut reader = BankReader::new(Cursor::new(bank)).unwrap();
+    let item = reader
+        .items()
+        .into_iter()
+        .find(|item| item.is_changelog_file())
+        .unwrap();
+    assert_eq!(item.file_name_lossy(), "changelog.md");
+    assert_eq!(reader.read_contents(&item).unwrap(), b"# 1.0\n");
+}
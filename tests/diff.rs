@@ -0,0 +1,140 @@
+//! Test the differences between versions of a bank.
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+
+use assert_fs::prelude::*;
+
+use serde_json::{json, Value};
+
+use kibank::diff::{self, BankDiff};
+use kibank::read::BankReader;
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+fn bank(samples: &[(&str, &[u8])]) -> BankReader<'static, Cursor<Vec<u8>>> {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    for (name, contents) in samples {
+        writer
+            .add(ItemKind::Sample, OsStr::new(name), contents.to_vec())
+            .unwrap();
+    }
+    writer.write().unwrap();
+    BankReader::new(Cursor::new(out)).unwrap()
+}
+
+#[test]
+fn between() {
+    let mut old = bank(&[
+        ("same.wav", b"same"),
+        ("resized.wav", b"small"),
+        ("edited.wav", b"before"),
+        ("gone.wav", b"gone"),
+    ]);
+    let mut new = bank(&[
+        ("same.wav", b"same"),
+        ("resized.wav", b"larger"),
+        ("edited.wav", b"after!"),
+        ("new.wav", b"new"),
+    ]);
+
+    let diff = BankDiff::between(&mut old, &mut new).unwrap();
+    assert_eq!(diff.added, vec!["samples/new.wav"]);
+    assert_eq!(diff.removed, vec!["samples/gone.wav"]);
+    assert_eq!(
+        diff.changed,
+        vec!["samples/edited.wav", "samples/resized.wav"]
+    );
+    // The metadata written for both banks is the same.
+    assert_eq!(diff.unchanged, 2);
+    assert!(!diff.is_empty());
+    assert_eq!(
+        diff.to_string(),
+        "~ samples/edited.wav\n- samples/gone.wav\n+ samples/new.wav\n~ samples/resized.wav\n"
+    );
+}
+
+#[test]
+fn unchanged() {
+    let mut old = bank(&[("same.wav", b"same")]);
+    let mut new = bank(&[("same.wav", b"same")]);
+
+    let diff = BankDiff::between(&mut old, &mut new).unwrap();
+    assert!(diff.is_empty());
+    assert_eq!(diff.unchanged, 2);
+    assert_eq!(diff.to_string(), "");
+}
+
+#[test]
+fn of_new_bank() {
+    let new = bank(&[("one.wav", b"1"), ("two.wav", b"2")]);
+
+    let diff = BankDiff::of_new_bank(&new);
+    assert_eq!(
+        diff.added,
+        vec!["index.json", "samples/one.wav", "samples/two.wav"]
+    );
+    assert!(diff.removed.is_empty());
+}
+
+#[test]
+fn with_directory() {
+    let mut reader = bank(&[
+        ("same.wav", b"same"),
+        ("edited.wav", b"before"),
+        ("gone.wav", b"gone"),
+    ]);
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("index.json")
+        .write_binary(
+            &reader
+                .read_contents(&reader.get("index.json").cloned().unwrap())
+                .unwrap(),
+        )
+        .unwrap();
+    dir.child("samples/same.wav").write_str("same").unwrap();
+    dir.child("samples/edited.wav").write_str("after!").unwrap();
+    dir.child("samples/new.wav").write_str("new").unwrap();
+
+    let diff = BankDiff::with_directory(&mut reader, dir.path()).unwrap();
+    assert_eq!(diff.added, vec!["samples/new.wav"]);
+    assert_eq!(diff.removed, vec!["samples/gone.wav"]);
+    assert_eq!(diff.changed, vec!["samples/edited.wav"]);
+    assert_eq!(diff.unchanged, 2);
+}
+
+#[test]
+fn metadata_differences() {
+    let bank = |json: &str| {
+        let mut out = Vec::new();
+        let mut writer = BankWriter::new(Cursor::new(&mut out));
+        writer
+            .add(ItemKind::Metadata, OsStr::new("index.json"), json.into())
+            .unwrap();
+        writer.write().unwrap();
+        BankReader::new(Cursor::new(out)).unwrap()
+    };
+    let mut old =
+        bank(r#"{"id":"me.drums","name":"Drums","author":"Me","description":"","legacy":1}"#);
+    let mut new =
+        bank(r#"{"id":"me.drums","name":"Drums 2","author":"Me","description":"","added":true}"#);
+
+    let differences = diff::metadata_differences(&mut old, &mut new).unwrap();
+    assert_eq!(
+        differences.keys().collect::<Vec<_>>(),
+        ["added", "legacy", "name"]
+    );
+    assert_eq!(differences["name"].from, json!("Drums"));
+    assert_eq!(differences["name"].to, json!("Drums 2"));
+    assert_eq!(differences["legacy"].from, json!(1));
+    assert_eq!(differences["legacy"].to, Value::Null);
+    assert_eq!(differences["added"].from, Value::Null);
+
+    let mut same =
+        bank(r#"{"id":"me.drums","name":"Drums","author":"Me","description":"","legacy":1}"#);
+    assert!(diff::metadata_differences(&mut old, &mut same)
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,23 @@
+//! Test encrypting and decrypting banks.
+#![cfg(feature = "encryption")]
+
+use std::io::ErrorKind;
+
+use kibank::encrypt::{decrypt, encrypt, is_encrypted};
+
+#[test]
+fn round_trip() {
+    let bank = std::fs::read("tests/metadata.bank").unwrap();
+    let encrypted = encrypt(&bank, b"secret").unwrap();
+    assert!(is_encrypted(&encrypted));
+    assert!(!is_encrypted(&bank));
+    assert_eq!(decrypt(&encrypted, b"secret").unwrap(), bank);
+}
+
+#[test]
+fn wrong_password() {
+    let bank = std::fs::read("tests/metadata.bank").unwrap();
+    let encrypted = encrypt(&bank, b"secret").unwrap();
+    let error = decrypt(&encrypted, b"guess").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
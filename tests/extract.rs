@@ -0,0 +1,279 @@
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+
+use assert_fs::prelude::*;
+
+use kibank::extract::{self, ExtractOptions, Origin};
+use kibank::read::BankReader;
+use kibank::write::BankWriter;
+use kibank::{ItemKind, Metadata};
+
+fn reader() -> BankReader<'static, Cursor<Vec<u8>>> {
+    let mut bytes = Vec::new();
+    let mut writer = BankWriter::new(&mut bytes);
+    writer
+        .add(ItemKind::Sample, OsStr::new("kick.wav"), vec![1; 10])
+        .unwrap();
+    writer
+        .add(
+            ItemKind::PhasePlantPreset,
+            OsStr::new("bass.phaseplant"),
+            vec![2; 10],
+        )
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    BankReader::new(Cursor::new(bytes)).unwrap()
+}
+
+/// Items that cannot be written are reported while the rest are extracted.
+#[test]
+fn keep_going() {
+    let dir = assert_fs::TempDir::new().unwrap();
+
+    // A file where the directory of samples should be.
+    dir.child("samples").write_str("in the way").unwrap();
+
+    let error = extract::extract_all(&mut reader(), dir.path(), &ExtractOptions::default());
+    assert!(error.is_err());
+
+    let options = ExtractOptions {
+        keep_going: true,
+        ..ExtractOptions::default()
+    };
+    let report = extract::extract_all(&mut reader(), dir.path(), &options).unwrap();
+    assert!(!report.is_complete());
+    let failed = report
+        .failed
+        .iter()
+        .map(|failure| failure.path.as_str())
+        .collect::<Vec<_>>();
+    assert!(failed.contains(&"samples/kick.wav"));
+    assert!(report
+        .extracted
+        .contains(&dir.path().join("phaseplant/bass.phaseplant")));
+}
+
+/// Files are renamed by the template and directories are only made for them.
+#[test]
+fn rename_template() {
+    let mut bytes = Vec::new();
+    let mut writer = BankWriter::new(&mut bytes);
+    writer
+        .add_metadata(&Metadata {
+            id: "me.drums".to_string(),
+            ..Metadata::default()
+        })
+        .unwrap();
+    writer
+        .add(ItemKind::Sample, OsStr::new("kick.wav"), vec![1; 10])
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let dir = assert_fs::TempDir::new().unwrap();
+
+    let options = ExtractOptions {
+        rename_template: Some("{kind}/{stem}_{bankid}.{ext}".to_string()),
+        ..ExtractOptions::default()
+    };
+    let mut reader = BankReader::new(Cursor::new(bytes)).unwrap();
+    let report = extract::extract_all(&mut reader, dir.path(), &options).unwrap();
+    assert_eq!(report.extracted.len(), 2);
+    dir.child("samples/kick_me.drums.wav")
+        .assert(predicates::path::is_file());
+    // The metadata has no directory of its kind.
+    dir.child("index_me.drums.json")
+        .assert(predicates::path::is_file());
+
+    // Every file with the same name.
+    let options = ExtractOptions {
+        rename_template: Some("{bankid}".to_string()),
+        ..ExtractOptions::default()
+    };
+    assert!(extract::extract_all(&mut reader, dir.path(), &options).is_err());
+}
+
+/// Each preset records the bank it was extracted from.
+#[test]
+fn tag_origin() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let options = ExtractOptions {
+        tag_origin: true,
+        ..ExtractOptions::default()
+    };
+    let mut reader = reader();
+    let report = extract::extract_all(&mut reader, dir.path(), &options).unwrap();
+    assert!(report
+        .extracted
+        .iter()
+        .all(|path| !path.to_string_lossy().ends_with(extract::ORIGIN_SUFFIX)));
+
+    let preset = dir.child("phaseplant/bass.phaseplant");
+    let origin = Origin::read(preset.path()).unwrap();
+    assert_eq!(origin.path, "phaseplant/bass.phaseplant");
+    let metadata = reader
+        .read_metadata(&reader.get(Metadata::FILE_NAME).cloned().unwrap())
+        .unwrap();
+    assert_eq!(origin.bank_id, metadata.id);
+    assert_eq!(origin.bank_name, metadata.name);
+
+    // Only presets have an origin.
+    dir.child("samples/kick.wav.origin.json")
+        .assert(predicates::path::missing());
+}
+
+/// Only files of the right size and contents are extracted, without the
+/// directories of the other files.
+#[test]
+fn filters() {
+    let mut bytes = Vec::new();
+    let mut writer = BankWriter::new(&mut bytes);
+    writer
+        .add(ItemKind::Sample, OsStr::new("kick.wav"), vec![1; 10])
+        .unwrap();
+    writer
+        .add(ItemKind::Sample, OsStr::new("snare.wav"), vec![3; 1000])
+        .unwrap();
+    writer
+        .add(
+            ItemKind::PhasePlantPreset,
+            OsStr::new("bass.phaseplant"),
+            vec![2; 10],
+        )
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let reader = || BankReader::new(Cursor::new(bytes.clone())).unwrap();
+
+    let dir = assert_fs::TempDir::new().unwrap();
+    let options = ExtractOptions {
+        min_size: Some(500),
+        ..ExtractOptions::default()
+    };
+    let report = extract::extract_all(&mut reader(), dir.path(), &options).unwrap();
+    assert_eq!(report.extracted, [dir.path().join("samples/snare.wav")]);
+
+    let dir = assert_fs::TempDir::new().unwrap();
+    let options = ExtractOptions {
+        max_size: Some(10),
+        sha256: vec![
+            "FFADF8D89D37B3B55FE1847B513CF92E3BE87E4C168708C7851845DF96FB36BE".to_string(),
+        ],
+        ..ExtractOptions::default()
+    };
+    #[cfg(feature = "checksums")]
+    {
+        let report = extract::extract_all(&mut reader(), dir.path(), &options).unwrap();
+        assert_eq!(report.extracted, [dir.path().join("samples/kick.wav")]);
+        dir.child("phaseplant").assert(predicates::path::missing());
+    }
+    #[cfg(not(feature = "checksums"))]
+    {
+        let error = extract::extract_all(&mut reader(), dir.path(), &options).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+    }
+}
+
+#[test]
+fn bank_dir_names() {
+    let ids = [
+        "me.drums".to_string(),
+        "ME.Drums".to_string(),
+        String::new(),
+        "../..".to_string(),
+        "me.drums".to_string(),
+    ];
+    let fallbacks = [
+        "a".to_string(),
+        "b".to_string(),
+        "Old Pads".to_string(),
+        String::new(),
+        "c".to_string(),
+    ];
+    assert_eq!(
+        extract::bank_dir_names(&ids, &fallbacks),
+        ["me.drums", "me.drums-2", "oldpads", "bank", "me.drums-3"]
+    );
+}
+
+#[test]
+fn extract_banks() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for (file_name, id, sample) in [
+        ("one.bank", "me.drums", "kick.wav"),
+        ("two.bank", "me.drums", "snare.wav"),
+        ("three.bank", "me.pads", "pad.wav"),
+    ] {
+        let mut bytes = Vec::new();
+        let mut writer = BankWriter::new(&mut bytes);
+        writer
+            .add_metadata(&Metadata {
+                id: id.to_string(),
+                ..Metadata::default()
+            })
+            .unwrap();
+        writer
+            .add(ItemKind::Sample, OsStr::new(sample), vec![1; 10])
+            .unwrap();
+        writer.write().unwrap();
+        drop(writer);
+        dir.child("banks")
+            .child(file_name)
+            .write_binary(&bytes)
+            .unwrap();
+    }
+    dir.child("banks/broken.bank")
+        .write_str("not a bank")
+        .unwrap();
+    let bank_paths = [
+        dir.child("banks/broken.bank").to_path_buf(),
+        dir.child("banks/one.bank").to_path_buf(),
+        dir.child("banks/three.bank").to_path_buf(),
+        dir.child("banks/two.bank").to_path_buf(),
+    ];
+
+    let out = dir.child("out");
+    let extractions =
+        extract::extract_banks(&bank_paths, out.path(), &ExtractOptions::default(), 2).unwrap();
+    assert_eq!(extractions.len(), 4);
+    assert!(extractions[0].result.is_err());
+    assert_eq!(extractions[0].dest_dir, out.child("broken").path());
+    assert_eq!(extractions[1].bank_path, bank_paths[1]);
+    assert!(extractions[1..]
+        .iter()
+        .all(|extraction| extraction.result.is_ok()));
+
+    out.child("me.drums/samples/kick.wav")
+        .assert(predicates::path::exists());
+    out.child("me.drums-2/samples/snare.wav")
+        .assert(predicates::path::exists());
+    out.child("me.pads/samples/pad.wav")
+        .assert(predicates::path::exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn file_mode_and_owner() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let dir = assert_fs::TempDir::new().unwrap();
+    let owner = std::fs::metadata(dir.path()).unwrap();
+    let options = ExtractOptions {
+        file_mode: Some(0o640),
+        uid: Some(owner.uid()),
+        gid: Some(owner.gid()),
+        fsync: true,
+        ..ExtractOptions::default()
+    };
+    let report = extract::extract_all(&mut reader(), dir.path(), &options).unwrap();
+    // The metadata, the sample and the preset.
+    assert_eq!(report.extracted.len(), 3);
+    for path in &report.extracted {
+        let metadata = std::fs::metadata(path).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o7777, 0o640);
+        assert_eq!(metadata.uid(), owner.uid());
+        assert_eq!(metadata.gid(), owner.gid());
+    }
+}
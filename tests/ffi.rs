@@ -0,0 +1,68 @@
+//! Test the functions for C hosts.
+#![cfg(feature = "ffi")]
+
+use std::ffi::{c_void, CString};
+use std::ptr;
+
+use kibank::ffi::{kibank_close, kibank_for_each_item, kibank_open, kibank_read_chunk, KibankItem};
+
+extern "C" fn collect(item: *const KibankItem, user_data: *mut c_void) -> bool {
+    let (item, paths) = unsafe { (&*item, &mut *user_data.cast::<Vec<(usize, Vec<u8>, u64)>>()) };
+    let path = unsafe { std::slice::from_raw_parts(item.path, item.path_len) };
+    paths.push((item.index, path.to_vec(), item.size));
+    true
+}
+
+extern "C" fn stop(_item: *const KibankItem, _user_data: *mut c_void) -> bool {
+    false
+}
+
+#[test]
+fn iterate_and_read() {
+    let path = CString::new("tests/metadata.bank").unwrap();
+    unsafe {
+        let bank = kibank_open(path.as_ptr());
+        assert!(!bank.is_null());
+
+        let mut items = Vec::<(usize, Vec<u8>, u64)>::new();
+        let count = kibank_for_each_item(
+            bank,
+            Some(collect),
+            ptr::addr_of_mut!(items).cast::<c_void>(),
+        );
+        assert_eq!(count, 1);
+        let (index, name, size) = items.pop().unwrap();
+        assert_eq!(name, b"index.json");
+        assert_eq!(kibank_for_each_item(bank, Some(stop), ptr::null_mut()), 1);
+
+        // Read the contents in small pieces.
+        let mut contents = Vec::new();
+        let mut buffer = [0_u8; 7];
+        loop {
+            let read = kibank_read_chunk(
+                bank,
+                index,
+                contents.len() as u64,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+            );
+            assert!(read >= 0);
+            if read == 0 {
+                break;
+            }
+            contents.extend_from_slice(&buffer[..read as usize]);
+        }
+        assert_eq!(contents.len() as u64, size);
+        assert!(contents.starts_with(b"{"));
+
+        assert_eq!(kibank_read_chunk(bank, 99, 0, buffer.as_mut_ptr(), 1), -1);
+        kibank_close(bank);
+    }
+
+    let missing = CString::new("tests/missing.bank").unwrap();
+    assert!(unsafe { kibank_open(missing.as_ptr()) }.is_null());
+    assert_eq!(
+        unsafe { kibank_for_each_item(ptr::null(), Some(stop), ptr::null_mut()) },
+        -1
+    );
+}
@@ -0,0 +1,64 @@
+//! Test mounting banks as filesystems.
+#![cfg(all(feature = "fuse", target_os = "linux"))]
+
+use std::ffi::OsStr;
+use std::fs;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::thread;
+
+use assert_fs::TempDir;
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+#[test]
+fn mount_and_read() {
+    let temp = TempDir::new().unwrap();
+    let bank_path = temp.path().join("test.bank");
+    let mut writer = BankWriter::new(File::create(&bank_path).unwrap());
+    writer
+        .add(ItemKind::Sample, OsStr::new("kick.wav"), b"kick".to_vec())
+        .unwrap();
+    writer
+        .add(ItemKind::Sample, OsStr::new("snare.wav"), vec![7; 300_000])
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let mountpoint = temp.path().join("mnt");
+    fs::create_dir(&mountpoint).unwrap();
+
+    let mount = match kibank::fuse::mount(&bank_path, &mountpoint) {
+        Ok(mount) => mount,
+        Err(error) => {
+            // Containers and build machines often don't allow mounting.
+            eprintln!("Not testing since mounting failed: {error}");
+            return;
+        }
+    };
+    let server = thread::spawn(move || mount.serve());
+
+    let samples = mountpoint.join("samples");
+    let mut names = fs::read_dir(&samples)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["kick.wav", "snare.wav"]);
+    assert!(fs::metadata(&samples).unwrap().is_dir());
+    assert_eq!(fs::read(samples.join("kick.wav")).unwrap(), b"kick");
+    assert_eq!(
+        fs::read(samples.join("snare.wav")).unwrap(),
+        vec![7; 300_000]
+    );
+    assert_eq!(
+        fs::metadata(samples.join("missing.wav"))
+            .unwrap_err()
+            .kind(),
+        ErrorKind::NotFound
+    );
+    assert!(fs::write(samples.join("kick.wav"), b"new").is_err());
+
+    kibank::fuse::unmount(&mountpoint).unwrap();
+    server.join().unwrap().unwrap();
+    assert_eq!(fs::read_dir(&mountpoint).unwrap().count(), 0);
+}
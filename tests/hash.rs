@@ -0,0 +1,206 @@
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+
+use kibank::hash::known::{self, Origin};
+#[cfg(feature = "checksums")]
+use kibank::hash::Checksums;
+use kibank::hash::{self, Algorithm};
+use kibank::read::BankReader;
+#[cfg(feature = "checksums")]
+use kibank::verify::{verify, VerifyOptions};
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+fn bank() -> BankReader<'static, Cursor<Vec<u8>>> {
+    let mut bytes = Vec::new();
+    let mut writer = BankWriter::new(&mut bytes);
+    writer
+        .add(ItemKind::Sample, OsStr::new("kick.wav"), b"kick".to_vec())
+        .unwrap();
+    writer
+        .add(
+            ItemKind::PhasePlantPreset,
+            OsStr::new("bass.phaseplant"),
+            b"bass".to_vec(),
+        )
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    BankReader::new(Cursor::new(bytes)).unwrap()
+}
+
+/// Hashes must not change between releases since they are stored in the
+/// metadata of banks and in catalogs.
+#[test]
+fn stable() {
+    assert_eq!(
+        hash::digest(Cursor::new(b"abc")).unwrap(),
+        "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+
+    let mut reader = bank();
+    assert_eq!(
+        hash::content_hash(&mut reader).unwrap(),
+        "6d1e74e0f6cb4129b8c3a70b69e2a20ee417cce1"
+    );
+    let item = reader.get("samples/kick.wav").cloned().unwrap();
+    assert_eq!(
+        hash::item_digest(&mut reader, &item).unwrap(),
+        "eac6136556a96e245d99e94e6f6c86b879857ccd"
+    );
+
+    // Metadata doesn't change the content hash.
+    let mut reader = BankReader::open("tests/metadata.bank").unwrap();
+    assert_eq!(
+        hash::content_hash(&mut reader).unwrap(),
+        "da39a3ee5e6b4b0d3255bfef95601890afd80709"
+    );
+
+    let entries = vec![
+        (b"b.wav".to_vec(), hash::digest(Cursor::new(b"b")).unwrap()),
+        (b"a.wav".to_vec(), hash::digest(Cursor::new(b"a")).unwrap()),
+    ];
+    assert_eq!(
+        hash::manifest_hash(entries),
+        "63ce0b4fba0385a7efe3dfd76411683fd8263282"
+    );
+}
+
+/// Banks that aren't listed are unknown rather than assumed to be third-party.
+#[test]
+fn identify_factory() {
+    for bank in known::FACTORY_BANKS {
+        assert_eq!(known::identify_factory(bank.hash), Origin::Factory(bank));
+        assert_eq!(
+            known::identify_factory(&bank.hash.to_ascii_uppercase()).factory(),
+            Some(bank)
+        );
+    }
+    let origin = known::identify_factory("6d1e74e0f6cb4129b8c3a70b69e2a20ee417cce1");
+    assert_eq!(origin, Origin::Unknown);
+    assert_eq!(origin.factory(), None);
+}
+
+#[test]
+fn digest_files() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let paths = (0..10)
+        .map(|n| {
+            let path = dir.path().join(format!("{n}.wav"));
+            std::fs::write(&path, n.to_string()).unwrap();
+            path
+        })
+        .collect::<Vec<_>>();
+    let expected = (0..10)
+        .map(|n| hash::digest(Cursor::new(n.to_string())).unwrap())
+        .collect::<Vec<_>>();
+
+    // The digests are in the order of the paths no matter how many threads.
+    for threads in [0, 1, 3, 20] {
+        assert_eq!(hash::digest_files(&paths, threads).unwrap(), expected);
+    }
+    assert!(hash::digest_files::<&str>(&[], 0).unwrap().is_empty());
+
+    let error = hash::digest_files(&[dir.path().join("missing.wav")], 1).unwrap_err();
+    assert!(error.to_string().contains("missing.wav"));
+}
+
+/// Digests match the published values for each algorithm.
+#[cfg(feature = "checksums")]
+#[test]
+fn algorithms() {
+    let digest = |name: &str, bytes: &[u8]| {
+        let algorithm = Algorithm::from_name(name).unwrap();
+        let digest = hash::digest_using(bytes, algorithm).unwrap();
+        assert_eq!(digest.len(), algorithm.hex_len());
+        digest
+    };
+    assert_eq!(
+        digest("blake3", b""),
+        "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+    );
+    assert_eq!(
+        digest("SHA-256", b"abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+        digest("sha1", b"abc"),
+        "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+    assert!(Algorithm::from_name("md5").is_none());
+}
+
+/// Only SHA-1 is available without the `checksums` feature.
+#[cfg(not(feature = "checksums"))]
+#[test]
+fn sha1_only() {
+    assert_eq!(Algorithm::NAMES, ["sha1"]);
+    assert!(Algorithm::from_name("blake3").is_none());
+    assert!(Algorithm::from_name("sha256").is_none());
+    assert_eq!(
+        hash::digest_using(b"abc".as_slice(), Algorithm::Sha1).unwrap(),
+        "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+}
+
+/// Checksums written for a bank can be read back and compared.
+#[cfg(feature = "checksums")]
+#[test]
+fn checksums() {
+    let mut reader = bank();
+    let checksums = Checksums::of_bank(&mut reader, Algorithm::Sha256).unwrap();
+    assert_eq!(checksums.digests.len(), 3);
+    let text = checksums.to_string();
+    assert!(text.contains("  samples/kick.wav\n"));
+    assert_eq!(
+        Checksums::parse(&text, Algorithm::Sha256).unwrap(),
+        checksums
+    );
+
+    // SHA-1 digests are too short to be SHA-256 digests.
+    let sha1 = Checksums::of_bank(&mut reader, Algorithm::Sha1).unwrap();
+    assert!(Checksums::parse(&sha1.to_string(), Algorithm::Sha256).is_err());
+    assert!(Checksums::parse("not a checksum", Algorithm::Sha256).is_err());
+}
+
+/// Checksums that don't match are errors when verifying.
+#[cfg(feature = "checksums")]
+#[test]
+fn verify_checksums() {
+    let mut reader = bank();
+    let mut checksums = Checksums::of_bank(&mut reader, Algorithm::Blake3).unwrap();
+    let options = |checksums: &Checksums| VerifyOptions {
+        checksums: Some(checksums.clone()),
+        ..VerifyOptions::default()
+    };
+    assert!(verify(&mut reader, &options(&checksums))
+        .unwrap()
+        .issues
+        .is_empty());
+
+    let digest = checksums.digests.get_mut("samples/kick.wav").unwrap();
+    *digest = "0".repeat(64);
+    checksums.digests.remove("index.json");
+    checksums
+        .digests
+        .insert("samples/snare.wav".to_string(), "0".repeat(64));
+    let report = verify(&mut reader, &options(&checksums)).unwrap();
+    assert!(!report.is_ok());
+    let messages = report
+        .issues
+        .iter()
+        .map(|issue| issue.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(messages.len(), 3, "{messages:?}");
+    assert!(messages
+        .iter()
+        .any(|message| message.contains("Missing samples/snare.wav")));
+    assert!(messages
+        .iter()
+        .any(|message| message.contains("kick.wav does not match")));
+    assert!(messages
+        .iter()
+        .any(|message| message.contains("No checksum for index.json")));
+}
@@ -0,0 +1,118 @@
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+
+use assert_fs::prelude::*;
+
+use kibank::inspect::{self, InspectOptions};
+use kibank::lint::Level;
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+/// A bank with a path that leads outside of the bank and a name with a
+/// control character. The writer refuses `..` so it is changed afterwards.
+fn suspicious_bank() -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add_with_path(ItemKind::Sample, OsStr::new("xx/evil.wav"), vec![1; 10])
+        .unwrap();
+    writer
+        .add(ItemKind::Sample, OsStr::new("bell\u{7}.wav"), vec![2; 10])
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let position = out
+        .windows(11)
+        .position(|window| window == b"xx/evil.wav")
+        .unwrap();
+    out[position..position + 2].copy_from_slice(b"..");
+    out
+}
+
+#[test]
+fn suspicious() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let bank = dir.child("suspicious.bank");
+    bank.write_binary(&suspicious_bank()).unwrap();
+
+    let report = inspect::inspect_file(bank.path(), &InspectOptions::sandbox()).unwrap();
+    assert!(!report.is_ok());
+    let errors = report
+        .issues
+        .iter()
+        .filter(|issue| issue.level == Level::Error)
+        .map(|issue| issue.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(errors.len(), 2);
+    assert!(errors[0].contains("leads outside of the bank"));
+    assert!(errors[1].contains("not printable"));
+}
+
+#[test]
+fn limits() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let bank = dir.child("large.bank");
+    bank.write_binary(&suspicious_bank()).unwrap();
+
+    let options = InspectOptions {
+        max_bank_size: Some(10),
+        ..InspectOptions::sandbox()
+    };
+    let report = inspect::inspect_file(bank.path(), &options).unwrap();
+    assert_eq!(report.issues.len(), 1);
+    assert!(report.issues[0].message.contains("more than the limit"));
+
+    // Limits on the items are checked when the bank is opened.
+    let options = InspectOptions {
+        max_item_size: Some(5),
+        ..InspectOptions::sandbox()
+    };
+    let report = inspect::inspect_file(bank.path(), &options).unwrap();
+    assert_eq!(report.issues.len(), 1);
+    assert!(report.issues[0].message.starts_with("Cannot be opened"));
+}
+
+#[test]
+fn clean() {
+    let report = inspect::inspect_file("tests/metadata.bank", &InspectOptions::sandbox()).unwrap();
+    assert!(report.issues.is_empty());
+}
+
+/// A bank with a sample of `size` bytes, of which only 10 are in the bank.
+fn oversized_bank(size: u64) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add(ItemKind::Sample, OsStr::new("kick.wav"), vec![1; 10])
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    // Sizes are the last field of each location after the 24 byte prefix.
+    let count = u64::from_le_bytes(out[16..24].try_into().unwrap()) as usize;
+    let position = (0..count)
+        .map(|index| 24 + index * 24 + 16)
+        .find(|position| out[*position..*position + 8] == 10_u64.to_le_bytes())
+        .unwrap();
+    out[position..position + 8].copy_from_slice(&size.to_le_bytes());
+    out
+}
+
+#[test]
+fn oversized() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let bank = dir.child("oversized.bank");
+    bank.write_binary(&oversized_bank(500_000_000)).unwrap();
+    let report = inspect::inspect_file(bank.path(), &InspectOptions::sandbox()).unwrap();
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.issues[0].level, Level::Error);
+    assert!(report.issues[0].message.contains("claims 500000000 bytes"));
+
+    bank.write_binary(&oversized_bank(12)).unwrap();
+    let report = inspect::inspect_file(bank.path(), &InspectOptions::sandbox()).unwrap();
+    assert_eq!(report.issues.len(), 1);
+    assert!(report.issues[0].message.contains("past the end"));
+}
@@ -1,6 +1,8 @@
+#![cfg(feature = "std")]
+
 use std::ffi::OsStr;
 
-use kibank::ItemKind;
+use kibank::{ItemKind, Product};
 
 /// Must not be able to add to a bank once it has been written.
 #[test]
@@ -19,3 +21,113 @@ fn has_extension() {
     assert!(ItemKind::Metadata.has_extension(OsStr::new("JSON")));
     assert!(!ItemKind::Metadata.has_extension(OsStr::new("txt")));
 }
+
+#[test]
+fn from_str() {
+    assert_eq!(
+        "PhasePlantPreset".parse::<ItemKind>().unwrap(),
+        ItemKind::PhasePlantPreset
+    );
+    assert_eq!(
+        "phase-plant-preset".parse::<ItemKind>().unwrap(),
+        ItemKind::PhasePlantPreset
+    );
+    assert_eq!("sample".parse::<ItemKind>().unwrap(), ItemKind::Sample);
+    assert!("preset".parse::<ItemKind>().is_err());
+}
+
+#[test]
+fn for_extension() {
+    assert_eq!(ItemKind::for_extension("kfat"), Some(ItemKind::Faturator));
+    assert_eq!(ItemKind::for_extension(".WAV"), Some(ItemKind::Sample));
+    assert_eq!(ItemKind::for_extension("txt"), None);
+}
+
+#[test]
+fn extension_map() {
+    let map = ItemKind::extension_map();
+
+    // Every extension is used by only one kind, except images and previews
+    // which share the extensions of the background and samples, and the
+    // changelog which is found by its name.
+    let count = ItemKind::all()
+        .iter()
+        .filter(|kind| {
+            !matches!(
+                kind,
+                ItemKind::Image | ItemKind::Preview | ItemKind::Changelog
+            )
+        })
+        .map(|kind| kind.extensions().len())
+        .sum::<usize>();
+    assert_eq!(map.len(), count);
+    assert_eq!(map["phaseplant"], ItemKind::PhasePlantPreset);
+    assert_eq!(map["png"], ItemKind::Background);
+    assert_eq!(map["mp3"], ItemKind::Sample);
+    assert_eq!(map["kfat"].directory(), Some("kfat"));
+}
+
+#[test]
+fn image() {
+    assert_eq!(ItemKind::from("images/logo.png"), Some(ItemKind::Image));
+    assert_eq!(ItemKind::from("Images/Logo.JPG"), Some(ItemKind::Image));
+    assert_eq!(ItemKind::from("logo.png"), Some(ItemKind::Background));
+    assert_eq!(ItemKind::from("images/notes.txt"), None);
+    assert_eq!(ItemKind::Image.directory(), Some("images"));
+    assert!(!ItemKind::Image.is_preset());
+}
+
+#[test]
+fn preview() {
+    assert_eq!(
+        ItemKind::from("previews/Big Bass.mp3"),
+        Some(ItemKind::Preview)
+    );
+    assert_eq!(ItemKind::from("Previews/Pad.FLAC"), Some(ItemKind::Preview));
+    assert_eq!(ItemKind::from("previews/Kick.wav"), Some(ItemKind::Sample));
+    assert_eq!(ItemKind::from("Big Bass.mp3"), Some(ItemKind::Sample));
+    assert_eq!(ItemKind::for_extension("flac"), Some(ItemKind::Sample));
+    assert_eq!(ItemKind::Preview.directory(), Some("previews"));
+    assert!(!ItemKind::Preview.is_preset());
+    assert_eq!(ItemKind::Preview.product(), None);
+}
+
+#[test]
+fn product() {
+    assert_eq!(
+        ItemKind::PhasePlantPreset.product(),
+        Some(Product::PhasePlant)
+    );
+    assert_eq!(ItemKind::Reverb.product(), Some(Product::Snapins));
+    assert_eq!(ItemKind::Sample.product(), None);
+
+    let counts = Product::count_presets([
+        ItemKind::PhasePlantPreset,
+        ItemKind::Reverb,
+        ItemKind::Delay,
+        ItemKind::Background,
+    ]);
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts[&Product::PhasePlant], 1);
+    assert_eq!(counts[&Product::Snapins], 2);
+    assert_eq!(Product::Snapins.to_string(), "snapins");
+}
+
+#[test]
+fn changelog() {
+    assert_eq!(ItemKind::from("changelog.md"), Some(ItemKind::Changelog));
+    assert_eq!(ItemKind::from("CHANGELOG.txt"), Some(ItemKind::Changelog));
+    assert_eq!(ItemKind::from("notes.txt"), None);
+    assert_eq!(ItemKind::for_extension("md"), None);
+    assert_eq!(ItemKind::Changelog.directory(), None);
+    assert!(!ItemKind::Changelog.is_preset());
+}
+
+#[test]
+fn label() {
+    assert_eq!(ItemKind::PhasePlantPreset.label(), "Phase Plant preset");
+    assert_eq!(ItemKind::ThreeBandEq.label(), "3-Band EQ preset");
+
+    // All kinds must have a label.
+    assert!(ItemKind::all().iter().all(|kind| !kind.label().is_empty()));
+}
@@ -0,0 +1,387 @@
+//! Test finding the items in a bank.
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+
+use kibank::read::{BankReader, BankReaderOptions, Item, OwnedItem};
+use kibank::roundtrip;
+use kibank::write::{AtomicFile, BankWriter, BankWriterOptions, ContentSource};
+use kibank::ItemKind;
+
+/// A bank with a background, metadata and two samples.
+fn create_bank() -> Cursor<Vec<u8>> {
+    let mut out = Vec::with_capacity(4096);
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add_file(
+            ItemKind::Background,
+            OsStr::new("background.png"),
+            "tests/images/background.png",
+        )
+        .unwrap();
+    writer
+        .add(ItemKind::Sample, OsStr::new("one.wav"), vec![1; 100])
+        .unwrap();
+    writer
+        .add(ItemKind::Sample, OsStr::new("two.wav"), vec![2; 100])
+        .unwrap();
+    writer.write().unwrap();
+    Cursor::new(out)
+}
+
+#[test]
+fn items_in_data_order() {
+    let reader = BankReader::new(create_bank()).unwrap();
+    let items = reader.items_in_data_order();
+    assert_eq!(items.len(), reader.items().len());
+    assert!(items.first().unwrap().is_directory());
+    assert!(items
+        .windows(2)
+        .all(|pair| pair[0].is_directory() || pair[0].offset() < pair[1].offset()));
+}
+
+/// Reading ahead must not change the contents that are read.
+#[test]
+fn readahead() {
+    let mut reader = BankReader::new(create_bank()).unwrap();
+    let options = BankReaderOptions {
+        readahead_bytes: 150,
+        ..BankReaderOptions::default()
+    };
+    let mut readahead_reader = BankReader::with_options(create_bank(), options).unwrap();
+    for item in reader.items_in_data_order() {
+        assert_eq!(
+            reader.read_contents(&item).unwrap(),
+            readahead_reader.read_contents(&item).unwrap()
+        );
+    }
+}
+
+/// Owned items outlive the reader and can be sent to another thread.
+#[test]
+fn into_owned() {
+    let items: Vec<OwnedItem> = {
+        let reader = BankReader::new(create_bank()).unwrap();
+        reader.items().into_iter().map(Item::into_owned).collect()
+    };
+    let names = std::thread::spawn(move || {
+        items
+            .iter()
+            .map(|item| item.file_name_lossy())
+            .collect::<Vec<_>>()
+    })
+    .join()
+    .unwrap();
+    assert!(names.iter().any(|name| name.ends_with("one.wav")));
+}
+
+#[test]
+fn lookups() {
+    let reader = BankReader::new(create_bank()).unwrap();
+    let item = reader.get("Samples/ONE.wav").unwrap();
+    assert_eq!(item.path_bytes(), b"samples/one.wav");
+    assert!(reader.get("samples/three.wav").is_none());
+
+    let by_kind = reader.items_by_kind();
+    assert_eq!(by_kind[&ItemKind::Sample].len(), 2);
+    assert_eq!(by_kind[&ItemKind::Background].len(), 1);
+    assert_eq!(by_kind[&ItemKind::Metadata].len(), 1);
+}
+
+/// Directories that don't belong to a kind of item are kept.
+#[test]
+fn unknown_directory() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer.add_directory(OsStr::new("custom")).unwrap();
+    writer.add_directory(OsStr::new("Samples")).unwrap();
+    writer
+        .add(ItemKind::Sample, OsStr::new("one.wav"), vec![1; 100])
+        .unwrap();
+    writer.write().unwrap();
+
+    let reader = BankReader::new(Cursor::new(out)).unwrap();
+    let directories = reader
+        .items()
+        .into_iter()
+        .filter(Item::is_directory)
+        .collect::<Vec<_>>();
+    assert_eq!(directories.len(), 2);
+    assert!(reader.get("custom").unwrap().is_unknown_directory());
+    assert!(!reader.get("samples").unwrap().is_unknown_directory());
+}
+
+#[test]
+fn directories() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer.add_directory(OsStr::new("custom")).unwrap();
+    writer
+        .add(ItemKind::Sample, OsStr::new("one.wav"), vec![1; 100])
+        .unwrap();
+    writer
+        .add(
+            ItemKind::PhasePlantPreset,
+            OsStr::new("Lead.phaseplant"),
+            vec![1; 100],
+        )
+        .unwrap();
+    writer.write().unwrap();
+
+    let reader = BankReader::new(Cursor::new(out)).unwrap();
+    let mut directories = reader
+        .directories()
+        .into_iter()
+        .map(|(item, kind)| (item.file_name_lossy(), kind))
+        .collect::<Vec<_>>();
+    directories.sort();
+    assert_eq!(
+        directories,
+        vec![
+            ("custom".to_string(), None),
+            ("phaseplant".to_string(), Some(ItemKind::PhasePlantPreset)),
+            ("samples".to_string(), Some(ItemKind::Sample)),
+        ]
+    );
+    assert_eq!(
+        reader.get("samples/one.wav").unwrap().directory_kind(),
+        None
+    );
+}
+
+/// Rebuilding a bank in its original order reproduces it exactly.
+#[test]
+fn preserve_order() {
+    let original = create_bank().into_inner();
+    let mut reader = BankReader::new(Cursor::new(original.clone())).unwrap();
+    let mut rebuilt = Vec::new();
+    let options = BankWriterOptions {
+        preserve_order: true,
+        ..BankWriterOptions::default()
+    };
+    let mut writer = BankWriter::with_options(Cursor::new(&mut rebuilt), options);
+    roundtrip::rebuild(&mut reader, &mut writer).unwrap();
+    writer.write().unwrap();
+    assert_eq!(rebuilt, original);
+}
+
+/// Images other than the background, like the logos in some factory banks,
+/// keep their kind and directory when a bank is rebuilt.
+#[test]
+fn rebuild_images() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add_file(
+            ItemKind::Background,
+            OsStr::new("background.png"),
+            "tests/images/background.png",
+        )
+        .unwrap();
+    writer
+        .add(ItemKind::Image, OsStr::new("logo.png"), vec![3; 10])
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let mut reader = BankReader::new(Cursor::new(out.clone())).unwrap();
+    let by_kind = reader.items_by_kind();
+    assert_eq!(by_kind[&ItemKind::Background].len(), 1);
+    assert_eq!(by_kind[&ItemKind::Image].len(), 1);
+    assert!(reader.get("images/logo.png").is_some());
+
+    let mut rebuilt = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut rebuilt));
+    roundtrip::rebuild(&mut reader, &mut writer).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    assert_eq!(rebuilt, out);
+}
+
+/// Paths written with Windows separators are read with the usual separator
+/// unless normalizing is turned off, and are reported by verify either way.
+#[test]
+fn windows_separators() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add(ItemKind::Sample, OsStr::new("one.wav"), vec![1; 10])
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    // The writer always uses the usual separator so change it afterwards.
+    let position = out
+        .windows(15)
+        .position(|window| window == b"samples/one.wav")
+        .unwrap();
+    out[position + 7] = b'\\';
+
+    let mut reader = BankReader::new(Cursor::new(out.clone())).unwrap();
+    let item = reader.get("samples/one.wav").cloned().unwrap();
+    assert!(item.has_windows_separators());
+    let report = kibank::verify::verify(&mut reader, &Default::default()).unwrap();
+    assert!(report
+        .issues
+        .iter()
+        .any(|issue| issue.message.contains("samples/one.wav")));
+
+    let options = BankReaderOptions {
+        normalize_separators: false,
+        ..BankReaderOptions::default()
+    };
+    let reader = BankReader::with_options(Cursor::new(out), options).unwrap();
+    assert!(reader.get("samples\\one.wav").is_some());
+    assert!(reader.get("samples/one.wav").is_none());
+}
+
+#[test]
+fn invalid_paths() {
+    let mut writer = BankWriter::new(Vec::new());
+    for path in ["", "/samples/one.wav", "samples/../one.wav", "one\0.wav"] {
+        let error = writer
+            .add_with_path(ItemKind::Sample, OsStr::new(path), vec![1])
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+    assert!(writer.add_directory(OsStr::new("..")).is_err());
+}
+
+/// Windows separators are written as the usual separator.
+#[test]
+fn write_windows_separators() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add_with_path(
+            ItemKind::Sample,
+            OsStr::new("samples\\one.wav"),
+            vec![1; 10],
+        )
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let options = BankReaderOptions {
+        normalize_separators: false,
+        ..BankReaderOptions::default()
+    };
+    let reader = BankReader::with_options(Cursor::new(out), options).unwrap();
+    let item = reader.get("samples/one.wav").unwrap();
+    assert!(!item.has_windows_separators());
+}
+
+#[test]
+fn limits() {
+    let options = BankReaderOptions {
+        max_items: Some(3),
+        ..BankReaderOptions::default()
+    };
+    let Err(error) = BankReader::with_options(create_bank(), options) else {
+        panic!("Limit was not enforced");
+    };
+    assert!(error.to_string().contains("more than the limit of 3"));
+
+    let options = BankReaderOptions {
+        max_item_size: Some(99),
+        ..BankReaderOptions::default()
+    };
+    let Err(error) = BankReader::with_options(create_bank(), options) else {
+        panic!("Limit was not enforced");
+    };
+    assert!(error
+        .to_string()
+        .contains("more than the limit of 99 bytes"));
+
+    let options = BankReaderOptions {
+        max_bank_size: Some(100),
+        ..BankReaderOptions::default()
+    };
+    let Err(error) = BankReader::with_options(create_bank(), options) else {
+        panic!("Limit was not enforced");
+    };
+    assert!(error.to_string().contains("more than the limit of 100"));
+
+    let options = BankReaderOptions {
+        max_items: Some(100),
+        max_item_size: Some(1024 * 1024),
+        ..BankReaderOptions::default()
+    };
+    assert!(BankReader::with_options(create_bank(), options).is_ok());
+}
+
+/// Streaming the contents gives the same bytes as reading them all at once,
+/// whether or not they are larger than a chunk or read ahead.
+#[test]
+fn copy_to() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    let large = (0..200_000_u32).map(|i| i as u8).collect::<Vec<_>>();
+    writer
+        .add(ItemKind::Sample, OsStr::new("large.wav"), large.clone())
+        .unwrap();
+    writer
+        .add(ItemKind::Sample, OsStr::new("small.wav"), vec![2; 100])
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    for readahead_bytes in [0, 150] {
+        let options = BankReaderOptions {
+            readahead_bytes,
+            ..BankReaderOptions::default()
+        };
+        let mut reader = BankReader::with_options(Cursor::new(out.clone()), options).unwrap();
+        for item in reader.items_in_data_order() {
+            let mut copied = Vec::new();
+            let written = reader.copy_to(&item, &mut copied).unwrap();
+            assert_eq!(written, item.size());
+            assert_eq!(copied, reader.read_contents(&item).unwrap());
+        }
+        let item = reader.get("samples/large.wav").unwrap().clone();
+        let mut copied = Vec::new();
+        reader.copy_to(&item, &mut copied).unwrap();
+        assert_eq!(copied, large);
+    }
+
+    // The end of the large sample is missing.
+    let truncated = out[..out.len() - 1000].to_vec();
+    let mut reader = BankReader::new(Cursor::new(truncated)).unwrap();
+    let item = reader.get("samples/large.wav").unwrap().clone();
+    let error = reader.copy_to(&item, &mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+/// Items of an opened bank are read from the file as a copy is written, so
+/// the copy can replace the bank.
+#[test]
+fn content_source() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let bank_path = temp.path().join("test.bank");
+    std::fs::write(&bank_path, create_bank().into_inner()).unwrap();
+
+    let mut reader = BankReader::open(&bank_path).unwrap();
+    let mut file = AtomicFile::create(&bank_path).unwrap();
+    let mut writer = BankWriter::new(&mut file);
+    for item in reader.items().into_iter().filter(Item::is_file) {
+        let source = reader.content_source(&item).unwrap();
+        assert!(matches!(source, ContentSource::Generated { .. }));
+        let path = OsStr::new(std::str::from_utf8(item.path_bytes()).unwrap());
+        let kind = ItemKind::from(path).unwrap();
+        writer.add_source_with_path(kind, path, source).unwrap();
+    }
+    writer.write().unwrap();
+    drop(writer);
+    file.commit().unwrap();
+    assert_eq!(
+        std::fs::read(&bank_path).unwrap(),
+        create_bank().into_inner()
+    );
+
+    // Contents of banks in memory are read straight away.
+    let mut reader = BankReader::new(create_bank()).unwrap();
+    let item = reader.get("samples/one.wav").unwrap().clone();
+    let source = reader.content_source(&item).unwrap();
+    assert!(matches!(source, ContentSource::Bytes(contents) if contents == vec![1; 100]));
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+
+use kibank::lint::{lint, Level, LintConfig, Rule};
+use kibank::read::BankReader;
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+fn bank_with_background() -> Cursor<Vec<u8>> {
+    let mut out = Vec::with_capacity(4096);
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add_file(
+            ItemKind::Background,
+            OsStr::new("background.PNG"),
+            "tests/images/background.png",
+        )
+        .unwrap();
+    writer.write().unwrap();
+    Cursor::new(out)
+}
+
+#[test]
+fn default_config() {
+    let mut reader = BankReader::new(bank_with_background()).unwrap();
+    let findings = lint(&mut reader, &LintConfig::default()).unwrap();
+    let rules = findings
+        .iter()
+        .map(|finding| (finding.rule, finding.level))
+        .collect::<Vec<_>>();
+    assert!(rules.contains(&(Rule::EmptyAuthor, Level::Error)));
+    assert!(rules.contains(&(Rule::MissingDescription, Level::Warning)));
+    assert!(rules.contains(&(Rule::SmallBackground, Level::Warning)));
+    assert!(rules.contains(&(Rule::UppercaseExtension, Level::Warning)));
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn configured_levels() {
+    let config: LintConfig = toml::from_str(
+        r#"
+        min-background-width = 100
+        min-background-height = 50
+
+        [rules]
+        empty-author = "allow"
+        missing-description = "error"
+        "#,
+    )
+    .unwrap();
+
+    let mut reader = BankReader::new(bank_with_background()).unwrap();
+    let findings = lint(&mut reader, &config).unwrap();
+    assert!(findings
+        .iter()
+        .all(|finding| finding.rule != Rule::EmptyAuthor && finding.rule != Rule::SmallBackground));
+    assert!(findings
+        .iter()
+        .any(|finding| finding.rule == Rule::MissingDescription && finding.level == Level::Error));
+}
@@ -0,0 +1,183 @@
+//! Test banks that are damaged or deliberately malformed.
+#![cfg(feature = "std")]
+
+use std::io::Cursor;
+
+use kibank::parse;
+use kibank::read::BankReader;
+use kibank::Location;
+
+/// Header of a bank up to and including the number of locations.
+fn header(location_count: u64) -> Vec<u8> {
+    let mut bank = Vec::with_capacity(256);
+    bank.extend_from_slice(&[137_u8, b'k', b'H', b's', 0x0d, 0x0a, 0x1a, 0x0a]);
+    bank.extend_from_slice(b"Bank0001");
+    bank.extend_from_slice(&location_count.to_le_bytes());
+    bank
+}
+
+fn location(bank: &mut Vec<u8>, file_name_offset: u64, data_offset: u64, data_size: u64) {
+    bank.extend_from_slice(&file_name_offset.to_le_bytes());
+    bank.extend_from_slice(&data_offset.to_le_bytes());
+    bank.extend_from_slice(&data_size.to_le_bytes());
+}
+
+/// The count of locations is far larger than could fit in the file.
+#[test]
+fn huge_location_count() {
+    let bank = header(u64::MAX / 2);
+    assert!(BankReader::new(Cursor::new(bank)).is_err());
+}
+
+/// The final file name is accepted without a trailing null.
+#[test]
+fn missing_final_null() {
+    let mut bank = header(2);
+    location(&mut bank, 0, 0, 0);
+    location(&mut bank, 4, 0, 0);
+    let names = b"abc\0def";
+    bank.extend_from_slice(&(names.len() as u64).to_le_bytes());
+    bank.extend_from_slice(names);
+
+    let reader = BankReader::new(Cursor::new(bank)).unwrap();
+    let items = reader.items();
+    assert_eq!(items[0].path_bytes(), b"abc".as_slice());
+    assert_eq!(items[1].path_bytes(), b"def".as_slice());
+}
+
+#[test]
+fn file_name_offset_past_block() {
+    let mut bank = header(1);
+    location(&mut bank, 10, 0, 0);
+    let names = b"abc\0";
+    bank.extend_from_slice(&(names.len() as u64).to_le_bytes());
+    bank.extend_from_slice(names);
+    assert!(BankReader::new(Cursor::new(bank)).is_err());
+}
+
+/// Numbers that look random but are the same every run, so a failure can be
+/// repeated.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+}
+
+/// Each stage of parsing returns an error rather than panicking, whatever it
+/// is given.
+#[test]
+fn parse_stages() {
+    let mut random = XorShift(0x2545_f491_4f6c_dd1d);
+    for len in 0..200 {
+        let bytes = random.bytes(len);
+        let _ = parse::parse_header(&bytes);
+        let _ = parse::location_block_len(random.next(), random.next());
+        let locations = parse::parse_locations(&bytes);
+        let _ = parse::parse_name_block(&bytes, &locations);
+        let _ = parse::parse(&bytes);
+
+        // Offsets near the end of the block and far past it.
+        let locations = (0..4)
+            .map(|_| Location {
+                file_name_offset: random.next() % (len as u64 + 2),
+                data_offset: random.next(),
+                data_size: random.next(),
+            })
+            .collect::<Vec<_>>();
+        let _ = parse::parse_name_block(&bytes, &locations);
+    }
+}
+
+/// Changing any byte of a valid bank never makes reading it panic.
+#[test]
+fn mutated_bank() {
+    let mut bank = header(2);
+    location(&mut bank, 0, 60, 3);
+    location(&mut bank, 4, 63, 3);
+    let names = b"abc\0def\0";
+    bank.extend_from_slice(&(names.len() as u64).to_le_bytes());
+    bank.extend_from_slice(names);
+    bank.extend_from_slice(b"onetwo");
+    assert!(parse::parse(&bank).is_ok());
+
+    let mut random = XorShift(0x9e37_79b9_7f4a_7c15);
+    for position in 0..bank.len() {
+        for _ in 0..8 {
+            let mut mutated = bank.clone();
+            mutated[position] = random.next() as u8;
+            let _ = parse::parse(&mutated);
+            if let Ok(mut reader) = BankReader::new(Cursor::new(mutated)) {
+                for item in reader.items() {
+                    let _ = reader.read_contents(&item);
+                }
+            }
+        }
+    }
+}
+
+/// Every problem is found rather than only the first.
+#[test]
+fn collecting() {
+    let mut bank = header(3);
+    bank[5] = b'X';
+    // A name past the block, then two files with the same contents.
+    location(&mut bank, 10, 0, 0);
+    location(&mut bank, 0, 100, 8);
+    location(&mut bank, 2, 104, 8);
+    let names = b"a\0b\0";
+    bank.extend_from_slice(&(names.len() as u64).to_le_bytes());
+    bank.extend_from_slice(names);
+    bank.resize(112, 0);
+
+    let (reader, issues) = BankReader::new_collecting(Cursor::new(bank));
+    assert!(reader.is_none());
+    let messages = issues
+        .iter()
+        .map(|issue| issue.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            "Unexpected check bytes \\rX\\x1a\\n",
+            "File name offset 10 is past the end of the file name block",
+            "Bank item a overlaps item b",
+        ]
+    );
+    assert_eq!(issues[0].offset, 4);
+    assert_eq!(issues[2].offset, 104);
+}
+
+/// A truncated bank can be opened but the missing contents are reported.
+#[test]
+fn collecting_truncated() {
+    let mut bank = header(1);
+    location(&mut bank, 0, 100, 50);
+    let names = b"a\0";
+    bank.extend_from_slice(&(names.len() as u64).to_le_bytes());
+    bank.extend_from_slice(names);
+    bank.resize(120, 0);
+
+    let (reader, issues) = BankReader::new_collecting(Cursor::new(bank));
+    assert!(reader.is_some());
+    assert_eq!(issues.len(), 1);
+    assert_eq!(
+        issues[0].to_string(),
+        "Contents of item a are past the end of the file at offset 100"
+    );
+
+    let (reader, issues) = BankReader::new_collecting(Cursor::new(header(u64::MAX / 2)));
+    assert!(reader.is_none());
+    assert_eq!(
+        issues[0].message,
+        "Location count 9223372036854775807 is larger than the file"
+    );
+}
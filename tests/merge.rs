@@ -0,0 +1,89 @@
+//! Test merging banks.
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+
+use serde_json::Value;
+
+use kibank::merge::MergePlan;
+use kibank::read::BankReader;
+use kibank::write::BankWriter;
+use kibank::{ItemKind, Metadata};
+
+fn bank(name: &str, samples: &[(&str, &[u8])]) -> BankReader<'static, Cursor<Vec<u8>>> {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add_metadata(&Metadata {
+            id: format!("me.{}", name.to_lowercase()),
+            name: name.to_string(),
+            author: "Me".to_string(),
+            ..Metadata::default()
+        })
+        .unwrap();
+    for (file_name, contents) in samples {
+        writer
+            .add(ItemKind::Sample, OsStr::new(file_name), contents.to_vec())
+            .unwrap();
+    }
+    writer.write().unwrap();
+    drop(writer);
+    BankReader::new(Cursor::new(out)).unwrap()
+}
+
+#[test]
+fn conflicts() {
+    let mut readers = vec![
+        bank("First", &[("same.wav", b"same"), ("kick.wav", b"first")]),
+        bank("Second", &[("same.wav", b"same"), ("kick.wav", b"second!")]),
+        bank("Third", &[("kick.wav", b"first"), ("snare.wav", b"snare")]),
+    ];
+    let plan = MergePlan::new(&mut readers).unwrap();
+
+    assert_eq!(
+        plan.files.keys().collect::<Vec<_>>(),
+        ["samples/kick.wav", "samples/same.wav", "samples/snare.wav"]
+    );
+    assert_eq!(plan.files["samples/snare.wav"].0, 2);
+
+    // Only the second bank has different contents.
+    assert_eq!(plan.item_conflicts.len(), 1);
+    let conflict = &plan.item_conflicts[0];
+    assert_eq!(conflict.path, "samples/kick.wav");
+    let banks = conflict
+        .candidates
+        .iter()
+        .map(|candidate| (candidate.bank, candidate.size))
+        .collect::<Vec<_>>();
+    assert_eq!(banks, [(0, 5), (1, 7)]);
+    assert_ne!(conflict.candidates[0].digest, conflict.candidates[1].digest);
+
+    let fields = plan
+        .field_conflicts
+        .iter()
+        .map(|conflict| conflict.field.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(fields, ["id", "name"]);
+    assert_eq!(plan.metadata().unwrap().name, "First");
+}
+
+#[test]
+fn choose() {
+    let mut readers = vec![
+        bank("First", &[("kick.wav", b"first")]),
+        bank("Second", &[("kick.wav", b"second")]),
+    ];
+    let mut plan = MergePlan::new(&mut readers).unwrap();
+
+    plan.choose_item("samples/kick.wav", 1).unwrap();
+    let (bank, item) = &plan.files["samples/kick.wav"];
+    assert_eq!(readers[*bank].read_contents(item).unwrap(), b"second");
+    assert!(plan.choose_item("samples/missing.wav", 1).is_err());
+
+    plan.choose_field("name", Value::from("Second")).unwrap();
+    assert_eq!(plan.metadata().unwrap().name, "Second");
+    // The value must be valid for the field.
+    assert!(plan.choose_field("name", Value::from(1)).is_err());
+    assert_eq!(plan.metadata().unwrap().name, "Second");
+}
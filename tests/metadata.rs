@@ -1,4 +1,7 @@
+#![cfg(feature = "std")]
+
 use kibank::read::BankReader;
+use kibank::{Metadata, MetadataIssue, Provenance};
 
 /// Read metadata
 #[test]
@@ -12,3 +15,237 @@ fn read() {
     assert_eq!(metadata.description, "Description");
     assert_eq!(metadata.id, "author.title");
 }
+
+/// Read metadata exactly as it is stored
+#[test]
+fn read_raw() {
+    let mut reader = BankReader::open("tests/metadata.bank").unwrap();
+    let items = reader.items();
+    let item = items.first().unwrap();
+    let json = reader.read_metadata_raw(item).unwrap();
+    assert_eq!(json, reader.read_contents(item).unwrap());
+    assert!(String::from_utf8(json)
+        .unwrap()
+        .contains("\"author.title\""));
+}
+
+/// Read metadata with a clone of the reader on another thread
+#[test]
+fn try_clone() {
+    let reader = BankReader::open("tests/metadata.bank").unwrap();
+    let mut clone = reader.try_clone().unwrap();
+    let author = std::thread::spawn(move || {
+        let item = clone.items().into_iter().next().unwrap();
+        clone.read_metadata(&item).unwrap().author
+    })
+    .join()
+    .unwrap();
+    assert_eq!(author, "Author");
+    assert_eq!(reader.items().len(), 1);
+}
+
+#[test]
+fn bump_version() {
+    let mut metadata = Metadata::default();
+    assert_eq!(metadata.bump_version(), 1);
+    assert_eq!(metadata.bump_version(), 2);
+    assert_eq!(metadata.version, Some(2));
+}
+
+#[test]
+fn provenance() {
+    let mut metadata = Metadata::default();
+    assert!(metadata.provenance().is_none());
+
+    let mut provenance = Provenance::new("0".repeat(40));
+    provenance.created = 1_708_967_100;
+    metadata.set_provenance(&provenance);
+    assert_eq!(metadata.provenance(), Some(provenance.clone()));
+    assert_eq!(provenance.created_utc(), "2024-02-26T17:05:00Z");
+}
+
+#[test]
+fn extra() {
+    let mut metadata = Metadata::default();
+    metadata
+        .extra_set("vendor.tags", &vec!["bass", "lead"])
+        .unwrap();
+    let tags: Option<Vec<String>> = metadata.extra_get("vendor.tags").unwrap();
+    assert_eq!(tags.unwrap(), ["bass", "lead"]);
+    assert!(metadata.extra_get::<u32>("vendor.tags").is_err());
+    assert!(metadata
+        .extra_get::<u32>("vendor.missing")
+        .unwrap()
+        .is_none());
+
+    // Keys without a namespace could collide with other tools.
+    assert!(metadata.extra_set("tags", &1).is_err());
+    assert!(metadata.extra_set(".tags", &1).is_err());
+
+    assert!(metadata.extra_remove("vendor.tags").is_some());
+    assert!(metadata.extra.is_empty());
+}
+
+#[test]
+fn validate() {
+    let metadata = Metadata {
+        id: "author.title".to_string(),
+        hash: Some("0".repeat(40)),
+        ..Metadata::default()
+    };
+    assert!(metadata.validate().is_empty());
+
+    let metadata = Metadata {
+        id: "Author Title".to_string(),
+        version: Some(0),
+        name: "n".repeat(Metadata::MAX_NAME_LENGTH + 1),
+        hash: Some("xyz".to_string()),
+        ..Metadata::default()
+    };
+    assert_eq!(
+        metadata.validate(),
+        vec![
+            MetadataIssue::UnsanitizedId {
+                id: "Author Title".to_string()
+            },
+            MetadataIssue::MalformedHash {
+                hash: "xyz".to_string()
+            },
+            MetadataIssue::ZeroVersion,
+            MetadataIssue::NameTooLong {
+                length: Metadata::MAX_NAME_LENGTH + 1
+            },
+        ]
+    );
+    assert_eq!(Metadata::default().validate(), vec![MetadataIssue::EmptyId]);
+}
+
+#[test]
+fn strip() {
+    let mut metadata = Metadata {
+        name: "Name".to_string(),
+        version: Some(3),
+        hash: Some("0123456789abcdef0123456789abcdef01234567".to_string()),
+        ..Metadata::default()
+    };
+    metadata.set_provenance(&Provenance::new("abc".to_string()));
+    metadata.extra_set("vendor.build", &42).unwrap();
+
+    metadata.strip("hash").unwrap();
+    metadata.strip("version").unwrap();
+    metadata.strip("provenance").unwrap();
+    assert_eq!(metadata.name, "Name");
+    assert!(metadata.hash.is_none());
+    assert!(metadata.version.is_none());
+    assert!(metadata.provenance().is_none());
+    assert_eq!(metadata.extra_get::<u32>("vendor.build").unwrap(), Some(42));
+
+    metadata.strip("vendor.build").unwrap();
+    assert!(metadata.extra.is_empty());
+    assert!(metadata.strip("colour").is_err());
+}
+
+#[test]
+fn localized() {
+    let json = r#"{
+        "id": "author.title",
+        "name": "Title",
+        "author": "Author",
+        "description": "Description",
+        "name_localized": { "de": "Titel", "ja-JP": "タイトル" }
+    }"#;
+    let metadata = BankReader::parse_metadata(json.as_bytes()).unwrap();
+    assert_eq!(metadata.localized_name("de"), "Titel");
+    assert_eq!(metadata.localized_name("DE-at"), "Titel");
+    assert_eq!(metadata.localized_name("ja-jp"), "タイトル");
+    assert_eq!(metadata.localized_name("fr"), "Title");
+    assert_eq!(metadata.localized_description("de"), "Description");
+    assert!(!metadata.extra.contains_key("name_localized"));
+
+    // Empty translations are not written.
+    let json = serde_json::to_string(&Metadata::default()).unwrap();
+    assert!(!json.contains("localized"));
+}
+
+/// IDs generated when there is no ID, author or name
+#[test]
+fn id_on_empty() {
+    use std::ffi::OsStr;
+    use std::io::Cursor;
+
+    use kibank::write::{BankWriter, BankWriterOptions, IdOnEmpty};
+    use kibank::ItemKind;
+
+    let id = |id_on_empty, metadata: &Metadata, samples: &[(&str, u8)]| {
+        let mut out = Vec::new();
+        let options = BankWriterOptions {
+            id_on_empty,
+            ..BankWriterOptions::default()
+        };
+        let mut writer = BankWriter::with_options(Cursor::new(&mut out), options);
+        writer.add_metadata(metadata)?;
+        for (name, contents) in samples {
+            writer.add(ItemKind::Sample, OsStr::new(name), vec![*contents; 10])?;
+        }
+        writer.write()?;
+        drop(writer);
+        let mut reader = BankReader::new(Cursor::new(out))?;
+        let item = reader.get(Metadata::FILE_NAME).cloned().unwrap();
+        reader.read_metadata(&item).map(|metadata| metadata.id)
+    };
+    let empty = Metadata::default();
+    let samples = [("one.wav", 1), ("two.wav", 2)];
+
+    assert_eq!(id(None, &empty, &samples).unwrap(), "");
+    assert!(id(Some(IdOnEmpty::Error), &empty, &samples).is_err());
+
+    let hash = id(Some(IdOnEmpty::Hash), &empty, &samples).unwrap();
+    assert_eq!(hash.len(), 40);
+    assert_eq!(Metadata::sanitize_id(&hash), hash);
+    // The same items in any order give the same ID, other items another.
+    let reversed = [samples[1], samples[0]];
+    assert_eq!(id(Some(IdOnEmpty::Hash), &empty, &reversed).unwrap(), hash);
+    assert_ne!(
+        id(Some(IdOnEmpty::Hash), &empty, &samples[..1]).unwrap(),
+        hash
+    );
+
+    let uuid = id(Some(IdOnEmpty::Uuid), &empty, &samples).unwrap();
+    assert_eq!(uuid.len(), 32);
+    assert_eq!(&uuid[12..13], "5");
+    assert_eq!(Metadata::sanitize_id(&uuid), uuid);
+
+    // An author or name is still used first.
+    let named = Metadata {
+        author: "Author".to_string(),
+        ..Metadata::default()
+    };
+    assert_eq!(
+        id(Some(IdOnEmpty::Error), &named, &samples).unwrap(),
+        "author"
+    );
+}
+
+/// Fields replaced or kept when metadata is merged
+#[test]
+fn changes_from() {
+    use serde_json::Value;
+
+    let original: Metadata = serde_json::from_str(
+        r#"{"id":"a.old","name":"Old","author":"A","description":"","vendor.tag":"x","vendor.gone":1}"#,
+    )
+    .unwrap();
+    let mut merged = original.clone();
+    merged.name = "New".to_string();
+    merged.extra.remove("vendor.gone");
+
+    let changes = merged.changes_from(&original);
+    assert_eq!(changes.changed.len(), 1);
+    let name = &changes.changed["name"];
+    assert_eq!(name.from, Value::from("Old"));
+    assert_eq!(name.to, Value::from("New"));
+    assert_eq!(changes.extra_kept, vec!["vendor.tag"]);
+    assert_eq!(changes.extra_dropped, vec!["vendor.gone"]);
+
+    assert!(original.changes_from(&original).changed.is_empty());
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "std")]
+
+use std::cmp::Ordering;
+
+use kibank::names::{lowercase_extension, natural_cmp, NameRules};
+
+#[test]
+fn collapse_and_title_case() {
+    let rules = NameRules {
+        title_case: true,
+        collapse_spaces: true,
+        ..NameRules::default()
+    };
+    assert_eq!(rules.apply("  deep   LFO bass ", 1), "Deep LFO Bass");
+}
+
+#[test]
+fn numbering() {
+    let rules = NameRules {
+        numbering: true,
+        ..NameRules::default()
+    };
+    assert_eq!(rules.apply("Pad", 4), "04 - Pad");
+    assert_eq!(rules.apply("7-Pad", 4), "07 - Pad");
+    assert_eq!(rules.apply("12 - Pad", 4), "12 - Pad");
+}
+
+#[test]
+fn max_length() {
+    let rules = NameRules {
+        collapse_spaces: true,
+        max_length: Some(6),
+        ..NameRules::default()
+    };
+    assert_eq!(rules.apply("Lead Synth", 1), "Lead S");
+    assert_eq!(rules.apply("Pluck Long", 1), "Pluck");
+}
+
+#[test]
+fn natural_order() {
+    let mut names = vec![
+        "Preset 10",
+        "preset 9",
+        "Preset 2",
+        "Pad",
+        "Preset 02",
+        "Preset 1b",
+        "Preset 1a",
+    ];
+    names.sort_by(|a, b| natural_cmp(a.as_bytes(), b.as_bytes()));
+    assert_eq!(
+        names,
+        [
+            "Pad",
+            "Preset 1a",
+            "Preset 1b",
+            "Preset 02",
+            "Preset 2",
+            "preset 9",
+            "Preset 10"
+        ]
+    );
+    assert_eq!(
+        natural_cmp(b"99999999999999999999999", b"100000000000000000000000"),
+        Ordering::Less
+    );
+    assert_eq!(natural_cmp(b"Bass", b"bass"), Ordering::Less);
+    assert_eq!(natural_cmp(b"Bass", b"Bass"), Ordering::Equal);
+}
+
+#[test]
+fn lowercase_extensions() {
+    assert_eq!(
+        lowercase_extension(b"Samples/Kick.WAV").unwrap(),
+        b"Samples/Kick.wav"
+    );
+    assert_eq!(
+        lowercase_extension(b"phaseplant/Big Pad.PhasePlant").unwrap(),
+        b"phaseplant/Big Pad.phaseplant"
+    );
+    assert!(lowercase_extension(b"samples/KICK.wav").is_none());
+    assert!(lowercase_extension(b"DIR.WAV/README").is_none());
+    assert!(lowercase_extension(b".HIDDEN").is_none());
+}
@@ -0,0 +1,43 @@
+//! Test parsing banks from bytes, which also works without the `std` feature.
+
+use std::fs;
+
+use kibank::parse;
+#[cfg(feature = "std")]
+use kibank::read::BankReader;
+
+#[cfg(feature = "std")]
+#[test]
+fn same_as_reader() {
+    let bank = fs::read("tests/metadata.bank").unwrap();
+    let header = parse::parse(&bank).unwrap();
+    let reader = BankReader::open("tests/metadata.bank").unwrap();
+    let items = reader.items();
+    assert_eq!(header.entries.len(), items.len());
+    for (entry, item) in header.entries.iter().zip(&items) {
+        assert_eq!(header.name(entry), item.path_bytes());
+        assert_eq!(entry.data_size, item.size());
+    }
+
+    // The contents are not needed.
+    let len = usize::try_from(header.len).unwrap();
+    assert_eq!(
+        parse::parse(&bank[..len]).unwrap().entries.len(),
+        items.len()
+    );
+}
+
+#[test]
+fn malformed() {
+    let bank = fs::read("tests/metadata.bank").unwrap();
+    assert!(parse::parse(&[]).is_err());
+    assert!(parse::parse(&bank[..parse::PREFIX_LEN + 1]).is_err());
+
+    let mut wrong_version = bank.clone();
+    wrong_version[8] = b'X';
+    let error = parse::parse(&wrong_version).unwrap_err();
+    assert!(error.to_string().starts_with("Unexpected format version"));
+
+    let len = usize::try_from(parse::parse(&bank).unwrap().len).unwrap();
+    assert!(parse::parse(&bank[..len - 1]).is_err());
+}
@@ -0,0 +1,57 @@
+//! Test updates between versions of a bank.
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+
+use kibank::patch::{apply, diff};
+use kibank::read::BankReader;
+use kibank::write::BankWriter;
+use kibank::{ItemKind, Metadata};
+
+fn bank(samples: &[(&str, u8)], version: u32) -> Cursor<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    for (name, value) in samples {
+        writer
+            .add(ItemKind::Sample, OsStr::new(name), vec![*value; 1000])
+            .unwrap();
+    }
+    let metadata = Metadata {
+        version: Some(version),
+        ..Metadata::default()
+    };
+    writer.add_metadata(&metadata).unwrap();
+    writer.write().unwrap();
+    Cursor::new(out)
+}
+
+#[test]
+fn diff_and_apply() {
+    let old_bank = bank(&[("one.wav", 1), ("two.wav", 2), ("gone.wav", 3)], 1);
+    let new_bank = bank(&[("one.wav", 1), ("two.wav", 20), ("three.wav", 30)], 2);
+    let mut old = BankReader::new(old_bank).unwrap();
+    let mut new = BankReader::new(new_bank.clone()).unwrap();
+
+    let mut patch = Vec::new();
+    let header = diff(&mut old, &mut new, &mut patch).unwrap();
+    assert_eq!(header.changed(), 3);
+    assert_eq!(header.removed, vec!["samples/gone.wav"]);
+    assert!(patch.len() < new_bank.get_ref().len());
+
+    let mut patched = Vec::new();
+    apply(&mut old, patch.as_slice(), Cursor::new(&mut patched)).unwrap();
+    assert_eq!(patched, new_bank.into_inner());
+}
+
+/// A patch cannot be applied to a bank it wasn't made from.
+#[test]
+fn wrong_bank() {
+    let mut old = BankReader::new(bank(&[("one.wav", 1)], 1)).unwrap();
+    let mut new = BankReader::new(bank(&[("one.wav", 1), ("two.wav", 2)], 2)).unwrap();
+    let mut patch = Vec::new();
+    diff(&mut old, &mut new, &mut patch).unwrap();
+
+    let mut other = BankReader::new(bank(&[("one.wav", 9)], 1)).unwrap();
+    assert!(apply(&mut other, patch.as_slice(), Vec::new()).is_err());
+}
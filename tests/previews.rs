@@ -0,0 +1,53 @@
+//! Test pairing presets with their previews.
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+
+use kibank::previews::Previews;
+use kibank::read::BankReader;
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+#[test]
+fn pair() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    for (kind, name) in [
+        (ItemKind::PhasePlantPreset, "Big Bass.phaseplant"),
+        (ItemKind::MultipassPreset, "Big Bass.multipass"),
+        (ItemKind::PhasePlantPreset, "Pad.phaseplant"),
+        (ItemKind::Sample, "Pad.wav"),
+        (ItemKind::Preview, "big bass.mp3"),
+        (ItemKind::Preview, "Lead.flac"),
+    ] {
+        writer.add(kind, OsStr::new(name), vec![1]).unwrap();
+    }
+    writer.write().unwrap();
+    let reader = BankReader::new(Cursor::new(out)).unwrap();
+    assert!(reader.get("previews/Lead.flac").is_some());
+
+    let previews = Previews::pair(&reader.items());
+    let presets = previews
+        .presets
+        .iter()
+        .map(|(preset, preview)| {
+            (
+                preset.file_name_lossy(),
+                preview.as_ref().map(|preview| preview.file_name_lossy()),
+            )
+        })
+        .collect::<Vec<_>>();
+    let bass = Some("previews/big bass.mp3".to_string());
+    assert!(presets.contains(&("phaseplant/Big Bass.phaseplant".to_string(), bass.clone())));
+    assert!(presets.contains(&("multipass/Big Bass.multipass".to_string(), bass)));
+    assert!(presets.contains(&("phaseplant/Pad.phaseplant".to_string(), None)));
+    assert_eq!(presets.len(), 3);
+
+    let unmatched = previews
+        .unmatched
+        .iter()
+        .map(|preview| preview.file_name_lossy())
+        .collect::<Vec<_>>();
+    assert_eq!(unmatched, vec!["previews/Lead.flac"]);
+}
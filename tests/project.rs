@@ -0,0 +1,145 @@
+//! Test building the banks of a project.
+#![cfg(feature = "std")]
+
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use assert_fs::prelude::*;
+
+use kibank::project::{Hook, Project, ProjectBank};
+use kibank::read::BankReader;
+use kibank::write::BankWriter;
+
+fn project_bank(name: &str, inputs: &[&str]) -> ProjectBank {
+    ProjectBank {
+        name: name.to_string(),
+        output: PathBuf::from(format!("{name}.bank")),
+        inputs: inputs.iter().map(PathBuf::from).collect(),
+        title: None,
+        author: None,
+        description: None,
+        id: None,
+        post_build: Vec::new(),
+    }
+}
+
+#[test]
+fn add_to() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("samples/kick.wav").write_str("kick").unwrap();
+    dir.child("lite/bass.phaseplant").write_str("bass").unwrap();
+    dir.child("full/lead.phaseplant").write_str("lead").unwrap();
+
+    let project = Project {
+        author: Some("Shared Author".to_string()),
+        description: None,
+        banks: vec![
+            ProjectBank {
+                title: Some("Lite".to_string()),
+                ..project_bank("lite", &["samples", "lite"])
+            },
+            project_bank("full", &["samples", "lite", "full"]),
+        ],
+        post_build: Vec::new(),
+    };
+    project.validate().unwrap();
+
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    let files = project
+        .add_to(project.bank("lite").unwrap(), dir.path(), &mut writer)
+        .unwrap();
+    writer.write().unwrap();
+    assert_eq!(files, 2);
+
+    let mut reader = BankReader::new(Cursor::new(out)).unwrap();
+    let paths = reader
+        .items()
+        .iter()
+        .filter(|item| item.is_file())
+        .map(|item| item.file_name_lossy())
+        .collect::<Vec<_>>();
+    assert!(paths.contains(&"phaseplant/bass.phaseplant".to_string()));
+    assert!(!paths.contains(&"phaseplant/lead.phaseplant".to_string()));
+    let metadata_item = reader
+        .items()
+        .into_iter()
+        .find(|item| item.is_metadata_file())
+        .unwrap();
+    let metadata = reader.read_metadata(&metadata_item).unwrap();
+    assert_eq!(metadata.name, "Lite");
+    assert_eq!(metadata.author, "Shared Author");
+}
+
+#[test]
+fn validate() {
+    let no_banks = Project::default();
+    assert!(no_banks.validate().is_err());
+
+    let duplicate = Project {
+        banks: vec![
+            project_bank("same", &["one"]),
+            project_bank("same", &["two"]),
+        ],
+        ..Project::default()
+    };
+    assert!(duplicate.validate().is_err());
+
+    let no_inputs = Project {
+        banks: vec![project_bank("empty", &[])],
+        ..Project::default()
+    };
+    assert!(no_inputs.validate().is_err());
+}
+
+#[test]
+fn post_build() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("bass.phaseplant").write_str("bass").unwrap();
+    let bank = project_bank("pack", &["bass.phaseplant"]);
+    let project = Project {
+        banks: vec![bank.clone()],
+        post_build: vec![
+            Hook::Checksum("sha1".to_string()),
+            Hook::CopyTo(PathBuf::from("release")),
+        ],
+        ..Project::default()
+    };
+    project.validate().unwrap();
+
+    let bank_path = dir.child("pack.bank");
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    project.add_to(&bank, dir.path(), &mut writer).unwrap();
+    writer.write().unwrap();
+    bank_path.write_binary(&out).unwrap();
+
+    project
+        .post_build(&bank, bank_path.path(), dir.path())
+        .unwrap();
+    let checksums = std::fs::read_to_string(dir.child("pack.bank.sha1").path()).unwrap();
+    assert!(checksums.contains("  phaseplant/bass.phaseplant"));
+    dir.child("release/pack.bank")
+        .assert(predicates::path::exists());
+    dir.child("release/pack.bank.sha1")
+        .assert(predicates::path::exists());
+}
+
+#[test]
+fn validate_hooks() {
+    let project = Project {
+        banks: vec![ProjectBank {
+            post_build: vec![Hook::Checksum("md5".to_string())],
+            ..project_bank("pack", &["one"])
+        }],
+        ..Project::default()
+    };
+    assert!(project.validate().is_err());
+
+    let project = Project {
+        banks: vec![project_bank("pack", &["one"])],
+        post_build: vec![Hook::Run(Vec::new())],
+        ..Project::default()
+    };
+    assert!(project.validate().is_err());
+}
@@ -0,0 +1,109 @@
+//! Test repairing banks with the damage seen from older export tools.
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+
+use kibank::read::BankReader;
+use kibank::repair::{self, Fix};
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+/// A bank with one preset, no directory for it, a file name block length
+/// that is too short and no null after the file name.
+fn damaged_bank() -> Vec<u8> {
+    let name = b"phaseplant/bass.phaseplant";
+    let data_offset = 24 + 24 + 8 + name.len() as u64;
+    let mut bank = Vec::new();
+    bank.extend_from_slice(&[137_u8, b'k', b'H', b's', 0x0d, 0x0a, 0x1a, 0x0a]);
+    bank.extend_from_slice(b"Bank0001");
+    bank.extend_from_slice(&1_u64.to_le_bytes());
+    bank.extend_from_slice(&0_u64.to_le_bytes());
+    bank.extend_from_slice(&data_offset.to_le_bytes());
+    bank.extend_from_slice(&6_u64.to_le_bytes());
+    bank.extend_from_slice(&3_u64.to_le_bytes());
+    bank.extend_from_slice(name);
+    bank.extend_from_slice(b"preset");
+    bank
+}
+
+#[test]
+fn damaged() {
+    let mut repaired = Vec::new();
+    let fixes = repair::repair(Cursor::new(damaged_bank()), &mut repaired).unwrap();
+    assert_eq!(
+        fixes,
+        [
+            Fix::FileNameBlockLength {
+                declared: 3,
+                actual: 26
+            },
+            Fix::MissingFinalNull,
+            Fix::MissingDirectory {
+                path: "phaseplant".to_string()
+            },
+        ]
+    );
+
+    let mut reader = BankReader::new(Cursor::new(repaired)).unwrap();
+    assert!(reader.get("phaseplant").unwrap().is_directory());
+    let item = reader.get("phaseplant/bass.phaseplant").unwrap().clone();
+    assert_eq!(reader.read_contents(&item).unwrap(), b"preset");
+}
+
+/// Nothing is fixed in a bank that isn't damaged.
+#[test]
+fn undamaged() {
+    let mut bank = Vec::new();
+    let mut writer = BankWriter::new(&mut bank);
+    writer
+        .add(
+            ItemKind::PhasePlantPreset,
+            OsStr::new("bass.phaseplant"),
+            b"preset".to_vec(),
+        )
+        .unwrap();
+    writer.write().unwrap();
+
+    let mut repaired = Vec::new();
+    let fixes = repair::repair(Cursor::new(bank), &mut repaired).unwrap();
+    assert!(fixes.is_empty());
+    assert!(BankReader::new(Cursor::new(repaired)).is_ok());
+}
+
+/// The size of the contents is checked before memory is allocated for them.
+#[test]
+fn item_past_end() {
+    let names = b"phaseplant\0phaseplant/bass.phaseplant\0phaseplant/lead.phaseplant\0";
+    let data_offset = 24 + 3 * 24 + 8 + names.len() as u64;
+    let mut bank = Vec::new();
+    bank.extend_from_slice(&[137_u8, b'k', b'H', b's', 0x0d, 0x0a, 0x1a, 0x0a]);
+    bank.extend_from_slice(b"Bank0001");
+    bank.extend_from_slice(&3_u64.to_le_bytes());
+    for (file_name_offset, offset, size) in [
+        (0_u64, data_offset, 0_u64),
+        (11, data_offset, 6),
+        (38, data_offset + 6, 1 << 62),
+    ] {
+        bank.extend_from_slice(&file_name_offset.to_le_bytes());
+        bank.extend_from_slice(&offset.to_le_bytes());
+        bank.extend_from_slice(&size.to_le_bytes());
+    }
+    bank.extend_from_slice(&(names.len() as u64).to_le_bytes());
+    bank.extend_from_slice(names);
+    bank.extend_from_slice(b"preset");
+
+    let mut repaired = Vec::new();
+    let fixes = repair::repair(Cursor::new(bank), &mut repaired).unwrap();
+    assert_eq!(
+        fixes,
+        [Fix::ItemPastEnd {
+            path: "phaseplant/lead.phaseplant".to_string()
+        }]
+    );
+
+    let mut reader = BankReader::new(Cursor::new(repaired)).unwrap();
+    assert!(reader.get("phaseplant/lead.phaseplant").is_none());
+    let item = reader.get("phaseplant/bass.phaseplant").unwrap().clone();
+    assert_eq!(reader.read_contents(&item).unwrap(), b"preset");
+}
@@ -0,0 +1,74 @@
+//! Test accounting for the bytes of a bank.
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+
+use kibank::read::BankReader;
+use kibank::size::SizeBreakdown;
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+#[test]
+fn size_breakdown() {
+    let items = [
+        (ItemKind::Metadata, "index.json", b"{}".to_vec()),
+        (ItemKind::PhasePlantPreset, "Bass.phaseplant", vec![1; 100]),
+        (ItemKind::PhasePlantPreset, "Pad.phaseplant", vec![2; 50]),
+        (ItemKind::Sample, "Kick.wav", vec![3; 1000]),
+    ];
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    for (kind, name, contents) in &items {
+        writer
+            .add(*kind, OsStr::new(name), contents.clone())
+            .unwrap();
+    }
+    writer.write().unwrap();
+    drop(writer);
+
+    let reader = BankReader::new(Cursor::new(out.clone())).unwrap();
+    let breakdown = reader.size_breakdown();
+    assert_eq!(breakdown.total(), out.len() as u64);
+    assert_eq!(breakdown.header, 24);
+    // Four files and the phaseplant and samples directories.
+    assert_eq!(breakdown.locations, 6 * 24);
+    assert_eq!(breakdown.data[&ItemKind::PhasePlantPreset], 150);
+    assert_eq!(breakdown.data[&ItemKind::Sample], 1000);
+    assert_eq!(breakdown.other_data, 0);
+    assert_eq!(breakdown.padding, 0);
+
+    // The same size is projected before the bank is written.
+    let projected = SizeBreakdown::projected(
+        items
+            .iter()
+            .map(|(kind, name, contents)| (*kind, OsStr::new(name), contents.len() as u64)),
+    );
+    assert_eq!(projected, breakdown);
+}
+
+#[test]
+fn padding() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add(ItemKind::Sample, OsStr::new("Kick.wav"), vec![3; 10])
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    // Moving the contents of the last item along leaves a gap before it.
+    let reader = BankReader::new(Cursor::new(out.clone())).unwrap();
+    let kick = reader.get("samples/Kick.wav").unwrap().clone();
+    let offset_position = out
+        .windows(8)
+        .position(|window| window == kick.offset().to_le_bytes())
+        .unwrap();
+    out[offset_position..offset_position + 8].copy_from_slice(&(kick.offset() + 4).to_le_bytes());
+    out.splice(kick.offset() as usize..kick.offset() as usize, [0; 4]);
+
+    let reader = BankReader::new(Cursor::new(out.clone())).unwrap();
+    let breakdown = reader.size_breakdown();
+    assert_eq!(breakdown.padding, 4);
+    assert_eq!(breakdown.total(), out.len() as u64);
+}
@@ -0,0 +1,32 @@
+//! Test writing catalogs to SQLite databases.
+#![cfg(feature = "sqlite")]
+
+use kibank::catalog::Catalog;
+use rusqlite::Connection;
+
+#[test]
+fn export() {
+    let catalog = Catalog::scan("tests").unwrap();
+    let db = assert_fs::NamedTempFile::new("catalog.db").unwrap();
+    kibank::sqlite::export(&catalog, db.path()).unwrap();
+
+    // Exporting again replaces the catalog.
+    kibank::sqlite::export(&catalog, db.path()).unwrap();
+
+    let connection = Connection::open(db.path()).unwrap();
+    let bank_count: i64 = connection
+        .query_row("SELECT COUNT(*) FROM banks", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(bank_count, 2);
+
+    let (name, kind): (String, String) = connection
+        .query_row(
+            "SELECT banks.name, items.kind FROM items JOIN banks ON items.bank = banks.id
+                WHERE banks.bank_id = 'author.title'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(name, "Title");
+    assert_eq!(kind, "Metadata");
+}
@@ -0,0 +1,49 @@
+#![cfg(feature = "std")]
+
+use kibank::template::{item_value, metadata_value, render};
+use kibank::Metadata;
+
+#[test]
+fn placeholders() {
+    let metadata = Metadata {
+        author: "Author".to_string(),
+        name: "Title".to_string(),
+        ..Metadata::default()
+    };
+    let value = |placeholder: &str| metadata_value(&metadata, placeholder);
+    assert_eq!(render("{author}.{name}", value).unwrap(), "Author.Title");
+    assert_eq!(render("{{{name}}}", value).unwrap(), "{Title}");
+    assert_eq!(render("{year}", value).unwrap().len(), 4);
+}
+
+#[test]
+fn malformed() {
+    let value = |placeholder: &str| metadata_value(&Metadata::default(), placeholder);
+    assert!(render("{unknown}", value).is_err());
+    assert!(render("{name", value).is_err());
+    assert!(render("name}", value).is_err());
+}
+
+#[test]
+fn item_placeholders() {
+    let metadata = Metadata {
+        id: "author.title".to_string(),
+        ..Metadata::default()
+    };
+    let value =
+        |placeholder: &str| item_value("phaseplant/Big Bass.phaseplant", &metadata, placeholder);
+    assert_eq!(
+        render("{kind}/{stem}_{bankid}.{ext}", value).unwrap(),
+        "phaseplant/Big Bass_author.title.phaseplant"
+    );
+    assert_eq!(
+        render("{dir}|{name}", value).unwrap(),
+        "phaseplant|Big Bass.phaseplant"
+    );
+
+    let value = |placeholder: &str| item_value("index.json", &metadata, placeholder);
+    assert_eq!(
+        render("{kind}|{dir}|{path}", value).unwrap(),
+        "||index.json"
+    );
+}
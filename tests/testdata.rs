@@ -0,0 +1,50 @@
+//! Test the banks built to match those written by Kilohearts Bank Maker.
+#![cfg(feature = "testdata")]
+
+use std::fs;
+use std::io::Cursor;
+
+use kibank::read::BankReader;
+use kibank::testdata;
+use kibank::{ItemKind, Metadata};
+
+#[test]
+fn blank_matches_bank_maker() {
+    assert_eq!(
+        testdata::blank().unwrap(),
+        fs::read("tests/blank.bank").unwrap()
+    );
+}
+
+#[test]
+fn metadata_matches_bank_maker() {
+    let metadata = Metadata {
+        id: "author.title".to_string(),
+        author: "Author".to_string(),
+        name: "Title".to_string(),
+        description: "Description".to_string(),
+        ..Metadata::default()
+    };
+    assert_eq!(
+        testdata::with_metadata(&metadata).unwrap(),
+        fs::read("tests/metadata.bank").unwrap()
+    );
+}
+
+#[test]
+fn one_of_each_kind() {
+    let mut reader = BankReader::new(Cursor::new(testdata::one_of_each_kind().unwrap())).unwrap();
+    for kind in ItemKind::all() {
+        let count = reader.items_by_kind().get(&kind).map_or(0, Vec::len);
+        let expected = usize::from(!matches!(
+            kind,
+            ItemKind::Changelog | ItemKind::Image | ItemKind::Preview
+        ));
+        assert_eq!(count, expected, "{kind:?}");
+    }
+
+    let items = reader.items();
+    let metadata_item = items.iter().find(|item| item.is_metadata_file()).unwrap();
+    let metadata = reader.read_metadata(metadata_item).unwrap();
+    assert_eq!(metadata.id, "kibank.test");
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+
+use kibank::read::BankReader;
+use kibank::tree::{BankTree, ROOT};
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+#[test]
+fn tree() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add(ItemKind::Sample, OsStr::new("kick.wav"), vec![1; 10])
+        .unwrap();
+    writer
+        .add_with_path(
+            ItemKind::Sample,
+            OsStr::new("samples/drums/snare.wav"),
+            vec![2; 20],
+        )
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let reader = BankReader::new(Cursor::new(out)).unwrap();
+    let tree = BankTree::new(&reader.items());
+
+    // The directory of drums is only implied by the path of the snare.
+    let samples = tree.find(b"SAMPLES").unwrap();
+    assert!(tree.node(samples).unwrap().is_directory());
+    assert_eq!(tree.node(samples).unwrap().parent, ROOT);
+    let drums = tree.lookup(samples, b"drums").unwrap();
+    let snare = tree.find(b"samples/drums/snare.wav").unwrap();
+    assert_eq!(tree.children(drums), [snare]);
+    assert_eq!(tree.node(snare).unwrap().size(), 20);
+    assert_eq!(tree.children(samples).len(), 2);
+
+    assert_eq!(tree.find(b""), Some(ROOT));
+    assert!(tree.find(b"samples/missing.wav").is_none());
+    assert!(tree.node(0).is_none());
+    assert!(tree.children(snare).is_empty());
+}
+
+#[test]
+fn sort_naturally() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    for name in ["Preset 10.wav", "Preset 2.wav", "Preset 1.wav"] {
+        writer
+            .add(ItemKind::Sample, OsStr::new(name), Vec::new())
+            .unwrap();
+    }
+    writer.write().unwrap();
+    drop(writer);
+
+    let reader = BankReader::new(Cursor::new(out)).unwrap();
+    let mut tree = BankTree::new(&reader.items());
+    tree.sort_naturally();
+    let samples = tree.find(b"samples").unwrap();
+    let names = tree
+        .children(samples)
+        .iter()
+        .map(|inode| tree.node(*inode).unwrap().name.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [&b"Preset 1.wav"[..], b"Preset 2.wav", b"Preset 10.wav"]
+    );
+}
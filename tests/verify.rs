@@ -0,0 +1,143 @@
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::Cursor;
+
+use kibank::read::BankReader;
+use kibank::roundtrip;
+use kibank::roundtrip::Difference;
+use kibank::verify::{verify, VerifyOptions};
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+fn bank_with_sample() -> Vec<u8> {
+    let mut out = Vec::with_capacity(4096);
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add_file(
+            ItemKind::Background,
+            OsStr::new("background.png"),
+            "tests/images/background.png",
+        )
+        .unwrap();
+    writer
+        .add(ItemKind::Sample, OsStr::new("silence.wav"), vec![0; 100])
+        .unwrap();
+    writer.write().unwrap();
+    out
+}
+
+#[test]
+fn deep() {
+    let mut reader = BankReader::new(Cursor::new(bank_with_sample())).unwrap();
+    let report = verify(
+        &mut reader,
+        &VerifyOptions {
+            deep: true,
+            ..VerifyOptions::default()
+        },
+    )
+    .unwrap();
+    assert!(report.is_ok());
+    assert!(report.issues.is_empty());
+    assert_eq!(report.fingerprint.unwrap().len(), 40);
+}
+
+/// Extensions that are not lowercase are a warning since some hosts ignore
+/// them.
+#[test]
+fn uppercase_extension() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add(ItemKind::Sample, OsStr::new("Kick.WAV"), vec![0; 100])
+        .unwrap();
+    writer.write().unwrap();
+
+    let mut reader = BankReader::new(Cursor::new(out)).unwrap();
+    let report = verify(&mut reader, &VerifyOptions::default()).unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.issues.len(), 1);
+    assert!(report.issues[0].message.contains("samples/Kick.WAV"));
+}
+
+/// A bank without any items is valid but reported.
+#[test]
+fn empty() {
+    let mut bank = Vec::new();
+    bank.extend_from_slice(&[137_u8, b'k', b'H', b's', 0x0d, 0x0a, 0x1a, 0x0a]);
+    bank.extend_from_slice(b"Bank0001");
+    bank.extend_from_slice(&0_u64.to_le_bytes());
+    bank.extend_from_slice(&0_u64.to_le_bytes());
+
+    let mut reader = BankReader::new(Cursor::new(bank)).unwrap();
+    assert!(reader.is_empty());
+    assert!(reader.items().is_empty());
+    let options = VerifyOptions {
+        deep: true,
+        ..VerifyOptions::default()
+    };
+    let report = verify(&mut reader, &options).unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.issues[0].message, "The bank has no items");
+
+    let reader = BankReader::new(Cursor::new(bank_with_sample())).unwrap();
+    assert!(!reader.is_empty());
+}
+
+/// Truncation is noticed from the header without reading the contents.
+#[test]
+fn truncated() {
+    let mut bank = bank_with_sample();
+    bank.truncate(bank.len() - 10);
+
+    let mut reader = BankReader::new(Cursor::new(bank)).unwrap();
+    let report = verify(&mut reader, &VerifyOptions::default()).unwrap();
+    assert!(!report.is_ok());
+    assert!(report.issues[0].message.contains("samples/silence.wav"));
+    assert!(report.issues[0].message.contains("past the end"));
+
+    let report = verify(
+        &mut reader,
+        &VerifyOptions {
+            deep: true,
+            ..VerifyOptions::default()
+        },
+    )
+    .unwrap();
+    assert!(!report.is_ok());
+    assert!(report.fingerprint.is_none());
+}
+
+/// A bank made by the writer can be rebuilt without any differences.
+#[test]
+fn roundtrip() {
+    let mut reader = BankReader::new(Cursor::new(bank_with_sample())).unwrap();
+    let options = VerifyOptions {
+        roundtrip: true,
+        ..VerifyOptions::default()
+    };
+    let report = verify(&mut reader, &options).unwrap();
+    assert!(report.issues.is_empty(), "{:?}", report.issues);
+}
+
+/// Files of unknown kinds cannot be rebuilt.
+#[test]
+fn roundtrip_unknown_kind() {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add_with_path(ItemKind::Sample, OsStr::new("notes.txt"), vec![1; 10])
+        .unwrap();
+    writer.write().unwrap();
+
+    let mut reader = BankReader::new(Cursor::new(out)).unwrap();
+    let differences = roundtrip::check_reader(&mut reader).unwrap();
+    assert_eq!(
+        differences,
+        vec![Difference::Missing {
+            path: "notes.txt".to_string()
+        }]
+    );
+}
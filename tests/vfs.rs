@@ -0,0 +1,67 @@
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::{Cursor, ErrorKind, Read, Seek, SeekFrom};
+
+use kibank::read::BankReader;
+use kibank::vfs::{BankVfs, Stat, Vfs};
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+fn bank() -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut writer = BankWriter::new(Cursor::new(&mut out));
+    writer
+        .add(ItemKind::Sample, OsStr::new("kick.wav"), b"kick".to_vec())
+        .unwrap();
+    writer
+        .add(
+            ItemKind::PhasePlantPreset,
+            OsStr::new("bass.phaseplant"),
+            b"bass".to_vec(),
+        )
+        .unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    out
+}
+
+fn check(vfs: &dyn Vfs) {
+    assert_eq!(
+        vfs.stat("samples/kick.wav").unwrap(),
+        Stat {
+            is_directory: false,
+            size: 4
+        }
+    );
+    assert!(vfs.stat("").unwrap().is_directory);
+    assert_eq!(vfs.list("samples").unwrap(), ["kick.wav"]);
+    assert_eq!(vfs.read("Samples/Kick.wav").unwrap(), b"kick");
+
+    let mut file = vfs.open("phaseplant/bass.phaseplant").unwrap();
+    file.seek(SeekFrom::Start(2)).unwrap();
+    let mut rest = String::new();
+    file.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "ss");
+
+    assert_eq!(
+        vfs.stat("missing.wav").unwrap_err().kind(),
+        ErrorKind::NotFound
+    );
+    assert!(vfs.open("samples").is_err());
+    assert!(vfs.list("samples/kick.wav").is_err());
+}
+
+#[test]
+fn in_memory() {
+    let reader = BankReader::new(Cursor::new(bank())).unwrap();
+    check(&BankVfs::new(reader));
+}
+
+#[test]
+fn file() {
+    let file = assert_fs::NamedTempFile::new("vfs.bank").unwrap();
+    std::fs::write(file.path(), bank()).unwrap();
+    let reader = BankReader::open(file.path()).unwrap();
+    check(&BankVfs::new(reader));
+}
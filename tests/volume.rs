@@ -0,0 +1,93 @@
+//! Test banks split into volumes.
+#![cfg(feature = "std")]
+
+use std::ffi::OsStr;
+use std::io::{BufReader, Cursor};
+
+use kibank::read::BankReader;
+use kibank::volume::{find_index, VolumeReader, VolumeWriter};
+use kibank::write::BankWriter;
+use kibank::ItemKind;
+
+fn add_items<W: std::io::Write>(writer: &mut BankWriter<W>) {
+    writer
+        .add(ItemKind::Sample, OsStr::new("one.wav"), vec![1; 250])
+        .unwrap();
+    writer
+        .add(ItemKind::Sample, OsStr::new("two.wav"), vec![2; 250])
+        .unwrap();
+    writer.write().unwrap();
+}
+
+#[test]
+fn split_and_read() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let bank_path = dir.path().join("pack.bank");
+    let mut volumes = VolumeWriter::new(&bank_path, 100).unwrap();
+    add_items(&mut BankWriter::new(&mut volumes));
+    volumes.commit().unwrap();
+
+    let mut whole = Vec::new();
+    add_items(&mut BankWriter::new(Cursor::new(&mut whole)));
+    assert!(dir.path().join("pack.bank.001").is_file());
+    assert!(dir
+        .path()
+        .join(format!("pack.bank.{:03}", whole.len().div_ceil(100)))
+        .is_file());
+
+    let index_path = find_index(&bank_path).unwrap();
+    assert_eq!(
+        find_index(&dir.path().join("pack.bank.001")),
+        Some(index_path.clone())
+    );
+    let volumes = BufReader::new(VolumeReader::open(&index_path).unwrap());
+    let mut reader = BankReader::new(volumes).unwrap();
+    let mut whole_reader = BankReader::new(Cursor::new(whole)).unwrap();
+    for item in whole_reader.items() {
+        assert_eq!(
+            reader.read_contents(&item).unwrap(),
+            whole_reader.read_contents(&item).unwrap()
+        );
+    }
+}
+
+/// Volumes only replace those of an old bank once they are all written, and
+/// volumes of the old bank that aren't needed any more are removed.
+#[test]
+fn commit() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let bank_path = dir.path().join("pack.bank");
+    let mut volumes = VolumeWriter::new(&bank_path, 100).unwrap();
+    add_items(&mut BankWriter::new(&mut volumes));
+    let old_count = volumes.commit().unwrap().volumes.len();
+
+    // Nothing is left behind by a writer that isn't committed.
+    let mut volumes = VolumeWriter::new(&bank_path, 1000).unwrap();
+    add_items(&mut BankWriter::new(&mut volumes));
+    drop(volumes);
+    let mut names = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    names.sort();
+    let mut expected = (1..=old_count)
+        .map(|number| format!("pack.bank.{number:03}"))
+        .collect::<Vec<_>>();
+    expected.push("pack.bank.volumes.json".to_string());
+    assert_eq!(names, expected);
+
+    let mut volumes = VolumeWriter::new(&bank_path, 1000).unwrap();
+    add_items(&mut BankWriter::new(&mut volumes));
+    let index = volumes.commit().unwrap();
+    assert_eq!(index.volumes.len(), 1);
+    assert!(dir.path().join("pack.bank.001").is_file());
+    assert!(!dir.path().join("pack.bank.002").exists());
+    let volumes = VolumeReader::open(find_index(&bank_path).unwrap()).unwrap();
+    assert_eq!(
+        BankReader::new(BufReader::new(volumes))
+            .unwrap()
+            .items()
+            .len(),
+        4
+    );
+}
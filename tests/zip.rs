@@ -0,0 +1,80 @@
+//! Test reading banks inside zip archives.
+#![cfg(feature = "zip")]
+
+use std::fs::File;
+use std::io::{BufReader, Cursor, ErrorKind, Read, Seek, SeekFrom};
+
+use kibank::read::BankReader;
+use kibank::zip;
+
+#[test]
+fn entries() {
+    let mut archive = File::open("tests/metadata.zip").unwrap();
+    let entries = zip::entries(&mut archive).unwrap();
+    let names = entries
+        .iter()
+        .map(|entry| entry.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["Pack/readme.txt", "Pack/Metadata.bank"]);
+    assert!(!entries[0].is_bank());
+    assert!(entries[1].is_bank());
+
+    // Stored without compression.
+    assert_eq!(
+        zip::read_entry(&mut archive, &entries[0]).unwrap(),
+        b"Read me\n"
+    );
+}
+
+#[test]
+fn open_bank() {
+    let archive = File::open("tests/metadata.zip").unwrap();
+    let (name, mut bank) = zip::open_bank(archive).unwrap();
+    assert_eq!(name, "Pack/Metadata.bank");
+    let mut prefix = [0; 4];
+    bank.read_exact(&mut prefix).unwrap();
+    assert!(zip::is_zip(&std::fs::read("tests/metadata.zip").unwrap()));
+    assert!(!zip::is_zip(&prefix));
+
+    let (_, bank) = zip::open_bank(File::open("tests/metadata.zip").unwrap()).unwrap();
+    let mut reader = BankReader::new(BufReader::new(bank)).unwrap();
+    let item = reader.items().first().cloned().unwrap();
+    assert_eq!(reader.read_metadata(&item).unwrap().id, "author.title");
+}
+
+#[test]
+fn damaged() {
+    let mut archive = std::fs::read("tests/metadata.zip").unwrap();
+    // The last byte of the deflated bank, just before the central directory.
+    let entries = zip::entries(&mut Cursor::new(&archive)).unwrap();
+    let position = archive
+        .windows(4)
+        .position(|window| window == [0x50, 0x4b, 0x01, 0x02])
+        .unwrap();
+    archive[position - 1] ^= 0xff;
+    let error = zip::read_entry(&mut Cursor::new(&archive), &entries[1]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    assert!(zip::entries(&mut Cursor::new(b"PK\x03\x04".to_vec())).is_err());
+}
+
+/// Stored files are read from the archive without reading them into memory,
+/// and sizes in the archive are not trusted.
+#[test]
+fn stored() {
+    let mut archive = File::open("tests/metadata.zip").unwrap();
+    let mut entries = zip::entries(&mut archive).unwrap();
+    let mut readme = zip::open_entry(&mut archive, &entries[0]).unwrap();
+    readme.seek(SeekFrom::Start(5)).unwrap();
+    let mut rest = String::new();
+    readme.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "me\n");
+    assert_eq!(readme.seek(SeekFrom::End(-3)).unwrap(), 5);
+
+    // A size larger than the contents is damage rather than a large
+    // allocation.
+    entries[0].size = u64::from(u32::MAX - 1);
+    let error = zip::read_entry(&mut archive, &entries[0]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(zip::open_entry(&mut archive, &entries[0]).is_err());
+}